@@ -0,0 +1,55 @@
+//! Time of the majority judgment depending on the number of ballots and of distinct grades.
+//!
+//! The tallies are sparse, so the time per ballot does not depend on the range of the grades:
+//! a poll with 3 grades spread over the whole `u32` range costs as much as a poll with grades 0, 1 and 2.
+//! It grows with the logarithm of the number of distinct grades (counting in a BTreeMap),
+//! and not with the number of ballots.
+//!
+//! Run with `cargo bench --bench grade_space`.
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use majority_judgement_rust::majority_judgment;
+
+/// A poll of two candidates with `ballots` grades each, taking `distinct` values spread by `spread`
+fn poll(ballots: usize, distinct: u32, spread: u32) -> BTreeMap<String, Vec<u32>> {
+    let mut poll_data = BTreeMap::new();
+    for (candidate, offset) in [("Pizza", 1), ("Chips", 7)] {
+        let grades = (0..ballots as u64)
+            .map(|ballot| ((ballot * 2_654_435_761 + offset) % distinct as u64) as u32 * spread)
+            .collect();
+        poll_data.insert(candidate.to_string(), grades);
+    }
+    poll_data
+}
+
+/// The mean time of `f`, repeated until it took at least 200 ms
+fn measure<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while runs == 0 || start.elapsed() < Duration::from_millis(200) {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    println!("{:>10} {:>10} {:>12} {:>12} {:>14}", "ballots", "distinct", "spread", "total", "per ballot");
+    for ballots in [10_000, 100_000, 1_000_000] {
+        for (distinct, spread) in [(3, 1), (3, u32::MAX / 2), (101, 1), (10_000, 1), (10_000, 400_000)] {
+            let poll_data = poll(ballots, distinct, spread);
+            let time = measure(|| {
+                black_box(majority_judgment(black_box(&poll_data)));
+            });
+            println!(
+                "{:>10} {:>10} {:>12} {:>12.3?} {:>11.2} ns",
+                ballots,
+                distinct,
+                spread,
+                time,
+                time.as_nanos() as f64 / (2 * ballots) as f64
+            );
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Time of the ranking depending on the number of ballots and of candidates.
+//!
+//! Run with `cargo bench --bench ranking`, which also counts the allocations of a ranking:
+//! a few per candidate, whatever the number of ballots. The performance contract (see the README):
+//! counting the grades is linear in the number of ballots, a candidate is then evaluated in
+//! O(k) for k distinct grades and the candidates are sorted in O(c log c) comparisons of O(k) each.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use majority_judgement_rust::{majority_judgment, majority_judgment_with_options, Options, BuiltinMethod};
+
+/// The system allocator, counting the allocations
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// A poll of `candidates` candidates with `ballots` grades each, on a scale of 7 grades
+fn poll(candidates: usize, ballots: usize) -> BTreeMap<String, Vec<u8>> {
+    (0..candidates as u64)
+        .map(|candidate| {
+            let grades = (0..ballots as u64)
+                .map(|ballot| ((ballot * 2_654_435_761 + candidate * 40_503) >> 7) as u8 % 7)
+                .collect();
+            (format!("Candidate {}", candidate), grades)
+        })
+        .collect()
+}
+
+/// The mean time and number of allocations of `f`, repeated until it took at least 200 ms
+fn measure<F: FnMut()>(mut f: F) -> (Duration, usize) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let mut runs = 0;
+    while runs == 0 || start.elapsed() < Duration::from_millis(200) {
+        f();
+        runs += 1;
+    }
+    (start.elapsed() / runs, (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / runs as usize)
+}
+
+fn main() {
+    println!("{:>10} {:>10} {:>16} {:>14} {:>14} {:>12}", "ballots", "candidates", "method", "total", "per grade", "allocations");
+    for ballots in [10, 10_000, 1_000_000] {
+        for candidates in [2, 10, 100] {
+            if ballots * candidates > 10_000_000 {
+                continue;
+            }
+            let poll_data = poll(candidates, ballots);
+            for method in [BuiltinMethod::MajorityValues, BuiltinMethod::MajorityGauge] {
+                let options = Options::new().method(method);
+                let (time, allocations) = match method {
+                    BuiltinMethod::MajorityValues => measure(|| {
+                        black_box(majority_judgment(black_box(&poll_data)));
+                    }),
+                    _ => measure(|| {
+                        black_box(majority_judgment_with_options(black_box(&poll_data), &options));
+                    }),
+                };
+                println!(
+                    "{:>10} {:>10} {:>16} {:>14.3?} {:>11.2} ns {:>12}",
+                    ballots,
+                    candidates,
+                    format!("{:?}", method),
+                    time,
+                    time.as_nanos() as f64 / (ballots * candidates) as f64,
+                    allocations
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! # no_std dependent
+//! Ranks a tally without `std`, through the API kept by `default-features = false`.
+#![no_std]
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use majority_judgement_rust::{try_majority_judgment_from_tally, RankingError};
+
+/// Function that gives the index of the best candidate of a tally, `counts[candidate][grade]`
+pub fn winner(counts: &[&[u64]]) -> Result<Option<usize>, RankingError> {
+    let tallies: BTreeMap<usize, BTreeMap<usize, u64>> =
+        counts.iter().enumerate().map(|(candidate, row)| (candidate, row.iter().copied().enumerate().collect())).collect();
+    let ranks = try_majority_judgment_from_tally(&tallies)?;
+    Ok(ranks.first().map(|&(&candidate, _)| candidate))
+}
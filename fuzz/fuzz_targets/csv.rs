@@ -0,0 +1,29 @@
+//! Ballots and tally files of any content: loaded or refused with a `CsvError`,
+//! then ranked or refused with a `RankingError`, never panicking
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use majority_judgement_rust::csv::{counts_from_ballots_reader, counts_from_csv, from_csv, parse_number, tally_from_csv};
+use majority_judgement_rust::{try_majority_judgment_from_tally, try_majority_judgment_with_options, Options};
+
+fuzz_target!(|data: &[u8]| {
+    // the first byte picks the delimiter, the others are the file
+    let Some((&delimiter, file)) = data.split_first() else { return };
+    let Ok(file) = std::str::from_utf8(file) else { return };
+    let delimiter = if delimiter % 2 == 0 { ',' } else { ';' };
+
+    if let Ok(poll) = from_csv(file, delimiter, parse_number::<u8>) {
+        if let Ok(poll_data) = poll.poll_data() {
+            let _ = try_majority_judgment_with_options(&poll_data, &Options::new());
+        }
+    }
+    // at most `MAX_EXPANDED_VOTES` grades, whatever the counts of the file
+    if let Ok(poll_data) = tally_from_csv(file, delimiter, parse_number::<u8>) {
+        let _ = try_majority_judgment_with_options(&poll_data, &Options::new());
+    }
+    if let Ok(tallies) = counts_from_csv(file, delimiter, parse_number::<u8>) {
+        let _ = try_majority_judgment_from_tally(&tallies);
+    }
+    if let Ok(tallies) = counts_from_ballots_reader(file.as_bytes(), delimiter, parse_number::<u8>) {
+        let _ = try_majority_judgment_from_tally(&tallies);
+    }
+});
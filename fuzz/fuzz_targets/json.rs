@@ -0,0 +1,24 @@
+//! JSON texts of any content: parsed or refused with a `JsonError`,
+//! then ranked or refused with a `RankingError`, never panicking
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use majority_judgement_rust::json::{from_mieux_voter, parse};
+use majority_judgement_rust::{try_majority_judgment_with_options, Options, Poll};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else { return };
+
+    if parse(text).is_err() {
+        return;
+    }
+    if let Ok(poll) = Poll::<u8>::from_json(text) {
+        if let Ok(poll_data) = poll.poll_data() {
+            let _ = try_majority_judgment_with_options(&poll_data, &Options::new());
+        }
+    }
+    if let Ok(exported) = from_mieux_voter(text) {
+        if let Ok(poll_data) = exported.poll.poll_data() {
+            let _ = try_majority_judgment_with_options(&poll_data, &Options::new());
+        }
+    }
+});
@@ -0,0 +1,53 @@
+//! Poll data and counts of votes of any shape, ranked with any options or refused with a `RankingError`,
+//! never panicking
+#![no_main]
+use std::collections::BTreeMap;
+use libfuzzer_sys::fuzz_target;
+use majority_judgement_rust::{
+    try_majority_judgment_from_tally, try_majority_judgment_from_tally_with_options, try_majority_judgment_with_options,
+    BuiltinMethod, Lexicographic, MedianConvention, Options, Participation,
+};
+
+fuzz_target!(|data: &[u8]| {
+    // the first byte picks the options, the others are the grades and the counts
+    let Some((&settings, bytes)) = data.split_first() else { return };
+    let methods = [
+        BuiltinMethod::MajorityValues,
+        BuiltinMethod::MajorityGauge,
+        BuiltinMethod::UsualJudgment,
+        BuiltinMethod::TypicalJudgment,
+        BuiltinMethod::MeanGrade,
+    ];
+    let mut options: Options<u8, u8> = Options::new().method(methods[settings as usize % methods.len()]);
+    if settings & 0x08 != 0 {
+        options = options.participation(Participation::Proportional);
+    }
+    if settings & 0x10 != 0 {
+        options = options.median(MedianConvention::UpperMedian);
+    }
+    if settings & 0x20 != 0 {
+        options = options.tie_breaker(Lexicographic);
+    }
+    if settings & 0x40 != 0 {
+        options = options.exact_grade_value(|&grade| grade as i64);
+    }
+    if settings & 0x80 != 0 {
+        options = options.none_of_the_above(0);
+    }
+
+    // `candidate grade` pairs: the candidates may have different numbers of grades
+    let mut poll_data: BTreeMap<u8, Vec<u8>> = BTreeMap::new();
+    for pair in bytes.chunks_exact(2) {
+        poll_data.entry(pair[0] % 8).or_default().push(pair[1]);
+    }
+    let _ = try_majority_judgment_with_options(&poll_data, &options);
+
+    // `candidate grade count` records, the count taking 8 bytes: up to `u64::MAX` votes
+    let mut tallies: BTreeMap<u8, BTreeMap<u8, u64>> = BTreeMap::new();
+    for record in bytes.chunks_exact(10) {
+        let count = u64::from_le_bytes(record[2..].try_into().expect("8 bytes"));
+        *tallies.entry(record[0] % 8).or_default().entry(record[1]).or_insert(0) = count;
+    }
+    let _ = try_majority_judgment_from_tally(&tallies);
+    let _ = try_majority_judgment_from_tally_with_options(&tallies, &options);
+});
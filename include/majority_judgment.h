@@ -0,0 +1,70 @@
+/*
+ * Majority judgment: C interface of the majority-judgement-rust crate.
+ *
+ * Build the shared library with `cargo rustc --release --lib --crate-type cdylib --features ffi` and link with
+ * -lmajority_judgement_rust. See src/ffi.rs for the details.
+ */
+#ifndef MAJORITY_JUDGMENT_H
+#define MAJORITY_JUDGMENT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Return codes */
+#define MJ_OK 0
+/* A pointer is null while its length is not zero */
+#define MJ_ERROR_NULL_POINTER (-1)
+/* The ranking method is unknown */
+#define MJ_ERROR_INVALID_METHOD (-2)
+/* The majority values need the same number of votes for every candidate */
+#define MJ_ERROR_UNEQUAL_VOTES (-3)
+/* A candidate has more than UINT64_MAX votes in total */
+#define MJ_ERROR_TOO_MANY_VOTES (-4)
+/* The tally or the result has more values than the memory can address, e.g. candidates * grades overflows */
+#define MJ_ERROR_TOO_LARGE (-5)
+
+/* Ranking methods */
+#define MJ_MAJORITY_VALUES 0
+#define MJ_MAJORITY_GAUGE 1
+#define MJ_USUAL_JUDGMENT 2
+#define MJ_TYPICAL_JUDGMENT 3
+
+/*
+ * Rank the candidates of a tally.
+ *
+ * tally:      candidates * grades counts, row by row: tally[c * grades + g] is the number of
+ *             votes of grade g (0 being the worst) for the candidate c
+ * ranks:      receives the rank of each candidate, 0 being the best; tied candidates share
+ *             the same rank, e.g. 0, 1, 1, 3
+ *
+ * Returns MJ_OK, or a negative error code leaving ranks untouched.
+ */
+int32_t mj_rank(const uint32_t *tally, size_t candidates, size_t grades, size_t *ranks);
+
+/* Like mj_rank, with one of the MJ_* ranking methods */
+int32_t mj_rank_with_method(const uint32_t *tally, size_t candidates, size_t grades, uint32_t method,
+                            size_t *ranks);
+
+/* Like mj_rank_with_method, with 64-bit counts for electorates beyond 4 billion votes */
+int32_t mj_rank64_with_method(const uint64_t *tally, size_t candidates, size_t grades, uint32_t method,
+                              size_t *ranks);
+
+/*
+ * Find the majority grade of each candidate of a tally, its lower median grade.
+ *
+ * majority_grades: receives the index of the majority grade of each candidate,
+ *                  SIZE_MAX for a candidate without any vote
+ *
+ * Returns MJ_OK, or a negative error code leaving majority_grades untouched.
+ */
+int32_t mj_majority_grades(const uint32_t *tally, size_t candidates, size_t grades, size_t *majority_grades);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MAJORITY_JUDGMENT_H */
@@ -0,0 +1,98 @@
+"""Majority judgment from Python, on top of the C interface of the majority-judgement-rust crate.
+
+Build the shared library first:
+
+    cargo rustc --release --lib --crate-type cdylib --features python
+
+The library is looked up in the MJ_LIBRARY environment variable, then in target/release
+of the repository.
+
+>>> majority_judgment({"Pizza": [0, 0, 3, 0, 2, 0, 3, 1, 2, 3], "Chips": [0, 1, 0, 2, 1, 2, 2, 3, 2, 3]})
+[('Chips', 0, 2), ('Pizza', 1, 1)]
+"""
+import ctypes
+import os
+import sys
+
+MJ_OK = 0
+MJ_ERROR_NULL_POINTER = -1
+MJ_ERROR_INVALID_METHOD = -2
+MJ_ERROR_UNEQUAL_VOTES = -3
+MJ_ERROR_TOO_MANY_VOTES = -4
+MJ_ERROR_TOO_LARGE = -5
+
+METHODS = {"majority-values": 0, "majority-gauge": 1, "usual": 2, "typical": 3}
+
+_ERRORS = {
+    MJ_ERROR_NULL_POINTER: "invalid tally",
+    MJ_ERROR_INVALID_METHOD: "unknown ranking method",
+    MJ_ERROR_UNEQUAL_VOTES: "the candidates have different numbers of grades",
+    MJ_ERROR_TOO_MANY_VOTES: "a candidate has more than 2**64 - 1 votes",
+    MJ_ERROR_TOO_LARGE: "the tally is too large for the memory",
+}
+
+
+def _library_path():
+    if "MJ_LIBRARY" in os.environ:
+        return os.environ["MJ_LIBRARY"]
+    name = {"darwin": "libmajority_judgement_rust.dylib", "win32": "majority_judgement_rust.dll"}.get(
+        sys.platform, "libmajority_judgement_rust.so")
+    return os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "target", "release", name)
+
+
+_library = None
+
+
+def _load():
+    global _library
+    if _library is None:
+        library = ctypes.CDLL(_library_path())
+        arguments = [ctypes.POINTER(ctypes.c_uint32), ctypes.c_size_t, ctypes.c_size_t]
+        library.mj_rank_with_method.argtypes = arguments + [ctypes.c_uint32, ctypes.POINTER(ctypes.c_size_t)]
+        library.mj_rank_with_method.restype = ctypes.c_int32
+        library.mj_majority_grades.argtypes = arguments + [ctypes.POINTER(ctypes.c_size_t)]
+        library.mj_majority_grades.restype = ctypes.c_int32
+        _library = library
+    return _library
+
+
+def majority_judgment(poll, method="majority-values"):
+    """Rank the candidates of a poll.
+
+    poll: a dict mapping each candidate to its grades, integers where the greatest is the best
+    method: "majority-values" (default), "majority-gauge", "usual" or "typical"
+
+    Returns a list of (candidate, rank, majority grade) tuples from the best candidate to the
+    worst, rank 0 being the best; tied candidates share the same rank. The majority grade is None
+    for a candidate without any grade.
+    """
+    if method not in METHODS:
+        raise ValueError("unknown ranking method {!r}".format(method))
+    candidates = list(poll)
+    scale = sorted({grade for grades in poll.values() for grade in grades})
+    index = {grade: position for position, grade in enumerate(scale)}
+
+    tally = (ctypes.c_uint32 * max(1, len(candidates) * len(scale)))()
+    for row, candidate in enumerate(candidates):
+        for grade in poll[candidate]:
+            tally[row * len(scale) + index[grade]] += 1
+
+    library = _load()
+    ranks = (ctypes.c_size_t * max(1, len(candidates)))()
+    majority_grades = (ctypes.c_size_t * max(1, len(candidates)))()
+    for code in (library.mj_rank_with_method(tally, len(candidates), len(scale), METHODS[method], ranks),
+                 library.mj_majority_grades(tally, len(candidates), len(scale), majority_grades)):
+        if code != MJ_OK:
+            raise ValueError(_ERRORS.get(code, "error {}".format(code)))
+
+    results = []
+    for row, candidate in enumerate(candidates):
+        majority_grade = scale[majority_grades[row]] if majority_grades[row] < len(scale) else None
+        results.append((candidate, ranks[row], majority_grade))
+    results.sort(key=lambda result: result[1])
+    return results
+
+
+if __name__ == "__main__":
+    import doctest
+    doctest.testmod()
@@ -0,0 +1,90 @@
+//! # Abstentions
+//! Incomplete ballots: a voter may leave a candidate without grade.
+//! Majority judgment conventionally treats a missing grade as the worst one ("to judge is to grade:
+//! no grade is Reject"), some polls rather exclude it from the count of the candidate.
+use std::collections::BTreeMap;
+
+/// How to count a missing grade
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum AbstentionPolicy<G> {
+    /// A missing grade counts as the worst grade given in the whole poll
+    #[default]
+    WorstGrade,
+    /// A missing grade is not counted: the candidates may then have different numbers of grades,
+    /// which only the methods based on shares (majority gauge, usual and typical judgment) can rank.
+    /// With weighted ballots, the grades would no longer match the weights: prefer the other policies.
+    Exclude,
+    /// A missing grade counts as the given grade
+    Custom(G),
+}
+
+/// Function that replaces or removes the missing grades of a poll
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<String, Vec<Option<G>>> with the poll data, None being an abstention
+/// * `policy`: how to count a missing grade
+///
+/// # Returns
+/// * `BTreeMap<String, Vec<G>>`: the poll data without abstentions
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{resolve_abstentions, AbstentionPolicy};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![Some(2), None, Some(1)]);
+/// poll_data.insert("Chips".to_string(), vec![Some(3), Some(0), None]);
+///
+/// let resolved = resolve_abstentions(&poll_data, &AbstentionPolicy::WorstGrade);
+/// assert_eq!(resolved["Pizza"], vec![2, 0, 1]);
+/// let resolved = resolve_abstentions(&poll_data, &AbstentionPolicy::Exclude);
+/// assert_eq!(resolved["Chips"], vec![3, 0]);
+/// ```
+pub fn resolve_abstentions<G: Ord + Clone>(
+    poll_data: &BTreeMap<String, Vec<Option<G>>>,
+    policy: &AbstentionPolicy<G>,
+) -> BTreeMap<String, Vec<G>> {
+    let replacement = match policy {
+        AbstentionPolicy::WorstGrade => poll_data.values().flatten().flatten().min().cloned(),
+        AbstentionPolicy::Exclude => None,
+        AbstentionPolicy::Custom(grade) => Some(grade.clone()),
+    };
+
+    poll_data
+        .iter()
+        .map(|(candidate, grades)| {
+            let grades = grades
+                .iter()
+                .filter_map(|grade| grade.clone().or_else(|| replacement.clone()))
+                .collect();
+            (candidate.clone(), grades)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_resolve_abstentions() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![Some(2), None, Some(1)]);
+        poll_data.insert("Chips".to_string(), vec![Some(3), Some(1), None]);
+
+        let resolved = resolve_abstentions(&poll_data, &AbstentionPolicy::WorstGrade);
+        assert_eq!(resolved["Pizza"], vec![2, 1, 1]);
+        assert_eq!(resolved["Chips"], vec![3, 1, 1]);
+
+        let resolved = resolve_abstentions(&poll_data, &AbstentionPolicy::Custom(0));
+        assert_eq!(resolved["Chips"], vec![3, 1, 0]);
+
+        let resolved = resolve_abstentions(&poll_data, &AbstentionPolicy::Exclude);
+        assert_eq!(resolved["Pizza"], vec![2, 1]);
+
+        // nobody graded anything: there is no worst grade to count
+        let mut empty: BTreeMap<String, Vec<Option<u8>>> = BTreeMap::new();
+        empty.insert("Pizza".to_string(), vec![None, None]);
+        assert_eq!(resolve_abstentions(&empty, &AbstentionPolicy::WorstGrade)["Pizza"], Vec::<u8>::new());
+    }
+}
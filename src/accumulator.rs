@@ -0,0 +1,583 @@
+//! # Incremental tally
+//! A live poll receives its ballots one at a time. Instead of transposing all the ballots again
+//! whenever a vote arrives, a `TallyAccumulator` keeps the number of votes of each grade for each
+//! candidate: adding or withdrawing a ballot updates the counts, and the ranking is computed from them.
+//!
+//! A long-running poll saves the counts with `TallyAccumulator::save_snapshot` and resumes from them
+//! with `TallyAccumulator::load_snapshot`, e.g. after a restart, without replaying the ballots.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display, Write};
+use std::io;
+use std::path::Path;
+use crate::grade_range::GradeRange;
+use crate::judgment::{median_shares, total_votes, TOO_MANY_VOTES};
+use crate::mj::{rank_candidates, OptionsMethod};
+use crate::options::{GradeValue, BuiltinMethod, TieOrder};
+use crate::poll::{ballot_issues, Ballot, BallotIssue};
+use crate::ranking::Ranking;
+use crate::ranking_method::RankingMethod;
+use crate::receipt::{escape, unescape};
+
+/// The first line of a snapshot, with the version of its format
+const SNAPSHOT_HEADER: &str = "majority-judgment-snapshot 1";
+
+/// The number of votes of each grade for each candidate, updated ballot by ballot
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{Ballot, TallyAccumulator};
+/// let mut tally = TallyAccumulator::new(["Pizza", "Chips"]);
+/// tally.add_ballot(&Ballot::new().grade("Pizza", 0).grade("Chips", 2)).unwrap();
+/// tally.add_ballot(&Ballot::new().grade("Pizza", 3).grade("Chips", 1)).unwrap();
+/// assert_eq!(*tally.current_ranking().winners()[0], "Chips");
+///
+/// tally.add_ballot(&Ballot::new().grade("Pizza", 3).grade("Chips", 0)).unwrap();
+/// assert_eq!(*tally.current_ranking().winners()[0], "Pizza");
+/// ```
+#[derive(Debug, Clone)]
+pub struct TallyAccumulator<G> {
+    candidates: Vec<String>,
+    tallies: BTreeMap<String, BTreeMap<G, u64>>,
+    ballots: u64,
+    method: BuiltinMethod,
+    grade_value: Option<fn(&G) -> f64>,
+    exact_grade_value: Option<fn(&G) -> i64>,
+    grade_range: Option<GradeRange<G>>,
+}
+
+impl<G: Ord + Clone> TallyAccumulator<G> {
+    /// Create an accumulator without any ballot, ranking with the majority values
+    ///
+    /// # Arguments
+    /// * `candidates`: the candidates, in their registration order. Repeated names are ignored.
+    pub fn new<I, S>(candidates: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut seen = BTreeSet::new();
+        let candidates: Vec<String> = candidates
+            .into_iter()
+            .map(Into::into)
+            .filter(|candidate: &String| seen.insert(candidate.clone()))
+            .collect();
+        let tallies = candidates.iter().map(|candidate| (candidate.clone(), BTreeMap::new())).collect();
+        TallyAccumulator {
+            candidates,
+            tallies,
+            ballots: 0,
+            method: BuiltinMethod::default(),
+            grade_value: None,
+            exact_grade_value: None,
+            grade_range: None,
+        }
+    }
+
+    /// Set the ranking method used by `current_ranking`, builder style
+    pub fn method(mut self, method: BuiltinMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Set the number a grade counts for with `BuiltinMethod::MeanGrade`, builder style
+    pub fn grade_value(mut self, grade_value: fn(&G) -> f64) -> Self {
+        self.grade_value = Some(grade_value);
+        self
+    }
+
+    /// Set the integer a grade counts for with `BuiltinMethod::MeanGrade`, the averages being exact, builder style
+    pub fn exact_grade_value(mut self, exact_grade_value: fn(&G) -> i64) -> Self {
+        self.exact_grade_value = Some(exact_grade_value);
+        self
+    }
+
+    /// Set the grades the accumulator counts, builder style: the ballots giving other grades are refused,
+    /// or their grades are clamped, see `GradeRange`
+    pub fn grade_range(mut self, grade_range: GradeRange<G>) -> Self {
+        self.grade_range = Some(grade_range);
+        self
+    }
+
+    /// The candidates in their registration order
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    /// The number of ballots counted so far, whatever their weights, a ballot of weight 0 counting for nothing
+    pub fn ballots(&self) -> u64 {
+        self.ballots
+    }
+
+    /// The number of votes (or total weight) of each grade given to a candidate, None for an unknown candidate
+    pub fn tally(&self, candidate: &str) -> Option<&BTreeMap<G, u64>> {
+        self.tallies.get(candidate)
+    }
+
+    /// Function that counts a ballot, with its weight
+    ///
+    /// # Arguments
+    /// * `ballot`: a ballot grading each candidate exactly once
+    ///
+    /// # Returns
+    /// * `Result<(), Vec<BallotIssue>>`: the issues of the ballot if it is invalid, in which case nothing is counted,
+    ///   e.g. `BallotIssue::TooManyVotes` if a candidate would have more than `u64::MAX` votes
+    pub fn add_ballot(&mut self, ballot: &Ballot<G>) -> Result<(), Vec<BallotIssue>> {
+        let mut issues = ballot_issues(&self.candidates, ballot, self.grade_range.as_ref());
+        if issues.is_empty() {
+            let weight = u64::from(ballot.weight());
+            let full = ballot.grades().iter().filter(|(candidate, _)| {
+                self.tallies.get(candidate).is_some_and(|tally| total_votes(tally.values()).and_then(|total| total.checked_add(weight)).is_none())
+            });
+            issues.extend(full.map(|(candidate, _)| BallotIssue::TooManyVotes(candidate.clone())));
+        }
+        if !issues.is_empty() {
+            return Err(issues);
+        }
+        if ballot.weight() == 0 {
+            return Ok(());
+        }
+
+        for (candidate, grade) in ballot.grades() {
+            let grade = self.counted(grade);
+            if let Some(tally) = self.tallies.get_mut(candidate) {
+                *tally.entry(grade).or_insert(0) += u64::from(ballot.weight());
+            }
+        }
+        self.ballots += 1;
+        Ok(())
+    }
+
+    /// Function that withdraws a ballot counted before, e.g. a voter changing their mind
+    ///
+    /// # Arguments
+    /// * `ballot`: the ballot as it was added, with the same weight
+    ///
+    /// # Returns
+    /// * `Result<(), Vec<BallotIssue>>`: the issues of the ballot if it is invalid or was not counted,
+    ///   in which case nothing is withdrawn
+    pub fn remove_ballot(&mut self, ballot: &Ballot<G>) -> Result<(), Vec<BallotIssue>> {
+        let mut issues = ballot_issues(&self.candidates, ballot, self.grade_range.as_ref());
+        for (candidate, grade) in ballot.grades() {
+            let grade = self.counted(grade);
+            let counted = self.tallies.get(candidate).and_then(|tally| tally.get(&grade)).copied().unwrap_or(0);
+            if counted < u64::from(ballot.weight()) && self.tallies.contains_key(candidate) {
+                issues.push(BallotIssue::UncountedGrade(candidate.clone()));
+            }
+        }
+        if !issues.is_empty() {
+            return Err(issues);
+        }
+        if ballot.weight() == 0 {
+            return Ok(());
+        }
+
+        for (candidate, grade) in ballot.grades() {
+            let grade = self.counted(grade);
+            if let Some(tally) = self.tallies.get_mut(candidate) {
+                if let Some(count) = tally.get_mut(&grade) {
+                    *count -= u64::from(ballot.weight());
+                    if *count == 0 {
+                        tally.remove(&grade);
+                    }
+                }
+            }
+        }
+        self.ballots = self.ballots.saturating_sub(1);
+        Ok(())
+    }
+
+    /// The accumulator with the same candidates, method and grade range, without any ballot
+    pub(crate) fn emptied(&self) -> Self {
+        let mut empty = self.clone();
+        empty.tallies.values_mut().for_each(BTreeMap::clear);
+        empty.ballots = 0;
+        empty
+    }
+
+    /// Function that adds the counts of another accumulator of the same candidates
+    ///
+    /// # Panics
+    /// * if a candidate would have more than `u64::MAX` votes, which `ConcurrentTally` prevents
+    pub(crate) fn absorb(&mut self, other: &Self) {
+        for (candidate, tally) in &other.tallies {
+            let total = self.tallies.entry(candidate.clone()).or_default();
+            for (grade, &count) in tally {
+                let sum = total.entry(grade.clone()).or_insert(0);
+                *sum = sum.checked_add(count).expect(TOO_MANY_VOTES);
+            }
+        }
+        self.ballots += other.ballots;
+    }
+
+    /// The largest number of votes of a candidate, with this candidate, None without any candidate
+    pub(crate) fn fullest(&self) -> Option<(&String, u64)> {
+        self.tallies
+            .iter()
+            .map(|(candidate, tally)| (candidate, total_votes(tally.values()).unwrap_or(u64::MAX)))
+            .max_by_key(|&(_, total)| total)
+    }
+
+    /// Function that gives the grade counted for a grade of a valid ballot, clamped to the grade range
+    fn counted(&self, grade: &G) -> G {
+        self.grade_range.as_ref().and_then(|range| range.counted(grade)).unwrap_or_else(|| grade.clone())
+    }
+
+    /// Function that ranks the candidates from the ballots counted so far
+    ///
+    /// # Returns
+    /// * `Ranking`: the candidates from the best to the worst, with their rank, tie status and majority grade
+    pub fn current_ranking(&self) -> Ranking<'_, String, G> {
+        self.ranking_with_method(&self.options_method())
+    }
+
+    /// The ranking method of the accumulator, with its grade values
+    pub(crate) fn options_method(&self) -> OptionsMethod<G> {
+        OptionsMethod::from_tally(self.method, GradeValue::new(self.grade_value, self.exact_grade_value))
+    }
+
+    /// Function that ranks the counted votes with a ranking method of another crate, see `RankingMethod`
+    ///
+    /// # Arguments
+    /// * `method`: the ranking method, used instead of the method of the accumulator
+    ///
+    /// # Returns
+    /// * `Ranking`: the candidates by decreasing merit, with their rank, tie status and majority grade
+    pub fn ranking_with_method<M: RankingMethod<G>>(&self, method: &M) -> Ranking<'_, String, G> {
+        self.ranking_reporting(method, |_| {})
+    }
+
+    /// Function that ranks the counted votes, calling `evaluated` with the number of candidates evaluated so far
+    pub(crate) fn ranking_reporting<M: RankingMethod<G>>(&self, method: &M, mut evaluated: impl FnMut(usize)) -> Ranking<'_, String, G> {
+        let merits = self
+            .tallies
+            .iter()
+            .enumerate()
+            .map(|(index, (candidate, tally))| {
+                let merit = method.merit(tally);
+                evaluated(index + 1);
+                (candidate, merit)
+            })
+            .collect();
+        rank_candidates::<String, G, _>(merits, &BTreeMap::new(), None, TieOrder::default())
+            .with_majority_grades(|candidate| median_shares(&self.tallies[candidate]))
+    }
+}
+
+impl<G: Ord + Clone + Display> TallyAccumulator<G> {
+    /// Function that writes the state of the accumulator as text, to resume it later
+    ///
+    /// The snapshot holds the candidates, the counts of their grades, the number of ballots and the method.
+    /// The `grade_value` functions and the grade range are not written: they are set again after loading.
+    ///
+    /// # Returns
+    /// * `String`: the header line, then the method, the number of ballots and one line per candidate
+    ///   with its escaped name and its `grade:count` pairs, in the registration order
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::{Ballot, TallyAccumulator};
+    /// let mut tally = TallyAccumulator::new(["Pizza", "Chips"]);
+    /// tally.add_ballot(&Ballot::new().grade("Pizza", 0u8).grade("Chips", 2)).unwrap();
+    ///
+    /// let snapshot = tally.to_snapshot();
+    /// let parse = |grade: &str| grade.parse::<u8>().map_err(|error| error.to_string());
+    /// assert_eq!(TallyAccumulator::from_snapshot(&snapshot, parse).unwrap(), tally);
+    /// ```
+    pub fn to_snapshot(&self) -> String {
+        let mut snapshot = String::new();
+        let _ = writeln!(snapshot, "{}", SNAPSHOT_HEADER);
+        let _ = writeln!(snapshot, "method {:?}", self.method);
+        let _ = writeln!(snapshot, "ballots {}", self.ballots);
+        for candidate in &self.candidates {
+            let _ = write!(snapshot, "candidate {}", escape(candidate));
+            for (grade, count) in &self.tallies[candidate] {
+                let _ = write!(snapshot, " {}:{}", escape(&grade.to_string()), count);
+            }
+            snapshot.push('\n');
+        }
+        snapshot
+    }
+
+    /// Function that saves the snapshot to a file, replacing it atomically
+    ///
+    /// The snapshot is written to `<path>.tmp` then renamed, so a crash while saving leaves the previous snapshot.
+    ///
+    /// # Arguments
+    /// * `path`: the file of the snapshot
+    ///
+    /// # Returns
+    /// * `Result<(), SnapshotError>`: the error of the file system, if any
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        std::fs::write(&temporary, self.to_snapshot())?;
+        std::fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+impl<G: Ord + Clone> TallyAccumulator<G> {
+    /// Function that resumes an accumulator from a snapshot written by `to_snapshot`
+    ///
+    /// # Arguments
+    /// * `snapshot`: the text of the snapshot
+    /// * `parse_grade`: the function reading a grade, e.g. `csv::parse_number`
+    ///
+    /// # Returns
+    /// * `Result<TallyAccumulator<G>, SnapshotError>`: the accumulator, or the first invalid line, e.g. a candidate
+    ///   whose votes cannot come from `ballots` ballots of weight 1 to `u32::MAX`
+    pub fn from_snapshot<F>(snapshot: &str, parse_grade: F) -> Result<Self, SnapshotError>
+    where
+        F: Fn(&str) -> Result<G, String>,
+    {
+        let invalid = |line: usize, message: String| SnapshotError::Invalid { line, message };
+        let mut lines = snapshot.lines().enumerate().map(|(index, line)| (index + 1, line));
+        match lines.next() {
+            Some((_, SNAPSHOT_HEADER)) => {}
+            _ => return Err(invalid(1, format!("expected {:?}", SNAPSHOT_HEADER))),
+        }
+
+        let mut accumulator = TallyAccumulator::new(Vec::<String>::new());
+        let mut candidate_lines = Vec::new();
+        for (number, line) in lines {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("method") => {
+                    accumulator.method = match fields.next() {
+                        Some("MajorityValues") => BuiltinMethod::MajorityValues,
+                        Some("MajorityGauge") => BuiltinMethod::MajorityGauge,
+                        Some("UsualJudgment") => BuiltinMethod::UsualJudgment,
+                        Some("TypicalJudgment") => BuiltinMethod::TypicalJudgment,
+                        Some("MeanGrade") => BuiltinMethod::MeanGrade,
+                        method => return Err(invalid(number, format!("unknown method {:?}", method.unwrap_or("")))),
+                    }
+                }
+                Some("ballots") => {
+                    accumulator.ballots = fields
+                        .next()
+                        .and_then(|ballots| ballots.parse().ok())
+                        .ok_or_else(|| invalid(number, "the number of ballots is not an integer".to_string()))?;
+                }
+                Some("candidate") => {
+                    let candidate = fields
+                        .next()
+                        .and_then(unescape)
+                        .ok_or_else(|| invalid(number, "invalid candidate name".to_string()))?;
+                    let mut tally = BTreeMap::new();
+                    for field in fields {
+                        let (grade, count) = field
+                            .split_once(':')
+                            .ok_or_else(|| invalid(number, format!("expected grade:count, found {:?}", field)))?;
+                        let grade = unescape(grade)
+                            .ok_or_else(|| invalid(number, format!("invalid grade {:?}", grade)))
+                            .and_then(|grade| parse_grade(&grade).map_err(|message| invalid(number, message)))?;
+                        let count: u64 = count
+                            .parse()
+                            .ok()
+                            .filter(|&count| count > 0)
+                            .ok_or_else(|| invalid(number, format!("invalid count {:?}", count)))?;
+                        if tally.insert(grade, count).is_some() {
+                            return Err(invalid(number, format!("grade {:?} counted twice", field)));
+                        }
+                    }
+                    if total_votes(tally.values()).is_none() {
+                        return Err(invalid(number, format!("candidate {:?} has more than {} votes", candidate, u64::MAX)));
+                    }
+                    if accumulator.tallies.insert(candidate.clone(), tally).is_some() {
+                        return Err(invalid(number, format!("candidate {:?} listed twice", candidate)));
+                    }
+                    accumulator.candidates.push(candidate);
+                    candidate_lines.push(number);
+                }
+                Some(other) => return Err(invalid(number, format!("unknown line {:?}", other))),
+                None => {}
+            }
+        }
+
+        // every ballot gives each candidate between 1 and u32::MAX votes
+        let ballots = accumulator.ballots;
+        for (candidate, number) in accumulator.candidates.iter().zip(candidate_lines) {
+            let total = total_votes(accumulator.tallies[candidate].values()).unwrap_or(u64::MAX);
+            if total < ballots || total as u128 > ballots as u128 * u32::MAX as u128 {
+                return Err(invalid(number, format!("the {} votes of candidate {:?} cannot come from {} ballots", total, candidate, ballots)));
+            }
+        }
+        Ok(accumulator)
+    }
+
+    /// Function that resumes an accumulator from a file written by `save_snapshot`
+    ///
+    /// # Arguments
+    /// * `path`: the file of the snapshot
+    /// * `parse_grade`: the function reading a grade, e.g. `csv::parse_number`
+    ///
+    /// # Returns
+    /// * `Result<TallyAccumulator<G>, SnapshotError>`: the accumulator, or why the file cannot be read
+    pub fn load_snapshot<P, F>(path: P, parse_grade: F) -> Result<Self, SnapshotError>
+    where
+        P: AsRef<Path>,
+        F: Fn(&str) -> Result<G, String>,
+    {
+        Self::from_snapshot(&std::fs::read_to_string(path)?, parse_grade)
+    }
+}
+
+/// A snapshot which cannot be saved or loaded
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The file cannot be read or written
+    Io(io::Error),
+    /// A line of the snapshot is invalid, the lines being numbered from 1
+    Invalid { line: usize, message: String },
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(error) => write!(f, "{}", error),
+            SnapshotError::Invalid { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(error: io::Error) -> Self {
+        SnapshotError::Io(error)
+    }
+}
+
+/// Two accumulators are equal when they counted the same votes for the same candidates with the same method
+/// (the `grade_value` functions cannot be compared, and the grade range is ignored like in the snapshots)
+impl<G: PartialEq> PartialEq for TallyAccumulator<G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.candidates == other.candidates
+            && self.tallies == other.tallies
+            && self.ballots == other.ballots
+            && self.method == other.method
+    }
+}
+
+impl<G: Eq> Eq for TallyAccumulator<G> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grade_range::OutOfRange;
+    use crate::mj::majority_judgment;
+
+    #[test]
+    fn calling_add_and_remove_ballot() {
+        let ballots = [
+            Ballot::new().grade("Pizza", 1).grade("Chips", 2).grade("Pasta", 0),
+            Ballot::new().grade("Pizza", 3).grade("Chips", 2).grade("Pasta", 2),
+            Ballot::new().grade("Pizza", 0).grade("Chips", 1).grade("Pasta", 3),
+            Ballot::new().grade("Pizza", 2).grade("Chips", 0).grade("Pasta", 3),
+        ];
+        let mut tally = TallyAccumulator::new(["Pizza", "Chips", "Pasta"]);
+        for ballot in ballots.iter() {
+            tally.add_ballot(ballot).unwrap();
+        }
+
+        // the same ranking as transposing the ballots
+        let mut poll_data: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        for ballot in ballots.iter() {
+            for (candidate, grade) in ballot.grades() {
+                poll_data.entry(candidate.clone()).or_default().push(*grade);
+            }
+        }
+        assert_eq!(tally.current_ranking().to_vec(), majority_judgment(&poll_data));
+
+        tally.remove_ballot(&ballots[2]).unwrap();
+        assert_eq!(tally.ballots(), 3);
+        assert_eq!(tally.tally("Pizza"), Some(&BTreeMap::from([(1, 1), (2, 1), (3, 1)])));
+        assert_eq!(tally.remove_ballot(&ballots[2]),
+                   Err(vec![BallotIssue::UncountedGrade("Pizza".to_string()),
+                            BallotIssue::UncountedGrade("Chips".to_string())]));
+
+        assert_eq!(tally.add_ballot(&Ballot::new().grade("Pizza", 1)),
+                   Err(vec![BallotIssue::MissingGrade("Chips".to_string()),
+                            BallotIssue::MissingGrade("Pasta".to_string())]));
+        assert_eq!(tally.ballots(), 3);
+
+        // the counts stop at u64::MAX votes
+        let parse = |text: &str| text.parse::<u8>().map_err(|error| error.to_string());
+        let full = format!("{}\nballots {}\ncandidate Pizza 0:{}\ncandidate Chips 1:{}\n", SNAPSHOT_HEADER, u32::MAX as u64 + 2, u64::MAX - 1, u64::MAX - 1);
+        let mut tally = TallyAccumulator::from_snapshot(&full, parse).unwrap();
+        let ballot = Ballot::new().grade("Pizza", 1).grade("Chips", 1);
+        tally.add_ballot(&ballot).unwrap();
+        assert_eq!(tally.add_ballot(&ballot), Err(vec![BallotIssue::TooManyVotes("Pizza".to_string()), BallotIssue::TooManyVotes("Chips".to_string())]));
+        assert_eq!(tally.current_ranking().winners(), vec!["Chips"]);
+        let overflow = format!("{}\ncandidate Pizza 0:{} 1:1\n", SNAPSHOT_HEADER, u64::MAX);
+        assert!(matches!(TallyAccumulator::from_snapshot(&overflow, parse), Err(SnapshotError::Invalid { line: 2, .. })));
+    }
+
+    #[test]
+    fn calling_current_ranking() {
+        let mut tally = TallyAccumulator::new(["Pizza", "Chips"]).method(BuiltinMethod::MajorityGauge);
+        assert!(tally.current_ranking().has_ties());
+
+        tally.add_ballot(&Ballot::new().grade("Pizza", 2).grade("Chips", 1).with_weight(3)).unwrap();
+        tally.add_ballot(&Ballot::new().grade("Pizza", 0).grade("Chips", 3).with_weight(2)).unwrap();
+        assert_eq!(tally.current_ranking().to_vec(), vec![(&"Pizza".to_string(), 0), (&"Chips".to_string(), 1)]);
+    }
+
+    #[test]
+    fn calling_add_ballot_with_grade_range() {
+        let range = GradeRange::between(0, 6).policy(OutOfRange::Clamp);
+        let mut tally = TallyAccumulator::new(["Pizza"]).grade_range(range);
+        tally.add_ballot(&Ballot::new().grade("Pizza", 255)).unwrap();
+        assert_eq!(tally.tally("Pizza"), Some(&BTreeMap::from([(6, 1)])));
+        tally.remove_ballot(&Ballot::new().grade("Pizza", 255)).unwrap();
+        assert_eq!(tally.ballots(), 0);
+
+        let mut strict = TallyAccumulator::new(["Pizza"]).grade_range(GradeRange::between(0, 6));
+        let issues = strict.add_ballot(&Ballot::new().grade("Pizza", 7)).unwrap_err();
+        assert_eq!(issues, vec![BallotIssue::OutOfRange("Pizza".to_string())]);
+    }
+
+    #[test]
+    fn calling_snapshot() {
+        let mut tally = TallyAccumulator::new(["Bike lanes", "Parks: 50%"]).method(BuiltinMethod::UsualJudgment);
+        tally.add_ballot(&Ballot::new().grade("Bike lanes", 2u8).grade("Parks: 50%", 1).with_weight(3)).unwrap();
+        tally.add_ballot(&Ballot::new().grade("Bike lanes", 0).grade("Parks: 50%", 1)).unwrap();
+
+        let snapshot = tally.to_snapshot();
+        assert_eq!(
+            snapshot,
+            "majority-judgment-snapshot 1\nmethod UsualJudgment\nballots 2\n\
+             candidate Bike%20lanes 0:1 2:3\ncandidate Parks%3A%2050%25 1:4\n"
+        );
+        let parse = |text: &str| text.parse::<u8>().map_err(|error| error.to_string());
+        let resumed = TallyAccumulator::from_snapshot(&snapshot, parse).unwrap();
+        assert_eq!(resumed, tally);
+        assert_eq!(resumed.current_ranking().to_vec(), tally.current_ranking().to_vec());
+
+        let path = std::env::temp_dir().join(format!("majority-judgment-snapshot-{}", std::process::id()));
+        tally.save_snapshot(&path).unwrap();
+        assert_eq!(TallyAccumulator::load_snapshot(&path, parse).unwrap(), tally);
+        std::fs::remove_file(&path).unwrap();
+
+        let error = TallyAccumulator::from_snapshot(&snapshot.replace("1:4", "1:0"), parse).unwrap_err();
+        assert_eq!(error.to_string(), "line 5: invalid count \"0\"");
+        assert!(TallyAccumulator::from_snapshot("ballots 2", parse).is_err());
+        // the number of ballots agrees with the votes of the candidates
+        let error = TallyAccumulator::from_snapshot(&snapshot.replace("ballots 2", "ballots 5"), parse).unwrap_err();
+        assert_eq!(error.to_string(), "line 4: the 4 votes of candidate \"Bike lanes\" cannot come from 5 ballots");
+        let error = TallyAccumulator::from_snapshot(&snapshot.replace("ballots 2\n", ""), parse).unwrap_err();
+        assert_eq!(error.to_string(), "line 3: the 4 votes of candidate \"Bike lanes\" cannot come from 0 ballots");
+        let heavy = format!("{}\nballots 1\ncandidate Pizza 0:{}\n", SNAPSHOT_HEADER, u32::MAX as u64 + 1);
+        assert!(TallyAccumulator::from_snapshot(&heavy, parse).is_err());
+
+        // a ballot of weight 0 counts for nothing, and the snapshot stays valid
+        let mut weightless = tally.clone();
+        let ballot = Ballot::new().grade("Bike lanes", 1).grade("Parks: 50%", 3).with_weight(0);
+        weightless.add_ballot(&ballot).unwrap();
+        assert_eq!(weightless, tally);
+        weightless.remove_ballot(&ballot).unwrap();
+        assert_eq!(TallyAccumulator::from_snapshot(&weightless.to_snapshot(), parse).unwrap(), tally);
+        assert!(TallyAccumulator::from_snapshot(&snapshot.replace("2:3", "x:3"), parse).is_err());
+    }
+}
@@ -0,0 +1,411 @@
+//! # Sensitivity analysis
+//! How robust the winner of a poll is: the fewest ballots which would let another candidate rank before it.
+//! * `additions_to_flip`: new ballots, each grading the challenger with the best grade of the scale
+//!   and the winner with the worst one
+//! * `changes_to_flip`: existing ballots regraded the same way
+//!
+//! A small margin compared to the number of ballots suggests a recount, a large one that errors
+//! in the count cannot change the result.
+//!
+//! The winner is the first candidate of `majority_judgment_ranking`. Only the winner and the challenger
+//! are regraded: the other candidates cannot keep the winner first once the challenger ranks before it.
+//!
+//! `ranking_confidence` measures the uncertainty of a preliminary result instead: how often each candidate
+//! holds each rank when the ballots are resampled (bootstrap).
+//!
+//! `stats` describes the grades of each candidate with the usual statistics (mean, median, mode,
+//! standard deviation and skewness), the context a report gives alongside the ranking.
+use std::collections::BTreeMap;
+use crate::judgment::median_shares;
+use crate::majority_value::MajorityValue;
+use crate::mj::{check_poll_length, compute_frequency_of_grades, majority_judgment, majority_judgment_ranking};
+use crate::tie_breaker::splitmix64;
+
+/// The fewest ballots letting a challenger rank before the winner, see `additions_to_flip` and `changes_to_flip`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Margin<'a, K> {
+    /// The candidate ranked first
+    pub winner: &'a K,
+    /// The candidate overtaking the winner with the fewest ballots, the highest ranked one if several do
+    pub challenger: &'a K,
+    /// The number of ballots
+    pub ballots: u64,
+}
+
+/// Function that computes the fewest new ballots letting another candidate rank before the winner
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `worst`, `best`: the worst and the best grades of the scale, given by the new ballots
+///
+/// # Returns
+/// * `Option<Margin>`: the winner, the challenger and the number of new ballots,
+///   None if the poll has less than two candidates or `best` is not above `worst`
+///
+/// # Panics
+/// * if the candidates have different numbers of grades
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::analysis::additions_to_flip;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![1, 2, 2, 3, 3]);
+/// poll_data.insert("Chips", vec![0, 1, 2, 2, 3]);
+///
+/// let margin = additions_to_flip(&poll_data, &0, &3).unwrap();
+/// assert_eq!((margin.winner, margin.challenger, margin.ballots), (&"Pizza", &"Chips", 2));
+/// ```
+pub fn additions_to_flip<'a, K: Ord, G: Ord + Clone>(poll_data: &'a BTreeMap<K, Vec<G>>, worst: &G, best: &G) -> Option<Margin<'a, K>> {
+    margin(poll_data, worst, best, |winner, challenger, ballots| {
+        let mut winner = winner.clone();
+        let mut challenger = challenger.clone();
+        *winner.entry(worst.clone()).or_insert(0) += ballots;
+        *challenger.entry(best.clone()).or_insert(0) += ballots;
+        (winner, challenger)
+    }, |grades| grades + 1)
+}
+
+/// Function that computes the fewest changed ballots letting another candidate rank before the winner
+///
+/// A changed ballot grades the challenger with the best grade and the winner with the worst one.
+/// The count supposes the changed ballots are at once the ones grading the challenger the lowest and
+/// the winner the highest: when no ballot does both, more changes are needed, so fewer changes than
+/// the margin can never overtake the winner.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `worst`, `best`: the worst and the best grades of the scale, given by the changed ballots
+///
+/// # Returns
+/// * `Option<Margin>`: the winner, the challenger and the number of changed ballots,
+///   None if the poll has less than two candidates, `best` is not above `worst` or no change can overtake
+///   the winner, e.g. without ballots
+///
+/// # Panics
+/// * if the candidates have different numbers of grades
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::analysis::changes_to_flip;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![3, 3, 3, 3, 3]);
+/// poll_data.insert("Chips", vec![0, 0, 0, 0, 0]);
+///
+/// let margin = changes_to_flip(&poll_data, &0, &3).unwrap();
+/// assert_eq!(margin.ballots, 3);
+/// ```
+pub fn changes_to_flip<'a, K: Ord, G: Ord + Clone>(poll_data: &'a BTreeMap<K, Vec<G>>, worst: &G, best: &G) -> Option<Margin<'a, K>> {
+    margin(poll_data, worst, best, |winner, challenger, ballots| {
+        (regrade(winner, ballots, true, worst), regrade(challenger, ballots, false, best))
+    }, |grades| grades)
+}
+
+/// Function that finds the challenger overtaking the winner with the fewest ballots
+///
+/// # Arguments
+/// * `poll_data`: the poll data
+/// * `worst`, `best`: the worst and the best grades of the scale
+/// * `apply`: the tallies of the winner and of the challenger once `ballots` ballots are added or changed
+/// * `limit`: the number of ballots sure to overtake the winner, from the number of grades of a candidate
+fn margin<'a, K, G, F>(
+    poll_data: &'a BTreeMap<K, Vec<G>>,
+    worst: &G,
+    best: &G,
+    apply: F,
+    limit: impl Fn(u64) -> u64,
+) -> Option<Margin<'a, K>>
+where
+    K: Ord,
+    G: Ord + Clone,
+    F: Fn(&BTreeMap<G, u64>, &BTreeMap<G, u64>, u64) -> (BTreeMap<G, u64>, BTreeMap<G, u64>),
+{
+    if poll_data.len() < 2 || best <= worst {
+        return None;
+    }
+    let _ = check_poll_length(poll_data);
+
+    let ranking = majority_judgment_ranking(poll_data);
+    let winner = ranking.candidates()[0].candidate;
+    let winner_tally = compute_frequency_of_grades(&poll_data[winner]);
+    let upper = limit(poll_data[winner].len() as u64);
+
+    let mut fewest: Option<Margin<'a, K>> = None;
+    for ranked in ranking.candidates().iter().skip(1) {
+        let challenger_tally = compute_frequency_of_grades(&poll_data[ranked.candidate]);
+        let overtakes = |ballots: u64| {
+            let (winner, challenger) = apply(&winner_tally, &challenger_tally, ballots);
+            MajorityValue::from_tally(&challenger) > MajorityValue::from_tally(&winner)
+        };
+        // e.g. a challenger tied with the winner and no ballot to change
+        if !overtakes(upper) {
+            continue;
+        }
+        // the more ballots, the better the challenger and the worse the winner: bisect the fewest ballots
+        let (mut low, mut high) = (0, upper);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if overtakes(middle) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        if fewest.as_ref().is_none_or(|fewest| low < fewest.ballots) {
+            fewest = Some(Margin { winner, challenger: ranked.candidate, ballots: low });
+        }
+    }
+    fewest
+}
+
+/// How often each candidate held each rank over the resampled polls, see `ranking_confidence`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Confidence<'a, K> {
+    /// The number of resampled polls
+    pub iterations: usize,
+    /// For each candidate, the number of resampled polls giving it rank 0, rank 1, ...
+    /// Tied candidates share the same rank, as in `majority_judgment`.
+    pub ranks: BTreeMap<&'a K, Vec<usize>>,
+}
+
+impl<K: Ord> Confidence<'_, K> {
+    /// The share of the resampled polls ranking the candidate first, ties included, 0 for an unknown candidate
+    pub fn win_share(&self, candidate: &K) -> f64 {
+        self.rank_share(candidate, 0)
+    }
+
+    /// The share of the resampled polls giving the candidate this rank, 0 for an unknown candidate
+    pub fn rank_share(&self, candidate: &K, rank: usize) -> f64 {
+        match self.ranks.get(candidate).and_then(|counts| counts.get(rank)) {
+            Some(&count) if self.iterations > 0 => count as f64 / self.iterations as f64,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Function that estimates how certain the ranking is by resampling the ballots (bootstrap)
+///
+/// Each iteration draws as many ballots as the poll has, with replacement, and ranks the candidates of
+/// this resampled poll. The draws are reproducible: the same seed gives the same confidence.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, each vector ordered by ballot
+/// * `iterations`: the number of resampled polls, e.g. 1000
+/// * `seed`: the seed of the draws
+///
+/// # Returns
+/// * `Confidence`: the number of resampled polls giving each candidate each rank
+///
+/// # Panics
+/// * if the candidates have different numbers of grades
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::analysis::ranking_confidence;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![3, 3, 2, 3, 1, 3, 2, 3]);
+/// poll_data.insert("Chips", vec![1, 0, 2, 0, 1, 2, 1, 0]);
+///
+/// let confidence = ranking_confidence(&poll_data, 200, 42);
+/// assert!(confidence.win_share(&"Pizza") > 0.95);
+/// assert_eq!(confidence.ranks[&"Chips"].iter().sum::<usize>(), 200);
+/// ```
+pub fn ranking_confidence<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>, iterations: usize, seed: u64) -> Confidence<'_, K> {
+    let mut ranks: BTreeMap<&K, Vec<usize>> = poll_data.keys().map(|candidate| (candidate, vec![0; poll_data.len()])).collect();
+    if poll_data.is_empty() {
+        return Confidence { iterations, ranks };
+    }
+    let _ = check_poll_length(poll_data);
+
+    let ballots = poll_data.values().next().map_or(0, Vec::len);
+    let mut state = seed;
+    let mut drawn = vec![0; ballots];
+    for _ in 0..iterations {
+        for index in drawn.iter_mut() {
+            *index = (splitmix64(state) % ballots as u64) as usize;
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        }
+        let resampled: BTreeMap<&K, Vec<G>> = poll_data
+            .iter()
+            .map(|(candidate, grades)| (candidate, drawn.iter().map(|&index| grades[index].clone()).collect()))
+            .collect();
+        for (candidate, rank) in majority_judgment(&resampled) {
+            ranks.get_mut(*candidate).expect("a candidate of the poll")[rank] += 1;
+        }
+    }
+    Confidence { iterations, ranks }
+}
+
+/// The descriptive statistics of the grades of a candidate, see `stats`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradeStats<G> {
+    /// The number of grades
+    pub count: u64,
+    /// The mean grade
+    pub mean: f64,
+    /// The lower median grade, the majority grade
+    pub median: G,
+    /// The most frequent grade, the lowest one if several are as frequent
+    pub mode: G,
+    /// The standard deviation of the grades, over all the voters (population)
+    pub std_dev: f64,
+    /// The skewness of the grades (Fisher-Pearson moment coefficient): negative when the low grades
+    /// form the longer tail, 0 when all the grades are the same
+    pub skewness: f64,
+}
+
+/// Function that computes the descriptive statistics of the grades of each candidate
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+///
+/// # Returns
+/// * `BTreeMap<&K, GradeStats<G>>`: the statistics of each candidate, the candidates without any grade left out
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::analysis::stats;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![0u8, 2, 3, 3]);
+/// poll_data.insert("Chips", vec![1u8, 1, 1, 1]);
+///
+/// let stats = stats(&poll_data);
+/// assert_eq!((stats[&"Pizza"].mean, stats[&"Pizza"].median, stats[&"Pizza"].mode), (2.0, 2, 3));
+/// assert!(stats[&"Pizza"].skewness < 0.0);
+/// assert_eq!((stats[&"Chips"].std_dev, stats[&"Chips"].skewness), (0.0, 0.0));
+/// ```
+pub fn stats<K: Ord, G: Ord + Clone + Into<f64>>(poll_data: &BTreeMap<K, Vec<G>>) -> BTreeMap<&K, GradeStats<G>> {
+    poll_data
+        .iter()
+        .filter_map(|(candidate, grades)| Some((candidate, grade_stats(&compute_frequency_of_grades(grades))?)))
+        .collect()
+}
+
+/// Function that computes the descriptive statistics of a tally, None without any vote
+fn grade_stats<G: Ord + Clone + Into<f64>>(tally: &BTreeMap<G, u64>) -> Option<GradeStats<G>> {
+    let median = median_shares(tally)?;
+    let count = median.total as f64;
+    let values: Vec<(f64, f64)> = tally.iter().map(|(grade, &votes)| (grade.clone().into(), votes as f64)).collect();
+    let mean = values.iter().map(|(value, votes)| value * votes).sum::<f64>() / count;
+    let moment = |order: i32| values.iter().map(|(value, votes)| (value - mean).powi(order) * votes).sum::<f64>() / count;
+    let std_dev = moment(2).sqrt();
+    let skewness = if std_dev > 0.0 { moment(3) / std_dev.powi(3) } else { 0.0 };
+    // the first of the most frequent grades: `max_by_key` would keep the last one
+    let (mode, _) = tally.iter().rev().max_by_key(|(_, &votes)| votes)?;
+    Some(GradeStats { count: median.total, mean, median: median.median, mode: mode.clone(), std_dev, skewness })
+}
+
+/// Function that replaces the lowest or the highest grades of a tally with a given grade
+///
+/// # Arguments
+/// * `tally`: the number of votes of each grade
+/// * `ballots`: the number of grades to replace
+/// * `highest`: whether the highest grades are replaced, else the lowest ones
+/// * `grade`: the grade replacing them
+fn regrade<G: Ord + Clone>(tally: &BTreeMap<G, u64>, ballots: u64, highest: bool, grade: &G) -> BTreeMap<G, u64> {
+    let mut regraded = tally.clone();
+    let mut remaining = ballots;
+    let grades: Vec<G> = match highest {
+        true => tally.keys().rev().cloned().collect(),
+        false => tally.keys().cloned().collect(),
+    };
+    for replaced in grades {
+        if remaining == 0 {
+            break;
+        }
+        let count = regraded.get_mut(&replaced).expect("a grade of the tally");
+        let moved = remaining.min(*count);
+        *count -= moved;
+        remaining -= moved;
+        if *count == 0 {
+            regraded.remove(&replaced);
+        }
+    }
+    *regraded.entry(grade.clone()).or_insert(0) += ballots - remaining;
+    regraded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_additions_to_flip() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![3, 3, 3, 3, 3]);
+        poll_data.insert("Chips", vec![0, 0, 0, 0, 0]);
+        poll_data.insert("Pasta", vec![0, 0, 0, 2, 2]);
+
+        // 4 new ballots: Pasta [0, 0, 0, 2, 2, 3, 3, 3, 3] and Pizza [0, 0, 0, 0, 3, 3, 3, 3, 3], both medians are 3
+        // 5 new ballots: the lower median of Pasta is 2, the one of Pizza is 0
+        let margin = additions_to_flip(&poll_data, &0, &3).unwrap();
+        assert_eq!((margin.winner, margin.challenger, margin.ballots), (&"Pizza", &"Pasta", 5));
+
+        poll_data.insert("Bread", vec![3, 3, 3, 3, 3]);
+        assert_eq!(additions_to_flip(&poll_data, &0, &3).unwrap().ballots, 1);
+        assert_eq!(additions_to_flip(&poll_data, &3, &3), None);
+        poll_data.retain(|candidate, _| *candidate == "Pizza");
+        assert_eq!(additions_to_flip(&poll_data, &0, &3), None);
+    }
+
+    #[test]
+    fn calling_changes_to_flip() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![1, 2, 2, 3, 3]);
+        poll_data.insert("Chips", vec![0, 1, 2, 2, 3]);
+
+        // one change: Pizza [0, 1, 2, 2, 3] against Chips [1, 2, 2, 3, 3]
+        let margin = changes_to_flip(&poll_data, &0, &3).unwrap();
+        assert_eq!((margin.winner, margin.challenger, margin.ballots), (&"Pizza", &"Chips", 1));
+
+        // without ballots nothing can be changed, but a new ballot flips the tie
+        let empty: BTreeMap<&str, Vec<u8>> = BTreeMap::from([("Pizza", vec![]), ("Chips", vec![])]);
+        assert_eq!(changes_to_flip(&empty, &0, &3), None);
+        assert_eq!(additions_to_flip(&empty, &0, &3).unwrap().ballots, 1);
+    }
+
+    #[test]
+    fn calling_ranking_confidence() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![3, 0, 2, 1, 3, 0]);
+        poll_data.insert("Chips", vec![0, 3, 1, 2, 0, 3]);
+        poll_data.insert("Pasta", vec![0, 0, 0, 0, 0, 0]);
+
+        let confidence = ranking_confidence(&poll_data, 500, 7);
+        assert_eq!(confidence, ranking_confidence(&poll_data, 500, 7));
+        assert_eq!(confidence.ranks[&"Pasta"][2] + confidence.ranks[&"Pasta"][0], 500);
+        let (pizza, chips) = (confidence.win_share(&"Pizza"), confidence.win_share(&"Chips"));
+        assert!(pizza > 0.3 && chips > 0.3 && pizza + chips >= 1.0, "{} {}", pizza, chips);
+        assert_eq!(confidence.rank_share(&"Bread", 0), 0.0);
+
+        assert!(ranking_confidence(&BTreeMap::<&str, Vec<u8>>::new(), 10, 7).ranks.is_empty());
+    }
+
+    #[test]
+    fn calling_stats() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![0u8, 0, 4, 4]);
+        poll_data.insert("Chips", vec![]);
+
+        let stats = stats(&poll_data);
+        assert_eq!(stats.len(), 1);
+        let pizza = &stats[&"Pizza"];
+        assert_eq!((pizza.count, pizza.mean, pizza.median, pizza.mode), (4, 2.0, 0, 0));
+        assert_eq!((pizza.std_dev, pizza.skewness), (2.0, 0.0));
+
+        let skewed = grade_stats(&BTreeMap::from([(0u8, 1), (1, 3)])).unwrap();
+        assert_eq!((skewed.mode, skewed.median), (1, 1));
+        assert!((skewed.skewness + 2.0 / 3f64.sqrt()).abs() < 1e-12, "{}", skewed.skewness);
+    }
+
+    #[test]
+    fn calling_regrade() {
+        let tally = BTreeMap::from([(0, 2), (1, 1), (3, 2)]);
+        assert_eq!(regrade(&tally, 3, true, &0), BTreeMap::from([(0, 5)]));
+        assert_eq!(regrade(&tally, 3, false, &3), BTreeMap::from([(3, 5)]));
+        assert_eq!(regrade(&tally, 1, false, &2), BTreeMap::from([(0, 1), (1, 1), (2, 1), (3, 2)]));
+    }
+}
@@ -0,0 +1,170 @@
+//! # Apportionment
+//! A list election fills several seats in proportion to the support of each list. Graded by the voters,
+//! a list is scored from its majority gauge: its majority grade `α` plus `p` if its proponents outnumber its
+//! opponents, minus `q` otherwise. The scores keep the order of the majority gauge and are then apportioned
+//! like vote counts:
+//! * `Apportionment::LargestRemainder`: each list gets the integer part of its quota, the seats left going to
+//!   the largest remainders (Hamilton)
+//! * `Apportionment::DHondt` and `Apportionment::SainteLague`: the seats go one by one to the largest
+//!   quotient, the score divided by 1, 2, 3… or by 1, 3, 5… (divisor methods)
+//!
+//! An exact tie for the last seat goes to the first list in the order of the keys.
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use alloc::vec;
+use crate::judgment::median_shares;
+use crate::mj::compute_frequency_of_grades;
+
+/// The rule turning scores into seats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Apportionment {
+    /// Hare quota and largest remainders
+    #[default]
+    LargestRemainder,
+    /// Divisors 1, 2, 3…, slightly favoring the strongest lists
+    DHondt,
+    /// Divisors 1, 3, 5…, the most proportional divisor method
+    SainteLague,
+}
+
+/// Function that scores each candidate of a poll from its majority gauge
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+///
+/// # Returns
+/// * `BTreeMap<&K, f64>`: for each candidate with grades, `α + p` if `p > q`, `α - q` otherwise,
+///   `α` being the value of its majority grade. A candidate with more grades above its majority
+///   grade has a greater score, the scores are never negative for grades from 0.
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::majority_gauge_scores;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Greens", vec![2u8, 2, 2, 3]);
+/// poll_data.insert("Blues", vec![0u8, 0, 1, 2]);
+/// let scores = majority_gauge_scores(&poll_data);
+/// assert_eq!(scores[&"Greens"], 2.0 + 0.25);
+/// assert_eq!(scores[&"Blues"], 0.0 + 0.5);
+/// ```
+pub fn majority_gauge_scores<K: Ord, G: Ord + Clone + Into<f64>>(poll_data: &BTreeMap<K, Vec<G>>) -> BTreeMap<&K, f64> {
+    poll_data
+        .iter()
+        .filter_map(|(candidate, grades)| {
+            let shares = median_shares(&compute_frequency_of_grades(grades))?;
+            let (p, q) = (shares.above as f64 / shares.total as f64, shares.below as f64 / shares.total as f64);
+            let gauge = if p > q { p } else { -q };
+            Some((candidate, shares.median.into() + gauge))
+        })
+        .collect()
+}
+
+/// Function that apportions seats to scores, e.g. the scores of `majority_gauge_scores`
+///
+/// # Arguments
+/// * `scores`: the score of each list, the negative scores counting as 0
+/// * `seats`: the number of seats to fill
+/// * `method`: the apportionment rule
+///
+/// # Returns
+/// * `BTreeMap<&K, usize>`: the seats of each list, adding up to `seats` unless there is no list
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{apportion_scores, Apportionment};
+/// let scores = BTreeMap::from([("A", 5.0), ("B", 3.0), ("C", 2.0)]);
+/// let seats = apportion_scores(&scores, 4, Apportionment::DHondt);
+/// assert_eq!(seats, BTreeMap::from([(&"A", 2), (&"B", 1), (&"C", 1)]));
+/// ```
+pub fn apportion_scores<K: Ord>(scores: &BTreeMap<K, f64>, seats: usize, method: Apportionment) -> BTreeMap<&K, usize> {
+    let lists: Vec<(&K, f64)> = scores.iter().map(|(list, &score)| (list, score.max(0.0))).collect();
+    let mut allocation: Vec<usize> = vec![0; lists.len()];
+    if lists.is_empty() {
+        return BTreeMap::new();
+    }
+
+    let mut remaining = seats;
+    let mut remainders: Vec<f64> = lists.iter().map(|&(_, score)| score).collect();
+    if method == Apportionment::LargestRemainder {
+        let total: f64 = lists.iter().map(|&(_, score)| score).sum();
+        if total > 0.0 {
+            for (index, &(_, score)) in lists.iter().enumerate() {
+                let quota = score * seats as f64 / total;
+                // the quota is never negative, its integer part is its floor
+                allocation[index] = (quota as usize).min(remaining);
+                remaining -= allocation[index];
+                remainders[index] = quota - (quota as usize) as f64;
+            }
+        }
+    }
+    for _ in 0..remaining {
+        let quotient = |index: usize| match method {
+            Apportionment::LargestRemainder => remainders[index],
+            Apportionment::DHondt => lists[index].1 / (allocation[index] + 1) as f64,
+            Apportionment::SainteLague => lists[index].1 / (2 * allocation[index] + 1) as f64,
+        };
+        let mut best = 0;
+        for index in 1..lists.len() {
+            if quotient(index) > quotient(best) {
+                best = index;
+            }
+        }
+        allocation[best] += 1;
+        // a largest remainder takes a single seat
+        if method == Apportionment::LargestRemainder {
+            remainders[best] = f64::NEG_INFINITY;
+        }
+    }
+    lists.into_iter().map(|(list, _)| list).zip(allocation).collect()
+}
+
+/// Function that apportions seats to the lists of a poll from their majority gauges
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the grades of each list
+/// * `seats`: the number of seats to fill
+/// * `method`: the apportionment rule
+///
+/// # Returns
+/// * `BTreeMap<&K, usize>`: the seats of each list, see `majority_gauge_scores` and `apportion_scores`
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{apportion, Apportionment};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Greens", vec![3u8, 3, 4, 4]);
+/// poll_data.insert("Blues", vec![1u8, 1, 2, 3]);
+/// poll_data.insert("Reds", vec![0u8, 0, 0, 1]);
+///
+/// let seats = apportion(&poll_data, 10, Apportionment::SainteLague);
+/// assert_eq!((seats[&"Greens"], seats[&"Blues"], seats[&"Reds"]), (7, 3, 0));
+/// ```
+pub fn apportion<K: Ord, G: Ord + Clone + Into<f64>>(poll_data: &BTreeMap<K, Vec<G>>, seats: usize, method: Apportionment) -> BTreeMap<&K, usize> {
+    let scores = majority_gauge_scores(poll_data);
+    apportion_scores(&scores, seats, method).into_iter().map(|(&list, seats)| (list, seats)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_apportion_scores() {
+        // the textbook example where the methods disagree
+        let scores = BTreeMap::from([("A", 47.0), ("B", 16.0), ("C", 15.8), ("D", 12.0), ("E", 6.1), ("F", 3.1)]);
+        let seats = |method| apportion_scores(&scores, 10, method).into_values().collect::<Vec<_>>();
+        assert_eq!(seats(Apportionment::LargestRemainder), [5, 2, 1, 1, 1, 0]);
+        assert_eq!(seats(Apportionment::DHondt), [5, 2, 2, 1, 0, 0]);
+        assert_eq!(seats(Apportionment::SainteLague), [4, 2, 2, 1, 1, 0]);
+
+        // a tie goes to the first list, even when there is nothing to share
+        let tied = BTreeMap::from([("A", 1.0), ("B", 1.0)]);
+        assert_eq!(apportion_scores(&tied, 3, Apportionment::LargestRemainder), BTreeMap::from([(&"A", 2), (&"B", 1)]));
+        let empty = BTreeMap::from([("A", 0.0), ("B", -1.0)]);
+        assert_eq!(apportion_scores(&empty, 2, Apportionment::DHondt), BTreeMap::from([(&"A", 2), (&"B", 0)]));
+        assert!(apportion_scores(&BTreeMap::<&str, f64>::new(), 2, Apportionment::DHondt).is_empty());
+    }
+}
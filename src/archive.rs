@@ -0,0 +1,426 @@
+//! # Election archives
+//! Past elections are stored to be checked again years later, with later versions of the crate. An archive
+//! holds everything needed for that in one self-describing text: the configuration of the count, the ballots
+//! or the tallies, the published results and a SHA-256 hash of all of it.
+//!
+//! ```text
+//! majority-judgment-archive 1
+//! crate 0.1.0
+//! config method majority-judgment
+//! candidate Pizza
+//! candidate Chips
+//! ballot 1 0 2
+//! ballot 2 1 3
+//! result 0 Chips 3
+//! result 1 Pizza 1
+//! sha256 5d2c...
+//! ```
+//!
+//! * the first line gives the version of the format, `Archive::read` refusing the versions it does not know
+//! * `crate` line: the version of the crate which wrote the archive, for information
+//! * `config` lines: the configuration, sorted by key, like the receipts
+//! * `candidate` lines: the candidates in registration order
+//! * `ballot` lines: the weight of a ballot and its grades in the order of the candidates,
+//!   or `tally` lines: a candidate and its `grade:count` pairs, like the snapshots
+//! * `result` lines: the rank, the candidate and its majority grade (`-` without grade) of the published ranking
+//! * `sha256` line: the hash of all the lines above it, in lowercase hexadecimal
+//!
+//! The names and the grades are escaped as in the receipts. The grade range of a poll is not archived,
+//! the grades are archived as cast.
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Write as _};
+use std::io::{self, Read, Write};
+use crate::judgment::median_shares;
+use crate::majority_value::MajorityValue;
+use crate::mj::rank_candidates;
+use crate::options::TieOrder;
+use crate::poll::{Ballot, BallotError, Poll};
+use crate::receipt::{escape, hex, sha256, unescape};
+
+/// The version of the archive format, written on its first line
+const VERSION: u32 = 1;
+
+/// The votes kept by an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchivedVotes<G> {
+    /// Every ballot, in the order of the poll, without the voters
+    Ballots(Vec<Ballot<G>>),
+    /// The number of votes (total weight) of each grade for each candidate
+    Tally(BTreeMap<String, BTreeMap<G, u64>>),
+}
+
+/// A candidate of the published ranking
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivedResult<G> {
+    /// The rank of the candidate, 0 for the winner
+    pub rank: usize,
+    /// The candidate, by name
+    pub candidate: String,
+    /// Its majority grade, None without any vote
+    pub majority_grade: Option<G>,
+}
+
+/// An election as it was counted and published, see the module documentation
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{Ballot, Poll};
+/// use majority_judgement_rust::archive::Archive;
+/// let mut poll = Poll::new(["Pizza", "Chips"]);
+/// poll.add_ballot(Ballot::new().grade("Pizza", 0u8).grade("Chips", 2));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 1).grade("Chips", 3).with_weight(2));
+///
+/// let archive = Archive::from_poll(&poll, &[("method", "majority-judgment")]).unwrap();
+/// let mut stored = Vec::new();
+/// archive.write(&mut stored).unwrap();
+///
+/// let read = Archive::read(stored.as_slice(), |grade| grade.parse::<u8>().map_err(|error| error.to_string())).unwrap();
+/// assert_eq!(read, archive);
+/// assert!(read.verify());
+/// assert_eq!(read.results[0].candidate, "Chips");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Archive<G> {
+    /// The settings of the count as key and value, sorted by key
+    pub configuration: Vec<(String, String)>,
+    /// The candidates in registration order
+    pub candidates: Vec<String>,
+    /// The ballots or the tallies
+    pub votes: ArchivedVotes<G>,
+    /// The published ranking, from the best candidate to the worst
+    pub results: Vec<ArchivedResult<G>>,
+}
+
+impl<G: Ord + Clone> Archive<G> {
+    /// Function that archives a poll with its ranking
+    ///
+    /// # Arguments
+    /// * `poll`: the poll, every ballot grading each candidate once, each ballot counting its weight
+    /// * `configuration`: the settings of the count as key and value, in any order
+    ///
+    /// # Returns
+    /// * `Result<Archive<G>, Vec<BallotError>>`: the archive, or the issues of the ballots if the poll is invalid
+    pub fn from_poll(poll: &Poll<G>, configuration: &[(&str, &str)]) -> Result<Self, Vec<BallotError>> {
+        let errors = poll.validate();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let ballots = poll.ballots().iter().map(|ballot| ballot.clone().without_voter()).collect();
+        Ok(Archive::new(poll.candidates().to_vec(), ArchivedVotes::Ballots(ballots), configuration))
+    }
+
+    /// Function that archives counted votes with their ranking
+    ///
+    /// # Arguments
+    /// * `tallies`: for each candidate in registration order, the number of votes of each grade
+    /// * `configuration`: the settings of the count as key and value, in any order
+    ///
+    /// # Returns
+    /// * `Archive<G>`: the archive
+    pub fn from_tally(tallies: Vec<(String, BTreeMap<G, u64>)>, configuration: &[(&str, &str)]) -> Self {
+        let candidates = tallies.iter().map(|(candidate, _)| candidate.clone()).collect();
+        Archive::new(candidates, ArchivedVotes::Tally(tallies.into_iter().collect()), configuration)
+    }
+
+    fn new(candidates: Vec<String>, votes: ArchivedVotes<G>, configuration: &[(&str, &str)]) -> Self {
+        let mut configuration: Vec<(String, String)> =
+            configuration.iter().map(|&(key, value)| (key.to_string(), value.to_string())).collect();
+        configuration.sort();
+        let mut archive = Archive { configuration, candidates, votes, results: Vec::new() };
+        archive.results = archive.recount();
+        archive
+    }
+
+    /// The number of votes (total weight) of each grade for each candidate
+    pub fn tallies(&self) -> BTreeMap<String, BTreeMap<G, u64>> {
+        match &self.votes {
+            ArchivedVotes::Tally(tallies) => tallies.clone(),
+            ArchivedVotes::Ballots(ballots) => {
+                let mut tallies: BTreeMap<String, BTreeMap<G, u64>> =
+                    self.candidates.iter().map(|candidate| (candidate.clone(), BTreeMap::new())).collect();
+                for ballot in ballots {
+                    for (candidate, grade) in ballot.grades() {
+                        if let Some(tally) = tallies.get_mut(candidate) {
+                            *tally.entry(grade.clone()).or_insert(0) += u64::from(ballot.weight());
+                        }
+                    }
+                }
+                tallies
+            }
+        }
+    }
+
+    /// Function that ranks the archived votes again with this version of the crate
+    ///
+    /// # Returns
+    /// * `Vec<ArchivedResult<G>>`: the ranking of the candidates, the tied candidates sharing their rank and listed by name
+    pub fn recount(&self) -> Vec<ArchivedResult<G>> {
+        let tallies = self.tallies();
+        let majority_values = tallies.iter().map(|(candidate, tally)| (candidate, MajorityValue::from_tally(tally))).collect();
+        rank_candidates::<String, G, _>(majority_values, &BTreeMap::new(), None, TieOrder::default())
+            .with_majority_grades(|candidate| median_shares(&tallies[candidate]))
+            .iter()
+            .map(|ranked| ArchivedResult { rank: ranked.rank, candidate: ranked.candidate.clone(), majority_grade: ranked.majority_grade.clone() })
+            .collect()
+    }
+
+    /// Whether ranking the archived votes again gives exactly the published results
+    pub fn verify(&self) -> bool {
+        self.recount() == self.results
+    }
+}
+
+impl<G: Display> Archive<G> {
+    /// Function that writes the archive, see the module documentation for its format
+    ///
+    /// # Arguments
+    /// * `writer`: where to write it, e.g. a file
+    ///
+    /// # Returns
+    /// * `io::Result<()>`: the error of the writer, if any
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut text = format!("majority-judgment-archive {}\ncrate {}\n", VERSION, env!("CARGO_PKG_VERSION"));
+        for (key, value) in &self.configuration {
+            let _ = writeln!(text, "config {} {}", escape(key), escape(value));
+        }
+        for candidate in &self.candidates {
+            let _ = writeln!(text, "candidate {}", escape(candidate));
+        }
+        match &self.votes {
+            ArchivedVotes::Ballots(ballots) => {
+                for ballot in ballots {
+                    let _ = write!(text, "ballot {}", ballot.weight());
+                    for candidate in &self.candidates {
+                        // the poll was valid: each candidate has exactly one grade
+                        if let Some((_, grade)) = ballot.grades().iter().find(|(graded, _)| graded == candidate) {
+                            let _ = write!(text, " {}", escape(&grade.to_string()));
+                        }
+                    }
+                    text.push('\n');
+                }
+            }
+            ArchivedVotes::Tally(tallies) => {
+                for (candidate, tally) in tallies {
+                    let _ = write!(text, "tally {}", escape(candidate));
+                    for (grade, count) in tally {
+                        let _ = write!(text, " {}:{}", escape(&grade.to_string()), count);
+                    }
+                    text.push('\n');
+                }
+            }
+        }
+        for result in &self.results {
+            let grade = result.majority_grade.as_ref().map_or("-".to_string(), |grade| escape(&grade.to_string()));
+            let _ = writeln!(text, "result {} {} {}", result.rank, escape(&result.candidate), grade);
+        }
+        let hash = hex(&sha256(text.as_bytes()));
+        let _ = writeln!(text, "sha256 {}", hash);
+        writer.write_all(text.as_bytes())
+    }
+}
+
+impl<G: Ord + Clone> Archive<G> {
+    /// Function that reads an archive written by `write`, by this version of the crate or an earlier one
+    ///
+    /// # Arguments
+    /// * `reader`: where to read it, e.g. a file
+    /// * `parse_grade`: the function reading a grade, e.g. `csv::parse_number`
+    ///
+    /// # Returns
+    /// * `Result<Archive<G>, ArchiveError>`: the archive, or why it cannot be trusted. The results are read
+    ///   as published: `verify` ranks the votes again to check them.
+    pub fn read<R, F>(mut reader: R, parse_grade: F) -> Result<Self, ArchiveError>
+    where
+        R: Read,
+        F: Fn(&str) -> Result<G, String>,
+    {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let invalid = |line: usize, message: String| ArchiveError::Invalid { line, message };
+        let grade = |line: usize, field: &str| {
+            unescape(field)
+                .ok_or_else(|| invalid(line, format!("invalid grade {:?}", field)))
+                .and_then(|grade| parse_grade(&grade).map_err(|message| invalid(line, message)))
+        };
+
+        let version = text
+            .lines()
+            .next()
+            .and_then(|header| header.strip_prefix("majority-judgment-archive "))
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or_else(|| invalid(1, "expected \"majority-judgment-archive <version>\"".to_string()))?;
+        if version != VERSION {
+            return Err(ArchiveError::UnsupportedVersion(version));
+        }
+        let hashed = text.rfind("sha256 ").filter(|&start| start == 0 || text[..start].ends_with('\n'));
+        let Some(start) = hashed else { return Err(invalid(text.lines().count(), "missing sha256 line".to_string())) };
+        let expected = text[start + "sha256 ".len()..].trim_end();
+        let found = hex(&sha256(&text.as_bytes()[..start]));
+        if expected != found {
+            return Err(ArchiveError::HashMismatch { expected: expected.to_string(), found });
+        }
+
+        let mut archive = Archive { configuration: Vec::new(), candidates: Vec::new(), votes: ArchivedVotes::Ballots(Vec::new()), results: Vec::new() };
+        let mut tallies: Option<BTreeMap<String, BTreeMap<G, u64>>> = None;
+        let mut ballots = Vec::new();
+        for (index, line) in text[..start].lines().enumerate().skip(1) {
+            let number = index + 1;
+            let mut fields = line.split_whitespace();
+            let name = |fields: &mut std::str::SplitWhitespace<'_>, what: &str| {
+                fields.next().and_then(unescape).ok_or_else(|| invalid(number, format!("invalid {}", what)))
+            };
+            match fields.next() {
+                Some("crate") => {}
+                Some("config") => {
+                    let key = name(&mut fields, "configuration key")?;
+                    let value = name(&mut fields, "configuration value")?;
+                    archive.configuration.push((key, value));
+                }
+                Some("candidate") => {
+                    let candidate = name(&mut fields, "candidate name")?;
+                    if archive.candidates.contains(&candidate) {
+                        return Err(invalid(number, format!("candidate {:?} listed twice", candidate)));
+                    }
+                    archive.candidates.push(candidate);
+                }
+                Some("ballot") => {
+                    let weight = fields
+                        .next()
+                        .and_then(|weight| weight.parse().ok())
+                        .ok_or_else(|| invalid(number, "the weight is not an integer".to_string()))?;
+                    let mut ballot = Ballot::new().with_weight(weight);
+                    let grades: Vec<&str> = fields.collect();
+                    if grades.len() != archive.candidates.len() {
+                        return Err(invalid(number, format!("expected {} grades, found {}", archive.candidates.len(), grades.len())));
+                    }
+                    for (candidate, field) in archive.candidates.iter().zip(grades) {
+                        ballot = ballot.grade(candidate.clone(), grade(number, field)?);
+                    }
+                    ballots.push(ballot);
+                }
+                Some("tally") => {
+                    let candidate = name(&mut fields, "candidate name")?;
+                    let mut tally = BTreeMap::new();
+                    for field in fields {
+                        let (counted, count) = field
+                            .split_once(':')
+                            .ok_or_else(|| invalid(number, format!("expected grade:count, found {:?}", field)))?;
+                        let count: u64 = count.parse().map_err(|_| invalid(number, format!("invalid count {:?}", count)))?;
+                        if tally.insert(grade(number, counted)?, count).is_some() {
+                            return Err(invalid(number, format!("grade {:?} counted twice", field)));
+                        }
+                    }
+                    tallies.get_or_insert_with(BTreeMap::new).insert(candidate, tally);
+                }
+                Some("result") => {
+                    let rank = fields
+                        .next()
+                        .and_then(|rank| rank.parse().ok())
+                        .ok_or_else(|| invalid(number, "the rank is not an integer".to_string()))?;
+                    let candidate = name(&mut fields, "candidate name")?;
+                    let majority_grade = match fields.next() {
+                        Some("-") => None,
+                        Some(field) => Some(grade(number, field)?),
+                        None => return Err(invalid(number, "missing majority grade".to_string())),
+                    };
+                    archive.results.push(ArchivedResult { rank, candidate, majority_grade });
+                }
+                Some(other) => return Err(invalid(number, format!("unknown line {:?}", other))),
+                None => {}
+            }
+        }
+        archive.votes = match tallies {
+            Some(_) if !ballots.is_empty() => return Err(invalid(1, "an archive holds ballots or tallies, not both".to_string())),
+            Some(tallies) => ArchivedVotes::Tally(tallies),
+            None => ArchivedVotes::Ballots(ballots),
+        };
+        Ok(archive)
+    }
+}
+
+/// An archive which cannot be read, see `Archive::read`
+#[derive(Debug)]
+pub enum ArchiveError {
+    /// The archive cannot be read
+    Io(io::Error),
+    /// The archive was written in a version of the format unknown to this version of the crate
+    UnsupportedVersion(u32),
+    /// The archive was modified after it was written
+    HashMismatch { expected: String, found: String },
+    /// A line of the archive is invalid, the lines being numbered from 1
+    Invalid { line: usize, message: String },
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Io(error) => write!(f, "{}", error),
+            ArchiveError::UnsupportedVersion(version) => write!(f, "unsupported archive version {}", version),
+            ArchiveError::HashMismatch { expected, found } => write!(f, "the archive hashes to {}, not {}", found, expected),
+            ArchiveError::Invalid { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+impl From<io::Error> for ArchiveError {
+    fn from(error: io::Error) -> Self {
+        ArchiveError::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(grade: &str) -> Result<u8, String> {
+        grade.parse().map_err(|_| format!("invalid grade {:?}", grade))
+    }
+
+    #[test]
+    fn calling_write_and_read() {
+        let tally = Archive::from_tally(vec![("Bike lanes".to_string(), BTreeMap::from([(0u8, 2), (3, 5)])),
+                                             ("Benches".to_string(), BTreeMap::from([(2, 7)]))],
+                                        &[("scale", "0-3"), ("method", "majority-judgment")]);
+        assert_eq!(tally.results.iter().map(|result| (result.rank, result.candidate.as_str(), result.majority_grade)).collect::<Vec<_>>(),
+                   [(0, "Bike lanes", Some(3)), (1, "Benches", Some(2))]);
+        assert_eq!(tally.configuration[0], ("method".to_string(), "majority-judgment".to_string()));
+        let mut stored = Vec::new();
+        tally.write(&mut stored).unwrap();
+        let text = String::from_utf8(stored).unwrap();
+        assert!(text.contains("\ncandidate Bike%20lanes\n") && text.contains("\ntally Benches 2:7\n"));
+        assert_eq!(Archive::read(text.as_bytes(), parse).unwrap(), tally);
+
+        // a modified result no longer matches the hash, nor the votes once the hash is recomputed
+        let forged = text.replace("result 0 Bike%20lanes 3", "result 0 Benches 2").replace("result 1 Benches 2", "result 1 Bike%20lanes 3");
+        assert!(matches!(Archive::read(forged.as_bytes(), parse), Err(ArchiveError::HashMismatch { .. })));
+        let body = &forged[..forged.rfind("sha256 ").unwrap()];
+        let hash = hex(&sha256(body.as_bytes()));
+        let rehashed = Archive::read(format!("{}sha256 {}\n", body, hash).as_bytes(), parse).unwrap();
+        assert!(!rehashed.verify());
+
+        let future = text.replace("majority-judgment-archive 1", "majority-judgment-archive 2");
+        assert!(matches!(Archive::read(future.as_bytes(), parse), Err(ArchiveError::UnsupportedVersion(2))));
+    }
+
+    #[test]
+    fn calling_from_poll() {
+        let mut poll = Poll::new(["Pizza", "Chips"]);
+        poll.add_ballot(Ballot::new().grade("Pizza", 2u8).grade("Chips", 0).with_voter("alice"));
+        poll.add_ballot(Ballot::new().grade("Pizza", 1).grade("Chips", 3).with_weight(3));
+        let archive = Archive::from_poll(&poll, &[]).unwrap();
+        assert_eq!(archive.tallies()["Pizza"], BTreeMap::from([(1, 3), (2, 1)]));
+        assert_eq!(archive.results[0].candidate, "Chips");
+
+        let mut stored = Vec::new();
+        archive.write(&mut stored).unwrap();
+        let text = String::from_utf8(stored).unwrap();
+        assert!(text.contains("\nballot 1 2 0\nballot 3 1 3\n") && !text.contains("alice"));
+        let read = Archive::read(text.as_bytes(), parse).unwrap();
+        assert!(read.verify() && read == archive);
+
+        poll.add_ballot(Ballot::new().grade("Soup", 1));
+        assert!(Archive::from_poll(&poll, &[]).is_err());
+    }
+}
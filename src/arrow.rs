@@ -0,0 +1,433 @@
+//! # Arrow interop
+//! Reads ballots from Apache Arrow record batches and writes the rankings back as Arrow arrays, through the
+//! [Arrow C data interface](https://arrow.apache.org/docs/format/CDataInterface.html) (enabled by the `arrow`
+//! feature). Any Arrow implementation exports and imports these structures without copying the columns:
+//! `pyarrow` with `RecordBatch._export_to_c`, Polars with `DataFrame.to_arrow()`, arrow-rs with `FFI_ArrowArray`.
+//!
+//! The ballots are a struct array (the record batch) with one integer column per candidate and one row per
+//! ballot, a null grade being an abstention to resolve with `resolve_abstentions`:
+//!
+//! ```text
+//! Pizza: int8   Chips: int8
+//! 2             0
+//! 3             null
+//! ```
+//!
+//! The ranking is a struct array with the columns `candidate` (utf8), `rank` (uint64, 0 being the best, as in
+//! `Ranking`), `tie` (utf8: `unique`, `tied` or `broken`, as in `Ranking::to_json`) and `majority_grade`
+//! (int64, null without grade).
+use std::collections::BTreeMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::fmt;
+use std::ptr;
+use crate::ranking::{Ranking, TieStatus};
+
+/// An array of the Arrow C data interface, `struct ArrowArray`
+#[repr(C)]
+#[derive(Debug)]
+pub struct ArrowArray {
+    pub length: i64,
+    pub null_count: i64,
+    pub offset: i64,
+    pub n_buffers: i64,
+    pub n_children: i64,
+    pub buffers: *mut *const c_void,
+    pub children: *mut *mut ArrowArray,
+    pub dictionary: *mut ArrowArray,
+    pub release: Option<unsafe extern "C" fn(*mut ArrowArray)>,
+    pub private_data: *mut c_void,
+}
+
+/// The type of an array of the Arrow C data interface, `struct ArrowSchema`
+#[repr(C)]
+#[derive(Debug)]
+pub struct ArrowSchema {
+    pub format: *const c_char,
+    pub name: *const c_char,
+    pub metadata: *const c_char,
+    pub flags: i64,
+    pub n_children: i64,
+    pub children: *mut *mut ArrowSchema,
+    pub dictionary: *mut ArrowSchema,
+    pub release: Option<unsafe extern "C" fn(*mut ArrowSchema)>,
+    pub private_data: *mut c_void,
+}
+
+/// The flag of the schemas whose array may hold nulls
+const NULLABLE: i64 = 2;
+
+impl Drop for ArrowArray {
+    /// Release the array if it was not moved to its consumer, e.g. by `std::ptr::read` or `std::mem::replace`
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            // SAFETY: an array with a release callback owns its buffers until released
+            unsafe { release(self) }
+        }
+    }
+}
+
+impl Drop for ArrowSchema {
+    /// Release the schema if it was not moved to its consumer
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            // SAFETY: a schema with a release callback owns its strings until released
+            unsafe { release(self) }
+        }
+    }
+}
+
+/// A record batch which is not a valid poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArrowError {
+    /// What is wrong
+    pub message: String,
+}
+
+impl ArrowError {
+    fn new(message: impl Into<String>) -> Self {
+        ArrowError { message: message.into() }
+    }
+}
+
+impl fmt::Display for ArrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ArrowError {}
+
+/// Function that reads the grades of a record batch of ballots
+///
+/// The record batch stays owned by the caller, who releases it: the grades are copied.
+///
+/// # Arguments
+/// * `array`: the struct array of the record batch, one row per ballot
+/// * `schema`: its type, one child per candidate, named after it, of an integer type
+///   (int8 to int64, uint8 to uint64)
+///
+/// # Returns
+/// * `Result<BTreeMap<String, Vec<Option<i64>>>, ArrowError>`: the grades of each candidate ordered by ballot,
+///   None for a null grade or a null row, or why the record batch is not a poll
+///
+/// # Safety
+/// `array` and `schema` must be valid structures of the Arrow C data interface, not released yet,
+/// the array being of the type described by the schema.
+pub unsafe fn poll_data_from_arrow(array: &ArrowArray, schema: &ArrowSchema) -> Result<BTreeMap<String, Vec<Option<i64>>>, ArrowError> {
+    if array.release.is_none() || schema.release.is_none() {
+        return Err(ArrowError::new("the record batch is released"));
+    }
+    let format = CStr::from_ptr(schema.format).to_bytes();
+    if format != b"+s" {
+        return Err(ArrowError::new(format!("expected a struct array, found the format {:?}", String::from_utf8_lossy(format))));
+    }
+    if array.n_children != schema.n_children {
+        return Err(ArrowError::new("the record batch and its schema have different numbers of columns"));
+    }
+    let (rows, offset) = (to_usize(array.length)?, to_usize(array.offset)?);
+    let row_validity = buffer(array, 0) as *const u8;
+
+    let mut poll_data = BTreeMap::new();
+    for index in 0..to_usize(array.n_children)? {
+        let (column, column_schema) = (&**array.children.add(index), &**schema.children.add(index));
+        let name = match column_schema.name.is_null() {
+            true => return Err(ArrowError::new(format!("column {} has no name", index))),
+            false => CStr::from_ptr(column_schema.name).to_string_lossy().into_owned(),
+        };
+        let format = CStr::from_ptr(column_schema.format).to_bytes();
+        let width = match format {
+            b"c" | b"C" => 1,
+            b"s" | b"S" => 2,
+            b"i" | b"I" => 4,
+            b"l" | b"L" => 8,
+            _ => {
+                let format = String::from_utf8_lossy(format);
+                return Err(ArrowError::new(format!("column {:?}: expected integer grades, found the format {:?}", name, format)));
+            }
+        };
+        if to_usize(column.length)? < offset + rows {
+            return Err(ArrowError::new(format!("column {:?} is shorter than the record batch", name)));
+        }
+        let (validity, values) = (buffer(column, 0) as *const u8, buffer(column, 1) as *const u8);
+        let column_offset = to_usize(column.offset)?;
+        let mut grades = Vec::with_capacity(rows);
+        for row in 0..rows {
+            let slot = column_offset + offset + row;
+            if !is_valid(row_validity, offset + row) || !is_valid(validity, slot) {
+                grades.push(None);
+                continue;
+            }
+            let value = values.add(slot * width);
+            let grade = match format {
+                b"c" => Some((value as *const i8).read_unaligned() as i64),
+                b"C" => Some(value.read_unaligned() as i64),
+                b"s" => Some((value as *const i16).read_unaligned() as i64),
+                b"S" => Some((value as *const u16).read_unaligned() as i64),
+                b"i" => Some((value as *const i32).read_unaligned() as i64),
+                b"I" => Some((value as *const u32).read_unaligned() as i64),
+                b"l" => Some((value as *const i64).read_unaligned()),
+                _ => i64::try_from((value as *const u64).read_unaligned()).ok(),
+            };
+            match grade {
+                Some(grade) => grades.push(Some(grade)),
+                None => return Err(ArrowError::new(format!("column {:?}: grade out of the range of i64", name))),
+            }
+        }
+        if poll_data.insert(name.clone(), grades).is_some() {
+            return Err(ArrowError::new(format!("candidate {:?} has two columns", name)));
+        }
+    }
+    Ok(poll_data)
+}
+
+/// Function that converts a length or an offset of the C data interface
+fn to_usize(value: i64) -> Result<usize, ArrowError> {
+    usize::try_from(value).map_err(|_| ArrowError::new(format!("invalid length or offset {}", value)))
+}
+
+/// The buffer of an array, null if the array has no such buffer
+unsafe fn buffer(array: &ArrowArray, index: usize) -> *const c_void {
+    match array.buffers.is_null() || index as i64 >= array.n_buffers {
+        true => ptr::null(),
+        false => *array.buffers.add(index),
+    }
+}
+
+/// Whether a slot is valid in a validity bitmap, every slot of a null bitmap being valid
+unsafe fn is_valid(validity: *const u8, slot: usize) -> bool {
+    validity.is_null() || *validity.add(slot / 8) & (1 << (slot % 8)) != 0
+}
+
+/// Function that writes a ranking as an Arrow struct array, to import in any Arrow implementation
+///
+/// The returned structures own their buffers: the consumer moves them (e.g. `std::ptr::write` to the address
+/// given by `pyarrow.Array._import_from_c`) and calls their release callbacks, else they are released when dropped.
+///
+/// # Arguments
+/// * `ranking`: the ranking, the candidates written with `Display` and the majority grades converted to i64
+///
+/// # Returns
+/// * `(ArrowArray, ArrowSchema)`: the struct array with the columns `candidate`, `rank`, `tie` and
+///   `majority_grade`, one row per candidate from the best to the worst, and its type
+pub fn ranking_to_arrow<K, G>(ranking: &Ranking<'_, K, G>) -> (ArrowArray, ArrowSchema)
+where
+    K: fmt::Display,
+    G: Clone + Into<i64>,
+{
+    let tie = |tie: TieStatus| match tie {
+        TieStatus::Unique => "unique",
+        TieStatus::Tied => "tied",
+        TieStatus::Broken => "broken",
+    };
+    let columns = vec![
+        ("candidate", Column::Utf8(ranking.iter().map(|ranked| ranked.candidate.to_string()).collect())),
+        ("rank", Column::UInt64(ranking.iter().map(|ranked| ranked.rank as u64).collect())),
+        ("tie", Column::Utf8(ranking.iter().map(|ranked| tie(ranked.tie).to_string()).collect())),
+        ("majority_grade", Column::Int64(ranking.iter().map(|ranked| ranked.majority_grade.clone().map(Into::into)).collect())),
+    ];
+    export_struct(ranking.len(), columns)
+}
+
+/// A column exported to the C data interface
+pub(crate) enum Column {
+    Int64(Vec<Option<i64>>),
+    UInt64(Vec<u64>),
+    Utf8(Vec<String>),
+}
+
+/// The memory owned by an exported array, freed by its release callback
+struct ArrayData {
+    buffers: Vec<*const c_void>,
+    children: Vec<*mut ArrowArray>,
+    _validity: Option<Vec<u8>>,
+    _values: Vec<u8>,
+    _integers: Vec<i64>,
+    _offsets: Vec<i32>,
+}
+
+/// The strings owned by an exported schema, freed by its release callback
+struct SchemaData {
+    _format: CString,
+    _name: CString,
+    children: Vec<*mut ArrowSchema>,
+}
+
+/// Function that exports columns of `length` rows as a struct array
+pub(crate) fn export_struct(length: usize, columns: Vec<(&str, Column)>) -> (ArrowArray, ArrowSchema) {
+    let mut children = Vec::new();
+    let mut schemas = Vec::new();
+    for (name, column) in columns {
+        let (array, schema) = export_column(name, column);
+        children.push(Box::into_raw(Box::new(array)));
+        schemas.push(Box::into_raw(Box::new(schema)));
+    }
+    let data = ArrayData {
+        buffers: vec![ptr::null()],
+        children,
+        _validity: None,
+        _values: Vec::new(),
+        _integers: Vec::new(),
+        _offsets: Vec::new(),
+    };
+    let array = new_array(length, 0, data);
+    let schema = new_schema("+s", "", 0, schemas);
+    (array, schema)
+}
+
+/// Function that exports a column as an array without children
+fn export_column(name: &str, column: Column) -> (ArrowArray, ArrowSchema) {
+    let mut data = ArrayData {
+        buffers: Vec::new(),
+        children: Vec::new(),
+        _validity: None,
+        _values: Vec::new(),
+        _integers: Vec::new(),
+        _offsets: Vec::new(),
+    };
+    let (length, null_count, format) = match column {
+        Column::Int64(values) => {
+            let nulls = values.iter().filter(|value| value.is_none()).count();
+            if nulls > 0 {
+                let mut validity = vec![0u8; values.len().div_ceil(8)];
+                for (slot, value) in values.iter().enumerate() {
+                    if value.is_some() {
+                        validity[slot / 8] |= 1 << (slot % 8);
+                    }
+                }
+                data._validity = Some(validity);
+            }
+            data._integers = values.iter().map(|value| value.unwrap_or(0)).collect();
+            (values.len(), nulls, "l")
+        }
+        Column::UInt64(values) => {
+            data._integers = values.iter().map(|&value| value as i64).collect();
+            (values.len(), 0, "L")
+        }
+        Column::Utf8(values) => {
+            let mut offsets = vec![0i32];
+            for value in &values {
+                data._values.extend_from_slice(value.as_bytes());
+                offsets.push(i32::try_from(data._values.len()).expect("the strings of a column exceed 2 GiB"));
+            }
+            data._offsets = offsets;
+            (values.len(), 0, "u")
+        }
+    };
+    let validity = data._validity.as_ref().map_or(ptr::null(), |validity| validity.as_ptr() as *const c_void);
+    data.buffers = match format {
+        "u" => vec![validity, data._offsets.as_ptr() as *const c_void, data._values.as_ptr() as *const c_void],
+        _ => vec![validity, data._integers.as_ptr() as *const c_void],
+    };
+    let flags = if null_count > 0 { NULLABLE } else { 0 };
+    (new_array(length, null_count, data), new_schema(format, name, flags, Vec::new()))
+}
+
+/// Function that builds an exported array owning its memory
+fn new_array(length: usize, null_count: usize, mut data: ArrayData) -> ArrowArray {
+    let (buffers, children) = (data.buffers.as_mut_ptr(), data.children.as_mut_ptr());
+    let (n_buffers, n_children) = (data.buffers.len() as i64, data.children.len() as i64);
+    ArrowArray {
+        length: length as i64,
+        null_count: null_count as i64,
+        offset: 0,
+        n_buffers,
+        n_children,
+        buffers,
+        children,
+        dictionary: ptr::null_mut(),
+        release: Some(release_array),
+        private_data: Box::into_raw(Box::new(data)) as *mut c_void,
+    }
+}
+
+/// Function that builds an exported schema owning its strings
+fn new_schema(format: &str, name: &str, flags: i64, mut children: Vec<*mut ArrowSchema>) -> ArrowSchema {
+    let format = CString::new(format).expect("a format without NUL");
+    let name = CString::new(name.replace('\0', "")).expect("a name without NUL");
+    let (n_children, children_pointer) = (children.len() as i64, children.as_mut_ptr());
+    let data = SchemaData { children, _format: format, _name: name };
+    ArrowSchema {
+        format: data._format.as_ptr(),
+        name: data._name.as_ptr(),
+        metadata: ptr::null(),
+        flags,
+        n_children,
+        children: children_pointer,
+        dictionary: ptr::null_mut(),
+        release: Some(release_schema),
+        private_data: Box::into_raw(Box::new(data)) as *mut c_void,
+    }
+}
+
+/// The release callback of the exported arrays, releasing their children then freeing their memory
+unsafe extern "C" fn release_array(array: *mut ArrowArray) {
+    let array = &mut *array;
+    let data = Box::from_raw(array.private_data as *mut ArrayData);
+    for &child in &data.children {
+        // dropping the child calls its own release callback, if not released by the consumer
+        drop(Box::from_raw(child));
+    }
+    array.private_data = ptr::null_mut();
+    array.release = None;
+}
+
+/// The release callback of the exported schemas
+unsafe extern "C" fn release_schema(schema: *mut ArrowSchema) {
+    let schema = &mut *schema;
+    let data = Box::from_raw(schema.private_data as *mut SchemaData);
+    for &child in &data.children {
+        drop(Box::from_raw(child));
+    }
+    schema.private_data = ptr::null_mut();
+    schema.release = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mj::majority_judgment_ranking;
+
+    #[test]
+    fn calling_poll_data_from_arrow() {
+        let columns = vec![
+            ("Pizza", Column::Int64(vec![Some(2), Some(3), None])),
+            ("Chips", Column::UInt64(vec![0, 1, 2])),
+        ];
+        let (array, schema) = export_struct(3, columns);
+        let poll_data = unsafe { poll_data_from_arrow(&array, &schema) }.unwrap();
+        assert_eq!(poll_data["Pizza"], vec![Some(2), Some(3), None]);
+        assert_eq!(poll_data["Chips"], vec![Some(0), Some(1), Some(2)]);
+
+        let (text, text_schema) = export_struct(1, vec![("Pizza", Column::Utf8(vec!["good".to_string()]))]);
+        let error = unsafe { poll_data_from_arrow(&text, &text_schema) }.unwrap_err();
+        assert_eq!(error.to_string(), "column \"Pizza\": expected integer grades, found the format \"u\"");
+        let error = unsafe { poll_data_from_arrow(&**array.children.add(0), &**schema.children.add(0)) }.unwrap_err();
+        assert_eq!(error.message, "expected a struct array, found the format \"l\"");
+    }
+
+    #[test]
+    fn calling_ranking_to_arrow() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![0i64, 2, 3]);
+        poll_data.insert("Chips", vec![1i64, 3, 3]);
+        let (array, schema) = ranking_to_arrow(&majority_judgment_ranking(&poll_data));
+        assert_eq!((array.length, array.n_children, schema.n_children), (2, 4, 4));
+
+        unsafe {
+            let names: Vec<&CStr> = (0..4).map(|index| CStr::from_ptr((**schema.children.add(index)).name)).collect();
+            assert_eq!(names, [c"candidate", c"rank", c"tie", c"majority_grade"]);
+            let candidates = &**array.children.add(0);
+            let offsets = std::slice::from_raw_parts(buffer(candidates, 1) as *const i32, 3);
+            let text = std::slice::from_raw_parts(buffer(candidates, 2) as *const u8, offsets[2] as usize);
+            assert_eq!((offsets, text), (&[0, 5, 10][..], &b"ChipsPizza"[..]));
+            let grades = std::slice::from_raw_parts(buffer(&**array.children.add(3), 1) as *const i64, 2);
+            assert_eq!(grades, [3, 2]);
+        }
+
+        // a consumer takes the array and releases it, dropping the moved-from structure does nothing
+        let mut taken = array;
+        let release = taken.release.unwrap();
+        unsafe { release(&mut taken) };
+        assert!(taken.release.is_none() && taken.private_data.is_null());
+    }
+}
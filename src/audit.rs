@@ -0,0 +1,242 @@
+//! # Audited rankings
+//! Legally binding counts are checked twice (enabled by the `audit` feature). `audited_majority_judgment` ranks a
+//! poll as `majority_judgment_ranking`, then recomputes the ranking along two independent paths which share no
+//! code with it nor with each other:
+//! * the iterative medians: the sorted grades of each candidate, whose lower median is withdrawn one vote at a time
+//! * the majority gauge in closed form: the median grade `α` with the number of votes above it `p` and below it `q`,
+//!   counted on the sorted grades
+//!
+//! The ranks and the majority grades must be the ones of the iterative medians, and whenever the majority gauge
+//! separates two candidates the iterative medians must rank them in the same order (Balinski and Laraki).
+//! Any disagreement is an `AuditError` instead of a ranking. `audit_ranking` checks a ranking computed elsewhere,
+//! e.g. read back from an archive.
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::checked::RankingError;
+use crate::mj::majority_judgment_ranking;
+use crate::ranking::Ranking;
+
+/// Why an audited ranking is refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditError<'a, K> {
+    /// The poll data cannot be ranked, e.g. candidates with different numbers of grades
+    Ranking(RankingError),
+    /// The ranking does not rank each candidate of the poll exactly once
+    Candidates,
+    /// The rank of the candidate is not the one of the iterative medians
+    Rank { candidate: &'a K, expected: usize, found: usize },
+    /// The majority grade of the candidate is not the lower median of its grades
+    MajorityGrade { candidate: &'a K },
+    /// The majority gauge ranks `below` above `above`, which the iterative medians rank first
+    Gauge { above: &'a K, below: &'a K },
+}
+
+impl<K: fmt::Debug> fmt::Display for AuditError<'_, K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditError::Ranking(error) => write!(f, "{}", error),
+            AuditError::Candidates => write!(f, "the ranking does not rank the candidates of the poll"),
+            AuditError::Rank { candidate, expected, found } =>
+                write!(f, "candidate {:?} is ranked {} instead of {}", candidate, found, expected),
+            AuditError::MajorityGrade { candidate } => write!(f, "candidate {:?} has a wrong majority grade", candidate),
+            AuditError::Gauge { above, below } =>
+                write!(f, "the majority gauge ranks {:?} above {:?}, against the iterative medians", below, above),
+        }
+    }
+}
+
+impl<K: fmt::Debug> std::error::Error for AuditError<'_, K> {}
+
+/// The majority gauge of a candidate: its majority grade, then `p` or `-q`
+type Gauge<G> = (Option<G>, i128);
+
+/// Function that withdraws the lower median of the grades one vote at a time
+///
+/// The withdrawn votes always form a gap in the middle of the sorted grades: the lower median of the votes
+/// left is the last vote below the gap when an even number of votes is left, the first above it otherwise.
+fn iterative_medians<G: Ord + Clone>(sorted: &[G]) -> Vec<G> {
+    let n = sorted.len();
+    let mut values = Vec::with_capacity(n);
+    if n == 0 {
+        return values;
+    }
+    // the votes left are sorted[..below] and sorted[above..]
+    let (mut below, mut above) = ((n - 1) / 2, (n - 1) / 2 + 1);
+    values.push(sorted[below].clone());
+    while values.len() < n {
+        if (below + n - above).is_multiple_of(2) {
+            below -= 1;
+            values.push(sorted[below].clone());
+        } else {
+            values.push(sorted[above].clone());
+            above += 1;
+        }
+    }
+    values
+}
+
+/// Function that computes the majority gauge of sorted grades: the lower median, then `p` if `p > q` and `-q`
+/// otherwise, `p` and `q` being the numbers of votes above and below the median
+fn majority_gauge<G: Ord + Clone>(sorted: &[G]) -> Gauge<G> {
+    let Some(median) = sorted.get(sorted.len().saturating_sub(1) / 2) else {
+        return (None, 0);
+    };
+    let below = sorted.partition_point(|grade| grade < median) as i128;
+    let above = (sorted.len() - sorted.partition_point(|grade| grade <= median)) as i128;
+    (Some(median.clone()), if above > below { above } else { -below })
+}
+
+/// Function that checks all the candidates have the same number of grades
+fn check_lengths<K, G>(poll_data: &BTreeMap<K, Vec<G>>) -> Result<(), AuditError<'_, K>> {
+    let mut lengths = poll_data.values().map(|grades| grades.len() as u64);
+    if let Some(expected) = lengths.next() {
+        if let Some(found) = lengths.find(|&found| found != expected) {
+            return Err(AuditError::Ranking(RankingError::DifferentNumbersOfGrades { expected, found }));
+        }
+    }
+    Ok(())
+}
+
+/// Function that checks a majority judgment ranking against the iterative medians and the majority gauge
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `ranking`: the ranking of the poll data to check, with the default options
+///
+/// # Returns
+/// * `Result<(), AuditError<K>>`: nothing if the three computations agree, the first disagreement otherwise
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::audit::{audit_ranking, AuditError};
+/// use majority_judgement_rust::majority_judgment_ranking;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![0, 2, 3, 3]);
+/// poll_data.insert("Chips", vec![0, 2, 2, 2]);
+/// assert_eq!(audit_ranking(&poll_data, &majority_judgment_ranking(&poll_data)), Ok(()));
+///
+/// // the ranking of another count
+/// let mut recount = poll_data.clone();
+/// recount.insert("Chips", vec![0, 3, 3, 3]);
+/// let error = audit_ranking(&poll_data, &majority_judgment_ranking(&recount)).unwrap_err();
+/// assert_eq!(error, AuditError::Rank { candidate: &"Pizza", expected: 0, found: 1 });
+/// ```
+pub fn audit_ranking<'a, K: Ord, G: Ord + Clone>(poll_data: &'a BTreeMap<K, Vec<G>>, ranking: &Ranking<'_, K, G>) -> Result<(), AuditError<'a, K>> {
+    check_lengths(poll_data)?;
+    let mut audited: Vec<(&K, Vec<G>, Gauge<G>)> = poll_data
+        .iter()
+        .map(|(candidate, grades)| {
+            let mut sorted = grades.clone();
+            sorted.sort();
+            (candidate, iterative_medians(&sorted), majority_gauge(&sorted))
+        })
+        .collect();
+    audited.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut found: BTreeMap<&K, (usize, &Option<G>)> = BTreeMap::new();
+    for ranked in ranking.iter() {
+        let Some((candidate, _)) = poll_data.get_key_value(ranked.candidate) else {
+            return Err(AuditError::Candidates);
+        };
+        if found.insert(candidate, (ranked.rank, &ranked.majority_grade)).is_some() {
+            return Err(AuditError::Candidates);
+        }
+    }
+    if found.len() != poll_data.len() {
+        return Err(AuditError::Candidates);
+    }
+
+    let mut expected = 0;
+    for (index, (candidate, values, gauge)) in audited.iter().enumerate() {
+        if index > 0 {
+            let (previous, previous_values, previous_gauge) = &audited[index - 1];
+            if values != previous_values {
+                expected = index;
+            }
+            if gauge > previous_gauge {
+                return Err(AuditError::Gauge { above: previous, below: candidate });
+            }
+        }
+        let (rank, majority_grade) = found[candidate];
+        if rank != expected {
+            return Err(AuditError::Rank { candidate, expected, found: rank });
+        }
+        if *majority_grade != gauge.0 {
+            return Err(AuditError::MajorityGrade { candidate });
+        }
+    }
+    Ok(())
+}
+
+/// Function that ranks a poll by the majority judgment and audits the ranking, see `audit_ranking`
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+///
+/// # Returns
+/// * `Result<Ranking<K, G>, AuditError<K>>`: the ranking of `majority_judgment_ranking`, or the first
+///   disagreement between the independent computations
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::audit::audited_majority_judgment;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![0, 2, 3, 3]);
+/// poll_data.insert("Chips", vec![0, 2, 2, 2]);
+/// assert_eq!(audited_majority_judgment(&poll_data).unwrap().winners(), vec![&"Pizza"]);
+/// assert!(audited_majority_judgment(&BTreeMap::from([("Pizza", vec![1]), ("Chips", vec![])])).is_err());
+/// ```
+pub fn audited_majority_judgment<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>) -> Result<Ranking<'_, K, G>, AuditError<'_, K>> {
+    check_lengths(poll_data)?;
+    let ranking = majority_judgment_ranking(poll_data);
+    audit_ranking(poll_data, &ranking)?;
+    Ok(ranking)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tie_breaker::splitmix64;
+
+    #[test]
+    fn calling_audited_majority_judgment() {
+        for grades in [&[][..], &[4], &[0, 5], &[2, 2, 0, 5, 5, 5, 1], &[0, 0, 0, 0, 9, 9]] {
+            let mut sorted = grades.to_vec();
+            sorted.sort();
+            let mut reference = Vec::new();
+            while !sorted.is_empty() {
+                reference.push(sorted.remove((sorted.len() - 1) / 2));
+            }
+            let mut sorted = grades.to_vec();
+            sorted.sort();
+            assert_eq!(iterative_medians(&sorted), reference);
+        }
+        assert_eq!(majority_gauge(&[0, 0, 1, 1, 2]), (Some(1), -2));
+        assert_eq!(majority_gauge(&[0, 1, 2, 2, 2]), (Some(2), -2));
+        assert_eq!(majority_gauge(&[0, 1, 1, 2, 3]), (Some(1), 2));
+
+        // random polls: the three computations always agree
+        let mut state = 0;
+        let mut next = |bound: u64| {
+            state += 1;
+            splitmix64(state) % bound
+        };
+        for _ in 0..2000 {
+            let voters = next(12);
+            let grades = next(5) + 1;
+            let poll_data: BTreeMap<u64, Vec<u64>> = (0..next(6)).map(|candidate| (candidate, (0..voters).map(|_| next(grades)).collect())).collect();
+            assert!(audited_majority_judgment(&poll_data).is_ok(), "{:?}", poll_data);
+        }
+
+        // a ranking of other grades, or of other candidates
+        let poll_data = BTreeMap::from([("Pizza", vec![0, 2, 3]), ("Chips", vec![1, 1, 2])]);
+        let other = BTreeMap::from([("Pizza", vec![0, 3, 3]), ("Chips", vec![1, 1, 2])]);
+        assert_eq!(audit_ranking(&poll_data, &majority_judgment_ranking(&other)), Err(AuditError::MajorityGrade { candidate: &"Pizza" }));
+        let fewer = BTreeMap::from([("Pizza", vec![0, 2, 3])]);
+        assert_eq!(audit_ranking(&poll_data, &majority_judgment_ranking(&fewer)), Err(AuditError::Candidates));
+        let unequal = BTreeMap::from([("Pizza", vec![1]), ("Chips", vec![])]);
+        assert_eq!(audited_majority_judgment(&unequal).unwrap_err().to_string(), "a candidate has 1 grades instead of 0");
+    }
+}
@@ -0,0 +1,412 @@
+//! # Binary ballot files
+//! A compact ballot format for very large elections (enabled by the `mmap` feature): one byte per grade and
+//! a fixed record length, so that a file of hundreds of millions of ballots is tallied without parsing a line.
+//! `BallotFile::open` maps the file into memory (`mmap`, on 64-bit Linux, Android, Darwin and BSD targets;
+//! the file is read at once elsewhere) and reads the ballots in place, without copying them. Mapping is
+//! `unsafe`: the file must stay unchanged while it is open. `BallotFile::read` reads the file into memory,
+//! without this requirement. `BallotFileWriter` writes the format.
+//!
+//! The file starts with a header, the integers being little-endian:
+//! * the magic bytes `MJB1`
+//! * the number of candidates, as a `u32`
+//! * the name of each candidate: its length in bytes as a `u32`, then its UTF-8 bytes
+//!
+//! Then each ballot is a record of one byte per candidate, in the order of the header: the grade given to
+//! the candidate, or `NO_GRADE` (255) if the ballot does not grade it.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The byte of a candidate the ballot does not grade
+pub const NO_GRADE: u8 = u8::MAX;
+
+/// The first bytes of a ballot file
+const MAGIC: &[u8; 4] = b"MJB1";
+
+/// Why a ballot file cannot be read, see `BallotFile::open`
+#[derive(Debug)]
+pub enum BallotFileError {
+    /// The file cannot be read
+    Io(io::Error),
+    /// The file does not start with the magic bytes `MJB1`
+    NotABallotFile,
+    /// The header ends before the names of all the candidates, or a name is not UTF-8
+    InvalidHeader,
+    /// The file ends in the middle of a ballot, with the bytes of this last ballot
+    PartialBallot(usize),
+}
+
+impl fmt::Display for BallotFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BallotFileError::Io(error) => write!(f, "{}", error),
+            BallotFileError::NotABallotFile => write!(f, "not a ballot file"),
+            BallotFileError::InvalidHeader => write!(f, "invalid header"),
+            BallotFileError::PartialBallot(bytes) => write!(f, "the last ballot only has {} bytes", bytes),
+        }
+    }
+}
+
+impl std::error::Error for BallotFileError {}
+
+impl From<io::Error> for BallotFileError {
+    fn from(error: io::Error) -> Self {
+        BallotFileError::Io(error)
+    }
+}
+
+/// The mapping of a file into memory, on the targets whose `<sys/mman.h>` is known
+#[cfg(all(
+    target_pointer_width = "64",
+    any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd", target_os = "dragonfly"),
+))]
+mod mapping {
+    use std::ffi::{c_int, c_void};
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+
+    // the same values in the <sys/mman.h> of Linux (asm-generic/mman-common.h), Darwin and the BSDs
+    const PROT_READ: c_int = 0x1;
+    const MAP_PRIVATE: c_int = 0x2;
+    // `off_t` is 64 bits on these targets
+    type OffT = i64;
+
+    extern "C" {
+        fn mmap(address: *mut c_void, length: usize, protection: c_int, flags: c_int, fd: c_int, offset: OffT) -> *mut c_void;
+        fn munmap(address: *mut c_void, length: usize) -> c_int;
+    }
+
+    /// A read-only mapping of a whole file, unmapped when dropped
+    #[derive(Debug)]
+    pub(super) struct Mapping {
+        address: *mut c_void,
+        length: usize,
+    }
+
+    // the mapping is read-only and owned by this value only
+    unsafe impl Send for Mapping {}
+    unsafe impl Sync for Mapping {}
+
+    impl Mapping {
+        /// Function that maps a file of `length` bytes, `length` being above 0
+        ///
+        /// # Safety
+        /// * the file must not be truncated nor modified while the mapping lives, see `BallotFile::open`
+        pub(super) unsafe fn new(file: &File, length: usize) -> io::Result<Self> {
+            // SAFETY: a new private mapping, read-only, of an open file descriptor
+            let address = unsafe { mmap(std::ptr::null_mut(), length, PROT_READ, MAP_PRIVATE, file.as_raw_fd(), 0) };
+            // MAP_FAILED
+            if address as usize == usize::MAX {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Mapping { address, length })
+        }
+
+        pub(super) fn bytes(&self) -> &[u8] {
+            // SAFETY: the mapping holds `length` readable bytes until it is dropped, the file being left
+            // unchanged as required by `Mapping::new`
+            unsafe { std::slice::from_raw_parts(self.address as *const u8, self.length) }
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            // SAFETY: the mapping was created by `mmap` with this length and is not used any more
+            unsafe {
+                munmap(self.address, self.length);
+            }
+        }
+    }
+}
+
+/// The file read at once, on the other targets
+#[cfg(not(all(
+    target_pointer_width = "64",
+    any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd", target_os = "dragonfly"),
+)))]
+mod mapping {
+    use std::fs::File;
+    use std::io::{self, Read};
+
+    #[derive(Debug)]
+    pub(super) struct Mapping(Vec<u8>);
+
+    impl Mapping {
+        /// Function that reads a file of `length` bytes
+        ///
+        /// # Safety
+        /// * nothing is required, the signature being the one of the mapping
+        pub(super) unsafe fn new(mut file: &File, length: usize) -> io::Result<Self> {
+            let mut bytes = Vec::with_capacity(length);
+            file.read_to_end(&mut bytes)?;
+            Ok(Mapping(bytes))
+        }
+
+        pub(super) fn bytes(&self) -> &[u8] {
+            &self.0
+        }
+    }
+}
+
+/// The bytes of a ballot file, mapped or read into memory
+#[derive(Debug)]
+enum Bytes {
+    Mapped(mapping::Mapping),
+    Owned(Vec<u8>),
+}
+
+impl Bytes {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Bytes::Mapped(mapping) => mapping.bytes(),
+            Bytes::Owned(bytes) => bytes,
+        }
+    }
+}
+
+/// A binary ballot file, its ballots read in place
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::ballot_file::{BallotFile, BallotFileWriter};
+/// let mut writer = BallotFileWriter::new(Vec::new(), ["Pizza", "Chips"]).unwrap();
+/// writer.write_ballot(&[3, 1]).unwrap();
+/// writer.write_ballot(&[2, 1]).unwrap();
+/// let file = BallotFile::from_bytes(writer.finish().unwrap()).unwrap();
+///
+/// assert_eq!(file.candidates(), ["Pizza", "Chips"]);
+/// assert_eq!(file.ballot(1), Some(&[2, 1][..]));
+/// assert_eq!(file.tally()["Chips"][&1], 2);
+/// ```
+#[derive(Debug)]
+pub struct BallotFile {
+    bytes: Bytes,
+    candidates: Vec<String>,
+    /// The position of the first ballot
+    start: usize,
+}
+
+impl BallotFile {
+    /// Function that opens a ballot file, mapping it into memory instead of reading it
+    ///
+    /// The ballots are read from the file itself, as they are needed: a file of several gigabytes is
+    /// tallied without holding it in memory. `BallotFile::read` reads the file at once instead, without
+    /// any requirement.
+    ///
+    /// # Arguments
+    /// * `path`: the path of the file
+    ///
+    /// # Returns
+    /// * `Result<BallotFile, BallotFileError>`: the file, or why it cannot be read
+    ///
+    /// # Safety
+    /// * the file must not be truncated nor modified, by this process or any other, until the `BallotFile`
+    ///   is dropped: the bytes of the ballots would change under the reader, or reading them would raise
+    ///   `SIGBUS`. Write the file once, then open it read-only, e.g. on a file system without other writers.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Self, BallotFileError> {
+        let file = File::open(path)?;
+        let length = usize::try_from(file.metadata()?.len()).map_err(|_| BallotFileError::InvalidHeader)?;
+        if length < MAGIC.len() {
+            return Err(BallotFileError::NotABallotFile);
+        }
+        // SAFETY: the file is left unchanged as long as the `BallotFile` lives, as required from the caller
+        BallotFile::parse(Bytes::Mapped(unsafe { mapping::Mapping::new(&file, length)? }))
+    }
+
+    /// Function that reads a ballot file into memory
+    ///
+    /// # Arguments
+    /// * `path`: the path of the file
+    ///
+    /// # Returns
+    /// * `Result<BallotFile, BallotFileError>`: the file, or why it cannot be read
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, BallotFileError> {
+        BallotFile::parse(Bytes::Owned(std::fs::read(path)?))
+    }
+
+    /// Function that reads a ballot file already in memory
+    ///
+    /// # Arguments
+    /// * `bytes`: the content of the file
+    ///
+    /// # Returns
+    /// * `Result<BallotFile, BallotFileError>`: the file, or why it is not a valid ballot file
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, BallotFileError> {
+        BallotFile::parse(Bytes::Owned(bytes))
+    }
+
+    fn parse(bytes: Bytes) -> Result<Self, BallotFileError> {
+        let data = bytes.as_slice();
+        if !data.starts_with(MAGIC) {
+            return Err(BallotFileError::NotABallotFile);
+        }
+        let mut position = MAGIC.len();
+        let read_u32 = |position: &mut usize| -> Result<usize, BallotFileError> {
+            let field = data.get(*position..*position + 4).ok_or(BallotFileError::InvalidHeader)?;
+            *position += 4;
+            Ok(u32::from_le_bytes(field.try_into().expect("4 bytes")) as usize)
+        };
+        let count = read_u32(&mut position)?;
+        // each name takes at least its length: a corrupted count cannot reserve more than the file
+        let mut candidates = Vec::with_capacity(count.min(data.len() / 4));
+        for _ in 0..count {
+            let length = read_u32(&mut position)?;
+            let name = data.get(position..position.saturating_add(length)).ok_or(BallotFileError::InvalidHeader)?;
+            candidates.push(std::str::from_utf8(name).map_err(|_| BallotFileError::InvalidHeader)?.to_string());
+            position += length;
+        }
+        let body = data.len() - position;
+        let partial = match candidates.len() {
+            0 => body,
+            width => body % width,
+        };
+        if partial > 0 {
+            return Err(BallotFileError::PartialBallot(partial));
+        }
+        Ok(BallotFile { bytes, candidates, start: position })
+    }
+
+    /// The candidates, in the order of the grades of each ballot
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    /// The number of ballots
+    pub fn len(&self) -> usize {
+        match self.candidates.len() {
+            0 => 0,
+            width => (self.bytes.as_slice().len() - self.start) / width,
+        }
+    }
+
+    /// Whether the file has no ballot
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The grades of a ballot, one byte per candidate, None beyond the last ballot
+    pub fn ballot(&self, index: usize) -> Option<&[u8]> {
+        let width = self.candidates.len();
+        let start = self.start + index.checked_mul(width)?;
+        (index < self.len()).then(|| &self.bytes.as_slice()[start..start + width])
+    }
+
+    /// The ballots, in the order of the file, one byte per candidate
+    pub fn ballots(&self) -> impl ExactSizeIterator<Item = &[u8]> {
+        self.bytes.as_slice()[self.start..].chunks_exact(self.candidates.len().max(1)).take(self.len())
+    }
+
+    /// Function that counts the grades of each candidate
+    ///
+    /// # Returns
+    /// * `BTreeMap<String, BTreeMap<u8, u64>>`: for each candidate, the number of votes of each grade,
+    ///   `NO_GRADE` left out, e.g. for `majority_judgment_from_tally`
+    pub fn tally(&self) -> BTreeMap<String, BTreeMap<u8, u64>> {
+        let mut counts = vec![[0u64; 256]; self.candidates.len()];
+        for ballot in self.ballots() {
+            for (count, &grade) in counts.iter_mut().zip(ballot) {
+                count[grade as usize] += 1;
+            }
+        }
+        self.candidates
+            .iter()
+            .zip(counts)
+            .map(|(candidate, count)| {
+                let tally = (0..NO_GRADE).filter(|&grade| count[grade as usize] > 0).map(|grade| (grade, count[grade as usize]));
+                (candidate.clone(), tally.collect())
+            })
+            .collect()
+    }
+}
+
+/// The writer of a binary ballot file, see the `ballot_file` module for the format
+#[derive(Debug)]
+pub struct BallotFileWriter<W: Write> {
+    writer: W,
+    candidates: usize,
+}
+
+impl<W: Write> BallotFileWriter<W> {
+    /// Function that writes the header of a ballot file
+    ///
+    /// # Arguments
+    /// * `writer`: where the file is written, e.g. a `BufWriter<File>`
+    /// * `candidates`: the names of the candidates, in the order of the grades of each ballot
+    ///
+    /// # Returns
+    /// * `io::Result<BallotFileWriter<W>>`: the writer, ready for the ballots
+    pub fn new<I, S>(mut writer: W, candidates: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let candidates: Vec<S> = candidates.into_iter().collect();
+        let too_long = || io::Error::new(io::ErrorKind::InvalidInput, "more than u32::MAX bytes");
+        writer.write_all(MAGIC)?;
+        writer.write_all(&u32::try_from(candidates.len()).map_err(|_| too_long())?.to_le_bytes())?;
+        for candidate in &candidates {
+            let name = candidate.as_ref().as_bytes();
+            writer.write_all(&u32::try_from(name.len()).map_err(|_| too_long())?.to_le_bytes())?;
+            writer.write_all(name)?;
+        }
+        Ok(BallotFileWriter { writer, candidates: candidates.len() })
+    }
+
+    /// Function that writes a ballot
+    ///
+    /// # Arguments
+    /// * `grades`: the grade of each candidate, in the order of the header, `NO_GRADE` if it is not graded
+    ///
+    /// # Returns
+    /// * `io::Result<()>`: an `InvalidInput` error if the ballot does not grade all the candidates
+    pub fn write_ballot(&mut self, grades: &[u8]) -> io::Result<()> {
+        if grades.len() != self.candidates {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} grades for {} candidates", grades.len(), self.candidates)));
+        }
+        self.writer.write_all(grades)
+    }
+
+    /// Function that flushes the file and returns the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_open() {
+        let mut writer = BallotFileWriter::new(Vec::new(), ["Pizza", "Crème brûlée", "Chips"]).unwrap();
+        for ballot in [[0, 3, 2], [1, NO_GRADE, 2], [1, 3, 0]] {
+            writer.write_ballot(&ballot).unwrap();
+        }
+        assert_eq!(writer.write_ballot(&[1, 2]).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        let bytes = writer.finish().unwrap();
+
+        let path = std::env::temp_dir().join(format!("majority-judgment-{}.mjb", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(BallotFile::read(&path).unwrap().tally(), BallotFile::from_bytes(bytes.clone()).unwrap().tally());
+        // SAFETY: the file is only removed once the ballot file is dropped
+        let file = unsafe { BallotFile::open(&path) }.unwrap();
+        assert_eq!((file.len(), file.candidates()[1].as_str()), (3, "Crème brûlée"));
+        assert_eq!(file.ballots().map(|ballot| ballot[2]).collect::<Vec<_>>(), [2, 2, 0]);
+        assert_eq!((file.ballot(1), file.ballot(3)), (Some(&[1, NO_GRADE, 2][..]), None));
+        let tally = file.tally();
+        assert_eq!(tally["Pizza"], BTreeMap::from([(0, 1), (1, 2)]));
+        assert_eq!(tally["Crème brûlée"], BTreeMap::from([(3, 2)]));
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(BallotFile::from_bytes(bytes[..bytes.len() - 1].to_vec()), Err(BallotFileError::PartialBallot(2))));
+        assert!(matches!(BallotFile::from_bytes(bytes[..10].to_vec()), Err(BallotFileError::InvalidHeader)));
+        assert!(matches!(BallotFile::from_bytes(b"MJB2".to_vec()), Err(BallotFileError::NotABallotFile)));
+        let empty = BallotFile::from_bytes(BallotFileWriter::new(Vec::new(), Vec::<String>::new()).unwrap().finish().unwrap()).unwrap();
+        assert!(empty.is_empty() && empty.tally().is_empty() && empty.ballots().next().is_none());
+    }
+}
@@ -0,0 +1,125 @@
+//! # Ballot papers
+//! Majority judgment also runs on paper: `generate_ballot_templates` writes the ballot of an election as plain
+//! text, `generate_html_ballot_templates` as a printable HTML page. Each question lists its candidates in their
+//! registration order, with one box per grade of its scale, from the worst to the best.
+//!
+//! The papers are then re-entered question by question as a ballots file, e.g. with the `csv` module: a header
+//! with the candidates of the question in the same order, then one row per paper with the labels checked,
+//! read with `GradeScale::parse_grade`.
+use std::fmt::Write;
+use crate::election::Election;
+use crate::report::escape;
+
+/// Function that writes the ballot paper of an election as plain text
+///
+/// # Arguments
+/// * `election`: the questions of the ballot, with their candidates and grade scales
+///
+/// # Returns
+/// * `String`: for each question, its name, then one line per candidate with a `[ ]` box before each grade label
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::scale::GradeScale;
+/// use majority_judgement_rust::{generate_ballot_templates, Election, Poll};
+/// let mut election = Election::new();
+/// election.add_question("Lunch", Poll::new(["Pizza", "Chips"]), GradeScale::new(["Bad", "Fair", "Good"])).unwrap();
+///
+/// assert_eq!(generate_ballot_templates(&election), "\
+/// Lunch
+/// Check one grade for each candidate.
+///
+/// Pizza  [ ] Bad  [ ] Fair  [ ] Good
+/// Chips  [ ] Bad  [ ] Fair  [ ] Good
+/// ");
+/// ```
+pub fn generate_ballot_templates(election: &Election) -> String {
+    let mut text = String::new();
+    for (index, question) in election.questions().iter().enumerate() {
+        if index > 0 {
+            text.push('\n');
+        }
+        let _ = writeln!(text, "{}\nCheck one grade for each candidate.\n", question.name);
+        let candidates = question.poll.candidates();
+        let width = candidates.iter().map(|candidate| candidate.chars().count()).max().unwrap_or(0);
+        for candidate in candidates {
+            let _ = write!(text, "{}{}", candidate, " ".repeat(width - candidate.chars().count()));
+            for label in question.scale.labels() {
+                let _ = write!(text, "  [ ] {}", label);
+            }
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Function that writes the ballot paper of an election as a self-contained HTML page, ready to print
+///
+/// # Arguments
+/// * `election`: the questions of the ballot, with their candidates and grade scales
+///
+/// # Returns
+/// * `String`: the HTML page with one table per question, a row per candidate and a column per grade
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::scale::GradeScale;
+/// use majority_judgement_rust::{generate_html_ballot_templates, Election, Poll};
+/// let mut election = Election::new();
+/// election.add_question("Lunch", Poll::new(["Pizza", "Chips"]), GradeScale::new(["Bad", "Good"])).unwrap();
+///
+/// let html = generate_html_ballot_templates(&election);
+/// assert!(html.contains("<tr><th></th><th>Bad</th><th>Good</th></tr>"));
+/// assert!(html.contains("<tr><td>Chips</td><td>&#9744;</td><td>&#9744;</td></tr>"));
+/// ```
+pub fn generate_html_ballot_templates(election: &Election) -> String {
+    let mut html = String::new();
+    let _ = writeln!(html, "<!DOCTYPE html>");
+    let _ = writeln!(html, r#"<html><head><meta charset="utf-8"><title>Ballot</title></head>"#);
+    let _ = writeln!(html, r#"<body style="font-family: sans-serif">"#);
+    for question in election.questions() {
+        let _ = writeln!(html, "<h2>{}</h2>", escape(&question.name));
+        let _ = writeln!(html, "<p>Check one grade for each candidate.</p>");
+        let _ = writeln!(html, r#"<table style="border-collapse: collapse; margin-bottom: 2em; text-align: center">"#);
+        let labels: String = question.scale.labels().iter().map(|label| format!("<th>{}</th>", escape(label))).collect();
+        let _ = writeln!(html, "<tr><th></th>{}</tr>", labels);
+        let boxes = "<td>&#9744;</td>".repeat(question.scale.len());
+        for candidate in question.poll.candidates() {
+            let _ = writeln!(html, "<tr><td>{}</td>{}</tr>", escape(candidate), boxes);
+        }
+        let _ = writeln!(html, "</table>");
+    }
+    let _ = writeln!(html, "</body></html>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poll::Poll;
+    use crate::scale::{GradeScale, Language};
+
+    #[test]
+    fn calling_generate_ballot_templates() {
+        let mut election = Election::new();
+        election.add_question("Parks", Poll::new(["Pond", "Playground"]), GradeScale::thumbs(Language::English)).unwrap();
+        election.add_question("Fish & chips", Poll::new(["<Cod>"]), GradeScale::new(["No", "Yes"])).unwrap();
+
+        let text = generate_ballot_templates(&election);
+        assert!(text.contains("Pond        [ ] Thumbs down  [ ] Neutral  [ ] Thumbs up\nPlayground  [ ] Thumbs down"));
+        assert!(text.ends_with("\n\nFish & chips\nCheck one grade for each candidate.\n\n<Cod>  [ ] No  [ ] Yes\n"));
+        let html = generate_html_ballot_templates(&election);
+        assert!(html.contains("<h2>Fish &amp; chips</h2>") && html.contains("<tr><td>&lt;Cod&gt;</td><td>&#9744;</td><td>&#9744;</td></tr>"));
+        assert_eq!(html.matches("<table").count(), 2);
+        assert_eq!(generate_ballot_templates(&Election::new()), "");
+
+        // the papers are re-entered with the candidates in the same order, reading the labels checked
+        #[cfg(feature = "csv")]
+        {
+            let parks = election.question("Parks").unwrap();
+            let header = parks.poll.candidates().join(",");
+            let poll = crate::csv::from_csv(&format!("{}\nThumbs up,Neutral\n", header), ',', |label| parks.scale.parse_grade(label)).unwrap();
+            assert_eq!(poll.poll_data().unwrap()["Pond"], vec![2]);
+        }
+    }
+}
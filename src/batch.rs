@@ -0,0 +1,233 @@
+//! # Batches of polls
+//! Platforms hosting community polls rank thousands of small polls at once. `majority_judgment_batch` ranks
+//! every poll of a slice from its ballots and their weights: the grades of each poll are counted into the same
+//! buffers, reused from one poll to the next, instead of being transposed into vectors of grades.
+//! `majority_judgment_batch_parallel` shares the polls between threads. An invalid poll does not stop the batch,
+//! its result holds the issues found on its ballots.
+use std::collections::BTreeMap;
+use crate::judgment::{median_shares_with, MedianShares};
+use crate::mj::{rank_candidates, OptionsMethod};
+use crate::options::{MedianConvention, Options};
+use crate::poll::{BallotError, Poll};
+use crate::ranking::OwnedRanking;
+use crate::ranking_method::{Merit, RankingMethod};
+
+/// The merit and the majority grade of each candidate of a poll, in the order of its candidates
+type Counted<G> = Vec<(Merit<G>, Option<MedianShares<G>>)>;
+
+/// The ranking of each poll of a batch, or the issues found on its ballots
+pub type BatchResults<G> = Vec<Result<OwnedRanking<String, G>, Vec<BallotError>>>;
+
+/// The buffers reused from one poll to the next
+struct Buffers<'p, G> {
+    /// The candidates of the poll sorted by name, with their position
+    index: Vec<(&'p str, usize)>,
+    /// The tally of each candidate, by position
+    tallies: Vec<BTreeMap<G, u64>>,
+}
+
+impl<'p, G: Ord + Clone> Buffers<'p, G> {
+    fn new() -> Self {
+        Buffers { index: Vec::new(), tallies: Vec::new() }
+    }
+
+    /// Function that counts the ballots of a poll with their weights and evaluates its candidates
+    ///
+    /// # Arguments
+    /// * `poll`: the poll, its grades being clamped to its grade range if any
+    /// * `method`: the ranking method of the options
+    /// * `median`: the median convention of the majority grades
+    ///
+    /// # Returns
+    /// * `Result<Counted<G>, Vec<BallotError>>`: the merit and the majority grade of each candidate,
+    ///   or the issues found on the ballots
+    fn count(&mut self, poll: &'p Poll<G>, method: &OptionsMethod<G>, median: MedianConvention) -> Result<Counted<G>, Vec<BallotError>> {
+        let errors = poll.validate();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let candidates = poll.candidates();
+        self.index.clear();
+        self.index.extend(candidates.iter().enumerate().map(|(position, candidate)| (candidate.as_str(), position)));
+        self.index.sort_unstable();
+        for tally in self.tallies.iter_mut() {
+            tally.clear();
+        }
+        if self.tallies.len() < candidates.len() {
+            self.tallies.resize_with(candidates.len(), BTreeMap::new);
+        }
+
+        for ballot in poll.ballots().iter().filter(|ballot| ballot.weight() > 0) {
+            for (candidate, grade) in ballot.grades() {
+                // a valid ballot only grades the candidates of the poll
+                if let Ok(found) = self.index.binary_search_by(|&(name, _)| name.cmp(candidate.as_str())) {
+                    *self.tallies[self.index[found].1].entry(poll.counted(grade)).or_insert(0) += ballot.weight() as u64;
+                }
+            }
+        }
+        Ok(self.tallies[..candidates.len()]
+            .iter()
+            .map(|tally| (method.merit(tally), median_shares_with(tally, median)))
+            .collect())
+    }
+}
+
+/// Function that ranks a poll from the merits of its candidates
+///
+/// # Arguments
+/// * `poll`: the poll counted
+/// * `counted`: the merit and the majority grade of each candidate, see `Buffers::count`
+/// * `options`: the tie breaker, the tie order and the "none of the above" candidate of the ranking
+///
+/// # Returns
+/// * `Result<OwnedRanking<String, G>, Vec<BallotError>>`: the ranking of the poll
+fn rank<G: Ord + Clone>(poll: &Poll<G>, counted: Counted<G>, options: &Options<G>) -> Result<OwnedRanking<String, G>, Vec<BallotError>> {
+    // only a tie breaker looks at the grades themselves
+    let poll_data = match options.tie_breaker {
+        Some(_) => poll.poll_data()?,
+        None => BTreeMap::new(),
+    };
+    let mut merits = BTreeMap::new();
+    let mut shares = BTreeMap::new();
+    for (candidate, (merit, median)) in poll.candidates().iter().zip(counted) {
+        merits.insert(candidate, merit);
+        shares.insert(candidate, median);
+    }
+
+    let mut ranking = rank_candidates(merits, &poll_data, options.tie_breaker.as_deref(), options.tie_order)
+        .with_majority_grades(|candidate| shares[candidate].clone());
+    if let Some(candidate) = options.none_of_the_above.as_ref().and_then(|none| poll.candidates().iter().find(|&candidate| candidate == none)) {
+        ranking = ranking.with_none_of_the_above(candidate);
+    }
+    Ok(ranking.to_owned_ranking())
+}
+
+/// Function that ranks many independent polls with the default options
+///
+/// # Arguments
+/// * `polls`: the polls, each ballot counting with its weight
+///
+/// # Returns
+/// * `BatchResults<G>`: the ranking of each poll, in the order of the polls, or the issues found on its ballots
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{majority_judgment_batch, Ballot, Poll};
+/// let mut lunch = Poll::new(["Pizza", "Chips"]);
+/// lunch.add_ballot(Ballot::new().grade("Pizza", 2).grade("Chips", 1));
+/// let mut dinner = Poll::new(["Soup", "Pasta"]);
+/// dinner.add_ballot(Ballot::new().grade("Soup", 0).grade("Pasta", 3).with_weight(2));
+/// dinner.add_ballot(Ballot::new().grade("Soup", 1).grade("Pasta", 0));
+///
+/// let results = majority_judgment_batch(&[lunch, dinner]);
+/// assert_eq!(results[0].as_ref().unwrap().ranking().winners(), vec!["Pizza"]);
+/// assert_eq!(results[1].as_ref().unwrap().ranking().winners(), vec!["Pasta"]);
+/// ```
+pub fn majority_judgment_batch<G: Ord + Clone>(polls: &[Poll<G>]) -> BatchResults<G> {
+    majority_judgment_batch_with_options(polls, &Options::new())
+}
+
+/// Function that ranks many independent polls with the given options
+///
+/// # Arguments
+/// * `polls`: the polls, each ballot counting with its weight
+/// * `options`: the options of the majority judgment, the same for every poll. The "none of the above"
+///   candidate is marked in the polls having it.
+///
+/// # Returns
+/// * `BatchResults<G>`: the ranking of each poll, in the order of the polls, or the issues found on its ballots
+///
+/// # Panics
+/// * for the mean grade without `Options::grade_value`
+pub fn majority_judgment_batch_with_options<G: Ord + Clone>(polls: &[Poll<G>], options: &Options<G>) -> BatchResults<G> {
+    let method = OptionsMethod::new(options);
+    let mut buffers = Buffers::new();
+    polls
+        .iter()
+        .map(|poll| {
+            let counted = buffers.count(poll, &method, options.median)?;
+            rank(poll, counted, options)
+        })
+        .collect()
+}
+
+/// Function that ranks many independent polls, counting them in parallel
+///
+/// The polls are split evenly between as many scoped threads as the available parallelism, each thread
+/// counting its polls in its own buffers. The results are the same as `majority_judgment_batch_with_options`.
+///
+/// # Arguments
+/// * `polls`: the polls, each ballot counting with its weight
+/// * `options`: the options of the majority judgment, the same for every poll
+///
+/// # Returns
+/// * `BatchResults<G>`: the ranking of each poll, in the order of the polls, or the issues found on its ballots
+#[cfg(feature = "parallel")]
+pub fn majority_judgment_batch_parallel<G: Ord + Clone + Send + Sync>(polls: &[Poll<G>], options: &Options<G>) -> BatchResults<G> {
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let chunk_size = polls.len().div_ceil(threads).max(1);
+    let method = OptionsMethod::new(options);
+    let (method, median) = (&method, options.median);
+
+    let counted: Vec<Result<Counted<G>, Vec<BallotError>>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = polls
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || {
+                let mut buffers = Buffers::new();
+                chunk.iter().map(|poll| buffers.count(poll, method, median)).collect::<Vec<_>>()
+            }))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("a poll counting thread panicked"))
+            .collect()
+    });
+    polls.iter().zip(counted).map(|(poll, counted)| rank(poll, counted?, options)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mj::majority_judgment_with_options;
+    use crate::options::BuiltinMethod;
+    use crate::poll::Ballot;
+    use crate::tie_breaker::Lexicographic;
+
+    #[test]
+    fn calling_majority_judgment_batch() {
+        let mut polls = Vec::new();
+        for seed in 0..20u8 {
+            let mut poll = Poll::new(["Pizza", "Chips", "Soup"]);
+            for voter in 0..(seed % 5 + 1) {
+                let grade = |offset: u8| (seed + voter * offset) % 4;
+                poll.add_ballot(Ballot::new().grade("Soup", grade(3)).grade("Pizza", grade(1)).grade("Chips", grade(2)));
+            }
+            polls.push(poll);
+        }
+        let mut invalid = Poll::new(["Pizza"]);
+        invalid.add_ballot(Ballot::new().grade("Pizza", 1).grade("Sushi", 2));
+        polls.insert(3, invalid);
+
+        let options = Options::new().method(BuiltinMethod::MajorityGauge).tie_breaker(Lexicographic);
+        let results = majority_judgment_batch_with_options(&polls, &options);
+        assert_eq!(results.len(), 21);
+        assert_eq!(results[3].as_ref().unwrap_err().len(), 1);
+        // the same rankings as one poll at a time
+        for (poll, result) in polls.iter().zip(results.iter()).filter(|(_, result)| result.is_ok()) {
+            let poll_data = poll.poll_data().unwrap();
+            assert_eq!(result.as_ref().unwrap(), &majority_judgment_with_options(&poll_data, &options).to_owned_ranking());
+        }
+        #[cfg(feature = "parallel")]
+        assert_eq!(majority_judgment_batch_parallel(&polls, &options), results);
+
+        // the weights count, as many times as the ballot
+        let mut weighted = Poll::new(["Pizza", "Chips"]);
+        weighted.add_ballot(Ballot::new().grade("Pizza", 3).grade("Chips", 0).with_weight(3));
+        weighted.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2).with_weight(2));
+        weighted.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 3).with_weight(0));
+        let ranking = majority_judgment_batch(&[weighted]).remove(0).unwrap();
+        assert_eq!(ranking.ranking().winners(), vec!["Pizza"]);
+        assert_eq!(ranking.ranking().candidates()[0].majority_grade, Some(3));
+    }
+}
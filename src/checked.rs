@@ -0,0 +1,241 @@
+//! # Checked rankings
+//! The rankings panic on misuse, e.g. candidates with different numbers of grades. A server ranking polls
+//! sent by its users, or a fuzzer, checks them first: `try_majority_judgment_with_options` and
+//! `try_majority_judgment_from_tally` return a `RankingError` instead of panicking, whatever the poll data
+//! and the counts of votes, up to `u64::MAX` votes per candidate. `try_majority_judgment_from_tally_with_options`
+//! ranks counts of votes with any built-in method, e.g. the majority gauge, refusing them beyond `u64::MAX`
+//! votes per candidate.
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use crate::judgment::{median_shares_with, total_votes};
+use crate::mj::{majority_judgment_from_tally, majority_judgment_with_options, rank_candidates, OptionsMethod};
+use crate::options::{BuiltinMethod, GradeValue, Options, Participation};
+use crate::ranking::Ranking;
+use crate::ranking_method::RankingMethod;
+
+/// Why a poll cannot be ranked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankingError {
+    /// Candidates with different numbers of grades, compared by the majority values of `Participation::Equal`
+    DifferentNumbersOfGrades { expected: u64, found: u64 },
+    /// A candidate with more than `u64::MAX` votes in total
+    TooManyVotes,
+    /// `BuiltinMethod::MeanGrade` without `Options::grade_value` nor `Options::exact_grade_value`
+    MissingGradeValue,
+    /// A "none of the above" candidate which is not part of the poll data
+    UnknownNoneOfTheAbove,
+    /// Weighted ballots whose number of weights differs from the number of grades of a candidate
+    DifferentNumbersOfWeights { weights: u64, grades: u64 },
+}
+
+impl fmt::Display for RankingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RankingError::DifferentNumbersOfGrades { expected, found } =>
+                write!(f, "a candidate has {} grades instead of {}", found, expected),
+            RankingError::TooManyVotes => write!(f, "a candidate has more than {} votes", u64::MAX),
+            RankingError::MissingGradeValue => write!(f, "the mean grade needs the number each grade counts for"),
+            RankingError::UnknownNoneOfTheAbove => write!(f, "\"none of the above\" is not a candidate of the poll"),
+            RankingError::DifferentNumbersOfWeights { weights, grades } =>
+                write!(f, "{} weights are given for {} grades", weights, grades),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RankingError {}
+
+/// Function that checks all the candidates have the same number of grades or votes
+fn check_same_totals(mut totals: impl Iterator<Item = u64>) -> Result<(), RankingError> {
+    if let Some(expected) = totals.next() {
+        if let Some(found) = totals.find(|&total| total != expected) {
+            return Err(RankingError::DifferentNumbersOfGrades { expected, found });
+        }
+    }
+    Ok(())
+}
+
+/// Function that counts the votes of each candidate
+fn totals<K, G>(tallies: &BTreeMap<K, BTreeMap<G, u64>>) -> Result<Vec<u64>, RankingError> {
+    tallies.values().map(|tally| total_votes(tally.values()).ok_or(RankingError::TooManyVotes)).collect()
+}
+
+/// Function that calculates the majority judgment of a poll with the given options, without panicking
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `options`: the options of the majority judgment, see `majority_judgment_with_options`
+///
+/// # Returns
+/// * `Result<Ranking, RankingError>`: the ranking of `majority_judgment_with_options`,
+///   or why the options cannot rank the poll data
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{try_majority_judgment_with_options, Options, RankingError};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![0u8, 2, 3]);
+/// poll_data.insert("Chips", vec![1u8, 3]);
+///
+/// let result = try_majority_judgment_with_options(&poll_data, &Options::new());
+/// assert_eq!(result.unwrap_err(), RankingError::DifferentNumbersOfGrades { expected: 2, found: 3 });
+/// ```
+pub fn try_majority_judgment_with_options<'a, K: Ord, G: Ord + Clone>(
+    poll_data: &'a BTreeMap<K, Vec<G>>,
+    options: &Options<G, K>,
+) -> Result<Ranking<'a, K, G>, RankingError> {
+    if options.method == BuiltinMethod::MajorityValues && options.participation == Participation::Equal {
+        check_same_totals(poll_data.values().map(|grades| grades.len() as u64))?;
+    }
+    if options.method == BuiltinMethod::MeanGrade && matches!(options.value(), GradeValue::Missing) {
+        return Err(RankingError::MissingGradeValue);
+    }
+    if options.none_of_the_above.as_ref().is_some_and(|candidate| !poll_data.contains_key(candidate)) {
+        return Err(RankingError::UnknownNoneOfTheAbove);
+    }
+    Ok(majority_judgment_with_options(poll_data, options))
+}
+
+/// Function that calculates the majority judgment of a poll given as the number of votes of each grade,
+/// without panicking
+///
+/// # Arguments
+/// * `tallies`: for each candidate, the number of votes of each grade, the absent grades counting 0
+///
+/// # Returns
+/// * `Result<Vec<(&K, usize)>, RankingError>`: the ranks of `majority_judgment_from_tally`, or why the
+///   counts cannot be ranked, e.g. a total of votes beyond `u64::MAX` read from a corrupted file
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{try_majority_judgment_from_tally, RankingError};
+/// let mut tallies = BTreeMap::new();
+/// tallies.insert("Pizza", BTreeMap::from([(0u8, u64::MAX), (1, 1)]));
+/// assert_eq!(try_majority_judgment_from_tally(&tallies), Err(RankingError::TooManyVotes));
+/// ```
+pub fn try_majority_judgment_from_tally<K: Ord, G: Ord + Clone>(tallies: &BTreeMap<K, BTreeMap<G, u64>>) -> Result<Vec<(&K, usize)>, RankingError> {
+    check_same_totals(totals(tallies)?.into_iter())?;
+    Ok(majority_judgment_from_tally(tallies))
+}
+
+/// Function that ranks a poll given as the number of votes of each grade with the given options, without panicking
+///
+/// # Arguments
+/// * `tallies`: for each candidate, the number of votes of each grade, the absent grades counting 0
+/// * `options`: the method, the participation, the grade values, the median convention, the tie order and the
+///   "none of the above" candidate of the ranking. The tallies do not hold the ballots: the tie breaker is given
+///   no grade, so `Lexicographic`, `RegistrationOrder` and `RandomTieBreaker` break the ties but `MeanGrade`
+///   leaves the candidates of equal merits tied.
+///
+/// # Returns
+/// * `Result<Ranking, RankingError>`: the candidates from the best to the worst, with their rank and majority grade,
+///   or why the options cannot rank the counts, e.g. more than `u64::MAX` votes for a candidate
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{try_majority_judgment_from_tally_with_options, BuiltinMethod, Options, RankingError};
+/// let gauge = Options::new().method(BuiltinMethod::MajorityGauge);
+/// let mut tallies = BTreeMap::new();
+/// tallies.insert("Pizza", BTreeMap::from([(0u8, u64::MAX / 2 + 10), (1, 5)]));
+/// tallies.insert("Chips", BTreeMap::from([(1u8, 3)]));
+/// let ranking = try_majority_judgment_from_tally_with_options(&tallies, &gauge).unwrap();
+/// assert_eq!(ranking.winners(), vec![&"Chips"]);
+///
+/// tallies.insert("Soup", BTreeMap::from([(0u8, u64::MAX), (1, 1)]));
+/// assert_eq!(try_majority_judgment_from_tally_with_options(&tallies, &gauge).unwrap_err(), RankingError::TooManyVotes);
+/// ```
+pub fn try_majority_judgment_from_tally_with_options<'a, K: Ord, G: Ord + Clone>(
+    tallies: &'a BTreeMap<K, BTreeMap<G, u64>>,
+    options: &Options<G, K>,
+) -> Result<Ranking<'a, K, G>, RankingError> {
+    let totals = totals(tallies)?;
+    if options.method == BuiltinMethod::MajorityValues && options.participation == Participation::Equal {
+        check_same_totals(totals.into_iter())?;
+    }
+    if options.method == BuiltinMethod::MeanGrade && matches!(options.value(), GradeValue::Missing) {
+        return Err(RankingError::MissingGradeValue);
+    }
+    let none_of_the_above = match &options.none_of_the_above {
+        Some(candidate) => Some(tallies.get_key_value(candidate).ok_or(RankingError::UnknownNoneOfTheAbove)?.0),
+        None => None,
+    };
+
+    let method = OptionsMethod::new(options);
+    let merits = tallies.iter().map(|(candidate, tally)| (candidate, method.merit(tally))).collect();
+    let ranking = rank_candidates(merits, &BTreeMap::new(), options.tie_breaker.as_deref(), options.tie_order)
+        .with_majority_grades(|candidate| median_shares_with(&tallies[candidate], options.median));
+    Ok(match none_of_the_above {
+        Some(candidate) => ranking.with_none_of_the_above(candidate),
+        None => ranking,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use crate::ranking::TieStatus;
+    use crate::tie_breaker::{splitmix64, Lexicographic, MeanGrade};
+
+    #[test]
+    fn calling_try_majority_judgment() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![0u8, 2, 3]);
+        poll_data.insert("Chips", vec![1u8, 3]);
+        assert!(try_majority_judgment_with_options(&poll_data, &Options::new().method(BuiltinMethod::MajorityGauge)).is_ok());
+        let mean = Options::new().method(BuiltinMethod::MeanGrade);
+        assert_eq!(try_majority_judgment_with_options(&poll_data, &mean).unwrap_err(), RankingError::MissingGradeValue);
+        let none = Options::new().participation(Participation::Proportional).none_of_the_above("Soup");
+        assert_eq!(try_majority_judgment_with_options(&poll_data, &none).unwrap_err(), RankingError::UnknownNoneOfTheAbove);
+
+        // the largest counts rank without overflowing
+        let mut tallies = BTreeMap::new();
+        tallies.insert("Pizza", BTreeMap::from([(0u8, u64::MAX - 1), (3, 1)]));
+        tallies.insert("Chips", BTreeMap::from([(1u8, u64::MAX / 2), (2, u64::MAX / 2 + 1)]));
+        assert_eq!(try_majority_judgment_from_tally(&tallies).unwrap()[0].0, &"Chips");
+        tallies.insert("Soup", BTreeMap::from([(2u8, 1)]));
+        assert_eq!(try_majority_judgment_from_tally(&tallies), Err(RankingError::DifferentNumbersOfGrades { expected: u64::MAX, found: 1 }));
+
+        // every method ranks counts up to u64::MAX votes, instead of doubling them
+        let mut tallies = BTreeMap::new();
+        tallies.insert("Pizza", BTreeMap::from([(0u8, u64::MAX / 2 + 10), (1, 5)]));
+        tallies.insert("Chips", BTreeMap::from([(0u8, 5), (1, u64::MAX / 2 + 10)]));
+        for method in [BuiltinMethod::MajorityValues, BuiltinMethod::MajorityGauge, BuiltinMethod::UsualJudgment, BuiltinMethod::TypicalJudgment] {
+            let ranking = try_majority_judgment_from_tally_with_options(&tallies, &Options::new().method(method)).unwrap();
+            assert_eq!(ranking.winners(), vec![&"Chips"]);
+            assert_eq!(ranking.iter().map(|ranked| ranked.majority_grade).collect::<Vec<_>>(), [Some(1), Some(0)]);
+        }
+        let none = Options::new().none_of_the_above("Chips");
+        assert!(matches!(try_majority_judgment_from_tally_with_options(&tallies, &none).unwrap().verdict(), crate::ranking::Verdict::NoWinner));
+        tallies.insert("Soup", BTreeMap::from([(0u8, u64::MAX), (2, 1)]));
+        let gauge = Options::new().method(BuiltinMethod::MajorityGauge);
+        assert_eq!(try_majority_judgment_from_tally_with_options(&tallies, &gauge).unwrap_err(), RankingError::TooManyVotes);
+
+        // the tie breakers comparing the candidates only break the ties of the counts
+        let tallies = BTreeMap::from([("Pizza", BTreeMap::from([(1u8, 2)])), ("Chips", BTreeMap::from([(1u8, 2)]))]);
+        let ranking = try_majority_judgment_from_tally_with_options(&tallies, &Options::new().tie_breaker(Lexicographic)).unwrap();
+        assert_eq!((ranking.winners(), ranking.candidates()[0].tie), (vec![&"Chips"], TieStatus::Broken));
+        let ranking = try_majority_judgment_from_tally_with_options(&tallies, &Options::new().tie_breaker(MeanGrade)).unwrap();
+        assert_eq!(ranking.winners().len(), 2);
+
+        // random poll data, of random lengths, never panic
+        let mut state = 0;
+        let mut next = |bound: u64| {
+            state += 1;
+            splitmix64(state) % bound
+        };
+        for _ in 0..500 {
+            let mut poll_data = BTreeMap::new();
+            for candidate in 0..next(4) {
+                let length = next(4);
+                poll_data.insert(candidate, (0..length).map(|_| next(3) as u8).collect::<Vec<_>>());
+            }
+            let method = [BuiltinMethod::MajorityValues, BuiltinMethod::UsualJudgment, BuiltinMethod::MeanGrade][next(3) as usize];
+            let _ = try_majority_judgment_with_options(&poll_data, &Options::new().method(method));
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! # Committees
+//! Multi-winner majority judgment: electing `k` candidates instead of one.
+//! * `CommitteeRule::Top`: the first `k` candidates of the ranking
+//! * `CommitteeRule::Sequential`: the seats are filled one at a time. After each election, the
+//!   supporters of the elected candidate (the ballots grading it at least at its majority grade)
+//!   count less: a ballot supporting `s` elected candidates weighs `1 / (1 + s)`, as in the D'Hondt method.
+//!   A majority then cannot take all the seats, the next seats go to the candidates of the minorities.
+use std::collections::BTreeMap;
+use crate::majority_value::MajorityValue;
+use crate::mj::{check_poll_length, majority_judgment_ranking};
+
+/// The weight of a ballot supporting no elected candidate: the weights `SCALE / (1 + s)` are integers
+/// as long as fewer than 16 candidates are elected, since 720720 is the least common multiple of 1 to 16
+const SCALE: u64 = 720_720;
+
+/// How the seats of a committee are filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommitteeRule {
+    /// The best candidates of the ranking
+    #[default]
+    Top,
+    /// One seat at a time, the supporters of the elected candidates being reweighted
+    Sequential,
+}
+
+/// Function that elects a committee with the majority judgment
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, each vector ordered by ballot
+/// * `seats`: the number of candidates to elect
+/// * `rule`: how the seats are filled
+///
+/// # Returns
+/// * `Vec<&K>`: the elected candidates in their order of election, all the candidates if there are
+///   fewer than `seats`. Tied candidates are elected in the order of the poll data.
+///
+/// # Panics
+/// * if the candidates have different numbers of grades
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{majority_judgment_multi, CommitteeRule};
+/// let mut poll_data = BTreeMap::new();
+/// // three voters support the left wing, two the right wing
+/// poll_data.insert("Left A".to_string(), vec![3, 3, 3, 0, 0]);
+/// poll_data.insert("Left B".to_string(), vec![3, 3, 2, 0, 0]);
+/// poll_data.insert("Right".to_string(), vec![0, 0, 0, 3, 3]);
+///
+/// assert_eq!(majority_judgment_multi(&poll_data, 2, CommitteeRule::Top), vec!["Left A", "Left B"]);
+/// assert_eq!(majority_judgment_multi(&poll_data, 2, CommitteeRule::Sequential), vec!["Left A", "Right"]);
+/// ```
+pub fn majority_judgment_multi<K: Ord, G: Ord + Clone>(
+    poll_data: &BTreeMap<K, Vec<G>>,
+    seats: usize,
+    rule: CommitteeRule,
+) -> Vec<&K> {
+    if poll_data.is_empty() || seats == 0 {
+        return Vec::new();
+    }
+    match rule {
+        CommitteeRule::Top => majority_judgment_ranking(poll_data)
+            .iter()
+            .take(seats)
+            .map(|ranked| ranked.candidate)
+            .collect(),
+        CommitteeRule::Sequential => elect_sequentially(poll_data, seats),
+    }
+}
+
+/// Function that fills the seats one at a time, reweighting the supporters of the elected candidates
+fn elect_sequentially<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>, seats: usize) -> Vec<&K> {
+    let _ = check_poll_length(poll_data);
+    let ballots = poll_data.values().next().map_or(0, Vec::len);
+    let mut supported = vec![0u64; ballots];
+    let mut weights = vec![SCALE; ballots];
+    let mut elected: Vec<&K> = Vec::new();
+
+    while elected.len() < seats.min(poll_data.len()) {
+        let mut best: Option<(&K, MajorityValue<G>, BTreeMap<G, u64>)> = None;
+        for (candidate, grades) in poll_data.iter().filter(|(candidate, _)| !elected.contains(candidate)) {
+            let tally = weighted_tally(grades, &weights);
+            let majority_value = MajorityValue::from_tally(&tally);
+            if best.as_ref().is_none_or(|(_, best_value, _)| majority_value > *best_value) {
+                best = Some((candidate, majority_value, tally));
+            }
+        }
+        let Some((winner, _, tally)) = best else { break };
+
+        if let Some(majority_grade) = lower_median(&tally) {
+            for ((grade, supported), weight) in poll_data[winner].iter().zip(supported.iter_mut()).zip(weights.iter_mut()) {
+                if *grade >= majority_grade {
+                    *supported += 1;
+                    *weight = SCALE / (1 + *supported);
+                }
+            }
+        }
+        elected.push(winner);
+    }
+    elected
+}
+
+/// Function that computes the total weight given to each grade
+fn weighted_tally<G: Ord + Clone>(grades: &[G], weights: &[u64]) -> BTreeMap<G, u64> {
+    let mut tally = BTreeMap::new();
+    for (grade, &weight) in grades.iter().zip(weights.iter()).filter(|(_, &weight)| weight > 0) {
+        *tally.entry(grade.clone()).or_insert(0) += weight;
+    }
+    tally
+}
+
+/// Function that finds the lower median grade of a weighted tally, None without any weight
+fn lower_median<G: Clone>(tally: &BTreeMap<G, u64>) -> Option<G> {
+    let total: u64 = tally.values().sum();
+    let mut cumulative = 0;
+    for (grade, &weight) in tally.iter() {
+        cumulative += weight;
+        if total > 0 && cumulative * 2 >= total {
+            return Some(grade.clone());
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_majority_judgment_multi() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("A1", vec![3, 3, 3, 3, 3, 3, 0, 0, 0, 0]);
+        poll_data.insert("A2", vec![3, 3, 3, 3, 3, 2, 0, 0, 0, 0]);
+        poll_data.insert("B1", vec![0, 0, 0, 0, 0, 0, 3, 3, 3, 3]);
+        poll_data.insert("B2", vec![0, 0, 0, 0, 0, 0, 3, 3, 3, 1]);
+
+        assert_eq!(majority_judgment_multi(&poll_data, 3, CommitteeRule::Top), vec![&"A1", &"A2", &"B1"]);
+        assert_eq!(majority_judgment_multi(&poll_data, 3, CommitteeRule::Sequential), vec![&"A1", &"B1", &"A2"]);
+        assert_eq!(majority_judgment_multi(&poll_data, 9, CommitteeRule::Sequential).len(), 4);
+        assert!(majority_judgment_multi(&poll_data, 0, CommitteeRule::Top).is_empty());
+    }
+
+    #[test]
+    fn calling_lower_median() {
+        assert_eq!(lower_median(&BTreeMap::from([(0, 2), (3, 2)])), Some(0));
+        assert_eq!(lower_median(&BTreeMap::from([(0, 1), (3, 2)])), Some(3));
+        assert_eq!(lower_median::<u8>(&BTreeMap::new()), None);
+    }
+}
@@ -0,0 +1,174 @@
+//! # Other voting rules
+//! Classic rules computed from the same graded ballots, to compare their results with the
+//! majority judgment on the same data:
+//! * Condorcet: the candidate preferred to each other candidate by a majority of the ballots,
+//!   a ballot preferring the candidate it grades higher
+//! * approval: the candidates are ranked by their number of grades at or above a threshold
+//! * Borda: each ballot ranks the candidates by grade, a candidate scoring one point per candidate
+//!   graded lower and half a point per other candidate graded the same
+//!
+//! The grades of the candidates must be ordered by ballot: the `i`-th grade of every candidate
+//! comes from the `i`-th voter.
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use crate::mj::{check_poll_length, majority_judgment, rank_candidates};
+use crate::options::TieOrder;
+
+/// The results of the majority judgment and of the other rules on the same poll, see `compare_rules`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison<'a, K> {
+    /// The ranking of the majority judgment
+    pub majority_judgment: Vec<(&'a K, usize)>,
+    /// The Condorcet winner, None if there is none
+    pub condorcet_winner: Option<&'a K>,
+    /// The ranking by number of approvals
+    pub approval: Vec<(&'a K, usize)>,
+    /// The ranking by Borda count
+    pub borda: Vec<(&'a K, usize)>,
+}
+
+/// Function that ranks the candidates with the majority judgment and the other rules
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, each vector ordered by ballot
+/// * `approval_threshold`: the lowest grade counted as an approval
+///
+/// # Returns
+/// * `Comparison`: the rankings of the rules, as vectors of tuple (candidate, rank)
+///
+/// # Panics
+/// * if the candidates have different numbers of grades
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::compare::compare_rules;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![3, 3, 0]);
+/// poll_data.insert("Chips".to_string(), vec![2, 2, 2]);
+///
+/// let comparison = compare_rules(&poll_data, &2);
+/// assert_eq!(comparison.majority_judgment[0].0, "Pizza");
+/// assert_eq!(comparison.condorcet_winner.unwrap(), "Pizza");
+/// assert_eq!(comparison.approval[0].0, "Chips");
+/// ```
+pub fn compare_rules<'a, K: Ord, G: Ord + Clone>(poll_data: &'a BTreeMap<K, Vec<G>>, approval_threshold: &G) -> Comparison<'a, K> {
+    Comparison {
+        majority_judgment: majority_judgment(poll_data),
+        condorcet_winner: condorcet_winner(poll_data),
+        approval: approval(poll_data, approval_threshold),
+        borda: borda(poll_data),
+    }
+}
+
+/// Function that finds the Condorcet winner: the candidate each other candidate loses against
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, each vector ordered by ballot
+///
+/// # Returns
+/// * `Option<&K>`: the Condorcet winner, None if no candidate beats all the others (e.g. a cycle or a tie)
+pub fn condorcet_winner<K: Ord, G: Ord>(poll_data: &BTreeMap<K, Vec<G>>) -> Option<&K> {
+    if poll_data.is_empty() {
+        return None;
+    }
+    let _ = check_poll_length(poll_data);
+
+    poll_data.iter().find_map(|(candidate, grades)| {
+        let beats_all = poll_data.iter().filter(|(other, _)| *other != candidate).all(|(_, other_grades)| {
+            let wins = grades.iter().zip(other_grades).filter(|(a, b)| a > b).count();
+            let losses = grades.iter().zip(other_grades).filter(|(a, b)| a < b).count();
+            wins > losses
+        });
+        beats_all.then_some(candidate)
+    })
+}
+
+/// Function that ranks the candidates by their number of approvals
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `threshold`: the lowest grade counted as an approval
+///
+/// # Returns
+/// * `Vec<(&K, usize)>`: a vector of tuple with the candidate and its rank, tied candidates share the same rank
+pub fn approval<'a, K: Ord, G: Ord>(poll_data: &'a BTreeMap<K, Vec<G>>, threshold: &G) -> Vec<(&'a K, usize)> {
+    let approvals = poll_data
+        .iter()
+        .map(|(candidate, grades)| (candidate, grades.iter().filter(|&grade| grade >= threshold).count()))
+        .collect();
+    rank_candidates::<K, G, usize>(approvals, poll_data, None, TieOrder::default()).to_vec()
+}
+
+/// Function that ranks the candidates by their Borda count
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, each vector ordered by ballot
+///
+/// # Returns
+/// * `Vec<(&K, usize)>`: a vector of tuple with the candidate and its rank, tied candidates share the same rank
+///
+/// # Panics
+/// * if the candidates have different numbers of grades
+pub fn borda<K: Ord, G: Ord>(poll_data: &BTreeMap<K, Vec<G>>) -> Vec<(&K, usize)> {
+    if poll_data.is_empty() {
+        return Vec::new();
+    }
+    let _ = check_poll_length(poll_data);
+
+    // twice the Borda count, so that half points stay integers
+    let points = poll_data
+        .iter()
+        .map(|(candidate, grades)| {
+            let points: usize = poll_data
+                .iter()
+                .filter(|(other, _)| *other != candidate)
+                .map(|(_, other_grades)| {
+                    grades.iter().zip(other_grades).map(|(a, b)| match a.cmp(b) {
+                        Ordering::Greater => 2,
+                        Ordering::Equal => 1,
+                        Ordering::Less => 0,
+                    }).sum::<usize>()
+                })
+                .sum();
+            (candidate, points)
+        })
+        .collect();
+    rank_candidates::<K, G, usize>(points, poll_data, None, TieOrder::default()).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_compare_rules() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![0, 0, 3, 0, 2, 0, 3, 1, 2, 3]);
+        poll_data.insert("Chips", vec![0, 1, 0, 2, 1, 2, 2, 3, 2, 3]);
+        poll_data.insert("Pasta", vec![0, 1, 0, 1, 2, 1, 3, 2, 3, 3]);
+        poll_data.insert("Bread", vec![0, 1, 2, 1, 1, 2, 1, 2, 2, 3]);
+
+        let comparison = compare_rules(&poll_data, &2);
+        assert_eq!(comparison.majority_judgment, vec![(&"Chips", 0), (&"Pasta", 1), (&"Bread", 2), (&"Pizza", 3)]);
+        assert_eq!(comparison.approval, vec![(&"Chips", 0), (&"Bread", 1), (&"Pasta", 1), (&"Pizza", 1)]);
+        assert_eq!(comparison.condorcet_winner, None);
+        assert_eq!(comparison.borda.len(), 4);
+    }
+
+    #[test]
+    fn calling_condorcet_winner() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("A", vec![2, 2, 0]);
+        poll_data.insert("B", vec![1, 1, 2]);
+        poll_data.insert("C", vec![0, 0, 1]);
+        assert_eq!(condorcet_winner(&poll_data), Some(&"A"));
+        assert_eq!(borda(&poll_data), vec![(&"A", 0), (&"B", 0), (&"C", 2)]);
+
+        // a cycle: A > B > C > A
+        poll_data.insert("A", vec![2, 0, 1]);
+        poll_data.insert("B", vec![1, 2, 0]);
+        poll_data.insert("C", vec![0, 1, 2]);
+        assert_eq!(condorcet_winner(&poll_data), None);
+    }
+}
@@ -0,0 +1,174 @@
+//! # Concurrent tally
+//! A web server handles many voters at once: a `TallyAccumulator` behind a single lock makes them wait
+//! for each other. `ConcurrentTally` spreads the ballots over several accumulators, each behind its own lock,
+//! so that simultaneous ballots are counted in parallel; a ranking locks them all, and so sees every ballot
+//! either fully counted or not at all. The shards never add up to more than `u64::MAX` votes for a candidate:
+//! the ballots beyond are refused with `BallotIssue::TooManyVotes`.
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use crate::accumulator::TallyAccumulator;
+use crate::poll::{Ballot, BallotIssue};
+use crate::ranking::OwnedRanking;
+
+/// A tally shared between threads, `add_ballot` taking `&self`
+///
+/// # Example
+/// ```
+/// use std::thread;
+/// use majority_judgement_rust::{Ballot, ConcurrentTally, TallyAccumulator};
+/// let tally = ConcurrentTally::new(TallyAccumulator::new(["Pizza", "Chips"]), 4);
+/// thread::scope(|scope| {
+///     for voter in 0..8 {
+///         let tally = &tally;
+///         scope.spawn(move || tally.add_ballot(&Ballot::new().grade("Pizza", voter % 3 + 1).grade("Chips", 1)).unwrap());
+///     }
+/// });
+/// assert_eq!(tally.ballots(), 8);
+/// assert_eq!(tally.ranking().ranking().winners(), vec!["Pizza"]);
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentTally<G> {
+    shards: Vec<Mutex<TallyAccumulator<G>>>,
+    next: AtomicUsize,
+    /// The votes the candidate with the most votes can still receive, whatever the shard
+    headroom: AtomicU64,
+    /// The candidate with the most votes, named by `BallotIssue::TooManyVotes`
+    fullest: String,
+}
+
+impl<G: Ord + Clone> ConcurrentTally<G> {
+    /// Create a concurrent tally
+    ///
+    /// # Arguments
+    /// * `tally`: the accumulator giving the candidates, the method and the grade range, with the ballots
+    ///   counted so far
+    /// * `shards`: the number of accumulators counting in parallel, e.g. the number of threads of the server
+    ///
+    /// # Panics
+    /// * if `shards` is 0
+    pub fn new(tally: TallyAccumulator<G>, shards: usize) -> Self {
+        assert!(shards > 0, "a concurrent tally needs at least one shard");
+        // every valid ballot grades every candidate: the candidate with the most votes reaches u64::MAX first
+        let (fullest, total) = tally.fullest().map(|(candidate, total)| (candidate.clone(), total)).unwrap_or_default();
+        let mut accumulators = vec![tally.emptied(); shards - 1];
+        accumulators.insert(0, tally);
+        ConcurrentTally {
+            shards: accumulators.into_iter().map(Mutex::new).collect(),
+            next: AtomicUsize::new(0),
+            headroom: AtomicU64::new(u64::MAX - total),
+            fullest,
+        }
+    }
+
+    /// Function that counts a ballot, with its weight, from any thread
+    ///
+    /// # Arguments
+    /// * `ballot`: a ballot grading each candidate exactly once
+    ///
+    /// # Returns
+    /// * `Result<(), Vec<BallotIssue>>`: the issues of the ballot if it is invalid, in which case nothing is counted
+    pub fn add_ballot(&self, ballot: &Ballot<G>) -> Result<(), Vec<BallotIssue>> {
+        // the weight is reserved before counting, and given back if the ballot is refused
+        let weight = u64::from(ballot.weight());
+        if self.headroom.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |headroom| headroom.checked_sub(weight)).is_err() {
+            return Err(vec![BallotIssue::TooManyVotes(self.fullest.clone())]);
+        }
+        // take the first free shard from a rotating start, waiting for the start only if they are all busy
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+        let free = (0..self.shards.len())
+            .map(|offset| &self.shards[(start + offset) % self.shards.len()])
+            .find_map(|shard| shard.try_lock().ok());
+        let counted = match free {
+            Some(mut shard) => shard.add_ballot(ballot),
+            None => lock(&self.shards[start]).add_ballot(ballot),
+        };
+        if counted.is_err() {
+            self.headroom.fetch_add(weight, Ordering::Relaxed);
+        }
+        counted
+    }
+
+    /// The number of ballots counted so far, whatever their weights
+    pub fn ballots(&self) -> u64 {
+        self.lock_all().iter().map(|shard| shard.ballots()).sum()
+    }
+
+    /// Function that adds up the shards into a single accumulator, e.g. to save a snapshot
+    ///
+    /// # Returns
+    /// * `TallyAccumulator<G>`: the ballots counted so far, no ballot being counted in the meantime
+    pub fn snapshot(&self) -> TallyAccumulator<G> {
+        let shards = self.lock_all();
+        let mut total = (*shards[0]).clone();
+        for shard in &shards[1..] {
+            total.absorb(shard);
+        }
+        total
+    }
+
+    /// Function that ranks the candidates from a snapshot of the ballots counted so far
+    ///
+    /// # Returns
+    /// * `OwnedRanking<String, G>`: the ranking of `TallyAccumulator::current_ranking`, owning its candidates
+    pub fn ranking(&self) -> OwnedRanking<String, G> {
+        self.snapshot().current_ranking().to_owned_ranking()
+    }
+
+    /// Function that locks every shard, always in the same order
+    fn lock_all(&self) -> Vec<MutexGuard<'_, TallyAccumulator<G>>> {
+        self.shards.iter().map(lock).collect()
+    }
+}
+
+/// Function that locks a shard, which is updated only once a ballot is valid and so is consistent even after a panic
+fn lock<G>(shard: &Mutex<TallyAccumulator<G>>) -> MutexGuard<'_, TallyAccumulator<G>> {
+    shard.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::thread;
+
+    #[test]
+    fn calling_add_ballot() {
+        let ballot = |voter: u8| Ballot::new().grade("Pizza", voter % 5).grade("Chips", voter % 3).grade("Soup", 2);
+        let mut expected = TallyAccumulator::new(["Pizza", "Chips", "Soup"]);
+        expected.add_ballot(&ballot(0)).unwrap();
+
+        let tally = ConcurrentTally::new(expected.clone(), 3);
+        thread::scope(|scope| {
+            for thread in 0..4u8 {
+                let tally = &tally;
+                scope.spawn(move || {
+                    for voter in 0..50 {
+                        tally.add_ballot(&ballot(thread * 50 + voter)).unwrap();
+                    }
+                });
+            }
+        });
+        for voter in 0..200 {
+            expected.add_ballot(&ballot(voter)).unwrap();
+        }
+        assert_eq!(tally.snapshot(), expected);
+        assert_eq!(tally.ballots(), 201);
+        assert_eq!(tally.ranking(), expected.current_ranking().to_owned_ranking());
+
+        let issues = tally.add_ballot(&Ballot::new().grade("Pizza", 1)).unwrap_err();
+        assert_eq!(issues[0], BallotIssue::MissingGrade("Chips".to_string()));
+        assert_eq!(tally.ballots(), 201);
+
+        // the shards add up to u64::MAX votes at most
+        let parse = |text: &str| text.parse::<u8>().map_err(|error| error.to_string());
+        let snapshot = format!("majority-judgment-snapshot 1\nballots {}\ncandidate Pizza 0:{}\ncandidate Chips 1:{}\n",
+                               u32::MAX as u64 + 2, u64::MAX - 3, u64::MAX / 2);
+        let tally = ConcurrentTally::new(TallyAccumulator::from_snapshot(&snapshot, parse).unwrap(), 2);
+        let ballot = Ballot::new().grade("Pizza", 1u8).grade("Chips", 0).with_weight(2);
+        tally.add_ballot(&ballot).unwrap();
+        assert_eq!(tally.add_ballot(&ballot), Err(vec![BallotIssue::TooManyVotes("Pizza".to_string())]));
+        assert!(tally.add_ballot(&Ballot::new().grade("Pizza", 1)).is_err());
+        tally.add_ballot(&ballot.clone().with_weight(1)).unwrap();
+        assert_eq!(tally.snapshot().tally("Pizza"), Some(&BTreeMap::from([(0, u64::MAX - 3), (1, 3)])));
+    }
+}
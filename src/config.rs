@@ -0,0 +1,445 @@
+//! # Configuration files
+//! The settings of a recurring election, read from a TOML or YAML file so that it is counted the same
+//! way every time, e.g. the configuration read by `mj --config`:
+//!
+//! ```toml
+//! # the municipal budget vote
+//! file = "ballots.csv"
+//! candidates = ["Park", "Library", "Bike lanes"]
+//! scale = ["Reject", "Poor", "Fair", "Good", "Excellent"]
+//! abstention = "worst"
+//! tie-breaker = "registration"
+//! ```
+//!
+//! ```yaml
+//! file: ballots.csv
+//! candidates: [Park, Library, Bike lanes]
+//! scale:
+//!   - Reject
+//!   - Poor
+//! ```
+//!
+//! Only flat files are read: keys with strings, integers, booleans or lists of them.
+//! TOML tables and nested YAML mappings are rejected rather than misread.
+use std::fmt;
+
+/// A value of a configuration file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigValue {
+    /// A string, quoted or, in YAML, plain
+    String(String),
+    /// An integer
+    Integer(i64),
+    /// `true` or `false`
+    Bool(bool),
+    /// A list of values
+    List(Vec<ConfigValue>),
+}
+
+impl ConfigValue {
+    /// The string, None if the value is not a string
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ConfigValue::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// The values of the list, None if the value is not a list
+    pub fn as_list(&self) -> Option<&[ConfigValue]> {
+        match self {
+            ConfigValue::List(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ConfigValue {
+    /// The value as written on a command line: strings unquoted, lists separated by commas
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigValue::String(string) => write!(f, "{}", string),
+            ConfigValue::Integer(integer) => write!(f, "{}", integer),
+            ConfigValue::Bool(value) => write!(f, "{}", value),
+            ConfigValue::List(values) => {
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Why a configuration file cannot be read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// The line of the error, from 1
+    pub line: usize,
+    /// What is wrong
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The keys of a configuration file with their values, in the order of the file
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Config {
+    entries: Vec<(String, ConfigValue)>,
+}
+
+impl Config {
+    /// The value of a key, None if the file does not set it
+    pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        self.entries.iter().find(|(name, _)| name == key).map(|(_, value)| value)
+    }
+
+    /// The keys and their values, in the order of the file
+    pub fn entries(&self) -> &[(String, ConfigValue)] {
+        &self.entries
+    }
+
+    fn insert(&mut self, line: usize, key: String, value: ConfigValue) -> Result<(), ConfigError> {
+        if self.get(&key).is_some() {
+            return Err(ConfigError { line, message: format!("duplicate key {:?}", key) });
+        }
+        self.entries.push((key, value));
+        Ok(())
+    }
+
+    /// Function that reads a flat TOML file
+    ///
+    /// # Arguments
+    /// * `input`: the TOML text
+    ///
+    /// # Returns
+    /// * `Result<Config, ConfigError>`: the keys and their values, or the line where the text cannot be read
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::config::{Config, ConfigValue};
+    /// let config = Config::from_toml("file = \"ballots.csv\" # the last vote\nscale = [\"Bad\", \"Good\"]").unwrap();
+    /// assert_eq!(config.get("file").and_then(ConfigValue::as_str), Some("ballots.csv"));
+    /// assert_eq!(config.get("scale").unwrap().to_string(), "Bad,Good");
+    /// ```
+    pub fn from_toml(input: &str) -> Result<Config, ConfigError> {
+        let mut scanner = Scanner { chars: input.chars().collect(), position: 0, line: 1 };
+        let mut config = Config::default();
+        loop {
+            scanner.skip_blank(true);
+            let Some(c) = scanner.peek() else { return Ok(config) };
+            if c == '[' {
+                return Err(scanner.error("tables are not supported"));
+            }
+            let line = scanner.line;
+            let key = match c {
+                '"' | '\'' => scanner.string()?,
+                _ => scanner.bare_key(),
+            };
+            if key.is_empty() {
+                return Err(scanner.error("expected a key"));
+            }
+            scanner.skip_blank(false);
+            if scanner.next() != Some('=') {
+                return Err(ConfigError { line, message: format!("expected = after {:?}", key) });
+            }
+            scanner.skip_blank(false);
+            let value = scanner.toml_value()?;
+            scanner.skip_blank(false);
+            if !matches!(scanner.next(), None | Some('\n')) {
+                return Err(scanner.error("expected the end of the line"));
+            }
+            config.insert(line, key, value)?;
+        }
+    }
+
+    /// Function that reads a flat YAML file
+    ///
+    /// The values are plain or quoted scalars, flow lists `[a, b]` or block lists of `- item` lines.
+    ///
+    /// # Arguments
+    /// * `input`: the YAML text
+    ///
+    /// # Returns
+    /// * `Result<Config, ConfigError>`: the keys and their values, or the line where the text cannot be read
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::config::{Config, ConfigValue};
+    /// let config = Config::from_yaml("width: 60\nscale:\n  - Bad\n  - Good\n").unwrap();
+    /// assert_eq!(config.get("width"), Some(&ConfigValue::Integer(60)));
+    /// assert_eq!(config.get("scale").unwrap().to_string(), "Bad,Good");
+    /// ```
+    pub fn from_yaml(input: &str) -> Result<Config, ConfigError> {
+        let mut config = Config::default();
+        // the key waiting for the items of its block list
+        let mut pending: Option<(usize, String, Vec<ConfigValue>)> = None;
+        for (index, raw) in input.lines().enumerate() {
+            let line = index + 1;
+            let error = |message: &str| ConfigError { line, message: message.to_string() };
+            let text = strip_yaml_comment(raw).trim_end();
+            let content = text.trim_start();
+            if content.is_empty() || content == "---" {
+                continue;
+            }
+            if let Some(item) = content.strip_prefix("- ").or(if content == "-" { Some("") } else { None }) {
+                let (_, _, items) = pending.as_mut().ok_or_else(|| error("list item without a key"))?;
+                items.push(yaml_scalar(item.trim(), line)?);
+                continue;
+            }
+            if content.len() != text.len() {
+                return Err(error("nested mappings are not supported"));
+            }
+            if let Some((line, key, items)) = pending.take() {
+                config.insert(line, key, ConfigValue::List(items))?;
+            }
+            let (key, value) = split_yaml_key(content).ok_or_else(|| error("expected key: value"))?;
+            let key = match yaml_scalar(key, line)? {
+                ConfigValue::String(key) if !key.is_empty() => key,
+                _ => return Err(error("expected a key")),
+            };
+            match value {
+                "" => pending = Some((line, key, Vec::new())),
+                value if value.starts_with('[') => {
+                    let items = value
+                        .strip_suffix(']')
+                        .ok_or_else(|| error("unclosed list"))?[1..]
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(|item| yaml_scalar(item, line))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    config.insert(line, key, ConfigValue::List(items))?;
+                }
+                value => config.insert(line, key, yaml_scalar(value, line)?)?,
+            }
+        }
+        if let Some((line, key, items)) = pending {
+            config.insert(line, key, ConfigValue::List(items))?;
+        }
+        Ok(config)
+    }
+}
+
+struct Scanner {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+}
+
+impl Scanner {
+    fn error(&self, message: &str) -> ConfigError {
+        ConfigError { line: self.line, message: message.to_string() }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    /// Skip the spaces and the comments, and the line breaks too if `newlines`
+    fn skip_blank(&mut self, newlines: bool) {
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\r' => {}
+                '\n' if newlines => {}
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.position += 1;
+                    }
+                    continue;
+                }
+                _ => return,
+            }
+            self.next();
+        }
+    }
+
+    fn bare_key(&mut self) -> String {
+        let mut key = String::new();
+        while let Some(c) = self.peek().filter(|&c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            key.push(c);
+            self.position += 1;
+        }
+        key
+    }
+
+    /// A basic string with escapes, or a literal string between single quotes
+    fn string(&mut self) -> Result<String, ConfigError> {
+        let quote = self.next();
+        let mut string = String::new();
+        loop {
+            match self.next() {
+                None | Some('\n') => return Err(self.error("unclosed string")),
+                Some(c) if Some(c) == quote => return Ok(string),
+                Some('\\') if quote == Some('"') => string.push(match self.next() {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    _ => return Err(self.error("unknown escape")),
+                }),
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    fn toml_value(&mut self) -> Result<ConfigValue, ConfigError> {
+        match self.peek() {
+            Some('"' | '\'') => self.string().map(ConfigValue::String),
+            Some('[') => {
+                self.next();
+                let mut values = Vec::new();
+                loop {
+                    self.skip_blank(true);
+                    if self.peek() == Some(']') {
+                        self.next();
+                        return Ok(ConfigValue::List(values));
+                    }
+                    values.push(self.toml_value()?);
+                    self.skip_blank(true);
+                    match self.next() {
+                        Some(',') => {}
+                        Some(']') => return Ok(ConfigValue::List(values)),
+                        _ => return Err(self.error("expected , or ] in the list")),
+                    }
+                }
+            }
+            _ => {
+                let word = self.bare_key();
+                match word.as_str() {
+                    "true" => Ok(ConfigValue::Bool(true)),
+                    "false" => Ok(ConfigValue::Bool(false)),
+                    _ => word
+                        .replace('_', "")
+                        .parse()
+                        .map(ConfigValue::Integer)
+                        .map_err(|_| self.error("expected a string, an integer, a boolean or a list")),
+                }
+            }
+        }
+    }
+}
+
+/// The line without its comment, a `#` at its start or after a space outside quotes
+///
+/// A quote only opens a string at the start of a scalar, so "Chef's choice" stays a plain scalar.
+fn strip_yaml_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') if previous.is_whitespace() => return &line[..index],
+            (None, '"' | '\'') if previous.is_whitespace() || matches!(previous, '[' | ',') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+        previous = c;
+    }
+    line
+}
+
+/// The key and the value of a `key: value` line, the colon being followed by a space or ending the line
+fn split_yaml_key(content: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    for (index, c) in content.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if index == 0 => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ':') if content[index + 1..].is_empty() || content[index + 1..].starts_with(' ') => {
+                return Some((content[..index].trim(), content[index + 1..].trim()));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn yaml_scalar(text: &str, line: usize) -> Result<ConfigValue, ConfigError> {
+    if text.starts_with('"') || text.starts_with('\'') {
+        let mut scanner = Scanner { chars: text.chars().collect(), position: 0, line };
+        let string = scanner.string()?;
+        if scanner.peek().is_some() {
+            return Err(scanner.error("unexpected text after the string"));
+        }
+        return Ok(ConfigValue::String(string));
+    }
+    Ok(match text {
+        "true" => ConfigValue::Bool(true),
+        "false" => ConfigValue::Bool(false),
+        _ => match text.parse() {
+            Ok(integer) => ConfigValue::Integer(integer),
+            Err(_) => ConfigValue::String(text.to_string()),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_from_toml() {
+        let config = Config::from_toml(
+            "# the yearly vote\n\
+             file = 'C:\\votes\\ballots.csv'\n\
+             \"tie-breaker\" = \"random:42\"\n\
+             width = 1_000\n\
+             tolerant = true\n\
+             candidates = [\n  \"Pizza\", # the favourite\n  \"Chips\",\n]\n",
+        )
+        .unwrap();
+        assert_eq!(config.get("file"), Some(&ConfigValue::String("C:\\votes\\ballots.csv".to_string())));
+        assert_eq!(config.get("tie-breaker").and_then(ConfigValue::as_str), Some("random:42"));
+        assert_eq!(config.get("width"), Some(&ConfigValue::Integer(1000)));
+        assert_eq!(config.get("tolerant"), Some(&ConfigValue::Bool(true)));
+        assert_eq!(config.get("candidates").and_then(ConfigValue::as_list).map(<[_]>::len), Some(2));
+        assert_eq!(config.entries()[4].0, "candidates");
+
+        assert_eq!(Config::from_toml("a = 1\n[poll]\n").unwrap_err().line, 2);
+        assert_eq!(Config::from_toml("a = 1\na = 2").unwrap_err().message, "duplicate key \"a\"");
+        assert_eq!(Config::from_toml("a = \"b\" c").unwrap_err().to_string(), "line 1: expected the end of the line");
+        assert!(Config::from_toml("a = [1, 2").is_err());
+    }
+
+    #[test]
+    fn calling_from_yaml() {
+        let config = Config::from_yaml(
+            "---\n\
+             file: ballots.csv # the last vote\n\
+             method: \"majority-gauge\"\n\
+             candidates: [Pizza, 'Fish: chips']\n\
+             scale:\n\
+             \x20 - Reject\n\
+             - Good\n\
+             width: 60\n",
+        )
+        .unwrap();
+        assert_eq!(config.get("file").and_then(ConfigValue::as_str), Some("ballots.csv"));
+        assert_eq!(config.get("method").and_then(ConfigValue::as_str), Some("majority-gauge"));
+        assert_eq!(config.get("candidates").unwrap().to_string(), "Pizza,Fish: chips");
+        assert_eq!(config.get("scale").unwrap().to_string(), "Reject,Good");
+        assert_eq!(config.get("width"), Some(&ConfigValue::Integer(60)));
+
+        assert_eq!(Config::from_yaml("poll:\n  file: a.csv").unwrap_err().message, "nested mappings are not supported");
+        assert_eq!(Config::from_yaml("- Pizza").unwrap_err().line, 1);
+        assert!(Config::from_yaml("file ballots.csv").is_err());
+    }
+}
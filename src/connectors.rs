@@ -0,0 +1,355 @@
+//! # Connectors
+//! Live ballots arrive from many backends: a file appended by another process, a Redis list filled by the
+//! voting frontends, the standard input of a pipeline. A `BallotSource` hides the backend behind
+//! `next_ballot`, and `consume` counts what it gives into a `TallyAccumulator`, so that a backend is swapped
+//! without touching the tally code:
+//! * `JsonLines`: one JSON ballot per line of any reader, e.g. `JsonLines::stdin()`
+//! * `FileTail`: the JSON lines appended to a file, waiting for new lines like `tail -f`
+//! * `RedisList`: the JSON ballots pushed to a Redis list, popped with `BLPOP`
+//!
+//! Every ballot is written in the format of the `json` module, e.g. `{"grades": {"Pizza": 2, "Chips": 0}}`
+//! with an optional `"weight"` and `"voter"`. The connectors only need `std::io` and `std::net`.
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use crate::accumulator::TallyAccumulator;
+use crate::json::{ballot_from_json, parse, FromJson};
+use crate::poll::{Ballot, BallotError};
+
+/// The largest ballot read from a backend, the limit of the body of a request to the server
+const MAX_BALLOT: usize = 1 << 20;
+
+/// A backend giving the ballots of a live poll one at a time
+pub trait BallotSource<G> {
+    /// Function that waits for the next ballot
+    ///
+    /// # Returns
+    /// * `Result<Option<Ballot<G>>, ConnectorError>`: the next ballot, None once the source is exhausted,
+    ///   or why it cannot be read. A malformed record is skipped, the next call reading the following one.
+    fn next_ballot(&mut self) -> Result<Option<Ballot<G>>, ConnectorError>;
+}
+
+/// Why a `BallotSource` cannot give its next ballot
+#[derive(Debug)]
+pub enum ConnectorError {
+    /// The backend cannot be read, e.g. a closed connection
+    Io(io::Error),
+    /// A record is not a valid JSON ballot, with where it was read
+    Malformed { record: String, message: String },
+    /// The backend answered something unexpected, e.g. a Redis error
+    Protocol(String),
+}
+
+impl fmt::Display for ConnectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectorError::Io(error) => write!(f, "cannot read the ballots: {}", error),
+            ConnectorError::Malformed { record, message } => write!(f, "{}: {}", record, message),
+            ConnectorError::Protocol(message) => write!(f, "unexpected answer: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConnectorError {}
+
+impl From<io::Error> for ConnectorError {
+    fn from(error: io::Error) -> Self {
+        ConnectorError::Io(error)
+    }
+}
+
+/// Function that counts the ballots of a source until it is exhausted
+///
+/// # Arguments
+/// * `source`: the backend giving the ballots
+/// * `tally`: the accumulator counting them, each ballot being counted as soon as it is read
+///
+/// # Returns
+/// * `Result<Vec<BallotError>, ConnectorError>`: the issues of the ballots refused, `ballot` being the index
+///   of the ballot among those read, or the first error of the source. The ballots read before an error stay
+///   counted: calling `consume` again goes on after a malformed record.
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::connectors::{consume, JsonLines};
+/// use majority_judgement_rust::TallyAccumulator;
+/// let lines = r#"{"grades": {"Pizza": 3, "Chips": 1}}
+/// {"grades": {"Pizza": 2, "Chips": 0}, "weight": 2}
+/// {"grades": {"Pizza": 1}}
+/// "#;
+/// let mut tally = TallyAccumulator::<u8>::new(["Pizza", "Chips"]);
+/// let errors = consume(&mut JsonLines::new(lines.as_bytes()), &mut tally).unwrap();
+/// assert_eq!(errors[0].ballot, 2);
+/// assert_eq!(tally.ballots(), 2);
+/// ```
+pub fn consume<G, S>(source: &mut S, tally: &mut TallyAccumulator<G>) -> Result<Vec<BallotError>, ConnectorError>
+where
+    G: Ord + Clone,
+    S: BallotSource<G> + ?Sized,
+{
+    let mut errors = Vec::new();
+    let mut index = 0;
+    while let Some(ballot) = source.next_ballot()? {
+        if let Err(issues) = tally.add_ballot(&ballot) {
+            errors.extend(issues.into_iter().map(|issue| BallotError { ballot: index, issue }));
+        }
+        index += 1;
+    }
+    Ok(errors)
+}
+
+/// Function that reads a JSON ballot, `record` telling where it was read
+fn read_ballot<G: FromJson>(json: &str, record: impl Fn() -> String) -> Result<Ballot<G>, ConnectorError> {
+    let value = parse(json).map_err(|error| ConnectorError::Malformed { record: record(), message: error.to_string() })?;
+    ballot_from_json(&value).map_err(|message| ConnectorError::Malformed { record: record(), message })
+}
+
+/// One JSON ballot per line of a reader, the blank lines being skipped
+#[derive(Debug)]
+pub struct JsonLines<R> {
+    reader: R,
+    line: usize,
+}
+
+impl<R: BufRead> JsonLines<R> {
+    /// Read the ballots of a reader, e.g. a file or a pipe
+    pub fn new(reader: R) -> Self {
+        JsonLines { reader, line: 0 }
+    }
+}
+
+impl JsonLines<io::StdinLock<'static>> {
+    /// Read the ballots of the standard input, until it is closed
+    pub fn stdin() -> Self {
+        JsonLines::new(io::stdin().lock())
+    }
+}
+
+impl<G: FromJson, R: BufRead> BallotSource<G> for JsonLines<R> {
+    fn next_ballot(&mut self) -> Result<Option<Ballot<G>>, ConnectorError> {
+        let mut text = String::new();
+        loop {
+            text.clear();
+            if self.reader.read_line(&mut text)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            if !text.trim().is_empty() {
+                let line = self.line;
+                return read_ballot(&text, || format!("line {}", line)).map(Some);
+            }
+        }
+    }
+}
+
+/// The JSON ballots appended to a file, one per line, like `tail -f`
+///
+/// The file is read from its start. At its end, the source waits for more lines rather than being exhausted,
+/// a line being read once its newline is written; a file truncated, e.g. rotated, is read again from its start.
+#[derive(Debug)]
+pub struct FileTail {
+    path: PathBuf,
+    reader: BufReader<File>,
+    position: u64,
+    pending: String,
+    line: usize,
+    interval: Duration,
+}
+
+impl FileTail {
+    /// Follow a file, checking for new lines every 200 milliseconds
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let reader = BufReader::new(File::open(&path)?);
+        Ok(FileTail { path, reader, position: 0, pending: String::new(), line: 0, interval: Duration::from_millis(200) })
+    }
+
+    /// Set how long to wait before checking for new lines at the end of the file, builder style
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Function that reads the next complete line, None at the end of the file for now
+    fn next_line(&mut self) -> io::Result<Option<String>> {
+        if std::fs::metadata(&self.path)?.len() < self.position {
+            self.reader = BufReader::new(File::open(&self.path)?);
+            self.position = 0;
+            self.pending.clear();
+        }
+        let read = self.reader.read_line(&mut self.pending)?;
+        self.position += read as u64;
+        if !self.pending.ends_with('\n') {
+            return Ok(None);
+        }
+        self.line += 1;
+        Ok(Some(std::mem::take(&mut self.pending)))
+    }
+}
+
+impl<G: FromJson> BallotSource<G> for FileTail {
+    fn next_ballot(&mut self) -> Result<Option<Ballot<G>>, ConnectorError> {
+        loop {
+            match self.next_line()? {
+                Some(text) if text.trim().is_empty() => continue,
+                Some(text) => {
+                    let (path, line) = (self.path.display(), self.line);
+                    return read_ballot(&text, || format!("{}:{}", path, line)).map(Some);
+                }
+                None => thread::sleep(self.interval),
+            }
+        }
+    }
+}
+
+/// The JSON ballots pushed to a Redis list by `RPUSH`, popped one at a time by `BLPOP`
+///
+/// The connection speaks the Redis protocol (RESP) over `std::net`, without authentication:
+/// reach a protected server through a local tunnel. The source is exhausted when the server closes the connection.
+/// A ballot larger than 1 MiB is refused as a protocol error, whatever length the server announces.
+#[derive(Debug)]
+pub struct RedisList {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    key: String,
+}
+
+impl RedisList {
+    /// Connect to a Redis server, e.g. `"127.0.0.1:6379"`, to pop the ballots of the list `key`
+    pub fn connect<A: ToSocketAddrs>(address: A, key: &str) -> io::Result<Self> {
+        let writer = TcpStream::connect(address)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(RedisList { reader, writer, key: key.to_string() })
+    }
+
+    /// Function that reads a line of a reply, without its `\r\n`, None if the connection is closed
+    fn reply_line(&mut self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+
+    /// Function that reads a bulk string of a reply, `$length` followed by its bytes, of `MAX_BALLOT` bytes at most
+    fn bulk_string(&mut self) -> Result<String, ConnectorError> {
+        let header = self.reply_line()?.ok_or_else(|| ConnectorError::Protocol("connection closed".to_string()))?;
+        if header == "$-1" {
+            return Err(ConnectorError::Protocol("nil bulk string".to_string()));
+        }
+        let length: usize = header
+            .strip_prefix('$')
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(|| ConnectorError::Protocol(format!("{:?} is not a bulk string", header)))?;
+        let with_end = length
+            .checked_add(2)
+            .filter(|_| length <= MAX_BALLOT)
+            .ok_or_else(|| ConnectorError::Protocol(format!("a ballot of {} bytes is larger than {} bytes", length, MAX_BALLOT)))?;
+        let mut bytes = vec![0; with_end];
+        self.reader.read_exact(&mut bytes)?;
+        bytes.truncate(length);
+        String::from_utf8(bytes).map_err(|_| ConnectorError::Protocol("the ballot is not UTF-8".to_string()))
+    }
+}
+
+impl<G: FromJson> BallotSource<G> for RedisList {
+    fn next_ballot(&mut self) -> Result<Option<Ballot<G>>, ConnectorError> {
+        loop {
+            let command = format!("*3\r\n$5\r\nBLPOP\r\n${}\r\n{}\r\n$1\r\n0\r\n", self.key.len(), self.key);
+            self.writer.write_all(command.as_bytes())?;
+            let Some(reply) = self.reply_line()? else { return Ok(None) };
+            match reply.as_str() {
+                // no ballot before the timeout
+                "*-1" => continue,
+                "*2" => {
+                    let key = self.bulk_string()?;
+                    let json = self.bulk_string()?;
+                    return read_ballot(&json, || format!("list {:?}", key)).map(Some);
+                }
+                reply => {
+                    return Err(ConnectorError::Protocol(reply.strip_prefix('-').unwrap_or(reply).to_string()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn calling_consume() {
+        let lines = "{\"grades\": {\"Pizza\": 3}}\n\n{\"grades\": {\"Pizza\": 2}\n{\"grades\": {\"Pizza\": 1}}\n";
+        let mut source = JsonLines::new(lines.as_bytes());
+        let mut tally = TallyAccumulator::<u8>::new(["Pizza"]);
+        let error = consume(&mut source, &mut tally).unwrap_err();
+        assert!(error.to_string().starts_with("line 3: "));
+        assert_eq!(consume(&mut source, &mut tally).unwrap(), []);
+        assert_eq!(tally.ballots(), 2);
+    }
+
+    #[test]
+    fn calling_file_tail() {
+        let path = std::env::temp_dir().join(format!("mj-tail-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "{\"grades\": {\"Pizza\": 3}}\n{\"grades\": ").unwrap();
+        let mut tail = FileTail::open(&path).unwrap().interval(Duration::from_millis(5));
+        let first: Ballot<u8> = tail.next_ballot().unwrap().unwrap();
+        assert_eq!(first.grades(), [("Pizza".to_string(), 3)]);
+
+        // the second line is read once it is complete
+        let writer = {
+            let path = path.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(20));
+                let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+                file.write_all(b"{\"Pizza\": 1}}\n").unwrap();
+            })
+        };
+        let second: Ballot<u8> = tail.next_ballot().unwrap().unwrap();
+        assert_eq!(second.grades(), [("Pizza".to_string(), 1)]);
+        writer.join().unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn calling_redis_list() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut stream = stream;
+            let ballot = r#"{"grades": {"Pizza": 2}}"#;
+            let replies = ["*-1\r\n".to_string(), format!("*2\r\n$5\r\nvotes\r\n${}\r\n{}\r\n", ballot.len(), ballot),
+                           "*2\r\n$5\r\nvotes\r\n$-1\r\n".to_string(), "*2\r\n$5\r\nvotes\r\n$2000000\r\n".to_string(),
+                           "-ERR wrong kind of value\r\n".to_string()];
+            for reply in replies {
+                // each command is an array of 3 bulk strings, 7 lines
+                let mut command = String::new();
+                for _ in 0..7 {
+                    reader.read_line(&mut command).unwrap();
+                }
+                assert!(command.starts_with("*3\r\n$5\r\nBLPOP\r\n$5\r\nvotes\r\n"));
+                stream.write_all(reply.as_bytes()).unwrap();
+            }
+        });
+
+        let mut redis = RedisList::connect(address, "votes").unwrap();
+        let ballot: Ballot<u8> = redis.next_ballot().unwrap().unwrap();
+        assert_eq!(ballot.grades(), [("Pizza".to_string(), 2)]);
+        let error = BallotSource::<u8>::next_ballot(&mut redis).unwrap_err();
+        assert_eq!(error.to_string(), "unexpected answer: nil bulk string");
+        // the length announced by the server is not trusted
+        let error = BallotSource::<u8>::next_ballot(&mut redis).unwrap_err();
+        assert_eq!(error.to_string(), "unexpected answer: a ballot of 2000000 bytes is larger than 1048576 bytes");
+        let error = BallotSource::<u8>::next_ballot(&mut redis).unwrap_err();
+        assert_eq!(error.to_string(), "unexpected answer: ERR wrong kind of value");
+        server.join().unwrap();
+    }
+}
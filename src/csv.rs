@@ -0,0 +1,451 @@
+//! # CSV input
+//! Loaders for election exports in CSV (enabled by the `csv` feature):
+//! * a ballots file: a header with the candidates, then one row per voter with the grade of each candidate
+//! * a tally file: a header with the grades, then one row per candidate with the number of votes for each grade
+//!
+//! A ballots file larger than the memory is counted with `counts_from_ballots_reader`, which reads it line by line
+//! and only keeps the number of votes of each grade for each candidate.
+//!
+//! Fields may be quoted with `"`, a quote inside a quoted field being written `""`.
+//! Quoted fields cannot span several lines. Blank lines are ignored.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+use crate::poll::{Ballot, Poll};
+
+/// The most votes `tally_from_csv` expands into grades, for all the candidates of a file: beyond,
+/// the counts are kept as they are with `counts_from_csv`
+pub const MAX_EXPANDED_VOTES: u64 = 1 << 24;
+
+/// An error found while reading a CSV file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvError {
+    /// The line of the error, starting at 1
+    pub line: usize,
+    /// The column of the error, starting at 1
+    pub column: usize,
+    /// What is wrong
+    pub message: String,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+/// Function that parses a grade written as a number, e.g. `u8` or `i8`
+///
+/// # Arguments
+/// * `field`: the content of the CSV field
+///
+/// # Returns
+/// * `Result<G, String>`: the grade or why it is not one
+pub fn parse_number<G: FromStr>(field: &str) -> Result<G, String> {
+    field.parse().map_err(|_| format!("{:?} is not a valid grade", field))
+}
+
+/// Function that loads a poll from a ballots file: rows are voters, columns are candidates
+///
+/// # Arguments
+/// * `input`: the content of the CSV file, starting with a header with the names of the candidates
+/// * `delimiter`: the field delimiter, usually `,` or `;`
+/// * `parse_grade`: the function parsing a grade from a field, e.g. `parse_number` or `GradeScale::parse_grade`
+///
+/// # Returns
+/// * `Result<Poll<G>, CsvError>`: the poll with one ballot per row, or the first error found
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::csv::{from_csv, parse_number};
+/// let poll = from_csv("Pizza,Chips\n0,2\n1,3\n", ',', parse_number::<u8>).unwrap();
+/// assert_eq!(poll.ballots().len(), 2);
+/// ```
+pub fn from_csv<G, F>(input: &str, delimiter: char, parse_grade: F) -> Result<Poll<G>, CsvError>
+where
+    F: Fn(&str) -> Result<G, String>,
+{
+    let mut records = records(input, delimiter);
+    let (_, candidates) = records.next().transpose()?.ok_or_else(empty_file_error)?;
+
+    let mut poll = Poll::new(candidates.iter().cloned());
+    if poll.candidates().len() != candidates.len() {
+        return Err(CsvError { line: 1, column: 1, message: "the header repeats a candidate".to_string() });
+    }
+
+    for record in records {
+        let (line, fields) = record?;
+        check_width(line, &fields, candidates.len())?;
+        let mut ballot = Ballot::new();
+        for (column, (candidate, field)) in candidates.iter().zip(fields.iter()).enumerate() {
+            let grade = parse_grade(field).map_err(|message| CsvError { line, column: column + 1, message })?;
+            ballot = ballot.grade(candidate.clone(), grade);
+        }
+        poll.add_ballot(ballot);
+    }
+    Ok(poll)
+}
+
+/// Function that loads the poll data from a tally file: rows are candidates, columns are grades
+///
+/// # Arguments
+/// * `input`: the content of the CSV file, starting with a header whose first field is ignored
+///   and whose following fields are the grades
+/// * `delimiter`: the field delimiter, usually `,` or `;`
+/// * `parse_grade`: the function parsing a grade from a header field
+///
+/// # Returns
+/// * `Result<BTreeMap<String, Vec<G>>, CsvError>`: the grades of each candidate, or the first error found,
+///   e.g. more than `MAX_EXPANDED_VOTES` votes in the file, each of them expanded into a grade
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::csv::{tally_from_csv, parse_number};
+/// let poll_data = tally_from_csv("candidate,0,1,2\nPizza,1,0,2\n", ',', parse_number::<u8>).unwrap();
+/// assert_eq!(poll_data["Pizza"], vec![0, 2, 2]);
+/// ```
+pub fn tally_from_csv<G, F>(input: &str, delimiter: char, parse_grade: F) -> Result<BTreeMap<String, Vec<G>>, CsvError>
+where
+    G: Ord + Clone,
+    F: Fn(&str) -> Result<G, String>,
+{
+    let mut poll_data = BTreeMap::new();
+    let mut expanded: u64 = 0;
+    for (line, candidate, tally) in count_rows(input, delimiter, parse_grade)? {
+        // one grade per vote: a corrupted count must not exhaust the memory
+        let total: u64 = tally.values().sum();
+        expanded = expanded.saturating_add(total);
+        let mut grades = Vec::new();
+        let reserved = (expanded <= MAX_EXPANDED_VOTES).then_some(total as usize).filter(|&total| grades.try_reserve_exact(total).is_ok());
+        reserved.ok_or_else(|| CsvError {
+            line,
+            column: 1,
+            message: format!("more than {} votes cannot be expanded into grades, see counts_from_csv", MAX_EXPANDED_VOTES),
+        })?;
+        for (grade, count) in tally {
+            grades.extend(std::iter::repeat_n(grade, count as usize));
+        }
+        poll_data.insert(candidate, grades);
+    }
+    Ok(poll_data)
+}
+
+/// Function that loads the number of votes of each grade from a tally file, without expanding them into ballots.
+/// The counts are `u64`, ready for `majority_judgment_from_tally`, so the size of the electorate is not bounded
+/// by the memory of one grade per voter.
+///
+/// # Arguments
+/// * `input`: the content of the CSV file, starting with a header whose first field is ignored
+///   and whose following fields are the grades
+/// * `delimiter`: the field delimiter, usually `,` or `;`
+/// * `parse_grade`: the function parsing a grade from a header field
+///
+/// # Returns
+/// * `Result<BTreeMap<String, BTreeMap<G, u64>>, CsvError>`: the tally of each candidate, or the first error found
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::csv::{counts_from_csv, parse_number};
+/// let tallies = counts_from_csv("candidate,0,1\nPizza,30000000000,50000000000\n", ',', parse_number::<u8>).unwrap();
+/// assert_eq!(tallies["Pizza"][&1], 50_000_000_000);
+/// ```
+pub fn counts_from_csv<G, F>(input: &str, delimiter: char, parse_grade: F) -> Result<BTreeMap<String, BTreeMap<G, u64>>, CsvError>
+where
+    G: Ord + Clone,
+    F: Fn(&str) -> Result<G, String>,
+{
+    Ok(count_rows(input, delimiter, parse_grade)?.into_iter().map(|(_, candidate, tally)| (candidate, tally)).collect())
+}
+
+/// The line, the candidate and the tally of a row of a tally file
+type Row<G> = (usize, String, BTreeMap<G, u64>);
+
+/// Function that reads the rows of a tally file
+///
+/// # Returns
+/// * `Result<Vec<Row<G>>, CsvError>`: the line, the candidate and the tally of each row,
+///   or the first error found, e.g. a candidate with more than `u64::MAX` votes
+fn count_rows<G, F>(input: &str, delimiter: char, parse_grade: F) -> Result<Vec<Row<G>>, CsvError>
+where
+    G: Ord + Clone,
+    F: Fn(&str) -> Result<G, String>,
+{
+    let mut records = records(input, delimiter);
+    let (_, header) = records.next().transpose()?.ok_or_else(empty_file_error)?;
+
+    let mut grades = Vec::new();
+    for (column, field) in header.iter().enumerate().skip(1) {
+        grades.push(parse_grade(field).map_err(|message| CsvError { line: 1, column: column + 1, message })?);
+    }
+
+    let mut rows = Vec::new();
+    let mut candidates = BTreeSet::new();
+    for record in records {
+        let (line, mut fields) = record?;
+        check_width(line, &fields, header.len())?;
+        let mut tally = BTreeMap::new();
+        let mut total: u64 = 0;
+        for (column, (grade, field)) in grades.iter().zip(fields.iter().skip(1)).enumerate() {
+            let count: u64 = field.parse().map_err(|_| CsvError {
+                line,
+                column: column + 2,
+                message: format!("{:?} is not a number of votes", field),
+            })?;
+            // the header may repeat a grade, the counts are then added
+            total = total.checked_add(count).ok_or_else(|| CsvError {
+                line,
+                column: column + 2,
+                message: format!("more than {} votes for the candidate", u64::MAX),
+            })?;
+            if count > 0 {
+                *tally.entry(grade.clone()).or_insert(0) += count;
+            }
+        }
+        let candidate = fields.swap_remove(0);
+        if !candidates.insert(candidate.clone()) {
+            return Err(CsvError { line, column: 1, message: format!("candidate {:?} is repeated", candidate) });
+        }
+        rows.push((line, candidate, tally));
+    }
+    Ok(rows)
+}
+
+/// Function that counts the votes of a ballots file read line by line, e.g. a file of several gigabytes:
+/// only the current line and the number of votes of each grade for each candidate are kept in memory
+///
+/// # Arguments
+/// * `reader`: the ballots file, read through its buffer, starting with a header with the names of the candidates
+/// * `delimiter`: the field delimiter, usually `,` or `;`
+/// * `parse_grade`: the function parsing a grade from a field, e.g. `parse_number` or `GradeScale::parse_grade`
+///
+/// # Returns
+/// * `Result<BTreeMap<String, BTreeMap<G, u64>>, CsvError>`: the tally of each candidate, ready for
+///   `majority_judgment_from_tally`, or the first error found, a read error being reported at its line
+///
+/// # Example
+/// ```
+/// use std::io::BufReader;
+/// use majority_judgement_rust::csv::{counts_from_ballots_reader, parse_number};
+/// let file = "Pizza,Chips\n0,2\n1,2\n".as_bytes();
+/// let tallies = counts_from_ballots_reader(BufReader::new(file), ',', parse_number::<u8>).unwrap();
+/// assert_eq!(tallies["Chips"][&2], 2);
+/// ```
+pub fn counts_from_ballots_reader<R, G, F>(mut reader: R, delimiter: char, parse_grade: F) -> Result<BTreeMap<String, BTreeMap<G, u64>>, CsvError>
+where
+    R: BufRead,
+    G: Ord,
+    F: Fn(&str) -> Result<G, String>,
+{
+    let mut candidates: Option<Vec<String>> = None;
+    let mut tallies: Vec<BTreeMap<G, u64>> = Vec::new();
+    let mut buffer = String::new();
+    let mut line = 0;
+    loop {
+        buffer.clear();
+        line += 1;
+        let read = reader
+            .read_line(&mut buffer)
+            .map_err(|error| CsvError { line, column: 1, message: error.to_string() })?;
+        if read == 0 {
+            break;
+        }
+        let record = if line == 1 { buffer.trim_start_matches('\u{feff}') } else { buffer.as_str() };
+        let record = record.trim_end_matches('\n');
+        if record.trim().is_empty() {
+            continue;
+        }
+        let fields = split_record(record, delimiter).map_err(|(column, message)| CsvError { line, column, message })?;
+
+        let Some(candidates) = &candidates else {
+            if fields.iter().collect::<BTreeSet<_>>().len() != fields.len() {
+                return Err(CsvError { line, column: 1, message: "the header repeats a candidate".to_string() });
+            }
+            tallies = fields.iter().map(|_| BTreeMap::new()).collect();
+            candidates = Some(fields);
+            continue;
+        };
+        check_width(line, &fields, candidates.len())?;
+        for (column, (tally, field)) in tallies.iter_mut().zip(fields.iter()).enumerate() {
+            let grade = parse_grade(field).map_err(|message| CsvError { line, column: column + 1, message })?;
+            *tally.entry(grade).or_insert(0) += 1;
+        }
+    }
+    let candidates = candidates.ok_or_else(empty_file_error)?;
+    Ok(candidates.into_iter().zip(tallies).collect())
+}
+
+fn empty_file_error() -> CsvError {
+    CsvError { line: 1, column: 1, message: "the file has no header".to_string() }
+}
+
+/// Function that checks a record has as many fields as the header
+fn check_width(line: usize, fields: &[String], width: usize) -> Result<(), CsvError> {
+    if fields.len() != width {
+        return Err(CsvError {
+            line,
+            column: fields.len().min(width) + 1,
+            message: format!("expected {} fields, found {}", width, fields.len()),
+        });
+    }
+    Ok(())
+}
+
+/// Function that splits the non blank lines of a CSV file into records
+///
+/// # Returns
+/// * an iterator over the line numbers (starting at 1) and the fields of each record
+fn records(input: &str, delimiter: char) -> impl Iterator<Item = Result<(usize, Vec<String>), CsvError>> + '_ {
+    input
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(move |(index, line)| {
+            split_record(line, delimiter)
+                .map(|fields| (index + 1, fields))
+                .map_err(|(column, message)| CsvError { line: index + 1, column, message })
+        })
+}
+
+/// Function that splits a line into its fields, unquoting them
+///
+/// # Arguments
+/// * `line`: a line of the CSV file
+/// * `delimiter`: the field delimiter
+///
+/// # Returns
+/// * `Result<Vec<String>, (usize, String)>`: the fields, or the column and the description of the error
+fn split_record(line: &str, delimiter: char) -> Result<Vec<String>, (usize, String)> {
+    let mut fields = Vec::new();
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+
+    loop {
+        let column = fields.len() + 1;
+        while chars.peek().is_some_and(|&c| c == ' ' || c == '\t') {
+            chars.next();
+        }
+
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err((column, "unterminated quoted field".to_string())),
+                }
+            }
+            while chars.peek().is_some_and(|&c| c == ' ' || c == '\t') {
+                chars.next();
+            }
+            if chars.peek().is_some_and(|&c| c != delimiter) {
+                return Err((column, "unexpected character after a quoted field".to_string()));
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == delimiter {
+                    break;
+                }
+                field.push(c);
+                chars.next();
+            }
+            field = field.trim_end().to_string();
+        }
+        fields.push(field);
+
+        if chars.next().is_none() {
+            return Ok(fields);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scale::GradeScale;
+
+    #[test]
+    fn calling_split_record() {
+        assert_eq!(split_record("a, b ,c", ','), Ok(vec!["a".to_string(), "b".to_string(), "c".to_string()]));
+        assert_eq!(split_record("\"Smith, J.\";\"say \"\"hi\"\"\";", ';'),
+                   Ok(vec!["Smith, J.".to_string(), "say \"hi\"".to_string(), String::new()]));
+        assert_eq!(split_record("a,\"b", ','), Err((2, "unterminated quoted field".to_string())));
+    }
+
+    #[test]
+    fn calling_from_csv() {
+        let poll = from_csv("\u{feff}Pizza,Chips\r\n0,2\r\n\r\n1,3\r\n", ',', parse_number::<u8>).unwrap();
+        let poll_data = poll.poll_data().unwrap();
+        assert_eq!(poll_data["Pizza"], vec![0, 1]);
+        assert_eq!(poll_data["Chips"], vec![2, 3]);
+
+        assert_eq!(
+            from_csv("Pizza,Chips\n0,2\n1,x\n", ',', parse_number::<u8>).unwrap_err(),
+            CsvError { line: 3, column: 2, message: "\"x\" is not a valid grade".to_string() });
+        assert_eq!(from_csv("Pizza,Chips\n0\n", ',', parse_number::<u8>).unwrap_err().line, 2);
+        assert!(from_csv("", ',', parse_number::<u8>).is_err());
+    }
+
+    #[test]
+    fn calling_from_csv_with_labels() {
+        let scale = GradeScale::new(["À rejeter", "Passable", "Très bien"]).tolerant();
+        let poll = from_csv("Pizza,Chips\ntres bien,passable\nA REJETER,Très bien\n", ',', |field| scale.parse_grade(field)).unwrap();
+        assert_eq!(poll.poll_data().unwrap()["Pizza"], vec![2, 0]);
+
+        let error = from_csv("Pizza,Chips\npassable,bof\n", ',', |field| scale.parse_grade(field)).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 2));
+        assert_eq!(error.to_string(), "line 2, column 2: \"bof\" is not a grade of the scale À rejeter < Passable < Très bien");
+    }
+
+    #[test]
+    fn calling_tally_from_csv() {
+        let poll_data = tally_from_csv("candidate;0;1;2\nPizza;1;0;2\nChips;0;3;0\n", ';', parse_number::<u8>).unwrap();
+        assert_eq!(poll_data["Pizza"], vec![0, 2, 2]);
+        assert_eq!(poll_data["Chips"], vec![1, 1, 1]);
+
+        let error = tally_from_csv("candidate,0,1\nPizza,1,-1\n", ',', parse_number::<u8>).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 3));
+        let error = tally_from_csv("candidate,0\nPizza,1\n\nChips,18446744073709551615\n", ',', parse_number::<u8>).unwrap_err();
+        assert_eq!((error.line, error.column), (4, 1));
+        let half = MAX_EXPANDED_VOTES / 2 + 1;
+        let error = tally_from_csv(&format!("candidate,0\nPizza,{}\nChips,{}\n", half, half), ',', parse_number::<u8>).unwrap_err();
+        assert_eq!((error.line, error.column), (3, 1));
+    }
+
+    #[test]
+    fn calling_counts_from_ballots_reader() {
+        let file = "\u{feff}Pizza,\"Chips, salted\"\r\n0,2\r\n\r\n1,3\r\n1,2\r\n";
+        // a buffer smaller than a line reads it in several chunks
+        let reader = std::io::BufReader::with_capacity(3, file.as_bytes());
+        let tallies = counts_from_ballots_reader(reader, ',', parse_number::<u8>).unwrap();
+        assert_eq!(tallies["Pizza"], BTreeMap::from([(0, 1), (1, 2)]));
+        assert_eq!(tallies["Chips, salted"], BTreeMap::from([(2, 2), (3, 1)]));
+        let poll_data = from_csv(file, ',', parse_number::<u8>).unwrap().poll_data().unwrap();
+        assert_eq!(crate::mj::majority_judgment_from_tally(&tallies), crate::mj::majority_judgment(&poll_data));
+
+        let error = counts_from_ballots_reader("Pizza,Chips\n0,2\n1,x\n".as_bytes(), ',', parse_number::<u8>).unwrap_err();
+        assert_eq!(error, CsvError { line: 3, column: 2, message: "\"x\" is not a valid grade".to_string() });
+        assert_eq!(counts_from_ballots_reader("Pizza,Pizza\n".as_bytes(), ',', parse_number::<u8>).unwrap_err().line, 1);
+        assert!(counts_from_ballots_reader("\n".as_bytes(), ',', parse_number::<u8>).is_err());
+    }
+
+    #[test]
+    fn calling_counts_from_csv() {
+        let tallies = counts_from_csv("candidate,0,1,2\nPizza,1,0,8000000000\n", ',', parse_number::<u8>).unwrap();
+        assert_eq!(tallies["Pizza"], BTreeMap::from([(0, 1), (2, 8_000_000_000)]));
+
+        // a repeated grade adds its counts, up to u64::MAX votes
+        let tallies = counts_from_csv("candidate,1,1\nPizza,2,3\n", ',', parse_number::<u8>).unwrap();
+        assert_eq!(tallies["Pizza"], BTreeMap::from([(1, 5)]));
+        let error = counts_from_csv("candidate,0,1\nPizza,18446744073709551615,1\n", ',', parse_number::<u8>).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 3));
+        assert_eq!(counts_from_csv("candidate,0\nPizza,1\nPizza,2\n", ',', parse_number::<u8>).unwrap_err().line, 3);
+    }
+}
@@ -0,0 +1,154 @@
+//! # Datasets
+//! Helpers to publish the raw ballots of a poll alongside its results:
+//! * `strip_voters`: remove the identifiers of the voters
+//! * `shuffle`: reorder the ballots, so their order does not reveal the order of the votes
+//! * `anonymize`: both of them
+//! * `split`: set aside a random sample of the ballots, e.g. for an audit
+//!
+//! The draws are reproducible (SplitMix64): the same seed gives the same dataset on every platform,
+//! so anyone can check a published dataset against its seed.
+//!
+//! # Example
+//! ```
+//! use majority_judgement_rust::{Ballot, Poll};
+//! use majority_judgement_rust::dataset::{anonymize, split};
+//! let mut poll = Poll::new(["Pizza", "Chips"]);
+//! for voter in 0..10 {
+//!     poll.add_ballot(Ballot::new().grade("Pizza", voter % 3).grade("Chips", 2).with_voter(format!("V-{}", voter)));
+//! }
+//!
+//! let published = anonymize(&poll, 42);
+//! assert!(published.ballots().iter().all(|ballot| ballot.voter().is_none()));
+//!
+//! let (training, audit) = split(&published, 2, 7);
+//! assert_eq!((training.ballots().len(), audit.ballots().len()), (8, 2));
+//! ```
+use std::collections::BTreeSet;
+use crate::poll::{Ballot, Poll};
+use crate::tie_breaker::splitmix64;
+
+/// Function that removes the identifiers of the voters from the ballots
+///
+/// # Arguments
+/// * `poll`: the poll whose ballots may record their voter
+///
+/// # Returns
+/// * `Poll<G>`: the same candidates and ballots, in the same order, without any voter
+pub fn strip_voters<G: Clone>(poll: &Poll<G>) -> Poll<G> {
+    rebuild(poll, poll.ballots().iter().map(|ballot| ballot.clone().without_voter()))
+}
+
+/// Function that reorders the ballots of a poll with a seeded draw
+///
+/// # Arguments
+/// * `poll`: the poll to shuffle
+/// * `seed`: the seed of the draw
+///
+/// # Returns
+/// * `Poll<G>`: the same candidates and ballots, the ballots in a random order
+pub fn shuffle<G: Clone>(poll: &Poll<G>, seed: u64) -> Poll<G> {
+    let order = permutation(poll.ballots().len(), seed);
+    rebuild(poll, order.into_iter().map(|index| poll.ballots()[index].clone()))
+}
+
+/// Function that prepares the ballots of a poll for publication: the voters are removed and the ballots shuffled
+///
+/// # Arguments
+/// * `poll`: the poll to publish
+/// * `seed`: the seed of the draw
+///
+/// # Returns
+/// * `Poll<G>`: the same candidates and ballots, without voter and in a random order
+pub fn anonymize<G: Clone>(poll: &Poll<G>, seed: u64) -> Poll<G> {
+    shuffle(&strip_voters(poll), seed)
+}
+
+/// Function that splits the ballots of a poll into two random samples, e.g. a training sample and an audit sample
+///
+/// # Arguments
+/// * `poll`: the poll to split
+/// * `audit_size`: the number of ballots of the audit sample, all the ballots if the poll has fewer
+/// * `seed`: the seed of the draw
+///
+/// # Returns
+/// * `(Poll<G>, Poll<G>)`: the training sample and the audit sample, with the candidates of the poll.
+///   Each ballot goes to exactly one sample and the samples keep the order of the ballots in the poll.
+pub fn split<G: Clone>(poll: &Poll<G>, audit_size: usize, seed: u64) -> (Poll<G>, Poll<G>) {
+    let audited: BTreeSet<usize> = permutation(poll.ballots().len(), seed).into_iter().take(audit_size).collect();
+    let sample = |in_audit: bool| {
+        let ballots = poll.ballots().iter().enumerate().filter(|(index, _)| audited.contains(index) == in_audit);
+        rebuild(poll, ballots.map(|(_, ballot)| ballot.clone()))
+    };
+    (sample(false), sample(true))
+}
+
+/// Function that builds a poll with the candidates of another one and the given ballots
+fn rebuild<G: Clone>(poll: &Poll<G>, ballots: impl Iterator<Item = Ballot<G>>) -> Poll<G> {
+    let mut rebuilt = poll.empty_copy();
+    for ballot in ballots {
+        rebuilt.add_ballot(ballot);
+    }
+    rebuilt
+}
+
+/// Function that draws a random permutation of `0..len` (Fisher-Yates shuffle)
+fn permutation(len: usize, seed: u64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..len).collect();
+    let mut state = seed;
+    for index in (1..len).rev() {
+        let draw = splitmix64(state);
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        order.swap(index, (draw % (index as u64 + 1)) as usize);
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll(ballots: u8) -> Poll<u8> {
+        let mut poll = Poll::new(["Pizza", "Chips"]);
+        for voter in 0..ballots {
+            poll.add_ballot(Ballot::new().grade("Pizza", voter).grade("Chips", 0).with_voter(voter.to_string()));
+        }
+        poll
+    }
+
+    fn pizza_grades(poll: &Poll<u8>) -> Vec<u8> {
+        poll.poll_data().unwrap()["Pizza"].clone()
+    }
+
+    #[test]
+    fn calling_shuffle() {
+        let poll = poll(20);
+        let shuffled = shuffle(&poll, 42);
+        assert_eq!(shuffled, shuffle(&poll, 42));
+        assert_ne!(pizza_grades(&shuffled), pizza_grades(&poll));
+        assert_ne!(pizza_grades(&shuffled), pizza_grades(&shuffle(&poll, 43)));
+
+        let mut grades = pizza_grades(&shuffled);
+        grades.sort();
+        assert_eq!(grades, pizza_grades(&poll));
+
+        let anonymized = anonymize(&poll, 42);
+        assert!(anonymized.ballots().iter().all(|ballot| ballot.voter().is_none()));
+        assert_eq!(pizza_grades(&anonymized), pizza_grades(&shuffled));
+    }
+
+    #[test]
+    fn calling_split() {
+        let poll = poll(20);
+        let (training, audit) = split(&poll, 5, 42);
+        assert_eq!((training.ballots().len(), audit.ballots().len()), (15, 5));
+        assert_eq!(training.candidates(), poll.candidates());
+
+        let mut grades = [pizza_grades(&training), pizza_grades(&audit)].concat();
+        grades.sort();
+        assert_eq!(grades, pizza_grades(&poll));
+        assert!(pizza_grades(&audit).is_sorted());
+
+        let (training, audit) = split(&poll, 50, 42);
+        assert_eq!((training.ballots().len(), audit.ballots().len()), (0, 20));
+    }
+}
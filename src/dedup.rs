@@ -0,0 +1,200 @@
+//! # Duplicate ballots
+//! Online polls identify their voters by a token, e.g. the voter recorded by `Ballot::with_voter`:
+//! a second ballot from the same token is either refused or replaces the first one, according to
+//! a `DuplicatePolicy`. `Poll::submit` applies it ballot by ballot as they arrive, `deduplicate` to
+//! the ballots of a poll already collected, with a `DedupReport` of the ballots discarded.
+//! Ballots without voter cannot be compared: they are always kept.
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::poll::{Ballot, Poll};
+
+/// What to do with a second ballot from the same voter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// The second ballot is an error, the first one is kept
+    #[default]
+    Reject,
+    /// The second ballot is ignored without error: first write wins
+    FirstWins,
+    /// The second ballot replaces the first one, e.g. a voter changing their mind: last write wins
+    LastWins,
+}
+
+/// What `Poll::submit` did with a ballot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Submission<G> {
+    /// The ballot is the first one of its voter, or has no voter
+    Added,
+    /// The ballot replaced this earlier ballot of its voter, with `DuplicatePolicy::LastWins`
+    Replaced(Ballot<G>),
+    /// The voter had already voted, the ballot is ignored with `DuplicatePolicy::FirstWins`
+    Ignored,
+}
+
+/// A second ballot refused with `DuplicatePolicy::Reject`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateBallotError {
+    /// The voter of both ballots
+    pub voter: String,
+    /// The index of the ballot already in the poll
+    pub ballot: usize,
+}
+
+impl fmt::Display for DuplicateBallotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "voter {:?} already voted, ballot {}", self.voter, self.ballot)
+    }
+}
+
+impl std::error::Error for DuplicateBallotError {}
+
+/// The ballots of a voter who voted more than once
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateVoter {
+    /// The voter
+    pub voter: String,
+    /// The index of the ballot kept, in the original poll
+    pub kept: usize,
+    /// The indices of the other ballots of the voter, in the original poll
+    pub discarded: Vec<usize>,
+}
+
+/// The voters found more than once by `deduplicate`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DedupReport {
+    /// The voters with several ballots, by their first ballot
+    pub duplicates: Vec<DuplicateVoter>,
+    /// The number of ballots without voter, kept as they are
+    pub anonymous: usize,
+}
+
+impl DedupReport {
+    /// Whether every voter voted once
+    pub fn is_clean(&self) -> bool {
+        self.duplicates.is_empty()
+    }
+
+    /// The number of ballots discarded
+    pub fn discarded(&self) -> usize {
+        self.duplicates.iter().map(|duplicate| duplicate.discarded.len()).sum()
+    }
+}
+
+impl fmt::Display for DedupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} duplicate voters, {} ballots discarded, {} ballots without voter",
+               self.duplicates.len(), self.discarded(), self.anonymous)?;
+        for duplicate in &self.duplicates {
+            let discarded: Vec<String> = duplicate.discarded.iter().map(ToString::to_string).collect();
+            write!(f, "\nvoter {:?}: kept ballot {}, discarded {}", duplicate.voter, duplicate.kept, discarded.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Function that keeps one ballot per voter
+///
+/// # Arguments
+/// * `poll`: the poll whose ballots record their voter
+/// * `policy`: which ballot of a voter is kept
+///
+/// # Returns
+/// * `Result<(Poll<G>, DedupReport), DedupReport>`: the poll with one ballot per voter, the kept ballots
+///   in their original order, and the report of the duplicates. With `DuplicatePolicy::Reject`, a poll
+///   with duplicates is an error reporting them, the first ballot of each voter being the one counted.
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{deduplicate, Ballot, DuplicatePolicy, Poll};
+/// let mut poll = Poll::new(["Pizza"]);
+/// poll.add_ballot(Ballot::new().grade("Pizza", 0).with_voter("token-1"));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 2).with_voter("token-2"));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 3).with_voter("token-1"));
+///
+/// let (poll, report) = deduplicate(&poll, DuplicatePolicy::LastWins).unwrap();
+/// assert_eq!(poll.poll_data().unwrap()["Pizza"], vec![2, 3]);
+/// assert_eq!(report.discarded(), 1);
+/// ```
+pub fn deduplicate<G: Clone>(poll: &Poll<G>, policy: DuplicatePolicy) -> Result<(Poll<G>, DedupReport), DedupReport> {
+    let mut ballots_of: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    let mut report = DedupReport::default();
+    for (index, ballot) in poll.ballots().iter().enumerate() {
+        match ballot.voter() {
+            Some(voter) => ballots_of.entry(voter).or_default().push(index),
+            None => report.anonymous += 1,
+        }
+    }
+
+    let mut discarded = vec![false; poll.ballots().len()];
+    for (voter, mut indices) in ballots_of.into_iter().filter(|(_, indices)| indices.len() > 1) {
+        let kept = match policy {
+            DuplicatePolicy::LastWins => indices.pop(),
+            DuplicatePolicy::Reject | DuplicatePolicy::FirstWins => Some(indices.remove(0)),
+        }
+        .unwrap_or_default();
+        for &index in &indices {
+            discarded[index] = true;
+        }
+        report.duplicates.push(DuplicateVoter { voter: voter.to_string(), kept, discarded: indices });
+    }
+    report.duplicates.sort_by_key(|duplicate| duplicate.kept.min(duplicate.discarded[0]));
+
+    if policy == DuplicatePolicy::Reject && !report.is_clean() {
+        return Err(report);
+    }
+    let mut deduplicated = poll.empty_copy();
+    for (ballot, _) in poll.ballots().iter().zip(&discarded).filter(|(_, &discarded)| !discarded) {
+        deduplicated.add_ballot(ballot.clone());
+    }
+    Ok((deduplicated, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ballot(grade: u8, voter: &str) -> Ballot<u8> {
+        Ballot::new().grade("Pizza", grade).with_voter(voter)
+    }
+
+    #[test]
+    fn calling_submit() {
+        let mut poll = Poll::new(["Pizza"]);
+        assert_eq!(poll.submit(ballot(0, "a"), DuplicatePolicy::Reject), Ok(Submission::Added));
+        assert_eq!(poll.submit(ballot(1, "b"), DuplicatePolicy::Reject), Ok(Submission::Added));
+        assert_eq!(
+            poll.submit(ballot(2, "a"), DuplicatePolicy::Reject),
+            Err(DuplicateBallotError { voter: "a".to_string(), ballot: 0 })
+        );
+        assert_eq!(poll.submit(ballot(2, "a"), DuplicatePolicy::FirstWins), Ok(Submission::Ignored));
+        assert_eq!(poll.submit(ballot(3, "a"), DuplicatePolicy::LastWins), Ok(Submission::Replaced(ballot(0, "a"))));
+        assert_eq!(poll.submit(Ballot::new().grade("Pizza", 1), DuplicatePolicy::Reject), Ok(Submission::Added));
+        assert_eq!(poll.poll_data().unwrap()["Pizza"], vec![1, 3, 1]);
+    }
+
+    #[test]
+    fn calling_deduplicate() {
+        let mut poll = Poll::new(["Pizza"]);
+        for (grade, voter) in [(0, "a"), (1, "b"), (2, "a"), (3, "c"), (0, "b"), (1, "a")] {
+            poll.add_ballot(ballot(grade, voter));
+        }
+        poll.add_ballot(Ballot::new().grade("Pizza", 2));
+
+        let (first, report) = deduplicate(&poll, DuplicatePolicy::FirstWins).unwrap();
+        assert_eq!(first.poll_data().unwrap()["Pizza"], vec![0, 1, 3, 2]);
+        assert_eq!(report.duplicates[0], DuplicateVoter { voter: "a".to_string(), kept: 0, discarded: vec![2, 5] });
+        assert_eq!((report.discarded(), report.anonymous), (3, 1));
+
+        let (last, report) = deduplicate(&poll, DuplicatePolicy::LastWins).unwrap();
+        assert_eq!(last.poll_data().unwrap()["Pizza"], vec![3, 0, 1, 2]);
+        assert_eq!(
+            report.to_string(),
+            "2 duplicate voters, 3 ballots discarded, 1 ballots without voter\n\
+             voter \"a\": kept ballot 5, discarded 0, 2\n\
+             voter \"b\": kept ballot 4, discarded 1"
+        );
+
+        assert_eq!(deduplicate(&poll, DuplicatePolicy::Reject).unwrap_err().duplicates.len(), 2);
+        assert!(deduplicate(&first, DuplicatePolicy::Reject).unwrap().1.is_clean());
+    }
+}
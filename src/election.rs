@@ -0,0 +1,246 @@
+//! # Elections of several questions
+//! A participatory budget asks the voters to grade many projects, an assembly votes on several motions at
+//! once: each question is a poll of its own, with its candidates and its grade scale. An `Election` holds the
+//! questions, ranks all of them in one pass and reports them together, question by question.
+use std::fmt;
+use crate::grade_range::GradeRange;
+use crate::mj::majority_judgment_ranking;
+use crate::poll::{BallotError, Poll};
+use crate::ranking::{OwnedRanking, TieStatus};
+use crate::scale::GradeScale;
+
+/// A question of an election, its poll graded on its own scale
+#[derive(Debug, Clone, PartialEq)]
+pub struct Question {
+    /// The name of the question, e.g. "Parks"
+    pub name: String,
+    /// The candidates and the ballots of the question
+    pub poll: Poll<u8>,
+    /// The labels of its grades
+    pub scale: GradeScale,
+}
+
+/// The questions of an election, in the order they were added
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::scale::{GradeScale, Language};
+/// use majority_judgement_rust::{Ballot, Election, Poll};
+/// let mut parks = Poll::new(["Playground", "Pond"]);
+/// parks.add_ballot(Ballot::new().grade("Playground", 3).grade("Pond", 1));
+/// let mut streets = Poll::new(["Bike lanes", "Benches"]);
+/// streets.add_ballot(Ballot::new().grade("Bike lanes", 0).grade("Benches", 2));
+///
+/// let mut election = Election::new();
+/// election.add_question("Parks", parks, GradeScale::balinski_laraki(Language::English)).unwrap();
+/// election.add_question("Streets", streets, GradeScale::thumbs(Language::English)).unwrap();
+/// let results = election.rank().unwrap();
+/// assert_eq!(results.winners(), [("Parks", vec!["Playground"]), ("Streets", vec!["Benches"])]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Election {
+    questions: Vec<Question>,
+}
+
+impl Election {
+    /// Create an election without any question
+    pub fn new() -> Self {
+        Election::default()
+    }
+
+    /// Function that adds a question to the election
+    ///
+    /// # Arguments
+    /// * `name`: the name of the question, unique in the election
+    /// * `poll`: its candidates and ballots. Without grade range, it accepts the grades of the scale only.
+    /// * `scale`: the labels of its grades
+    ///
+    /// # Returns
+    /// * `Result<&mut Self, ElectionError>`: the election, or `ElectionError::DuplicateQuestion`
+    ///   if a question already has this name
+    pub fn add_question(&mut self, name: impl Into<String>, poll: Poll<u8>, scale: GradeScale) -> Result<&mut Self, ElectionError> {
+        let name = name.into();
+        if self.question(&name).is_some() {
+            return Err(ElectionError::DuplicateQuestion(name));
+        }
+        let poll = match (poll.grade_range(), scale.len()) {
+            (None, grades) if grades > 0 => poll.with_grade_range(GradeRange::between(0, (grades - 1) as u8)),
+            _ => poll,
+        };
+        self.questions.push(Question { name, poll, scale });
+        Ok(self)
+    }
+
+    /// The questions, in the order they were added
+    pub fn questions(&self) -> &[Question] {
+        &self.questions
+    }
+
+    /// The question with this name, None if there is none
+    pub fn question(&self, name: &str) -> Option<&Question> {
+        self.questions.iter().find(|question| question.name == name)
+    }
+
+    /// The poll of a question, e.g. to add its ballots, None if there is no question with this name
+    pub fn poll_mut(&mut self, name: &str) -> Option<&mut Poll<u8>> {
+        self.questions.iter_mut().find(|question| question.name == name).map(|question| &mut question.poll)
+    }
+
+    /// Function that ranks the candidates of every question
+    ///
+    /// # Returns
+    /// * `Result<ElectionResults<'_>, ElectionError>`: the ranking of each question, in the order of the questions,
+    ///   or the invalid ballots of the first question which has some
+    pub fn rank(&self) -> Result<ElectionResults<'_>, ElectionError> {
+        let mut results = Vec::with_capacity(self.questions.len());
+        for question in &self.questions {
+            let poll_data = question.poll.poll_data().map_err(|errors| ElectionError::InvalidBallots {
+                question: question.name.clone(),
+                errors,
+            })?;
+            let ranking = majority_judgment_ranking(&poll_data).to_owned_ranking();
+            results.push(QuestionResult { question, ranking, ballots: question.poll.ballots().len() });
+        }
+        Ok(ElectionResults { questions: results })
+    }
+}
+
+/// The ranking of a question, see `Election::rank`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuestionResult<'a> {
+    /// The question ranked
+    pub question: &'a Question,
+    /// The ranking of its candidates
+    pub ranking: OwnedRanking<String, u8>,
+    /// The number of ballots of the question
+    pub ballots: usize,
+}
+
+/// The rankings of all the questions of an election
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElectionResults<'a> {
+    /// The ranking of each question, in the order of the questions
+    pub questions: Vec<QuestionResult<'a>>,
+}
+
+impl ElectionResults<'_> {
+    /// The winners of each question, by name, several of them for a tie
+    pub fn winners(&self) -> Vec<(&str, Vec<&str>)> {
+        self.questions
+            .iter()
+            .map(|result| {
+                let winners = result.ranking.ranking().winners().into_iter().map(String::as_str).collect();
+                (result.question.name.as_str(), winners)
+            })
+            .collect()
+    }
+
+    /// Function that writes the results as Markdown: a summary table with the winners of the questions,
+    /// followed by the ranking of each question with the labels of its scale
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::scale::GradeScale;
+    /// use majority_judgement_rust::{Ballot, Election, Poll};
+    /// let mut motion = Poll::new(["Yes", "No"]);
+    /// motion.add_ballot(Ballot::new().grade("Yes", 1).grade("No", 0));
+    /// let mut election = Election::new();
+    /// election.add_question("Motion 1", motion, GradeScale::new(["Reject", "Accept"])).unwrap();
+    ///
+    /// assert_eq!(election.rank().unwrap().to_markdown(), "\
+    /// | Question | Winner | Majority grade | Ballots |
+    /// |---|---|---|---:|
+    /// | Motion 1 | Yes | Accept | 1 |
+    ///
+    /// **Motion 1**
+    ///
+    /// | Rank | Candidate | Majority grade |
+    /// |---:|---|---|
+    /// | 1 | Yes | Accept |
+    /// | 2 | No | Reject |
+    /// ");
+    /// ```
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::from("| Question | Winner | Majority grade | Ballots |\n|---|---|---|---:|\n");
+        for result in &self.questions {
+            let ranking = result.ranking.ranking();
+            let winners = ranking.iter().filter(|ranked| ranked.rank == 0);
+            let names: Vec<String> = winners.clone().map(|ranked| escape_cell(ranked.candidate)).collect();
+            let grade = winners.filter_map(|ranked| ranked.majority_grade_label(&result.question.scale)).next().unwrap_or_default();
+            markdown.push_str(&format!("| {} | {} | {} | {} |\n", escape_cell(&result.question.name), names.join(", "), escape_cell(grade), result.ballots));
+        }
+        for result in &self.questions {
+            markdown.push_str(&format!("\n**{}**\n\n| Rank | Candidate | Majority grade |\n|---:|---|---|\n", result.question.name));
+            for ranked in result.ranking.ranking().iter() {
+                let rank = match ranked.tie {
+                    TieStatus::Tied => format!("{} (tied)", ranked.rank + 1),
+                    TieStatus::Unique | TieStatus::Broken => (ranked.rank + 1).to_string(),
+                };
+                let grade = ranked.majority_grade_label(&result.question.scale).unwrap_or_default();
+                markdown.push_str(&format!("| {} | {} | {} |\n", rank, escape_cell(ranked.candidate), escape_cell(grade)));
+            }
+        }
+        markdown
+    }
+}
+
+/// Function that escapes the pipes of a Markdown table cell
+fn escape_cell(cell: &str) -> String {
+    cell.replace('|', "\\|")
+}
+
+/// An election refused by `Election::add_question` or `Election::rank`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElectionError {
+    /// Another question already has this name
+    DuplicateQuestion(String),
+    /// The ballots of a question are invalid
+    InvalidBallots { question: String, errors: Vec<BallotError> },
+}
+
+impl fmt::Display for ElectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElectionError::DuplicateQuestion(question) => write!(f, "question {:?} is already part of the election", question),
+            ElectionError::InvalidBallots { question, errors } => match errors.first() {
+                Some(error) => write!(f, "question {:?}: {}", question, error),
+                None => write!(f, "question {:?}: invalid ballots", question),
+            },
+        }
+    }
+}
+
+impl std::error::Error for ElectionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poll::{Ballot, BallotIssue};
+    use crate::scale::Language;
+
+    #[test]
+    fn calling_rank() {
+        let mut election = Election::new();
+        election.add_question("Parks", Poll::new(["Pond", "Playground"]), GradeScale::thumbs(Language::English)).unwrap();
+        election.add_question("Streets", Poll::new(["Benches", "Bike | lanes"]), GradeScale::new(["No", "Yes"])).unwrap();
+        let error = election.add_question("Parks", Poll::new(["Fountain"]), GradeScale::new(["No", "Yes"])).unwrap_err();
+        assert_eq!(error, ElectionError::DuplicateQuestion("Parks".to_string()));
+
+        let parks = election.poll_mut("Parks").unwrap();
+        parks.add_ballot(Ballot::new().grade("Pond", 2).grade("Playground", 2));
+        parks.add_ballot(Ballot::new().grade("Pond", 1).grade("Playground", 1));
+        let streets = election.poll_mut("Streets").unwrap();
+        streets.add_ballot(Ballot::new().grade("Benches", 0).grade("Bike | lanes", 1));
+        let results = election.rank().unwrap();
+        assert_eq!(results.winners(), [("Parks", vec!["Playground", "Pond"]), ("Streets", vec!["Bike | lanes"])]);
+        assert!(results.to_markdown().contains("| Parks | Playground, Pond | Neutral | 2 |\n| Streets | Bike \\| lanes | Yes | 1 |\n"));
+        assert!(results.to_markdown().contains("\n**Parks**\n\n| Rank | Candidate | Majority grade |\n|---:|---|---|\n| 1 (tied) | Playground | Neutral |\n"));
+
+        // the grades out of the scale of a question are refused
+        election.poll_mut("Streets").unwrap().add_ballot(Ballot::new().grade("Benches", 2).grade("Bike | lanes", 1));
+        let error = election.rank().unwrap_err();
+        assert!(matches!(&error, ElectionError::InvalidBallots { question, errors }
+                         if question == "Streets" && errors[0].issue == BallotIssue::OutOfRange("Benches".to_string())));
+        assert_eq!(election.questions().len(), 2);
+    }
+}
@@ -0,0 +1,41 @@
+//! # Errors
+//! The errors reported by the crate, gathered in one place:
+//! * `ArchiveError`: an election archive which cannot be read, was modified or has an unknown version
+//! * `BallotError` and its `BallotIssue`: an invalid ballot of a `Poll` or of a `TallyAccumulator`
+//! * `DuplicateBallotError`: a second ballot of a voter refused by `Poll::submit`
+//! * `ElectionError`: two questions of an `Election` with the same name, or a question with invalid ballots
+//! * `MergeError`: two polls over different candidates or grade ranges refused by `Poll::merge`
+//! * `PrecinctError` and its `PrecinctIssue`: an invalid tally reported by a precinct
+//! * `RankingError`: poll data the options cannot rank, returned by `try_majority_judgment_with_options`
+//! * `SnapshotError`: a snapshot of a `TallyAccumulator` which cannot be saved or loaded
+//! * `RescaleError`: a conversion between two grade scales which is not increasing
+//! * `RetallyError`: an invalid ballot added or removed by `retally_with_changes`
+//! * `CsvError` with the `csv` feature, `JsonError` with the `json` feature: unreadable input
+//! * `ConfigError` with the `config` feature: an unreadable configuration file
+//! * `SqliteError` with the `sqlite` feature: a failing database or a table which is not a valid poll
+//! * `ArrowError` with the `arrow` feature: a record batch which is not a valid poll
+//! * `ConnectorError` with the `connectors` feature: a live backend which cannot be read or gives a malformed ballot
+//!
+//! All of them implement `std::error::Error`. The rankings themselves only panic on misuse,
+//! e.g. candidates with different numbers of grades, see `validate_poll` to report it beforehand
+//! or the `try_` functions of the `checked` rankings to get a `RankingError` instead.
+pub use crate::accumulator::SnapshotError;
+pub use crate::archive::ArchiveError;
+#[cfg(feature = "arrow")]
+pub use crate::arrow::ArrowError;
+#[cfg(feature = "config")]
+pub use crate::config::ConfigError;
+#[cfg(feature = "connectors")]
+pub use crate::connectors::ConnectorError;
+#[cfg(feature = "csv")]
+pub use crate::csv::CsvError;
+#[cfg(feature = "json")]
+pub use crate::json::JsonError;
+pub use crate::election::ElectionError;
+pub use crate::poll::{BallotError, BallotIssue, DuplicateBallotError, MergeError};
+pub use crate::precinct::{PrecinctError, PrecinctIssue};
+pub use crate::checked::RankingError;
+pub use crate::rescale::RescaleError;
+pub use crate::retally::RetallyError;
+#[cfg(feature = "sqlite")]
+pub use crate::sqlite::SqliteError;
@@ -0,0 +1,369 @@
+//! # Explanations
+//! A trace of the decisions behind a ranking, for election administrators to publish a result
+//! anyone can check by hand: each candidate is compared to the next one in the ranking, and the
+//! trace tells how many grades were withdrawn before their median grades diverged, and which
+//! median grades decided.
+//!
+//! After `step` withdrawals the two candidates had the same median grades all along, so the grades
+//! at `step` decide: `step == 0` means the majority grades themselves differ.
+//!
+//! `compare_candidates` answers the same question for any two candidates, without ranking the others,
+//! and `dominance_matrix` for every pair of candidates: the pairs decided after many withdrawals are near-ties.
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use crate::majority_value::MajorityValue;
+use crate::mj::{check_poll_length, compute_majority_values, majority_judgment_ranking};
+use crate::ranking::Ranking;
+
+/// How two candidates were separated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome<G> {
+    /// The median grades diverged after `step` withdrawals
+    Decided {
+        /// The number of median grades withdrawn from each candidate before they diverged
+        step: u64,
+        /// The median grade of the higher candidate at this step
+        higher_grade: G,
+        /// The median grade of the lower candidate at this step
+        lower_grade: G,
+    },
+    /// The majority values are exactly the same: the candidates share the same rank
+    Tied,
+}
+
+/// The comparison of two candidates, consecutive in the ranking of `explain` or chosen with `compare_candidates`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision<'a, K, G> {
+    /// The candidate ranked first
+    pub higher: &'a K,
+    /// The candidate ranked next
+    pub lower: &'a K,
+    /// How they were separated
+    pub outcome: Outcome<G>,
+}
+
+/// A ranking with the decisions separating its consecutive candidates, see `explain`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Explanation<'a, K, G> {
+    /// The ranking of the majority judgment
+    pub ranking: Ranking<'a, K, G>,
+    /// The comparison of each candidate with the next one, from the top of the ranking
+    pub decisions: Vec<Decision<'a, K, G>>,
+}
+
+impl<K: fmt::Display, G: fmt::Display> fmt::Display for Explanation<'_, K, G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, decision) in self.decisions.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            match &decision.outcome {
+                Outcome::Decided { step: 0, higher_grade, lower_grade } => write!(
+                    f,
+                    "{} ranks before {}: majority grade {} against {}",
+                    decision.higher, decision.lower, higher_grade, lower_grade
+                )?,
+                Outcome::Decided { step, higher_grade, lower_grade } => write!(
+                    f,
+                    "{} ranks before {}: same median grades for {} withdrawals, then {} against {}",
+                    decision.higher, decision.lower, step, higher_grade, lower_grade
+                )?,
+                Outcome::Tied => write!(f, "{} and {} are tied: same majority values", decision.higher, decision.lower)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Function that ranks the candidates and explains how each one was separated from the next
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+///
+/// # Returns
+/// * `Explanation`: the ranking of `majority_judgment_ranking` and one decision per pair of
+///   consecutive candidates
+///
+/// # Panics
+/// * if the candidates have different numbers of grades
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::explain;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![0, 2, 2, 3]);
+/// poll_data.insert("Chips".to_string(), vec![0, 2, 2, 2]);
+///
+/// let explanation = explain(&poll_data);
+/// assert_eq!(
+///     explanation.to_string(),
+///     "Pizza ranks before Chips: same median grades for 3 withdrawals, then 3 against 2");
+/// ```
+pub fn explain<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>) -> Explanation<'_, K, G> {
+    let _ = check_poll_length(poll_data);
+
+    let ranking = majority_judgment_ranking(poll_data);
+    let majority_values: BTreeMap<&K, _> = poll_data
+        .iter()
+        .map(|(candidate, grades)| (candidate, compute_majority_values(grades)))
+        .collect();
+
+    let decisions = ranking
+        .candidates()
+        .windows(2)
+        .map(|pair| {
+            let (higher, lower) = (pair[0].candidate, pair[1].candidate);
+            let outcome = match majority_values[higher].first_difference(&majority_values[lower]) {
+                Some((step, higher_grade, lower_grade)) => Outcome::Decided {
+                    step,
+                    higher_grade: higher_grade.clone(),
+                    lower_grade: lower_grade.clone(),
+                },
+                None => Outcome::Tied,
+            };
+            Decision { higher, lower, outcome }
+        })
+        .collect();
+
+    Explanation { ranking, decisions }
+}
+
+/// Function that compares two candidates of a poll head-to-head, computing only their own majority values
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `a`, `b`: the two candidates to compare
+///
+/// # Returns
+/// * `Option<(Ordering, Decision)>`: `Ordering::Greater` if `a` ranks before `b`, `Ordering::Less` if `b` ranks
+///   before `a`, `Ordering::Equal` if they are tied, with the decision separating them
+///   (`higher` being `a` when they are tied). None if `a` or `b` is not a candidate of the poll.
+///
+/// # Panics
+/// * if the two candidates have different numbers of grades
+///
+/// # Example
+/// ```
+/// use std::cmp::Ordering;
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{compare_candidates, Outcome};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![0, 2, 2, 3]);
+/// poll_data.insert("Chips", vec![0, 2, 2, 2]);
+///
+/// let (ordering, decision) = compare_candidates(&poll_data, &"Chips", &"Pizza").unwrap();
+/// assert_eq!(ordering, Ordering::Less);
+/// assert_eq!(decision.higher, &"Pizza");
+/// assert_eq!(decision.outcome, Outcome::Decided { step: 3, higher_grade: 3, lower_grade: 2 });
+/// ```
+pub fn compare_candidates<'a, K: Ord, G: Ord + Clone>(
+    poll_data: &'a BTreeMap<K, Vec<G>>,
+    a: &K,
+    b: &K,
+) -> Option<(Ordering, Decision<'a, K, G>)> {
+    let (a, a_grades) = poll_data.get_key_value(a)?;
+    let (b, b_grades) = poll_data.get_key_value(b)?;
+    if a_grades.len() != b_grades.len() {
+        panic!("The polls have different lengths!");
+    }
+
+    Some(decide((a, &compute_majority_values(a_grades)), (b, &compute_majority_values(b_grades))))
+}
+
+/// Function that compares two candidates from their majority values, see `compare_candidates`
+fn decide<'a, K, G: Ord + Clone>(
+    (a, a_values): (&'a K, &MajorityValue<G>),
+    (b, b_values): (&'a K, &MajorityValue<G>),
+) -> (Ordering, Decision<'a, K, G>) {
+    let ordering = a_values.cmp(b_values);
+    let (higher, lower) = if ordering == Ordering::Less { (b, a) } else { (a, b) };
+    let difference = match ordering {
+        Ordering::Less => b_values.first_difference(a_values),
+        _ => a_values.first_difference(b_values),
+    };
+    let outcome = match difference {
+        Some((step, higher_grade, lower_grade)) => Outcome::Decided {
+            step,
+            higher_grade: higher_grade.clone(),
+            lower_grade: lower_grade.clone(),
+        },
+        None => Outcome::Tied,
+    };
+    (ordering, Decision { higher, lower, outcome })
+}
+
+/// A cell of a `DominanceMatrix`, the result of `compare_candidates`
+type Cell<'a, K, G> = Option<(Ordering, Decision<'a, K, G>)>;
+
+/// The head-to-head comparison of every pair of candidates, see `dominance_matrix`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DominanceMatrix<'a, K, G> {
+    /// The candidates by increasing key, the rows and the columns of the matrix
+    pub candidates: Vec<&'a K>,
+    /// `decisions[i][j]` compares `candidates[i]` with `candidates[j]` as `compare_candidates` does,
+    /// None on the diagonal
+    pub decisions: Vec<Vec<Cell<'a, K, G>>>,
+}
+
+impl<'a, K: Ord, G> DominanceMatrix<'a, K, G> {
+    /// The comparison of `a` with `b`, None if one of them is not a candidate or if they are the same
+    pub fn get(&self, a: &K, b: &K) -> Option<&(Ordering, Decision<'a, K, G>)> {
+        let row = self.candidates.binary_search(&a).ok()?;
+        let column = self.candidates.binary_search(&b).ok()?;
+        self.decisions[row][column].as_ref()
+    }
+
+    /// Function that finds the pairs of candidates hardest to separate
+    ///
+    /// # Arguments
+    /// * `min_step`: the number of withdrawals from which a pair is a near-tie, 0 listing every pair
+    ///
+    /// # Returns
+    /// * `Vec<&Decision>`: the decisions of the pairs tied or decided after at least `min_step` withdrawals,
+    ///   each pair once, the tied pairs first then by decreasing step
+    pub fn near_ties(&self, min_step: u64) -> Vec<&Decision<'a, K, G>> {
+        let mut near_ties: Vec<&Decision<'a, K, G>> = self
+            .decisions
+            .iter()
+            .enumerate()
+            .flat_map(|(row, decisions)| decisions.iter().skip(row + 1).flatten())
+            .map(|(_, decision)| decision)
+            .filter(|decision| match decision.outcome {
+                Outcome::Decided { step, .. } => step >= min_step,
+                Outcome::Tied => true,
+            })
+            .collect();
+        near_ties.sort_by_key(|decision| match decision.outcome {
+            Outcome::Decided { step, .. } => core::cmp::Reverse(step),
+            Outcome::Tied => core::cmp::Reverse(u64::MAX),
+        });
+        near_ties
+    }
+}
+
+/// Function that compares every pair of candidates of a poll head-to-head
+///
+/// The majority values of each candidate are computed once, then compared with those of every other candidate.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+///
+/// # Returns
+/// * `DominanceMatrix`: for each pair of candidates, which one the majority judgment prefers and by which grades
+///
+/// # Panics
+/// * if the candidates have different numbers of grades
+///
+/// # Example
+/// ```
+/// use std::cmp::Ordering;
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{dominance_matrix, Outcome};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![0, 2, 2, 3]);
+/// poll_data.insert("Chips", vec![0, 2, 2, 2]);
+/// poll_data.insert("Soup", vec![0, 0, 1, 1]);
+///
+/// let matrix = dominance_matrix(&poll_data);
+/// let (ordering, decision) = matrix.get(&"Soup", &"Chips").unwrap();
+/// assert_eq!((*ordering, decision.higher), (Ordering::Less, &"Chips"));
+/// assert_eq!(decision.outcome, Outcome::Decided { step: 0, higher_grade: 2, lower_grade: 0 });
+///
+/// let near_ties = matrix.near_ties(2);
+/// assert_eq!((near_ties.len(), near_ties[0].higher), (1, &"Pizza"));
+/// ```
+pub fn dominance_matrix<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>) -> DominanceMatrix<'_, K, G> {
+    let _ = check_poll_length(poll_data);
+
+    let majority_values: Vec<(&K, MajorityValue<G>)> = poll_data
+        .iter()
+        .map(|(candidate, grades)| (candidate, compute_majority_values(grades)))
+        .collect();
+    let decisions = majority_values
+        .iter()
+        .enumerate()
+        .map(|(row, (a, a_values))| {
+            majority_values
+                .iter()
+                .enumerate()
+                .map(|(column, (b, b_values))| (row != column).then(|| decide((*a, a_values), (*b, b_values))))
+                .collect()
+        })
+        .collect();
+
+    DominanceMatrix { candidates: poll_data.keys().collect(), decisions }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_explain() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![0, 2, 1, 3]);
+        poll_data.insert("Chips", vec![3, 1, 2, 0]);
+        poll_data.insert("Pasta", vec![0, 1, 1, 1]);
+        poll_data.insert("Bread", vec![3, 3, 3, 3]);
+
+        let explanation = explain(&poll_data);
+        assert_eq!(explanation.decisions[0].outcome, Outcome::Decided { step: 0, higher_grade: 3, lower_grade: 1 });
+        assert_eq!(explanation.decisions[1].outcome, Outcome::Tied);
+        assert_eq!(explanation.decisions[2].outcome, Outcome::Decided { step: 1, higher_grade: 2, lower_grade: 1 });
+        assert_eq!(
+            explanation.to_string(),
+            "Bread ranks before Chips: majority grade 3 against 1\n\
+             Chips and Pizza are tied: same majority values\n\
+             Pizza ranks before Pasta: same median grades for 1 withdrawals, then 2 against 1");
+
+        assert!(explain(&BTreeMap::<&str, Vec<u8>>::new()).decisions.is_empty());
+    }
+
+    #[test]
+    fn calling_compare_candidates() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![0, 2, 1, 3]);
+        poll_data.insert("Chips", vec![3, 1, 2, 0]);
+        poll_data.insert("Bread", vec![3, 3, 3, 3]);
+
+        let explanation = explain(&poll_data);
+        let (ordering, decision) = compare_candidates(&poll_data, &"Bread", &"Chips").unwrap();
+        assert_eq!(ordering, Ordering::Greater);
+        assert_eq!(decision, explanation.decisions[0]);
+
+        let (ordering, decision) = compare_candidates(&poll_data, &"Pizza", &"Bread").unwrap();
+        assert_eq!(ordering, Ordering::Less);
+        assert_eq!((decision.higher, decision.lower), (&"Bread", &"Pizza"));
+
+        let (ordering, decision) = compare_candidates(&poll_data, &"Pizza", &"Chips").unwrap();
+        assert_eq!((ordering, decision.higher, decision.outcome), (Ordering::Equal, &"Pizza", Outcome::Tied));
+        assert!(compare_candidates(&poll_data, &"Pizza", &"Pasta").is_none());
+    }
+
+    #[test]
+    fn calling_dominance_matrix() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![0, 2, 1, 3]);
+        poll_data.insert("Chips", vec![3, 1, 2, 0]);
+        poll_data.insert("Pasta", vec![0, 1, 1, 1]);
+        poll_data.insert("Bread", vec![3, 3, 3, 3]);
+
+        let matrix = dominance_matrix(&poll_data);
+        assert_eq!(matrix.candidates, vec![&"Bread", &"Chips", &"Pasta", &"Pizza"]);
+        for a in poll_data.keys() {
+            assert!(matrix.get(a, a).is_none());
+            for b in poll_data.keys().filter(|b| *b != a) {
+                assert_eq!(matrix.get(a, b), compare_candidates(&poll_data, a, b).as_ref());
+            }
+        }
+
+        let near_ties = matrix.near_ties(1);
+        assert_eq!(near_ties.len(), 3);
+        assert_eq!((near_ties[0].higher, near_ties[0].lower, &near_ties[0].outcome), (&"Chips", &"Pizza", &Outcome::Tied));
+        assert_eq!(matrix.near_ties(0).len(), 6);
+        assert!(matrix.get(&"Bread", &"Soup").is_none());
+    }
+}
@@ -0,0 +1,256 @@
+//! # C interface
+//! A stable C ABI (enabled by the `ffi` feature) for bindings in C, C++, Python or R, declared in
+//! `include/majority_judgment.h`. The crate is an `rlib`, so that `no_std` crates can depend on it; the shared
+//! library is built on demand as a `cdylib`:
+//! ```text
+//! cargo rustc --release --lib --crate-type cdylib --features ffi
+//! ```
+//! gives `target/release/libmajority_judgement_rust.so` (`.dylib` on macOS, `.dll` on Windows).
+//!
+//! The poll is passed as a tally: `candidates` rows of `grades` counts, the number of votes of each
+//! grade from the worst (index 0) to the best. The functions never panic across the boundary,
+//! they return an error code instead.
+use std::collections::BTreeMap;
+use crate::judgment::total_votes;
+use crate::mj::{rank_candidates, OptionsMethod};
+use crate::ranking_method::RankingMethod;
+use crate::options::{GradeValue, BuiltinMethod, TieOrder};
+
+/// Success
+pub const MJ_OK: i32 = 0;
+/// A pointer is null while its length is not zero
+pub const MJ_ERROR_NULL_POINTER: i32 = -1;
+/// The ranking method is unknown
+pub const MJ_ERROR_INVALID_METHOD: i32 = -2;
+/// The majority values need the same number of votes for every candidate
+pub const MJ_ERROR_UNEQUAL_VOTES: i32 = -3;
+/// A candidate has more than `u64::MAX` (`UINT64_MAX`) votes in total
+pub const MJ_ERROR_TOO_MANY_VOTES: i32 = -4;
+/// The tally or the result has more values than the memory can address, e.g. `candidates * grades` overflows
+pub const MJ_ERROR_TOO_LARGE: i32 = -5;
+
+/// Function that ranks the candidates of a tally with the majority values, see `mj_rank_with_method`
+///
+/// # Safety
+/// See `mj_rank_with_method`.
+#[no_mangle]
+pub unsafe extern "C" fn mj_rank(tally: *const u32, candidates: usize, grades: usize, ranks: *mut usize) -> i32 {
+    mj_rank_with_method(tally, candidates, grades, 0, ranks)
+}
+
+/// Function that ranks the candidates of a tally
+///
+/// # Arguments
+/// * `tally`: `candidates * grades` counts, row by row: `tally[c * grades + g]` is the number of
+///   votes of grade `g` for the candidate `c`
+/// * `candidates`, `grades`: the dimensions of the tally
+/// * `method`: 0 majority values, 1 majority gauge, 2 usual judgment, 3 typical judgment
+/// * `ranks`: where to write the rank of each candidate, `candidates` values. The rank 0 is the
+///   best, tied candidates share the same rank, e.g. 0, 1, 1, 3.
+///
+/// # Returns
+/// * `i32`: `MJ_OK`, or a negative error code leaving `ranks` untouched
+///
+/// # Safety
+/// `tally` must point to `candidates * grades` readable counts and `ranks` to `candidates`
+/// writable values, unless these lengths are 0.
+#[no_mangle]
+pub unsafe extern "C" fn mj_rank_with_method(
+    tally: *const u32,
+    candidates: usize,
+    grades: usize,
+    method: u32,
+    ranks: *mut usize,
+) -> i32 {
+    rank_raw(tally, candidates, grades, method, ranks)
+}
+
+/// Function that ranks the candidates of a tally of 64-bit counts, for electorates beyond 4 billion
+/// votes, see `mj_rank_with_method`
+///
+/// # Safety
+/// See `mj_rank_with_method`.
+#[no_mangle]
+pub unsafe extern "C" fn mj_rank64_with_method(
+    tally: *const u64,
+    candidates: usize,
+    grades: usize,
+    method: u32,
+    ranks: *mut usize,
+) -> i32 {
+    rank_raw(tally, candidates, grades, method, ranks)
+}
+
+/// Function that computes the number of counts of a tally, None if the tally of `C` counts or the
+/// `candidates` results do not fit in the memory a slice can address
+fn cells<C>(candidates: usize, grades: usize) -> Option<usize> {
+    let fits = |values: usize, size: usize| values.checked_mul(size).is_some_and(|bytes| bytes <= isize::MAX as usize);
+    let cells = candidates.checked_mul(grades)?;
+    (fits(cells, std::mem::size_of::<C>()) && fits(candidates, std::mem::size_of::<usize>())).then_some(cells)
+}
+
+/// Function that checks the arguments of the ranking functions and ranks the tally
+unsafe fn rank_raw<C: Copy + Into<u64>>(
+    tally: *const C,
+    candidates: usize,
+    grades: usize,
+    method: u32,
+    ranks: *mut usize,
+) -> i32 {
+    let Some(cells) = cells::<C>(candidates, grades) else { return MJ_ERROR_TOO_LARGE };
+    if (cells > 0 && tally.is_null()) || (candidates > 0 && ranks.is_null()) {
+        return MJ_ERROR_NULL_POINTER;
+    }
+    let method = match method {
+        0 => BuiltinMethod::MajorityValues,
+        1 => BuiltinMethod::MajorityGauge,
+        2 => BuiltinMethod::UsualJudgment,
+        3 => BuiltinMethod::TypicalJudgment,
+        _ => return MJ_ERROR_INVALID_METHOD,
+    };
+    if candidates == 0 {
+        return MJ_OK;
+    }
+    let counts: &[C] = if cells == 0 { &[] } else { std::slice::from_raw_parts(tally, cells) };
+
+    match rank_tally(counts, candidates, grades, method) {
+        Ok(result) => {
+            std::slice::from_raw_parts_mut(ranks, candidates).copy_from_slice(&result);
+            MJ_OK
+        }
+        Err(code) => code,
+    }
+}
+
+/// Function that finds the majority grade of each candidate of a tally: its lower median grade
+///
+/// # Arguments
+/// * `tally`, `candidates`, `grades`: the tally, see `mj_rank_with_method`
+/// * `majority_grades`: where to write the index of the majority grade of each candidate,
+///   `candidates` values, `usize::MAX` (`SIZE_MAX`) for a candidate without any vote
+///
+/// # Returns
+/// * `i32`: `MJ_OK`, or a negative error code leaving `majority_grades` untouched
+///
+/// # Safety
+/// `tally` must point to `candidates * grades` readable counts and `majority_grades`
+/// to `candidates` writable values, unless these lengths are 0.
+#[no_mangle]
+pub unsafe extern "C" fn mj_majority_grades(
+    tally: *const u32,
+    candidates: usize,
+    grades: usize,
+    majority_grades: *mut usize,
+) -> i32 {
+    let Some(cells) = cells::<u32>(candidates, grades) else { return MJ_ERROR_TOO_LARGE };
+    if (cells > 0 && tally.is_null()) || (candidates > 0 && majority_grades.is_null()) {
+        return MJ_ERROR_NULL_POINTER;
+    }
+    if candidates == 0 {
+        return MJ_OK;
+    }
+    let counts: &[u32] = if cells == 0 { &[] } else { std::slice::from_raw_parts(tally, cells) };
+    // written once every row is counted, leaving `majority_grades` untouched on error
+    let mut grades_found = Vec::with_capacity(candidates);
+    for candidate in 0..candidates {
+        let row = &counts[candidate * grades..(candidate + 1) * grades];
+        let Some(total) = row.iter().try_fold(0u64, |total, &count| total.checked_add(count as u64)) else {
+            return MJ_ERROR_TOO_MANY_VOTES;
+        };
+        let mut cumulative = 0;
+        grades_found.push(
+            row.iter()
+                .position(|&count| {
+                    cumulative += count as u64;
+                    total > 0 && cumulative >= total - cumulative
+                })
+                .unwrap_or(usize::MAX),
+        );
+    }
+    std::slice::from_raw_parts_mut(majority_grades, candidates).copy_from_slice(&grades_found);
+    MJ_OK
+}
+
+/// Function that ranks the rows of a tally, the rank of each row in the order of the rows
+fn rank_tally<C: Copy + Into<u64>>(
+    counts: &[C],
+    candidates: usize,
+    grades: usize,
+    method: BuiltinMethod,
+) -> Result<Vec<usize>, i32> {
+    let indices: Vec<usize> = (0..candidates).collect();
+    let tallies: Vec<BTreeMap<usize, u64>> = indices
+        .iter()
+        .map(|&candidate| {
+            (0..grades)
+                .map(|grade| (grade, counts[candidate * grades + grade].into()))
+                .filter(|&(_, count)| count > 0)
+                .collect()
+        })
+        .collect();
+
+    let totals = tallies
+        .iter()
+        .map(|tally| total_votes(tally.values()).ok_or(MJ_ERROR_TOO_MANY_VOTES))
+        .collect::<Result<Vec<u64>, i32>>()?;
+    if method == BuiltinMethod::MajorityValues && totals.windows(2).any(|pair| pair[0] != pair[1]) {
+        return Err(MJ_ERROR_UNEQUAL_VOTES);
+    }
+
+    let merits = indices.iter().zip(tallies.iter())
+        .map(|(candidate, tally)| (candidate, OptionsMethod::from_tally(method, GradeValue::Missing).merit(tally)))
+        .collect();
+    let ranking = rank_candidates::<usize, usize, _>(merits, &BTreeMap::new(), None, TieOrder::default());
+
+    let mut ranks = vec![0; candidates];
+    for ranked in ranking.iter() {
+        ranks[*ranked.candidate] = ranked.rank;
+    }
+    Ok(ranks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_mj_rank() {
+        // Pizza, Chips, Pasta, Bread of the README, 4 grades
+        let tally: [u32; 16] = [4, 1, 2, 3, 2, 2, 4, 2, 2, 3, 2, 3, 1, 4, 4, 1];
+        let mut ranks = [usize::MAX; 4];
+        assert_eq!(unsafe { mj_rank(tally.as_ptr(), 4, 4, ranks.as_mut_ptr()) }, MJ_OK);
+        assert_eq!(ranks, [3, 0, 1, 2]);
+        let wide: Vec<u64> = tally.iter().map(|&count| count as u64 * 1_000_000_000).collect();
+        let mut wide_ranks = [usize::MAX; 4];
+        assert_eq!(unsafe { mj_rank64_with_method(wide.as_ptr(), 4, 4, 0, wide_ranks.as_mut_ptr()) }, MJ_OK);
+        assert_eq!(wide_ranks, ranks);
+
+        let unequal: [u32; 4] = [1, 1, 0, 1];
+        assert_eq!(unsafe { mj_rank(unequal.as_ptr(), 2, 2, ranks.as_mut_ptr()) }, MJ_ERROR_UNEQUAL_VOTES);
+        assert_eq!(unsafe { mj_rank_with_method(unequal.as_ptr(), 2, 2, 1, ranks.as_mut_ptr()) }, MJ_OK);
+        assert_eq!(ranks[..2], [1, 0]);
+        assert_eq!(unsafe { mj_rank_with_method(unequal.as_ptr(), 2, 2, 9, ranks.as_mut_ptr()) }, MJ_ERROR_INVALID_METHOD);
+        assert_eq!(unsafe { mj_rank(std::ptr::null(), 2, 2, ranks.as_mut_ptr()) }, MJ_ERROR_NULL_POINTER);
+        assert_eq!(unsafe { mj_rank(std::ptr::null(), 0, 0, std::ptr::null_mut()) }, MJ_OK);
+        // dimensions beyond the memory are refused before the pointers are read
+        assert_eq!(unsafe { mj_rank(tally.as_ptr(), usize::MAX, 2, ranks.as_mut_ptr()) }, MJ_ERROR_TOO_LARGE);
+        assert_eq!(unsafe { mj_rank64_with_method(wide.as_ptr(), usize::MAX / 8, 1, 0, ranks.as_mut_ptr()) }, MJ_ERROR_TOO_LARGE);
+        assert_eq!(unsafe { mj_majority_grades(tally.as_ptr(), 2, usize::MAX, ranks.as_mut_ptr()) }, MJ_ERROR_TOO_LARGE);
+
+        // more than half of u64::MAX votes, then more than u64::MAX votes
+        let large = [u64::MAX / 2 + 10, 5, 5, u64::MAX / 2 + 10];
+        for method in 0..4 {
+            assert_eq!(unsafe { mj_rank64_with_method(large.as_ptr(), 2, 2, method, ranks.as_mut_ptr()) }, MJ_OK);
+            assert_eq!(ranks[..2], [1, 0]);
+        }
+        let overflow = [u64::MAX, 1, 0, 0];
+        assert_eq!(unsafe { mj_rank64_with_method(overflow.as_ptr(), 2, 2, 1, ranks.as_mut_ptr()) }, MJ_ERROR_TOO_MANY_VOTES);
+
+        let mut majority_grades = [0; 4];
+        assert_eq!(unsafe { mj_majority_grades(tally.as_ptr(), 4, 4, majority_grades.as_mut_ptr()) }, MJ_OK);
+        assert_eq!(majority_grades, [1, 2, 1, 1]);
+        let empty = [0u32; 2];
+        assert_eq!(unsafe { mj_majority_grades(empty.as_ptr(), 1, 2, majority_grades.as_mut_ptr()) }, MJ_OK);
+        assert_eq!(majority_grades[0], usize::MAX);
+    }
+}
@@ -0,0 +1,296 @@
+//! # Localized results
+//! The results of a poll written in the language of its voters, with the labels of its grade scale:
+//! * `majority_grade`: "Majority grade: Good", "Mention majoritaire : Assez bien"
+//! * `rank`: "2nd (tied)", "2e ex æquo"
+//! * `format_ranking`: one line per candidate with its rank and majority grade
+//! * `Ranking::to_markdown`: a Markdown table of the results, to paste into meeting minutes or an issue,
+//!   its percentages written with the `ReportOptions` of `Ranking::to_markdown_with_options`
+//!
+//! French results follow the French typography, with a space before the colon.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use crate::mj::majority_judgment_ranking;
+use crate::profile::merit_profile;
+use crate::ranking::{Ranking, TieStatus};
+use crate::report::ReportOptions;
+use crate::scale::{GradeScale, Language};
+
+/// Function that writes the majority grade of a candidate
+///
+/// # Arguments
+/// * `grade`: the majority grade
+/// * `scale`: the labels of the grades, a grade out of the scale is written as its number
+/// * `language`: the language of the result
+///
+/// # Returns
+/// * `String`: the majority grade with its label
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::format::majority_grade;
+/// use majority_judgement_rust::scale::{GradeScale, Language};
+/// let scale = GradeScale::balinski_laraki(Language::French);
+/// assert_eq!(majority_grade(3, &scale, Language::French), "Mention majoritaire : Assez bien");
+/// ```
+pub fn majority_grade(grade: usize, scale: &GradeScale, language: Language) -> String {
+    let label = scale.label(grade).map_or_else(|| grade.to_string(), str::to_string);
+    match language {
+        Language::English => format!("Majority grade: {}", label),
+        Language::French => format!("Mention majoritaire : {}", label),
+    }
+}
+
+/// Function that writes the rank of a candidate as an ordinal
+///
+/// # Arguments
+/// * `rank`: the rank, 0 being the best
+/// * `tie`: whether the candidate shares its rank
+/// * `language`: the language of the result
+///
+/// # Returns
+/// * `String`: the ordinal of the rank, e.g. "1st" or "1er", marked when tied
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::format::rank;
+/// use majority_judgement_rust::scale::Language;
+/// use majority_judgement_rust::TieStatus;
+/// assert_eq!(rank(0, TieStatus::Unique, Language::French), "1er");
+/// assert_eq!(rank(1, TieStatus::Tied, Language::English), "2nd (tied)");
+/// ```
+pub fn rank(rank: usize, tie: TieStatus, language: Language) -> String {
+    let position = rank + 1;
+    let ordinal = match language {
+        Language::English => {
+            let suffix = match (position % 10, position % 100) {
+                (_, 11..=13) => "th",
+                (1, _) => "st",
+                (2, _) => "nd",
+                (3, _) => "rd",
+                _ => "th",
+            };
+            format!("{}{}", position, suffix)
+        }
+        Language::French if position == 1 => "1er".to_string(),
+        Language::French => format!("{}e", position),
+    };
+    match (tie, language) {
+        (TieStatus::Unique | TieStatus::Broken, _) => ordinal,
+        (TieStatus::Tied, Language::English) => format!("{} (tied)", ordinal),
+        (TieStatus::Tied, Language::French) => format!("{} ex æquo", ordinal),
+    }
+}
+
+/// Function that writes the ranking of a poll, with the majority grade of each candidate
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, the grades being indices in the scale
+/// * `scale`: the labels of the grades
+/// * `language`: the language of the result
+///
+/// # Returns
+/// * `String`: one line per candidate in the order of the majority judgment
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::format::format_ranking;
+/// use majority_judgement_rust::scale::{GradeScale, Language};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![3u8, 4, 6]);
+/// poll_data.insert("Chips", vec![2u8, 5, 5]);
+///
+/// let scale = GradeScale::balinski_laraki(Language::French);
+/// assert_eq!(
+///     format_ranking(&poll_data, &scale, Language::French),
+///     "1er Chips, mention majoritaire : Très bien\n2e Pizza, mention majoritaire : Bien"
+/// );
+/// ```
+pub fn format_ranking<K, G>(poll_data: &BTreeMap<K, Vec<G>>, scale: &GradeScale, language: Language) -> String
+where
+    K: Ord + Display,
+    G: Ord + Copy + Into<usize>,
+{
+    let mut lines = Vec::new();
+    for ranked in majority_judgment_ranking(poll_data).iter() {
+        let mut line = format!("{} {}", rank(ranked.rank, ranked.tie, language), ranked.candidate);
+        if let Some(grade) = ranked.majority_grade {
+            let grade = majority_grade(grade.into(), scale, language);
+            line.push_str(", ");
+            // lowercase the first letter, "Majority grade" in the middle of the line
+            let mut chars = grade.chars();
+            line.extend(chars.next().into_iter().flat_map(char::to_lowercase));
+            line.push_str(chars.as_str());
+        }
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+impl<K: Display, G: Display> Ranking<'_, K, G> {
+    /// Function that writes the ranking as a Markdown table: the rank, the candidate, its majority grade
+    /// and the shares of the votes above and below it
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::majority_judgment_ranking;
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza", vec![0, 2, 3, 3]);
+    /// poll_data.insert("Chips", vec![1, 1, 2, 2]);
+    /// assert_eq!(majority_judgment_ranking(&poll_data).to_markdown(), "\
+    /// | Rank | Candidate | Majority grade | Above | Below |
+    /// |---:|---|---|---:|---:|
+    /// | 1 | Pizza | 2 | 50.0% | 25.0% |
+    /// | 2 | Chips | 1 | 50.0% | 0.0% |
+    /// ");
+    /// ```
+    pub fn to_markdown(&self) -> String {
+        self.to_markdown_with_options(&ReportOptions::default())
+    }
+
+    /// Function that writes the ranking as the Markdown table of `to_markdown`, writing the percentages with the given options
+    ///
+    /// # Arguments
+    /// * `options`: the precision and the rounding of the percentages
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::majority_judgment_ranking;
+    /// use majority_judgement_rust::report::{ReportOptions, Rounding};
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza", vec![0, 2, 2, 3, 3, 3]);
+    /// let options = ReportOptions::default().precision(2).rounding(Rounding::HalfUp);
+    /// assert!(majority_judgment_ranking(&poll_data).to_markdown_with_options(&options).ends_with("| 1 | Pizza | 2 | 50.00% | 16.67% |\n"));
+    /// ```
+    pub fn to_markdown_with_options(&self, options: &ReportOptions) -> String {
+        let mut markdown = String::from("| Rank | Candidate | Majority grade | Above | Below |\n|---:|---|---|---:|---:|\n");
+        for ranked in self.iter() {
+            let rank = match ranked.tie {
+                TieStatus::Tied => format!("{} (tied)", ranked.rank + 1),
+                TieStatus::Unique | TieStatus::Broken => (ranked.rank + 1).to_string(),
+            };
+            let grade = ranked.majority_grade.as_ref().map(ToString::to_string).unwrap_or_default();
+            let (above, below) = match ranked.shares {
+                Some(shares) => (options.percent(shares.above, shares.total), options.percent(shares.below, shares.total)),
+                None => (String::new(), String::new()),
+            };
+            markdown.push_str(&format!("| {} | {} | {} | {} | {} |\n", rank, escape_cell(ranked.candidate), escape_cell(&grade), above, below));
+        }
+        markdown
+    }
+
+    /// Function that writes the ranking as a Markdown table followed by the merit profile of each candidate,
+    /// one line per candidate with the share of each grade it received, from the worst to the best
+    ///
+    /// # Arguments
+    /// * `poll_data`: the poll data of the ranking
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::majority_judgment_ranking;
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza", vec![0, 2, 2, 2]);
+    /// let markdown = majority_judgment_ranking(&poll_data).to_markdown_with_profile(&poll_data);
+    /// assert!(markdown.ends_with("\n**Merit profiles**\n\n- Pizza: 0 25.0%, 2 75.0%\n"));
+    /// ```
+    pub fn to_markdown_with_profile(&self, poll_data: &BTreeMap<K, Vec<G>>) -> String
+    where
+        K: Ord,
+        G: Ord + Clone,
+    {
+        self.to_markdown_with_profile_and_options(poll_data, &ReportOptions::default())
+    }
+
+    /// Function that writes the ranking and the merit profiles of `to_markdown_with_profile`,
+    /// writing the percentages with the given options
+    ///
+    /// # Arguments
+    /// * `poll_data`: the poll data of the ranking
+    /// * `options`: the precision and the rounding of the percentages
+    pub fn to_markdown_with_profile_and_options(&self, poll_data: &BTreeMap<K, Vec<G>>, options: &ReportOptions) -> String
+    where
+        K: Ord,
+        G: Ord + Clone,
+    {
+        let profiles = merit_profile(poll_data);
+        let mut markdown = self.to_markdown_with_options(options);
+        markdown.push_str("\n**Merit profiles**\n\n");
+        for ranked in self.iter() {
+            let Some(profile) = profiles.get(ranked.candidate) else { continue };
+            let total = profile.iter().map(|(_, count, _)| count).sum();
+            let shares: Vec<String> = profile
+                .iter()
+                .filter(|(_, count, _)| *count > 0)
+                .map(|(grade, count, _)| format!("{} {}", grade, options.percent(*count, total)))
+                .collect();
+            markdown.push_str(&format!("- {}: {}\n", ranked.candidate, shares.join(", ")));
+        }
+        markdown
+    }
+}
+
+/// Function that escapes the pipes of a Markdown table cell
+fn escape_cell<T: Display + ?Sized>(cell: &T) -> String {
+    cell.to_string().replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_rank() {
+        let ordinals: Vec<String> = [0, 1, 2, 3, 10, 11, 12, 20, 21, 110, 111]
+            .iter()
+            .map(|&index| rank(index, TieStatus::Unique, Language::English))
+            .collect();
+        assert_eq!(ordinals, ["1st", "2nd", "3rd", "4th", "11th", "12th", "13th", "21st", "22nd", "111th", "112th"]);
+        assert_eq!(rank(0, TieStatus::Tied, Language::French), "1er ex æquo");
+        assert_eq!(rank(2, TieStatus::Unique, Language::French), "3e");
+    }
+
+    #[test]
+    fn calling_format_ranking() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![0u8, 1, 2]);
+        poll_data.insert("Chips", vec![2u8, 1, 0]);
+        poll_data.insert("Soup", vec![9u8, 9, 9]);
+
+        assert_eq!(
+            format_ranking(&poll_data, &GradeScale::school(Language::English), Language::English),
+            "1st Soup, majority grade: 9\n2nd (tied) Chips, majority grade: Pass\n2nd (tied) Pizza, majority grade: Pass"
+        );
+        assert_eq!(format_ranking(&BTreeMap::<&str, Vec<u8>>::new(), &GradeScale::new(["Bad"]), Language::French), "");
+    }
+
+    #[test]
+    fn calling_to_markdown() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Fish | Chips", vec![1u8, 2]);
+        poll_data.insert("Pizza", vec![2u8, 1]);
+        poll_data.insert("Soup", vec![0u8, 0]);
+
+        let markdown = majority_judgment_ranking(&poll_data).to_markdown_with_profile(&poll_data);
+        assert_eq!(markdown, "\
+| Rank | Candidate | Majority grade | Above | Below |
+|---:|---|---|---:|---:|
+| 1 (tied) | Fish \\| Chips | 1 | 50.0% | 0.0% |
+| 1 (tied) | Pizza | 1 | 50.0% | 0.0% |
+| 3 | Soup | 0 | 0.0% | 0.0% |
+
+**Merit profiles**
+
+- Fish | Chips: 1 50.0%, 2 50.0%
+- Pizza: 1 50.0%, 2 50.0%
+- Soup: 0 100.0%
+");
+
+        let options = ReportOptions::default().precision(0);
+        let markdown = majority_judgment_ranking(&poll_data).to_markdown_with_profile_and_options(&poll_data, &options);
+        assert!(markdown.contains("| 3 | Soup | 0 | 0% | 0% |\n"));
+        assert!(markdown.ends_with("- Pizza: 1 50%, 2 50%\n- Soup: 0 100%\n"));
+    }
+}
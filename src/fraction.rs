@@ -0,0 +1,94 @@
+//! # Fraction
+//! Exact fractions of vote counts, so that shares of the electorate are compared
+//! without any floating point rounding.
+//!
+//! The comparisons multiply in 256 bits, so they never overflow: any `i128` numerator and `u128`
+//! denominator compare exactly, e.g. a sum of `i64` grade values over the total of `u64` vote counts.
+use core::cmp::Ordering;
+
+/// An exact fraction `numerator / denominator`, the denominator being positive
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fraction {
+    numerator: i128,
+    denominator: u128,
+}
+
+impl Fraction {
+    /// Create the fraction `numerator / denominator`, `0 / 1` if the denominator is zero
+    ///
+    /// # Arguments
+    /// * `numerator`: a signed count of votes
+    /// * `denominator`: a count of votes
+    pub(crate) fn new(numerator: i128, denominator: u128) -> Self {
+        if denominator == 0 {
+            return Fraction { numerator: 0, denominator: 1 };
+        }
+        Fraction { numerator, denominator }
+    }
+}
+
+impl PartialEq for Fraction {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Fraction {}
+
+impl PartialOrd for Fraction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Fraction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let sign = self.numerator.signum().cmp(&other.numerator.signum());
+        if sign != Ordering::Equal {
+            return sign;
+        }
+        // same sign: compare the magnitudes by cross multiplication
+        let left = widening_mul(self.numerator.unsigned_abs(), other.denominator);
+        let right = widening_mul(other.numerator.unsigned_abs(), self.denominator);
+        if self.numerator < 0 {
+            right.cmp(&left)
+        } else {
+            left.cmp(&right)
+        }
+    }
+}
+
+/// Function that multiplies two `u128` without overflow
+///
+/// # Returns
+/// * `(u128, u128)`: the high and the low 128 bits of the product, compared lexicographically
+fn widening_mul(a: u128, b: u128) -> (u128, u128) {
+    const LOW: u128 = u64::MAX as u128;
+    let (a_high, a_low, b_high, b_low) = (a >> 64, a & LOW, b >> 64, b & LOW);
+    let low = a_low * b_low;
+    let middle_a = a_high * b_low;
+    let middle_b = a_low * b_high;
+    let (middle, middle_carry) = middle_a.overflowing_add(middle_b);
+    let (low, low_carry) = low.overflowing_add(middle << 64);
+    let high = a_high * b_high + (middle >> 64) + ((middle_carry as u128) << 64) + low_carry as u128;
+    (high, low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_cmp() {
+        assert_eq!(Fraction::new(1, 2), Fraction::new(2, 4));
+        assert!(Fraction::new(1, 3) < Fraction::new(1, 2));
+        assert!(Fraction::new(-1, 3) > Fraction::new(-1, 2));
+        assert!(Fraction::new(-1, 3) < Fraction::new(0, 2));
+        assert_eq!(Fraction::new(3, 0), Fraction::new(0, 1));
+
+        // the cross products exceed 128 bits
+        assert!(Fraction::new(i128::MAX, u128::MAX) < Fraction::new(i128::MAX, u128::MAX - 1));
+        assert_eq!(Fraction::new(i128::MAX / 3 * 2, u64::MAX as u128 * 2), Fraction::new(i128::MAX / 3, u64::MAX as u128));
+        assert_eq!(widening_mul(u128::MAX, u128::MAX), (u128::MAX - 1, 1));
+    }
+}
@@ -0,0 +1,196 @@
+//! # Typed grades
+//! A grade is an ordinal, compared by its position in its scale. Written as `u8`, the grades of two
+//! different scales, e.g. "Reject < Accept" and "Bad < Fair < Good", mix without any warning.
+//! A `Grade<S>` wraps the ordinal with its scale `S` as a type: the grades of another scale do not compile
+//! in the same poll, and a grade displays as its label. `Grade<S>` is a grade like any other for the rankings,
+//! the polls and the tallies of the crate.
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use alloc::string::String;
+#[cfg(feature = "std")]
+use crate::grade_range::GradeRange;
+use crate::scale::GradeScale;
+
+/// A grade scale known at compile time, implemented by a type without value
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{Grade, Scale};
+/// struct Mood;
+///
+/// impl Scale for Mood {
+///     const LABELS: &'static [&'static str] = &["Bad", "Fair", "Good"];
+/// }
+///
+/// let good = Grade::<Mood>::from_label("Good").unwrap();
+/// assert!(good > Grade::new(1).unwrap());
+/// assert_eq!(good.to_string(), "Good");
+/// assert_eq!(Grade::<Mood>::new(3), None);
+/// ```
+pub trait Scale {
+    /// The labels of the grades, from the worst to the best, 256 at most
+    const LABELS: &'static [&'static str];
+}
+
+/// A grade of the scale `S`, ordered by its position in the scale
+pub struct Grade<S> {
+    ordinal: u8,
+    scale: PhantomData<fn() -> S>,
+}
+
+impl<S: Scale> Grade<S> {
+    /// The grade at this position of the scale, 0 being the worst, None beyond the scale
+    pub fn new(ordinal: u8) -> Option<Self> {
+        ((ordinal as usize) < S::LABELS.len()).then_some(Grade { ordinal, scale: PhantomData })
+    }
+
+    /// The grade with exactly this label, None if the scale has no such label
+    pub fn from_label(label: &str) -> Option<Self> {
+        S::LABELS.iter().position(|&candidate| candidate == label).and_then(|ordinal| Grade::new(ordinal as u8))
+    }
+
+    /// Function that reads a grade written as its label or as its number, see `GradeScale::parse_grade`,
+    /// e.g. to load it with the `csv` module
+    ///
+    /// # Arguments
+    /// * `text`: the label or the number of the grade
+    ///
+    /// # Returns
+    /// * `Result<Grade<S>, String>`: the grade, or why it is not part of the scale
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Self::scale().parse_grade(text).map(|ordinal| Grade { ordinal, scale: PhantomData })
+    }
+
+    /// The position of the grade in its scale, 0 being the worst
+    pub fn ordinal(self) -> u8 {
+        self.ordinal
+    }
+
+    /// The label of the grade
+    pub fn label(self) -> &'static str {
+        S::LABELS[self.ordinal as usize]
+    }
+
+    /// The grades of the scale, from the worst to the best
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..S::LABELS.len()).map(|ordinal| Grade { ordinal: ordinal as u8, scale: PhantomData })
+    }
+
+    /// The grade range accepting the grades of the scale, e.g. for `Poll::with_grade_range`
+    ///
+    /// # Panics
+    /// * if the scale has no grade
+    #[cfg(feature = "std")]
+    pub fn range() -> GradeRange<Self> {
+        let last = Self::all().last().expect("a scale needs at least one grade");
+        GradeRange::between(Grade { ordinal: 0, scale: PhantomData }, last)
+    }
+
+    /// The scale as a `GradeScale`, e.g. to draw a merit profile
+    pub fn scale() -> GradeScale {
+        GradeScale::new(S::LABELS.iter().copied())
+    }
+}
+
+impl<S> Clone for Grade<S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for Grade<S> {}
+
+impl<S> PartialEq for Grade<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.ordinal == other.ordinal
+    }
+}
+
+impl<S> Eq for Grade<S> {}
+
+impl<S> PartialOrd for Grade<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S> Ord for Grade<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ordinal.cmp(&other.ordinal)
+    }
+}
+
+impl<S> Hash for Grade<S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.ordinal.hash(state);
+    }
+}
+
+impl<S: Scale> fmt::Debug for Grade<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Grade({}, {:?})", self.ordinal, self.label())
+    }
+}
+
+impl<S: Scale> fmt::Display for Grade<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+impl<S> From<Grade<S>> for u8 {
+    fn from(grade: Grade<S>) -> Self {
+        grade.ordinal
+    }
+}
+
+/// The position in the scale, e.g. for `RankedCandidate::majority_grade_label`
+impl<S> From<Grade<S>> for usize {
+    fn from(grade: Grade<S>) -> Self {
+        grade.ordinal as usize
+    }
+}
+
+/// The position in the scale, e.g. for `Options::mean_grade`
+impl<S> From<Grade<S>> for f64 {
+    fn from(grade: Grade<S>) -> Self {
+        grade.ordinal as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::collections::BTreeMap;
+    use alloc::vec;
+    use crate::mj::majority_judgment_ranking;
+
+    struct Motion;
+
+    impl Scale for Motion {
+        const LABELS: &'static [&'static str] = &["Reject", "Abstain", "Accept"];
+    }
+
+    #[test]
+    fn calling_grade() {
+        let [reject, abstain, accept] = [0, 1, 2].map(|ordinal| Grade::<Motion>::new(ordinal).unwrap());
+        assert_eq!(Grade::<Motion>::all().collect::<alloc::vec::Vec<_>>(), [reject, abstain, accept]);
+        assert_eq!(Grade::<Motion>::parse("Abstain"), Ok(abstain));
+        assert_eq!(Grade::<Motion>::parse("2"), Ok(accept));
+        assert!(Grade::<Motion>::parse("Maybe").is_err() && Grade::<Motion>::from_label("reject").is_none());
+        #[cfg(feature = "std")]
+        assert!(Grade::<Motion>::range().contains(&accept));
+        assert_eq!(alloc::format!("{:?} {}", reject, accept), "Grade(0, \"Reject\") Accept");
+
+        // ranked like any grade, and displayed with the labels of its scale
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Motion 1", vec![accept, accept, reject]);
+        poll_data.insert("Motion 2", vec![abstain, accept, reject]);
+        let ranking = majority_judgment_ranking(&poll_data);
+        assert_eq!(ranking.winners(), vec![&"Motion 1"]);
+        assert_eq!(ranking.candidates()[0].majority_grade.map(Grade::label), Some("Accept"));
+        assert_eq!(ranking.candidates()[1].majority_grade_label(&Grade::<Motion>::scale()), Some("Abstain"));
+    }
+}
@@ -0,0 +1,118 @@
+//! # Grade range
+//! The grades a poll accepts. A stray `7` on a scale from 0 to 6, or a `255` read from a corrupted export,
+//! would silently move the medians: a `GradeRange` declared on a `Poll` or a `TallyAccumulator` rejects the
+//! ballots giving such grades, or clamps them to the scale with `OutOfRange::Clamp`.
+/// What to do with a grade out of the `GradeRange`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutOfRange {
+    /// The ballot is invalid, reported as `BallotIssue::OutOfRange`
+    #[default]
+    Reject,
+    /// A grade below the worst grade counts as the worst grade, a grade above the best one as the best grade
+    Clamp,
+}
+
+/// The valid grades of a range
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ValidGrades<G> {
+    /// From the worst grade to the best, both included
+    Between(G, G),
+    /// Only these grades, sorted from the worst to the best
+    OneOf(Vec<G>),
+}
+
+/// The grades accepted by a poll, see `Poll::with_grade_range`
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{Ballot, BallotIssue, GradeRange, OutOfRange, Poll};
+/// let mut poll = Poll::new(["Pizza"]).with_grade_range(GradeRange::between(0, 6));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 2));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 255));
+/// assert_eq!(poll.validate()[0].issue, BallotIssue::OutOfRange("Pizza".to_string()));
+///
+/// let poll = poll.with_grade_range(GradeRange::between(0, 6).policy(OutOfRange::Clamp));
+/// assert_eq!(poll.poll_data().unwrap()["Pizza"], vec![2, 6]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GradeRange<G> {
+    valid: ValidGrades<G>,
+    policy: OutOfRange,
+}
+
+impl<G: PartialOrd + Clone> GradeRange<G> {
+    /// Accept the grades from `worst` to `best`, both included, rejecting the others
+    ///
+    /// # Panics
+    /// * if `worst` is greater than `best`, or if they cannot be compared
+    pub fn between(worst: G, best: G) -> Self {
+        assert!(worst <= best, "the worst grade of a range must not be greater than the best one");
+        GradeRange { valid: ValidGrades::Between(worst, best), policy: OutOfRange::Reject }
+    }
+
+    /// Accept only the given grades, rejecting the others, e.g. a scale with gaps
+    ///
+    /// # Panics
+    /// * if no grade is given, or if two grades cannot be compared, e.g. a NaN
+    pub fn one_of<I: IntoIterator<Item = G>>(grades: I) -> Self {
+        let mut grades: Vec<G> = grades.into_iter().collect();
+        assert!(!grades.is_empty(), "a range must accept at least one grade");
+        grades.sort_by(|a, b| a.partial_cmp(b).expect("the grades of a range must be comparable"));
+        grades.dedup();
+        GradeRange { valid: ValidGrades::OneOf(grades), policy: OutOfRange::Reject }
+    }
+
+    /// Set what to do with the grades out of the range, builder style.
+    /// With `OutOfRange::Clamp`, a grade between two grades of `one_of` is still rejected.
+    pub fn policy(mut self, policy: OutOfRange) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Whether the grade is part of the range
+    pub fn contains(&self, grade: &G) -> bool {
+        match &self.valid {
+            ValidGrades::Between(worst, best) => worst <= grade && grade <= best,
+            ValidGrades::OneOf(grades) => grades.contains(grade),
+        }
+    }
+
+    /// Function that gives the grade counted for a grade of a ballot
+    ///
+    /// # Returns
+    /// * `Option<G>`: the grade itself if it is part of the range, the worst or the best grade if it is clamped,
+    ///   None if it is rejected
+    pub(crate) fn counted(&self, grade: &G) -> Option<G> {
+        if self.contains(grade) {
+            return Some(grade.clone());
+        }
+        let (worst, best) = match &self.valid {
+            ValidGrades::Between(worst, best) => (worst, best),
+            ValidGrades::OneOf(grades) => (grades.first()?, grades.last()?),
+        };
+        match self.policy {
+            OutOfRange::Reject => None,
+            OutOfRange::Clamp if grade < worst => Some(worst.clone()),
+            OutOfRange::Clamp if grade > best => Some(best.clone()),
+            OutOfRange::Clamp => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_counted() {
+        let range = GradeRange::between(1, 5);
+        assert_eq!((range.counted(&3), range.counted(&0), range.counted(&6)), (Some(3), None, None));
+
+        let range = range.policy(OutOfRange::Clamp);
+        assert_eq!((range.counted(&3), range.counted(&0), range.counted(&6)), (Some(3), Some(1), Some(5)));
+
+        let gaps = GradeRange::one_of([0, 5, 10]).policy(OutOfRange::Clamp);
+        assert!(gaps.contains(&5) && !gaps.contains(&4));
+        assert_eq!((gaps.counted(&-1), gaps.counted(&4), gaps.counted(&255)), (Some(0), None, Some(10)));
+    }
+}
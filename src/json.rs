@@ -0,0 +1,849 @@
+//! # JSON
+//! JSON input and output of polls and results (enabled by the `json` feature),
+//! so web backends can accept poll payloads and return structured results.
+//!
+//! A poll is written as:
+//! ```json
+//! {"candidates": ["Pizza", "Chips"],
+//!  "ballots": [{"grades": {"Pizza": 0, "Chips": 2}},
+//!              {"grades": {"Pizza": 3, "Chips": 1}, "weight": 2, "voter": "V-0042"}],
+//!  "metadata": {"Pizza": {"party": "Italian"}}}
+//! ```
+//! The weight (1 by default), the identifier of the voter and the metadata of the candidates are optional.
+//! The crate has no dependency: this module carries its own small JSON parser and writer.
+//! Objects keep the order and the repetitions of their keys, so duplicate grades in a ballot
+//! are reported by `Poll::validate` rather than silently dropped.
+//!
+//! `from_mieux_voter` reads the polls exported by the majority judgment platforms in the style of the
+//! Mieux Voter API, with their `candidates`, `mentions` and `votes` arrays, to verify their results.
+use std::fmt;
+use crate::majority_value::MajorityValue;
+use crate::poll::{Ballot, Poll};
+use crate::ranking::{Metadata, Ranking, TieStatus};
+use crate::scale::GradeScale;
+
+/// The maximal nesting of arrays and objects accepted by the parser
+const MAX_DEPTH: usize = 128;
+
+/// A JSON value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// A number without fraction nor exponent
+    Integer(i128),
+    /// Any other number
+    Float(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// The members of an object, in their order of appearance
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// The first member of an object with the given key
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The string, if the value is one
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The integer, if the value is one
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// The number, if the value is one
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            _ => None,
+        }
+    }
+
+    /// The elements, if the value is an array
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    /// The members, if the value is an object
+    pub fn as_object(&self) -> Option<&[(String, Value)]> {
+        match self {
+            Value::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// Compact JSON text of the value
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::Float(x) if x.is_finite() => write!(f, "{:?}", x),
+            Value::Float(_) => write!(f, "null"),
+            Value::String(s) => write_string(f, s),
+            Value::Array(elements) => {
+                write!(f, "[")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(members) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in members.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// An error found while reading JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    /// The byte offset of the error in the input, 0 when the JSON is valid but its content is not
+    pub position: usize,
+    /// What is wrong
+    pub message: String,
+}
+
+impl JsonError {
+    fn content(message: impl Into<String>) -> Self {
+        JsonError { position: 0, message: message.into() }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at byte {}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+/// Function that parses a JSON text
+///
+/// # Arguments
+/// * `input`: the JSON text
+///
+/// # Returns
+/// * `Result<Value, JsonError>`: the parsed value, or where and why the text is not valid JSON
+pub fn parse(input: &str) -> Result<Value, JsonError> {
+    let mut parser = Parser { bytes: input.as_bytes(), position: 0 };
+    parser.skip_whitespace();
+    let value = parser.value(0)?;
+    parser.skip_whitespace();
+    if parser.position < parser.bytes.len() {
+        return Err(parser.error("unexpected trailing characters"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> JsonError {
+        JsonError { position: self.position, message: message.to_string() }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), JsonError> {
+        if self.bytes[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", literal)))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("too deeply nested"));
+        }
+        match self.peek() {
+            Some(b'n') => self.expect("null").map(|_| Value::Null),
+            Some(b't') => self.expect("true").map(|_| Value::Bool(true)),
+            Some(b'f') => self.expect("false").map(|_| Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => {
+                self.position += 1;
+                let mut elements = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.position += 1;
+                    return Ok(Value::Array(elements));
+                }
+                loop {
+                    self.skip_whitespace();
+                    elements.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        Some(b']') => {
+                            self.position += 1;
+                            return Ok(Value::Array(elements));
+                        }
+                        _ => return Err(self.error("expected , or ]")),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.position += 1;
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b'}') {
+                    self.position += 1;
+                    return Ok(Value::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some(b'"') {
+                        return Err(self.error("expected a key"));
+                    }
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(":")?;
+                    self.skip_whitespace();
+                    members.push((key, self.value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        Some(b'}') => {
+                            self.position += 1;
+                            return Ok(Value::Object(members));
+                        }
+                        _ => return Err(self.error("expected , or }")),
+                    }
+                }
+            }
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.position;
+        let mut integer = true;
+        while let Some(c) = self.peek() {
+            match c {
+                b'0'..=b'9' | b'-' | b'+' => {}
+                b'.' | b'e' | b'E' => integer = false,
+                _ => break,
+            }
+            self.position += 1;
+        }
+        // the bytes are ASCII, so they are valid UTF-8
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).unwrap_or_default();
+        let valid = text.strip_prefix('-').unwrap_or(text).starts_with(|c: char| c.is_ascii_digit());
+        let parsed = match (valid, integer) {
+            (false, _) => None,
+            (true, true) => text.parse().ok().map(Value::Integer),
+            (true, false) => text.parse().ok().map(Value::Float),
+        };
+        parsed.ok_or_else(|| JsonError { position: start, message: format!("invalid number {}", text) })
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.position += 1;
+        let mut bytes = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.position += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    self.position += 1;
+                    let escaped = match self.peek() {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.position += 1;
+                            let c = self.unicode_escape()?;
+                            let mut buffer = [0; 4];
+                            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                            continue;
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    self.position += 1;
+                    let mut buffer = [0; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut buffer).as_bytes());
+                }
+                Some(c) if c < 0x20 => return Err(self.error("control character in string")),
+                Some(c) => {
+                    bytes.push(c);
+                    self.position += 1;
+                }
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8"))
+    }
+
+    /// The character of a `\u` escape, the position being after the `u`
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let first = self.hex4()?;
+        if (0xd800..0xdc00).contains(&first) {
+            // surrogate pair
+            self.expect("\\u")?;
+            let second = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&second) {
+                return Err(self.error("invalid surrogate pair"));
+            }
+            let code = 0x10000 + ((first - 0xd800) << 10) + (second - 0xdc00);
+            return char::from_u32(code).ok_or_else(|| self.error("invalid surrogate pair"));
+        }
+        char::from_u32(first).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self.bytes.get(self.position..self.position + 4).ok_or_else(|| self.error("invalid unicode escape"))?;
+        let text = std::str::from_utf8(digits).map_err(|_| self.error("invalid unicode escape"))?;
+        let code = u32::from_str_radix(text, 16).map_err(|_| self.error("invalid unicode escape"))?;
+        self.position += 4;
+        Ok(code)
+    }
+}
+
+/// A type that can be read from a JSON value, e.g. a grade
+pub trait FromJson: Sized {
+    /// Function that converts a JSON value
+    ///
+    /// # Returns
+    /// * `Result<Self, String>`: the converted value or why the value does not fit
+    fn from_json(value: &Value) -> Result<Self, String>;
+}
+
+/// A type that can be written as a JSON value, e.g. a grade
+pub trait ToJson {
+    /// Function that converts to a JSON value
+    fn to_json(&self) -> Value;
+}
+
+macro_rules! json_integer {
+    ($($t:ty),*) => {$(
+        impl FromJson for $t {
+            fn from_json(value: &Value) -> Result<Self, String> {
+                value
+                    .as_integer()
+                    .and_then(|i| <$t>::try_from(i).ok())
+                    .ok_or_else(|| format!("{} is not a valid {}", value, stringify!($t)))
+            }
+        }
+
+        impl ToJson for $t {
+            fn to_json(&self) -> Value {
+                Value::Integer(*self as i128)
+            }
+        }
+    )*};
+}
+
+json_integer!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl FromJson for String {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        value.as_str().map(str::to_string).ok_or_else(|| format!("{} is not a string", value))
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> Value {
+        Value::String(self.clone())
+    }
+}
+
+/// A missing grade, e.g. an abstention, is written `null`
+impl<T: FromJson> FromJson for Option<T> {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_json(value).map(Some),
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> Value {
+        self.as_ref().map_or(Value::Null, ToJson::to_json)
+    }
+}
+
+impl<G: FromJson> Poll<G> {
+    /// Function that reads a poll from JSON, see the `json` module for the format
+    ///
+    /// # Arguments
+    /// * `json`: the JSON text of the poll
+    ///
+    /// # Returns
+    /// * `Result<Poll<G>, JsonError>`: the poll, not validated yet, or why it cannot be read
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::{majority_judgment, Poll};
+    /// let json = r#"{"candidates": ["Pizza", "Chips"],
+    ///                "ballots": [{"grades": {"Pizza": 0, "Chips": 2}},
+    ///                            {"grades": {"Pizza": 1, "Chips": 3}}]}"#;
+    /// let poll: Poll<u8> = Poll::from_json(json).unwrap();
+    /// let poll_data = poll.poll_data().unwrap();
+    /// assert_eq!(majority_judgment(&poll_data)[0].0, "Chips");
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, JsonError> {
+        Poll::from_json_value(&parse(json)?)
+    }
+
+    /// Function that reads a poll from a parsed JSON value, see `Poll::from_json`
+    pub fn from_json_value(value: &Value) -> Result<Self, JsonError> {
+        let candidates = value
+            .get("candidates")
+            .and_then(Value::as_array)
+            .ok_or_else(|| JsonError::content("\"candidates\" must be an array"))?
+            .iter()
+            .map(|candidate| String::from_json(candidate).map_err(JsonError::content))
+            .collect::<Result<Vec<String>, JsonError>>()?;
+
+        let mut poll = Poll::new(candidates);
+        let ballots = match value.get("ballots") {
+            None => &[][..],
+            Some(ballots) => ballots.as_array().ok_or_else(|| JsonError::content("\"ballots\" must be an array"))?,
+        };
+        for (index, ballot) in ballots.iter().enumerate() {
+            let ballot = ballot_from_json(ballot).map_err(|message| JsonError::content(format!("ballot {}: {}", index, message)))?;
+            poll.add_ballot(ballot);
+        }
+        if let Some(metadata) = value.get("metadata") {
+            let metadata = metadata.as_object().ok_or_else(|| JsonError::content("\"metadata\" must be an object"))?;
+            for (candidate, information) in metadata {
+                let information = information
+                    .as_object()
+                    .ok_or_else(|| JsonError::content(format!("the metadata of {:?} must be an object", candidate)))?;
+                for (key, value) in information {
+                    poll.set_metadata(candidate, key.clone(), String::from_json(value).map_err(JsonError::content)?);
+                }
+            }
+        }
+        Ok(poll)
+    }
+}
+
+/// Function that reads a ballot of a poll, `{"grades": {...}}` with an optional `"weight"` and `"voter"`
+pub(crate) fn ballot_from_json<G: FromJson>(ballot: &Value) -> Result<Ballot<G>, String> {
+    let grades = ballot
+        .get("grades")
+        .and_then(Value::as_object)
+        .ok_or_else(|| "\"grades\" must be an object".to_string())?;
+    let mut parsed = Ballot::new();
+    for (candidate, grade) in grades {
+        parsed = parsed.grade(candidate.clone(), G::from_json(grade)?);
+    }
+    if let Some(weight) = ballot.get("weight") {
+        parsed = parsed.with_weight(u32::from_json(weight)?);
+    }
+    if let Some(voter) = ballot.get("voter") {
+        parsed = parsed.with_voter(String::from_json(voter)?);
+    }
+    Ok(parsed)
+}
+
+impl<G: ToJson> Poll<G> {
+    /// Function that writes the poll as JSON, see the `json` module for the format
+    pub fn to_json(&self) -> String {
+        self.to_json_value().to_string()
+    }
+
+    /// Function that converts the poll to a JSON value, see `Poll::to_json`
+    pub fn to_json_value(&self) -> Value {
+        let candidates = self.candidates().iter().map(ToJson::to_json).collect();
+        let ballots = self.ballots().iter().map(|ballot| {
+            let grades = ballot.grades().iter().map(|(c, g)| (c.clone(), g.to_json())).collect();
+            let mut members = vec![("grades".to_string(), Value::Object(grades))];
+            if ballot.weight() != 1 {
+                members.push(("weight".to_string(), ballot.weight().to_json()));
+            }
+            if let Some(voter) = ballot.voter() {
+                members.push(("voter".to_string(), Value::String(voter.to_string())));
+            }
+            Value::Object(members)
+        }).collect();
+        let mut members = vec![
+            ("candidates".to_string(), Value::Array(candidates)),
+            ("ballots".to_string(), Value::Array(ballots)),
+        ];
+        if !self.metadata().is_empty() {
+            let metadata = self.metadata().iter().map(|(candidate, metadata)| (candidate.clone(), metadata_to_json(metadata)));
+            members.push(("metadata".to_string(), Value::Object(metadata.collect())));
+        }
+        Value::Object(members)
+    }
+}
+
+/// Function that writes the metadata of a candidate as a JSON object of strings
+fn metadata_to_json(metadata: &Metadata) -> Value {
+    Value::Object(metadata.iter().map(|(key, value)| (key.clone(), Value::String(value.clone()))).collect())
+}
+
+impl ToJson for TieStatus {
+    fn to_json(&self) -> Value {
+        let status = match self {
+            TieStatus::Unique => "unique",
+            TieStatus::Tied => "tied",
+            TieStatus::Broken => "broken",
+        };
+        Value::String(status.to_string())
+    }
+}
+
+/// The majority values as their segments, e.g. `[{"first": 2, "second": 3, "length": 40}]` for 40 values
+/// alternating between 2 and 3, so that cached values stay short whatever the number of votes
+impl<G: ToJson> ToJson for MajorityValue<G> {
+    fn to_json(&self) -> Value {
+        Value::Array(self.segments().map(|(first, second, length)| Value::Object(vec![
+            ("first".to_string(), first.to_json()),
+            ("second".to_string(), second.to_json()),
+            ("length".to_string(), length.to_json()),
+        ])).collect())
+    }
+}
+
+impl<G: FromJson + Clone + PartialEq> FromJson for MajorityValue<G> {
+    fn from_json(value: &Value) -> Result<Self, String> {
+        let segments = value
+            .as_array()
+            .ok_or_else(|| format!("{} is not an array of segments", value))?
+            .iter()
+            .map(|segment| {
+                let member = |key: &str| segment.get(key).ok_or_else(|| format!("a segment needs {:?}", key));
+                Ok((G::from_json(member("first")?)?, G::from_json(member("second")?)?, u64::from_json(member("length")?)?))
+            })
+            .collect::<Result<Vec<(G, G, u64)>, String>>()?;
+        MajorityValue::from_segments(segments).ok_or_else(|| "a segment is empty".to_string())
+    }
+}
+
+impl<K: ToJson, G: ToJson> Ranking<'_, K, G> {
+    /// Function that writes the ranking as JSON: an array of `{"candidate", "rank", "tie", "majority_grade"}` objects
+    /// from the best candidate to the worst, with the `"proponents"` and `"opponents"` shares around the majority grade
+    /// and a `"metadata"` object for the candidates having metadata
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::majority_judgment_ranking;
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza".to_string(), vec![0, 1]);
+    /// assert_eq!(majority_judgment_ranking(&poll_data).to_json(),
+    ///            r#"[{"candidate":"Pizza","rank":0,"tie":"unique","majority_grade":0,"proponents":0.5,"opponents":0.0}]"#);
+    /// ```
+    pub fn to_json(&self) -> String {
+        self.to_json_value().to_string()
+    }
+
+    /// Function that converts the ranking to a JSON value, see `Ranking::to_json`
+    pub fn to_json_value(&self) -> Value {
+        Value::Array(self.iter().map(|ranked| {
+            let mut members = vec![
+                ("candidate".to_string(), ranked.candidate.to_json()),
+                ("rank".to_string(), Value::Integer(ranked.rank as i128)),
+                ("tie".to_string(), ranked.tie.to_json()),
+                ("majority_grade".to_string(), ranked.majority_grade.to_json()),
+            ];
+            if let Some(shares) = ranked.shares {
+                members.push(("proponents".to_string(), Value::Float(shares.proponents())));
+                members.push(("opponents".to_string(), Value::Float(shares.opponents())));
+            }
+            if let Some(metadata) = ranked.metadata {
+                members.push(("metadata".to_string(), metadata_to_json(metadata)));
+            }
+            Value::Object(members)
+        }).collect())
+    }
+}
+
+/// A poll exported by a majority judgment platform, see `from_mieux_voter`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportedPoll {
+    /// The name of the election, if the export gives one
+    pub name: Option<String>,
+    /// The ballots, the grade 0 being the worst mention
+    pub poll: Poll<u8>,
+    /// The mentions, from the worst to the best, e.g. to label the majority grades
+    pub scale: GradeScale,
+}
+
+/// Function that reads a poll exported in the style of the Mieux Voter API
+///
+/// The export lists the `"candidates"`, as names or `{"id", "name"}` objects, and the `"mentions"`
+/// (or `"grades"`) from the best to the worst, as names or `{"id", "name", "value"}` objects, the
+/// greatest value being the best mention. Each vote of `"votes"` is either an array giving the mention of
+/// each candidate in their order, as its index (0 for the best mention) or its name, or an object whose
+/// `"votes"` array holds `{"candidate_id", "grade_id"}` pairs. An optional `"name"` names the election.
+///
+/// # Arguments
+/// * `json`: the exported JSON text
+///
+/// # Returns
+/// * `Result<ExportedPoll, JsonError>`: the poll on the scale of the mentions, not validated yet, or why it
+///   cannot be read, e.g. a vote referring to an unknown candidate or mention
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::json::from_mieux_voter;
+/// use majority_judgement_rust::majority_judgment_ranking;
+/// let json = r#"{"name": "Lunch", "candidates": ["Pizza", "Chips"],
+///                "mentions": ["Très bien", "Bien", "Passable", "À rejeter"],
+///                "votes": [[0, 2], [1, "Bien"], [3, 0]]}"#;
+/// let exported = from_mieux_voter(json).unwrap();
+/// let poll_data = exported.poll.poll_data().unwrap();
+/// assert_eq!(poll_data["Pizza"], vec![3, 2, 0]);
+///
+/// let ranking = majority_judgment_ranking(&poll_data);
+/// assert_eq!(ranking.candidates()[0].majority_grade_label(&exported.scale), Some("Bien"));
+/// ```
+pub fn from_mieux_voter(json: &str) -> Result<ExportedPoll, JsonError> {
+    let value = parse(json)?;
+    let array = |key: &str| value.get(key).and_then(Value::as_array);
+
+    let candidates: Vec<(Option<&Value>, String)> = array("candidates")
+        .ok_or_else(|| JsonError::content("\"candidates\" must be an array"))?
+        .iter()
+        .map(|candidate| named(candidate).map_err(|message| JsonError::content(format!("candidate {}", message))))
+        .collect::<Result<_, _>>()?;
+    let mut mentions: Vec<(Option<&Value>, String, i128)> = array("mentions")
+        .or_else(|| array("grades"))
+        .ok_or_else(|| JsonError::content("\"mentions\" must be an array"))?
+        .iter()
+        .enumerate()
+        .map(|(index, mention)| {
+            let (id, name) = named(mention).map_err(|message| JsonError::content(format!("mention {}", message)))?;
+            // without values, the mentions are listed from the best to the worst
+            let value = match mention.get("value") {
+                None => -(index as i128),
+                Some(value) => value.as_integer().ok_or_else(|| JsonError::content(format!("the value of mention {:?} must be an integer", name)))?,
+            };
+            Ok((id, name, value))
+        })
+        .collect::<Result<_, JsonError>>()?;
+    // from the worst mention to the best: the index is the grade
+    mentions.sort_by_key(|&(_, _, value)| value);
+    if mentions.len() > 256 {
+        return Err(JsonError::content("more than 256 mentions"));
+    }
+    let best = mentions.len().saturating_sub(1);
+
+    let mut poll = Poll::new(candidates.iter().map(|(_, name)| name.clone()));
+    let votes = array("votes").ok_or_else(|| JsonError::content("\"votes\" must be an array"))?;
+    for (index, vote) in votes.iter().enumerate() {
+        let error = |message: String| JsonError::content(format!("vote {}: {}", index, message));
+        let mut ballot = Ballot::new();
+        match vote {
+            Value::Array(grades) => {
+                if grades.len() != candidates.len() {
+                    return Err(error(format!("{} mentions for {} candidates", grades.len(), candidates.len())));
+                }
+                for ((_, candidate), mention) in candidates.iter().zip(grades) {
+                    let grade = match mention {
+                        Value::String(name) => mentions.iter().position(|(_, known, _)| known == name),
+                        _ => mention.as_integer().and_then(|rank| usize::try_from(rank).ok()).and_then(|rank| best.checked_sub(rank)),
+                    };
+                    ballot = ballot.grade(candidate.clone(), grade.ok_or_else(|| error(format!("unknown mention {}", mention)))? as u8);
+                }
+            }
+            _ => {
+                let pairs = vote.get("votes").and_then(Value::as_array).ok_or_else(|| error("must be an array or have a \"votes\" array".to_string()))?;
+                for pair in pairs {
+                    let (candidate_id, grade_id) = (pair.get("candidate_id"), pair.get("grade_id"));
+                    let candidate = candidates.iter().find(|(id, _)| id.is_some() && *id == candidate_id);
+                    let grade = mentions.iter().position(|(id, _, _)| id.is_some() && *id == grade_id);
+                    match (candidate, grade) {
+                        (Some((_, candidate)), Some(grade)) => ballot = ballot.grade(candidate.clone(), grade as u8),
+                        (None, _) => return Err(error(format!("unknown candidate id {}", candidate_id.unwrap_or(&Value::Null)))),
+                        (_, None) => return Err(error(format!("unknown grade id {}", grade_id.unwrap_or(&Value::Null)))),
+                    }
+                }
+            }
+        }
+        poll.add_ballot(ballot);
+    }
+
+    let name = value.get("name").map(|name| String::from_json(name).map_err(JsonError::content)).transpose()?;
+    Ok(ExportedPoll { name, poll, scale: GradeScale::new(mentions.into_iter().map(|(_, name, _)| name)) })
+}
+
+/// Function that reads a candidate or a mention of an export, a name or an object with a `"name"` and an `"id"`
+fn named(value: &Value) -> Result<(Option<&Value>, String), String> {
+    match value {
+        Value::String(name) => Ok((None, name.clone())),
+        _ => {
+            let name = value.get("name").and_then(Value::as_str).ok_or_else(|| format!("{} has no name", value))?;
+            Ok((value.get("id"), name.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_parse() {
+        let value = parse(r#" {"a": [1, -2.5e1, true, null], "b": "é\n\"", "a": {}} "#).unwrap();
+        assert_eq!(value.get("a").unwrap().as_array().unwrap().len(), 4);
+        assert_eq!(value.get("a").unwrap().as_array().unwrap()[1], Value::Float(-25.0));
+        assert_eq!(value.get("b").unwrap().as_str(), Some("é\n\""));
+        assert_eq!(value.as_object().unwrap().len(), 3);
+        assert_eq!(parse(r#""😀""#).unwrap(), Value::String("😀".to_string()));
+
+        assert_eq!(parse("[1, 2").unwrap_err().position, 5);
+        assert_eq!(parse("{\"a\" 1}").unwrap_err().position, 5);
+        assert!(parse("[1] 2").is_err());
+        assert!(parse("-").is_err());
+        assert!(parse(&"[".repeat(1000)).is_err());
+    }
+
+    #[test]
+    fn calling_to_string() {
+        let value = Value::Object(vec![
+            ("name".to_string(), Value::String("say \"hi\"\t".to_string())),
+            ("values".to_string(), Value::Array(vec![Value::Integer(-3), Value::Float(0.5), Value::Null])),
+        ]);
+        let text = value.to_string();
+        assert_eq!(text, r#"{"name":"say \"hi\"\t","values":[-3,0.5,null]}"#);
+        assert_eq!(parse(&text).unwrap(), value);
+    }
+
+    #[test]
+    fn calling_majority_value_to_json() {
+        let values = MajorityValue::from_grades(&[0u8, 2, 2, 3, 3]);
+        let json = values.to_json().to_string();
+        assert_eq!(json, r#"[{"first":2,"second":2,"length":1},{"first":2,"second":3,"length":2},{"first":0,"second":3,"length":2}]"#);
+        let read = MajorityValue::<u8>::from_json(&parse(&json).unwrap()).unwrap();
+        assert_eq!((read.to_string(), read.len()), (values.to_string(), 5));
+        assert_eq!(read.iter().collect::<Vec<_>>(), values.iter().collect::<Vec<_>>());
+
+        assert!(MajorityValue::<u8>::from_json(&parse(r#"[{"first":2,"second":3,"length":0}]"#).unwrap()).is_err());
+        assert!(MajorityValue::<u8>::from_json(&parse(r#"[{"first":2,"length":1}]"#).unwrap()).is_err());
+    }
+
+    #[test]
+    fn calling_poll_from_json() {
+        let json = r#"{"candidates": ["Pizza", "Chips"],
+                       "ballots": [{"grades": {"Pizza": 0, "Chips": 2}},
+                                   {"grades": {"Pizza": 3, "Pizza": 1}, "weight": 2, "voter": "V-7"}]}"#;
+        let poll: Poll<u8> = Poll::from_json(json).unwrap();
+        assert_eq!(poll.ballots()[1].weight(), 2);
+        assert_eq!((poll.ballots()[0].voter(), poll.ballots()[1].voter()), (None, Some("V-7")));
+        assert_eq!(poll.validate().len(), 2);
+        assert_eq!(Poll::<u8>::from_json(&poll.to_json()).unwrap(), poll);
+
+        let error = Poll::<u8>::from_json(r#"{"candidates": ["Pizza"], "ballots": [{"grades": {"Pizza": 300}}]}"#);
+        assert_eq!(error.unwrap_err().message, "ballot 0: 300 is not a valid u8");
+        assert!(Poll::<u8>::from_json(r#"{"ballots": []}"#).is_err());
+
+        let poll: Poll<Option<u8>> = Poll::from_json(r#"{"candidates": ["Pizza"], "ballots": [{"grades": {"Pizza": null}}]}"#).unwrap();
+        assert_eq!(poll.ballots()[0].grades()[0], ("Pizza".to_string(), None));
+        assert_eq!(Poll::<Option<u8>>::from_json(&poll.to_json()).unwrap(), poll);
+    }
+
+    #[test]
+    fn calling_from_mieux_voter() {
+        let json = r#"{"candidates": [{"id": 11, "name": "Pizza"}, {"id": 12, "name": "Chips"}],
+                       "grades": [{"id": 3, "name": "Good", "value": 2}, {"id": 1, "name": "Reject", "value": 0},
+                                  {"id": 2, "name": "Fair", "value": 1}],
+                       "votes": [{"votes": [{"candidate_id": 11, "grade_id": 3}, {"candidate_id": 12, "grade_id": 1}]},
+                                 {"votes": [{"candidate_id": 12, "grade_id": 2}, {"candidate_id": 11, "grade_id": 2}]}]}"#;
+        let exported = from_mieux_voter(json).unwrap();
+        assert_eq!(exported.name, None);
+        assert_eq!(exported.scale.labels(), ["Reject", "Fair", "Good"]);
+        let poll_data = exported.poll.poll_data().unwrap();
+        assert_eq!((poll_data["Pizza"].clone(), poll_data["Chips"].clone()), (vec![2, 1], vec![0, 1]));
+
+        let unknown = r#"{"candidates": ["Pizza"], "mentions": ["Good", "Bad"], "votes": [[0], [2]]}"#;
+        assert_eq!(from_mieux_voter(unknown).unwrap_err().message, "vote 1: unknown mention 2");
+        let short = r#"{"candidates": ["Pizza", "Chips"], "mentions": ["Good", "Bad"], "votes": [[0]]}"#;
+        assert_eq!(from_mieux_voter(short).unwrap_err().message, "vote 0: 1 mentions for 2 candidates");
+        assert!(from_mieux_voter(r#"{"candidates": ["Pizza"], "votes": []}"#).is_err());
+    }
+
+    #[test]
+    fn calling_metadata_to_json() {
+        let json = r#"{"candidates": ["Pizza", "Chips"],
+                       "ballots": [{"grades": {"Pizza": 0, "Chips": 2}}],
+                       "metadata": {"Chips": {"party": "Crisps", "photo": "chips.png"}}}"#;
+        let poll: Poll<u8> = Poll::from_json(json).unwrap();
+        assert_eq!(poll.metadata()["Chips"]["photo"], "chips.png");
+        assert_eq!(Poll::<u8>::from_json(&poll.to_json()).unwrap(), poll);
+
+        let poll_data = poll.poll_data().unwrap();
+        assert_eq!(
+            crate::mj::majority_judgment_ranking(&poll_data).with_metadata(poll.metadata()).to_json(),
+            r#"[{"candidate":"Chips","rank":0,"tie":"unique","majority_grade":2,"proponents":0.0,"opponents":0.0,"#.to_string()
+                + r#""metadata":{"party":"Crisps","photo":"chips.png"}},"#
+                + r#"{"candidate":"Pizza","rank":1,"tie":"unique","majority_grade":0,"proponents":0.0,"opponents":0.0}]"#
+        );
+        assert!(Poll::<u8>::from_json(r#"{"candidates": [], "metadata": {"Pizza": "Italian"}}"#).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! # Majority gauge, usual and typical judgment
+//! Tie-break rules of the highest median computed from the majority gauge `(p, α, q)`,
+//! see Balinski & Laraki (2011) and Fabre (2021).
+//! The candidates are first ranked by their majority grade `α` (the lower median, or the upper one with
+//! `MedianConvention::UpperMedian`),
+//! then by a score built from the share of proponents `p` (grades strictly above the majority grade)
+//! and the share of opponents `q` (grades strictly below):
+//! * majority gauge: `p` if `p > q`, `-q` otherwise
+//! * usual judgment: `(p - q) / (1 - p - q)`
+//! * typical judgment: `p - q`
+//!
+//! Unlike the iterative withdrawal of medians, each score is computed once per candidate.
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use crate::fraction::Fraction;
+use crate::mj::median_grade;
+use crate::options::MedianConvention;
+
+/// Why the infallible rankings panic on a tally beyond `u64::MAX` votes
+pub(crate) const TOO_MANY_VOTES: &str = "a candidate has more than u64::MAX votes, see try_majority_judgment_from_tally";
+
+/// Function that counts the votes of a tally
+///
+/// # Returns
+/// * `Option<u64>`: the total of the counts, None beyond `u64::MAX`
+pub(crate) fn total_votes<'a>(counts: impl IntoIterator<Item = &'a u64>) -> Option<u64> {
+    counts.into_iter().try_fold(0u64, |total, &count| total.checked_add(count))
+}
+
+/// The majority grade of a candidate and the number of votes above and below it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MedianShares<G> {
+    pub(crate) median: G,
+    pub(crate) above: u64,
+    pub(crate) below: u64,
+    pub(crate) total: u64,
+}
+
+/// Function that computes the majority grade and the votes above and below it from the tally
+///
+/// # Arguments
+/// * `tally`: the number of votes (or total weight) of each grade
+///
+/// # Returns
+/// * `Option<MedianShares<G>>`: the lower median grade with the votes around it, None without any vote
+pub(crate) fn median_shares<G: Clone>(tally: &BTreeMap<G, u64>) -> Option<MedianShares<G>> {
+    median_shares_with(tally, MedianConvention::LowerMedian)
+}
+
+/// Function that computes the majority grade, lower or upper median, and the votes above and below it
+///
+/// # Arguments
+/// * `tally`: the number of votes (or total weight) of each grade
+/// * `convention`: which middle grade is the majority grade of an even number of votes
+///
+/// # Returns
+/// * `Option<MedianShares<G>>`: the median grade with the votes around it, None without any vote
+///
+/// # Panics
+/// * if the tally has more than `u64::MAX` votes
+pub(crate) fn median_shares_with<G: Clone>(tally: &BTreeMap<G, u64>, convention: MedianConvention) -> Option<MedianShares<G>> {
+    trace_span!("median");
+    let cumsum: Vec<u64> = tally.values().scan(0, |sum, &count| {
+        *sum = u64::checked_add(*sum, count).expect(TOO_MANY_VOTES);
+        Some(*sum)
+    }).collect();
+    let total = *cumsum.last()?;
+    if total == 0 {
+        return None;
+    }
+
+    let idx = median_grade(&cumsum, convention) as usize;
+    let below = if idx == 0 { 0 } else { cumsum[idx - 1] };
+    let median = tally.keys().nth(idx)?.clone();
+    Some(MedianShares { median, above: total - cumsum[idx], below, total })
+}
+
+/// Function that computes the majority gauge of a candidate
+///
+/// A candidate with more proponents than opponents (`p > q`) beats any candidate with the same
+/// majority grade and at least as many opponents as proponents. Among the former the largest `p` wins,
+/// among the latter the smallest `q` wins.
+///
+/// # Arguments
+/// * `tally`: the number of votes (or total weight) of each grade
+/// * `convention`: which middle grade is the majority grade of an even number of votes
+///
+/// # Returns
+/// * `(Option<G>, Fraction)`: the majority grade and `p` or `-q`, compared lexicographically
+pub(crate) fn majority_gauge<G: Clone>(tally: &BTreeMap<G, u64>, convention: MedianConvention) -> (Option<G>, Fraction) {
+    match median_shares_with(tally, convention) {
+        Some(shares) if shares.above > shares.below => {
+            (Some(shares.median), Fraction::new(shares.above as i128, shares.total as u128))
+        }
+        Some(shares) => (Some(shares.median), Fraction::new(-(shares.below as i128), shares.total as u128)),
+        None => (None, Fraction::new(0, 1)),
+    }
+}
+
+/// Function that computes the usual judgment score of a candidate
+///
+/// # Arguments
+/// * `tally`: the number of votes (or total weight) of each grade
+/// * `convention`: which middle grade is the majority grade of an even number of votes
+///
+/// # Returns
+/// * `(Option<G>, Fraction)`: the majority grade and `(p - q) / (1 - p - q)`, compared lexicographically
+pub(crate) fn usual_judgment<G: Clone>(tally: &BTreeMap<G, u64>, convention: MedianConvention) -> (Option<G>, Fraction) {
+    match median_shares_with(tally, convention) {
+        Some(shares) => {
+            let at_median = shares.total - shares.above - shares.below;
+            (Some(shares.median), Fraction::new(shares.above as i128 - shares.below as i128, at_median as u128))
+        }
+        None => (None, Fraction::new(0, 1)),
+    }
+}
+
+/// Function that computes the typical judgment score of a candidate
+///
+/// # Arguments
+/// * `tally`: the number of votes (or total weight) of each grade
+/// * `convention`: which middle grade is the majority grade of an even number of votes
+///
+/// # Returns
+/// * `(Option<G>, Fraction)`: the majority grade and `p - q`, compared lexicographically
+pub(crate) fn typical_judgment<G: Clone>(tally: &BTreeMap<G, u64>, convention: MedianConvention) -> (Option<G>, Fraction) {
+    match median_shares_with(tally, convention) {
+        Some(shares) => {
+            (Some(shares.median), Fraction::new(shares.above as i128 - shares.below as i128, shares.total as u128))
+        }
+        None => (None, Fraction::new(0, 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tally(counts: &[(u8, u64)]) -> BTreeMap<u8, u64> {
+        counts.iter().cloned().collect()
+    }
+
+    const LOWER: MedianConvention = MedianConvention::LowerMedian;
+
+    #[test]
+    fn calling_median_shares() {
+        let result = median_shares(&tally(&[(0, 2), (1, 3), (3, 5)]));
+        assert_eq!(result, Some(MedianShares { median: 1, above: 5, below: 2, total: 10 }));
+        assert_eq!(median_shares(&tally(&[(0, 1), (2, 1)])).unwrap().median, 0);
+        assert_eq!(median_shares::<u8>(&tally(&[])), None);
+
+        // the upper median of an even number of votes
+        let result = median_shares_with(&tally(&[(0, 1), (2, 1)]), MedianConvention::UpperMedian);
+        assert_eq!(result, Some(MedianShares { median: 2, above: 0, below: 1, total: 2 }));
+        let result = median_shares_with(&tally(&[(0, 2), (1, 3), (3, 5)]), MedianConvention::UpperMedian);
+        assert_eq!(result, Some(MedianShares { median: 3, above: 0, below: 5, total: 10 }));
+
+        // a majority of the votes beyond half of u64::MAX
+        let result = median_shares(&tally(&[(0, u64::MAX / 2 + 10), (1, 5)])).unwrap();
+        assert_eq!((result.median, result.above), (0, 5));
+        assert_eq!(total_votes(&[u64::MAX, 1]), None);
+    }
+
+    #[test]
+    fn calling_majority_gauge() {
+        let unanimous = tally(&[(2, 4)]);
+        let proponent = tally(&[(2, 3), (3, 1)]);
+        let opponent = tally(&[(1, 1), (2, 3)]);
+        let balanced = tally(&[(1, 1), (2, 2), (3, 1)]);
+        assert!(majority_gauge(&proponent, LOWER) > majority_gauge(&unanimous, LOWER));
+        assert!(majority_gauge(&unanimous, LOWER) > majority_gauge(&opponent, LOWER));
+        assert!(majority_gauge(&opponent, LOWER) == majority_gauge(&balanced, LOWER));
+        assert!(majority_gauge(&balanced, LOWER) > majority_gauge(&tally(&[(0, 3), (3, 1)]), LOWER));
+    }
+
+    #[test]
+    fn calling_usual_and_typical_judgment() {
+        // Both have 2 as majority grade, B is more polarized than A
+        let a = tally(&[(0, 1), (2, 5), (3, 4)]);
+        let b = tally(&[(1, 4), (2, 1), (3, 5)]);
+        assert!(usual_judgment(&a, LOWER) < usual_judgment(&b, LOWER));
+        assert!(typical_judgment(&a, LOWER) > typical_judgment(&b, LOWER));
+    }
+}
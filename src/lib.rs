@@ -1,3 +1,192 @@
+//! # Majority Judgment
+//! The majority judgment of Michel Balinski and Rida Laraki: the voters grade every candidate,
+//! the candidate with the best median grade wins.
+//!
+//! The library is organized in modules:
+//! * `poll`: the ballots of the voters, how to count missing grades and how to check them
+//! * `methods`: the ranking functions and their options
+//! * `ranking`: the results, the candidates with their ranks and ties
+//! * `scale`: the labels of the grades and the conversions between scales
+//! * `error`: the errors reported by the crate
+//!
+//! `prelude` imports what most polls need, the crate root re-exports everything as before.
+//!
+//! Without the default `std` feature the crate only needs `alloc`: the rankings, their options and
+//! explanations, the merit profiles and the grade scales are kept, the polls, the files and the
+//! other interfaces are left out. A voting kiosk or a secure enclave can then embed the tally logic with
+//! `default-features = false`, see the `no_std` section of the README.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+extern crate alloc;
+
+/// Time the rest of the block as a span of the `trace` module, with the `tracing` feature
+macro_rules! trace_span {
+    ($name:literal) => {
+        #[cfg(feature = "tracing")]
+        let _span = crate::trace::Span::enter($name);
+    };
+}
+
+/// Report an intermediate value of a span to the `trace` module, with the `tracing` feature
+macro_rules! trace_event {
+    ($span:literal, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        crate::trace::event($span, || format!($($arg)*));
+    };
+}
+
+#[cfg(feature = "std")]
+mod abstention;
+#[cfg(feature = "std")]
+mod accumulator;
+#[cfg(feature = "std")]
+pub mod analysis;
+mod apportionment;
+#[cfg(feature = "std")]
+pub mod archive;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "mmap")]
+pub mod ballot_file;
+#[cfg(feature = "std")]
+mod ballot_paper;
+#[cfg(feature = "std")]
+mod batch;
+mod checked;
+#[cfg(feature = "std")]
+mod committee;
+#[cfg(feature = "std")]
+pub mod compare;
+#[cfg(feature = "std")]
+mod concurrent;
+#[cfg(feature = "connectors")]
+pub mod connectors;
+#[cfg(feature = "config")]
+pub mod config;
+#[cfg(feature = "csv")]
+pub mod csv;
+#[cfg(feature = "std")]
+pub mod dataset;
+#[cfg(feature = "std")]
+mod dedup;
+#[cfg(feature = "std")]
+mod election;
+#[cfg(feature = "std")]
+pub mod error;
+mod explain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "std")]
+pub mod format;
+mod fraction;
+mod grade;
+#[cfg(feature = "std")]
+mod grade_range;
+#[cfg(feature = "json")]
+pub mod json;
+mod judgment;
+mod majority_value;
+pub mod methods;
 mod mj;
+mod options;
+#[cfg(feature = "std")]
+pub mod poll;
+#[cfg(feature = "std")]
+mod precinct;
+#[cfg(feature = "std")]
+pub mod prelude;
+mod profile;
+#[cfg(feature = "std")]
+mod progress;
+#[cfg(feature = "std")]
+mod projection;
+#[cfg(feature = "std")]
+mod quorum;
+pub mod ranking;
+mod ranking_method;
+#[cfg(feature = "std")]
+pub mod receipt;
+#[cfg(feature = "std")]
+mod render;
+#[cfg(feature = "std")]
+pub mod report;
+#[cfg(feature = "std")]
+mod rescale;
+#[cfg(feature = "std")]
+mod retally;
+pub mod scale;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "std")]
+pub mod simulate;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "async")]
+pub mod stream;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod tie_breaker;
+#[cfg(feature = "tracing")]
+pub mod trace;
+#[cfg(feature = "std")]
+mod validation;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-pub use mj::majority_judgment;
+pub use apportionment::{apportion, apportion_scores, majority_gauge_scores, Apportionment};
+#[cfg(feature = "std")]
+pub use abstention::{resolve_abstentions, AbstentionPolicy};
+#[cfg(feature = "std")]
+pub use accumulator::{SnapshotError, TallyAccumulator};
+#[cfg(feature = "std")]
+pub use ballot_paper::{generate_ballot_templates, generate_html_ballot_templates};
+#[cfg(feature = "std")]
+pub use batch::{majority_judgment_batch, majority_judgment_batch_with_options, BatchResults};
+#[cfg(feature = "parallel")]
+pub use batch::majority_judgment_batch_parallel;
+pub use checked::{try_majority_judgment_from_tally, try_majority_judgment_from_tally_with_options, try_majority_judgment_with_options, RankingError};
+#[cfg(feature = "std")]
+pub use committee::{majority_judgment_multi, CommitteeRule};
+#[cfg(feature = "std")]
+pub use concurrent::ConcurrentTally;
+#[cfg(feature = "std")]
+pub use dedup::{deduplicate, DedupReport, DuplicateBallotError, DuplicatePolicy, DuplicateVoter, Submission};
+#[cfg(feature = "std")]
+pub use election::{Election, ElectionError, ElectionResults, Question, QuestionResult};
+pub use explain::{compare_candidates, dominance_matrix, explain, Decision, DominanceMatrix, Explanation, Outcome};
+pub use grade::{Grade, Scale};
+#[cfg(feature = "std")]
+pub use grade_range::{GradeRange, OutOfRange};
+pub use majority_value::{MajorityValue, MajorityValueIter};
+pub use mj::{
+    majority_judgment, majority_judgment_from_tally, majority_judgment_ranking, majority_judgment_weighted,
+    majority_judgment_winner, majority_judgment_with_method, majority_judgment_with_options, majority_values, rank_subset,
+    top_n,
+};
+#[cfg(feature = "parallel")]
+pub use mj::majority_judgment_parallel;
+pub use options::{BuiltinMethod, MedianConvention, Options, Participation, TieOrder};
+#[cfg(feature = "std")]
+pub use poll::{Ballot, BallotError, BallotIssue, MergeError, Poll};
+#[cfg(feature = "std")]
+pub use precinct::{PrecinctError, PrecinctIssue, PrecinctResults};
+pub use profile::{merit_profile, merit_profile_from_tally};
+#[cfg(feature = "std")]
+pub use progress::{Progress, ProgressStage, ProgressTally};
+#[cfg(feature = "std")]
+pub use projection::{project_majority_grades, ProjectedGrade, Projection};
+#[cfg(feature = "std")]
+pub use quorum::{rank_validated, CandidateInvalidity, PollInvalidity, ValidatedRanking, ValidityRules};
+pub use ranking::{GradeChange, GradeShares, Metadata, OwnedRanking, RankMove, RankedCandidate, Ranking, RankingDiff, TieStatus, Verdict};
+pub use ranking_method::{Average, MajorityGauge, MajorityValues, Merit, RankingMethod, TypicalJudgment, UsualJudgment};
+#[cfg(feature = "std")]
+pub use render::{render_profile, render_profile_from_tally};
+#[cfg(feature = "std")]
+pub use rescale::{migrate_tallies, remap, Bucketing, Migration, RescaleError, Rescaler};
+#[cfg(feature = "std")]
+pub use retally::{retally_with_changes, RankChange, Retally, RetallyError};
+pub use scale::{GradeScale, Language};
+pub use tie_breaker::{DrawKey, Lexicographic, MeanGrade, RandomTieBreaker, RegistrationOrder, TieBreaker};
+#[cfg(feature = "std")]
+pub use validation::{validate_poll, PollDiagnostics};
@@ -2,22 +2,918 @@
 //! This is a simple implementation of the Majority Judgment voting system.
 //! The Majority Judgment is a voting system that was proposed by Michel Balinski and Rida Laraki.
 //! It is a single-winner voting system that selects the candidate who has the highest median grade.
+//!
+//! The `mj` command line ranks the candidates of a poll read from a CSV or JSON file,
+//! run `mj help` for the usage. The options of a recurring election can be kept in a TOML or YAML file
+//! read with `--config`. `mj interactive` collects the ballots of a small poll from the terminal instead.
 
-use std::collections::BTreeMap;
-use majority_judgement_rust::majority_judgment;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use majority_judgement_rust::config::Config;
+use majority_judgement_rust::csv::{counts_from_csv, from_csv};
+use majority_judgement_rust::json::Value;
+use majority_judgement_rust::report::{html_report_from_tally_with_options, ReportOptions, Rounding};
+use majority_judgement_rust::{
+    merit_profile_from_tally, render_profile_from_tally, try_majority_judgment_from_tally_with_options, AbstentionPolicy, Ballot,
+    GradeScale, Language, Lexicographic, Options, Participation, Poll, RandomTieBreaker, Ranking, BuiltinMethod, RegistrationOrder,
+    TallyAccumulator, TieBreaker, TieStatus,
+};
 
+/// The number of votes of each grade for each candidate, the ballots weighted and their abstentions resolved
+type Tallies = BTreeMap<String, BTreeMap<u8, u64>>;
 
-fn main() {
+const USAGE: &str = "\
+mj - majority judgment from the command line
 
-    // Declare a BTreeMap with the poll data
-    let mut poll_data : BTreeMap<String, Vec<u8> > = BTreeMap::new();
+USAGE:
+    mj <COMMAND> <FILE> [OPTIONS]
+    mj <COMMAND> --config <CONFIG> [OPTIONS]
+    mj interactive [OPTIONS]
 
-    poll_data.insert("Pizza".to_string(), vec![0, 0, 3, 0, 2, 0, 3, 1, 2, 3]);
-    poll_data.insert("Chips".to_string(), vec![0, 1, 0, 2, 1, 2, 2, 3, 2, 3]);
-    poll_data.insert("Pasta".to_string(), vec![0, 1, 0, 1, 2, 1, 3, 2, 3, 3]);
-    poll_data.insert("Bread".to_string(), vec![0, 1, 2, 1, 1, 2, 1, 2, 2, 3]);
+    FILE is a CSV or JSON file, or - to read the standard input
 
-    println!("Data: {:?}", poll_data);
-    println!("Results as a vector of tuple (Candidate, Rank): {:?}",majority_judgment(&poll_data));
+COMMANDS:
+    rank       rank the candidates
+    tally      count the votes of each grade for each candidate
+    profile    show the share of each grade for each candidate (merit profile)
+    chart      draw the merit profiles as bars, from the winner to the last
+    report     write an HTML page with the ranking and the merit profiles
+    interactive
+               ask for the candidates and the grades (unless given with --candidates and --scale),
+               then for the ballots one voter at a time, printing the ranking after each ballot;
+               an empty grade for the first candidate or the end of the input ends the poll
+    help       print this message
 
+OPTIONS:
+    --input <csv|tally-csv|json>    input format, guessed from the file extension by default:
+                                    csv is one row per voter and one column per candidate,
+                                    tally-csv is one row per candidate and one column per grade
+    --delimiter <CHAR>              CSV field delimiter, default ,
+    --scale <LABELS>                grade labels from the worst to the best, separated by commas,
+                                    grades can then be written as labels (whatever their case,
+                                    accents and spaces) or numbers
+    --method <METHOD>               majority-values (default), majority-gauge, usual, typical or mean
+    --participation <MODE>          equal (default): with majority-values, every candidate needs as many grades,
+                                    proportional: withdraw the grades by shares, any number of grades
+    --format <table|json>           output format, default table
+    --width <N>                     width of the bars drawn by chart, default 40
+    --precision <N>                 decimals of the percentages of profile and report, default 1
+    --rounding <MODE>               rounding of the percentages: half-even (default), half-up, down or up
+    --abstention <POLICY>           accept missing grades (empty CSV fields, null or absent in JSON),
+                                    counted as the worst grade (worst), not counted (exclude)
+                                    or counted as the given grade, a label or a number of --scale
+    --candidates <NAMES>            the candidates in registration order, separated by commas:
+                                    the poll must grade exactly these candidates
+    --tie-breaker <RULE>            rank tied candidates by lexicographic order, registration order
+                                    (see --candidates), mean grade or random:SEED, default none
+    --config <CONFIG>               read the options from a TOML (.toml) or YAML (.yaml, .yml) file,
+                                    one key per option without the dashes and `file` for FILE,
+                                    relative to the configuration file; the command line overrides it
+
+EXIT CODES:
+    0 success, 64 invalid usage, 65 invalid data, 66 unreadable input
+";
+
+/// An error of the command line, mapped to an exit code
+#[derive(Debug)]
+enum CliError {
+    /// The command line is invalid
+    Usage(String),
+    /// The poll is invalid
+    Data(String),
+    /// The input cannot be read
+    Input(String),
+}
+
+impl CliError {
+    fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::Usage(_) => ExitCode::from(64),
+            CliError::Data(_) => ExitCode::from(65),
+            CliError::Input(_) => ExitCode::from(66),
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            CliError::Usage(message) | CliError::Data(message) | CliError::Input(message) => message,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Rank,
+    Tally,
+    Profile,
+    Chart,
+    Report,
+    Interactive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputFormat {
+    Csv,
+    TallyCsv,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// The rule breaking the ties of the ranking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TieBreakRule {
+    Lexicographic,
+    Registration,
+    Mean,
+    Random(u64),
+}
+
+/// The options a configuration file may set, `file` excepted
+const CONFIG_KEYS: [&str; 12] = [
+    "input", "delimiter", "scale", "method", "participation", "format", "width", "precision", "rounding", "abstention",
+    "candidates", "tie-breaker",
+];
+
+/// The parsed command line
+#[derive(Debug)]
+struct Arguments {
+    command: Command,
+    file: String,
+    input: Option<InputFormat>,
+    delimiter: char,
+    scale: Option<GradeScale>,
+    method: BuiltinMethod,
+    participation: Participation,
+    format: OutputFormat,
+    abstention: Option<AbstentionPolicy<u8>>,
+    width: usize,
+    report: ReportOptions,
+    candidates: Option<Vec<String>>,
+    tie_breaker: Option<TieBreakRule>,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || matches!(args[0].as_str(), "help" | "--help" | "-h") {
+        print!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    match parse_arguments(&args).and_then(|arguments| run(&arguments)) {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("mj: {}", error.message());
+            if let CliError::Usage(_) = error {
+                eprintln!("run `mj help` for the usage");
+            }
+            error.exit_code()
+        }
+    }
+}
+
+/// Function that parses the command line arguments, the program name excluded
+fn parse_arguments(args: &[String]) -> Result<Arguments, CliError> {
+    let command = match args[0].as_str() {
+        "rank" => Command::Rank,
+        "tally" => Command::Tally,
+        "profile" => Command::Profile,
+        "chart" => Command::Chart,
+        "report" => Command::Report,
+        "interactive" => Command::Interactive,
+        other => return Err(CliError::Usage(format!("unknown command {:?}", other))),
+    };
+
+    let mut arguments = Arguments {
+        command,
+        file: String::new(),
+        input: None,
+        delimiter: ',',
+        scale: None,
+        method: BuiltinMethod::MajorityValues,
+        participation: Participation::Equal,
+        format: OutputFormat::Table,
+        abstention: None,
+        width: 40,
+        report: ReportOptions::default(),
+        candidates: None,
+        tie_breaker: None,
+    };
+    // the options of the configuration file come first, so that the command line overrides them
+    let (mut options, config_file) = match args.iter().position(|arg| arg == "--config") {
+        Some(index) => {
+            let path = args.get(index + 1).ok_or_else(|| CliError::Usage("--config expects a value".to_string()))?;
+            read_config(path)?
+        }
+        None => (Vec::new(), None),
+    };
+    options.extend(args[1..].iter().cloned());
+
+    let mut file = None;
+    // the grade of --abstention is parsed once the scale is known, wherever --scale comes
+    let mut abstention = None;
+    let mut rest = options.iter();
+    while let Some(arg) = rest.next() {
+        if !arg.starts_with("--") {
+            if file.replace(arg.clone()).is_some() {
+                return Err(CliError::Usage(format!("unexpected argument {:?}", arg)));
+            }
+            continue;
+        }
+        let value = rest.next().ok_or_else(|| CliError::Usage(format!("{} expects a value", arg)))?;
+        match arg.as_str() {
+            "--input" => arguments.input = Some(match value.as_str() {
+                "csv" => InputFormat::Csv,
+                "tally-csv" => InputFormat::TallyCsv,
+                "json" => InputFormat::Json,
+                _ => return Err(CliError::Usage(format!("unknown input format {:?}", value))),
+            }),
+            "--delimiter" => {
+                let mut chars = value.chars();
+                arguments.delimiter = match (chars.next(), chars.next()) {
+                    (Some(c), None) => c,
+                    _ => return Err(CliError::Usage("the delimiter must be a single character".to_string())),
+                }
+            }
+            "--scale" => arguments.scale = Some(GradeScale::new(value.split(',').map(str::trim)).tolerant()),
+            "--method" => arguments.method = match value.as_str() {
+                "majority-values" => BuiltinMethod::MajorityValues,
+                "majority-gauge" => BuiltinMethod::MajorityGauge,
+                "usual" => BuiltinMethod::UsualJudgment,
+                "typical" => BuiltinMethod::TypicalJudgment,
+                "mean" => BuiltinMethod::MeanGrade,
+                _ => return Err(CliError::Usage(format!("unknown method {:?}", value))),
+            },
+            "--participation" => arguments.participation = match value.as_str() {
+                "equal" => Participation::Equal,
+                "proportional" => Participation::Proportional,
+                _ => return Err(CliError::Usage(format!("unknown participation {:?}", value))),
+            },
+            "--format" => arguments.format = match value.as_str() {
+                "table" => OutputFormat::Table,
+                "json" => OutputFormat::Json,
+                _ => return Err(CliError::Usage(format!("unknown output format {:?}", value))),
+            },
+            "--width" => arguments.width = value
+                .parse()
+                .ok()
+                .filter(|&width| width > 0)
+                .ok_or_else(|| CliError::Usage(format!("invalid width {:?}", value)))?,
+            "--precision" => arguments.report = arguments.report.precision(
+                value
+                    .parse()
+                    .ok()
+                    .filter(|&precision| precision <= ReportOptions::MAX_PRECISION)
+                    .ok_or_else(|| CliError::Usage(format!("invalid precision {:?}", value)))?,
+            ),
+            "--rounding" => arguments.report = arguments.report.rounding(match value.as_str() {
+                "half-even" => Rounding::HalfEven,
+                "half-up" => Rounding::HalfUp,
+                "down" => Rounding::Down,
+                "up" => Rounding::Up,
+                _ => return Err(CliError::Usage(format!("unknown rounding {:?}", value))),
+            }),
+            "--abstention" => abstention = Some(value),
+            "--candidates" => arguments.candidates = Some(value.split(',').map(|name| name.trim().to_string()).collect()),
+            "--tie-breaker" => arguments.tie_breaker = Some(match value.as_str() {
+                "lexicographic" => TieBreakRule::Lexicographic,
+                "registration" => TieBreakRule::Registration,
+                "mean" => TieBreakRule::Mean,
+                rule => TieBreakRule::Random(
+                    rule.strip_prefix("random:")
+                        .and_then(|seed| seed.parse().ok())
+                        .ok_or_else(|| CliError::Usage(format!("unknown tie breaker {:?}", rule)))?,
+                ),
+            }),
+            "--config" => {}
+            _ => return Err(CliError::Usage(format!("unknown option {}", arg))),
+        }
+    }
+    arguments.abstention = match abstention.map(String::as_str) {
+        None => None,
+        Some("worst") => Some(AbstentionPolicy::WorstGrade),
+        Some("exclude") => Some(AbstentionPolicy::Exclude),
+        Some(grade) => Some(AbstentionPolicy::Custom(
+            parse_grade(arguments.scale.as_ref(), grade).map_err(|message| CliError::Usage(format!("--abstention: {}", message)))?,
+        )),
+    };
+    if arguments.tie_breaker == Some(TieBreakRule::Registration) && arguments.candidates.is_none() {
+        return Err(CliError::Usage("--tie-breaker registration needs --candidates".to_string()));
+    }
+    if arguments.command == Command::Interactive {
+        return match file {
+            Some(file) => Err(CliError::Usage(format!("unexpected argument {:?}, interactive reads the ballots from the terminal", file))),
+            None => Ok(arguments),
+        };
+    }
+    arguments.file = file.or(config_file).ok_or_else(|| CliError::Usage("missing input file".to_string()))?;
+    Ok(arguments)
+}
+
+/// Function that reads a configuration file
+///
+/// # Returns
+/// * `Result<(Vec<String>, Option<String>), CliError>`: its options as command line arguments,
+///   and its input file relative to the working directory
+fn read_config(path: &str) -> Result<(Vec<String>, Option<String>), CliError> {
+    let content = std::fs::read_to_string(path).map_err(|error| CliError::Input(format!("cannot read {}: {}", path, error)))?;
+    let config = match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => Config::from_toml(&content),
+        Some("yaml" | "yml") => Config::from_yaml(&content),
+        _ => return Err(CliError::Usage(format!("{}: the configuration must be a .toml, .yaml or .yml file", path))),
+    }
+    .map_err(|error| CliError::Usage(format!("{}: {}", path, error)))?;
+    config_arguments(path, &config)
+}
+
+/// Function that turns the options of a configuration file into command line arguments
+///
+/// # Arguments
+/// * `path`: the configuration file, its directory being the one of a relative `file`
+/// * `config`: its content
+///
+/// # Returns
+/// * `Result<(Vec<String>, Option<String>), CliError>`: the options, and the input file relative to the working directory
+fn config_arguments(path: &str, config: &Config) -> Result<(Vec<String>, Option<String>), CliError> {
+    let mut options = Vec::new();
+    let mut file = None;
+    for (key, value) in config.entries() {
+        match key.as_str() {
+            "file" => {
+                let name = value.to_string();
+                file = Some(match Path::new(path).parent() {
+                    Some(directory) if name != "-" && Path::new(&name).is_relative() => {
+                        directory.join(&name).to_string_lossy().into_owned()
+                    }
+                    _ => name,
+                });
+            }
+            "config" => return Err(CliError::Usage(format!("{}: a configuration cannot include another one", path))),
+            key if CONFIG_KEYS.contains(&key) => options.extend([format!("--{}", key), value.to_string()]),
+            _ => return Err(CliError::Usage(format!("{}: unknown key {:?}", path, key))),
+        }
+    }
+    Ok((options, file))
+}
+
+/// Function that runs a command
+///
+/// # Returns
+/// * `Result<String, CliError>`: what to print on the standard output
+fn run(arguments: &Arguments) -> Result<String, CliError> {
+    if arguments.command == Command::Interactive {
+        return interactive(arguments, &mut std::io::stdin().lock(), &mut std::io::stdout());
+    }
+    let tallies = load(arguments)?;
+    if let Some(candidates) = &arguments.candidates {
+        if let Some(unknown) = tallies.keys().find(|&candidate| !candidates.contains(candidate)) {
+            return Err(CliError::Data(format!("{}: candidate {} is not part of --candidates", arguments.file, unknown)));
+        }
+        if let Some(missing) = candidates.iter().find(|&candidate| !tallies.contains_key(candidate)) {
+            return Err(CliError::Data(format!("{}: candidate {} is not graded", arguments.file, missing)));
+        }
+    }
+    let grades: BTreeSet<u8> = match &arguments.scale {
+        Some(scale) => (0..scale.len()).filter_map(|grade| u8::try_from(grade).ok()).collect(),
+        None => tallies.values().flat_map(BTreeMap::keys).copied().collect(),
+    };
+    let label = |grade: u8| match &arguments.scale {
+        Some(scale) => scale.label(grade as usize).unwrap_or_default().to_string(),
+        None => grade.to_string(),
+    };
+
+    let mut totals = BTreeMap::new();
+    for (candidate, tally) in &tallies {
+        let total = tally.values().try_fold(0u64, |total, &count| total.checked_add(count));
+        let total = total.ok_or_else(|| CliError::Data(format!("{}: candidate {} has more than {} votes", arguments.file, candidate, u64::MAX)))?;
+        totals.insert(candidate, total);
+    }
+    // the most common number of votes, the greatest one in case of a tie
+    let mut frequencies: BTreeMap<u64, usize> = BTreeMap::new();
+    for &total in totals.values() {
+        *frequencies.entry(total).or_insert(0) += 1;
+    }
+    let expected = frequencies.iter().max_by_key(|&(&total, &count)| (count, total)).map_or(0, |(&total, _)| total);
+    let mismatched = totals.iter().find(|&(_, &total)| total != expected);
+    let mismatched_lengths = |hint: &str| match mismatched {
+        Some((candidate, total)) => Err(CliError::Data(format!("{}: candidate {} has {} grades instead of {}{}",
+                                                               arguments.file, candidate, total, expected, hint))),
+        None => Ok(()),
+    };
+
+    match arguments.command {
+        Command::Chart | Command::Report => {
+            mismatched_lengths("")?;
+            Ok(match arguments.command {
+                Command::Report => html_report_from_tally_with_options(&tallies, &arguments.file, |&grade| label(grade), &arguments.report),
+                _ => render_profile_from_tally(&tallies, arguments.width, |&grade| label(grade)),
+            })
+        }
+        Command::Rank => {
+            if arguments.method == BuiltinMethod::MajorityValues && arguments.participation == Participation::Equal {
+                mismatched_lengths(", rank with --participation proportional or another --method")?;
+            }
+            let options = Options::new()
+                .method(arguments.method)
+                .participation(arguments.participation)
+                .exact_grade_value(|&grade| grade as i64);
+            let options = match arguments.tie_breaker {
+                None => options,
+                Some(TieBreakRule::Lexicographic) => options.tie_breaker(Lexicographic),
+                Some(TieBreakRule::Registration) => {
+                    options.tie_breaker(RegistrationOrder::new(arguments.candidates.iter().flatten().cloned()))
+                }
+                Some(TieBreakRule::Mean) => options.tie_breaker(TallyMean::new(&tallies)),
+                Some(TieBreakRule::Random(seed)) => options.tie_breaker(RandomTieBreaker::with_seed(seed)),
+            };
+            let ranking = try_majority_judgment_from_tally_with_options(&tallies, &options)
+                .map_err(|error| CliError::Data(format!("{}: {}", arguments.file, error)))?;
+            Ok(match arguments.format {
+                OutputFormat::Json => ranking.to_json(),
+                OutputFormat::Table => ranking_table(&ranking, label),
+            })
+        }
+        Command::Tally | Command::Profile => {
+            let profile = arguments.command == Command::Profile;
+            let mut header = vec!["Candidate".to_string()];
+            header.extend(grades.iter().map(|&grade| label(grade)));
+            let mut rows = vec![header];
+            let mut members = Vec::new();
+            for (candidate, candidate_profile) in merit_profile_from_tally(&tallies) {
+                let total = candidate_profile.iter().map(|&(_, count, _)| count).sum();
+                let mut row = vec![candidate.clone()];
+                let mut counts = Vec::new();
+                for &grade in grades.iter() {
+                    let (count, share) = candidate_profile
+                        .iter()
+                        .find(|&&(g, _, _)| g == grade)
+                        .map_or((0, 0.0), |&(_, count, share)| (count, share));
+                    if profile {
+                        row.push(arguments.report.percent(count, total));
+                        counts.push((label(grade), Value::Float(share / 100.0)));
+                    } else {
+                        row.push(count.to_string());
+                        counts.push((label(grade), Value::Integer(count as i128)));
+                    }
+                }
+                rows.push(row);
+                members.push((candidate.clone(), Value::Object(counts)));
+            }
+            Ok(match arguments.format {
+                OutputFormat::Json => Value::Object(members).to_string(),
+                OutputFormat::Table => table(&rows),
+            })
+        }
+        Command::Interactive => unreachable!("an interactive poll reads its ballots from the terminal"),
+    }
+}
+
+/// Function that collects the ballots of a poll one voter at a time, printing the ranking after each ballot
+///
+/// # Arguments
+/// * `arguments`: the command line, its `--candidates` and `--scale` sparing the first questions
+/// * `input`, `output`: the terminal
+///
+/// # Returns
+/// * `Result<String, CliError>`: the final ranking, to print on the standard output
+fn interactive<R: BufRead, W: Write>(arguments: &Arguments, input: &mut R, output: &mut W) -> Result<String, CliError> {
+    let candidates = match &arguments.candidates {
+        Some(candidates) => candidates.clone(),
+        None => {
+            let answer = ask(input, output, "Candidates, separated by commas: ")?.unwrap_or_default();
+            answer.split(',').map(str::trim).filter(|name| !name.is_empty()).map(str::to_string).collect()
+        }
+    };
+    if candidates.is_empty() {
+        return Err(CliError::Usage("a poll needs at least one candidate".to_string()));
+    }
+    let scale = match &arguments.scale {
+        Some(scale) => scale.clone(),
+        None => {
+            let answer = ask(input, output, "Grades from the worst to the best, separated by commas (empty for Reject to Excellent): ")?;
+            match answer.as_deref().unwrap_or_default() {
+                "" => GradeScale::balinski_laraki(Language::English).tolerant(),
+                labels => GradeScale::new(labels.split(',').map(str::trim)).tolerant(),
+            }
+        }
+    };
+    let label = |grade: u8| scale.label(grade as usize).unwrap_or_default().to_string();
+    say(output, &format!("Grades: {}", scale))?;
+
+    let mut tally = TallyAccumulator::new(candidates.iter().cloned()).method(arguments.method).exact_grade_value(|&grade| grade as i64);
+    'voters: loop {
+        say(output, &format!("\nVoter {}", tally.ballots() + 1))?;
+        let mut ballot = Ballot::new();
+        for (index, candidate) in candidates.iter().enumerate() {
+            let grade = loop {
+                let answer = match ask(input, output, &format!("  {}: ", candidate))? {
+                    None => break 'voters,
+                    Some(answer) if answer.is_empty() && index == 0 => break 'voters,
+                    Some(answer) => answer,
+                };
+                match scale.parse_grade(&answer) {
+                    Ok(grade) => break grade,
+                    Err(message) => say(output, &format!("  {}", message))?,
+                }
+            };
+            ballot = ballot.grade(candidate.clone(), grade);
+        }
+        tally.add_ballot(&ballot).map_err(|issues| CliError::Data(format!("invalid ballot: {:?}", issues)))?;
+        say(output, &ranking_table(&tally.current_ranking(), label))?;
+    }
+    let ballots = match tally.ballots() {
+        1 => "1 ballot".to_string(),
+        ballots => format!("{} ballots", ballots),
+    };
+    Ok(format!("\nRanking after {}\n{}", ballots, ranking_table(&tally.current_ranking(), label)))
+}
+
+/// Function that asks a question on the terminal
+///
+/// # Returns
+/// * `Result<Option<String>, CliError>`: the answer without its surrounding spaces, None at the end of the input
+fn ask<R: BufRead, W: Write>(input: &mut R, output: &mut W, question: &str) -> Result<Option<String>, CliError> {
+    let terminal_error = |error: std::io::Error| CliError::Input(format!("cannot use the terminal: {}", error));
+    write!(output, "{}", question).and_then(|_| output.flush()).map_err(terminal_error)?;
+    let mut answer = String::new();
+    match input.read_line(&mut answer).map_err(terminal_error)? {
+        0 => Ok(None),
+        _ => Ok(Some(answer.trim().to_string())),
+    }
+}
+
+/// Function that writes a line on the terminal
+fn say<W: Write>(output: &mut W, line: &str) -> Result<(), CliError> {
+    writeln!(output, "{}", line).map_err(|error| CliError::Input(format!("cannot use the terminal: {}", error)))
+}
+
+/// Function that writes a ranking as a table of the rank, the candidate, its majority grade and its tie status
+fn ranking_table(ranking: &Ranking<'_, String, u8>, label: impl Fn(u8) -> String) -> String {
+    let mut rows = vec![vec!["Rank".to_string(), "Candidate".to_string(), "Majority grade".to_string(), "Tie".to_string()]];
+    for ranked in ranking.iter() {
+        let tie = match ranked.tie {
+            TieStatus::Unique => "",
+            TieStatus::Tied => "tied",
+            TieStatus::Broken => "broken",
+        };
+        let majority_grade = ranked.majority_grade.map(&label).unwrap_or_default();
+        rows.push(vec![(ranked.rank + 1).to_string(), ranked.candidate.clone(), majority_grade, tie.to_string()]);
+    }
+    table(&rows)
+}
+
+/// Function that reads the number of votes of each grade for each candidate from the input file
+///
+/// A weighted ballot counts as many votes as its weight, without repeating its grades.
+fn load(arguments: &Arguments) -> Result<Tallies, CliError> {
+    let mut content = String::new();
+    if arguments.file == "-" {
+        std::io::stdin().read_to_string(&mut content)
+    } else {
+        std::fs::File::open(&arguments.file).and_then(|mut file| file.read_to_string(&mut content))
+    }
+    .map_err(|error| CliError::Input(format!("cannot read {}: {}", arguments.file, error)))?;
+
+    let input = arguments.input.unwrap_or(if arguments.file.ends_with(".json") {
+        InputFormat::Json
+    } else {
+        InputFormat::Csv
+    });
+    let parse_grade = |field: &str| parse_grade(arguments.scale.as_ref(), field);
+    // an empty field is an abstention, only accepted with an abstention policy
+    let parse_optional_grade = |field: &str| -> Result<Option<u8>, String> {
+        if field.is_empty() && arguments.abstention.is_some() {
+            return Ok(None);
+        }
+        parse_grade(field).map(Some)
+    };
+    let data_error = |error: &dyn std::fmt::Display| CliError::Data(format!("{}: {}", arguments.file, error));
+
+    let poll: Poll<Option<u8>> = match input {
+        InputFormat::TallyCsv => {
+            let mut tallies = counts_from_csv(&content, arguments.delimiter, parse_grade).map_err(|e| data_error(&e))?;
+            for tally in tallies.values_mut() {
+                tally.retain(|_, count| *count > 0);
+            }
+            return Ok(tallies);
+        }
+        InputFormat::Csv => from_csv(&content, arguments.delimiter, parse_optional_grade).map_err(|e| data_error(&e))?,
+        InputFormat::Json => Poll::from_json(&content).map_err(|e| data_error(&e))?,
+    };
+
+    let poll_data = match arguments.abstention {
+        Some(_) => poll.poll_data_with_abstentions(&AbstentionPolicy::Custom(None)),
+        None => poll.poll_data(),
+    };
+    let poll_data = poll_data.map_err(|errors| {
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        CliError::Data(format!("{}: invalid ballots\n  {}", arguments.file, messages.join("\n  ")))
+    })?;
+    if let Some(scale) = &arguments.scale {
+        if let Some(grade) = poll_data.values().flatten().flatten().find(|&&grade| grade as usize >= scale.len()) {
+            return Err(CliError::Data(format!("{}: grade {} is not part of the scale {}", arguments.file, grade, scale)));
+        }
+    }
+
+    let weights = poll.weights();
+    let mut counts: BTreeMap<String, BTreeMap<Option<u8>, u64>> = BTreeMap::new();
+    for (candidate, grades) in poll_data {
+        let tally = counts.entry(candidate).or_default();
+        for (grade, &weight) in grades.into_iter().zip(weights.iter()).filter(|&(_, &weight)| weight > 0) {
+            *tally.entry(grade).or_insert(0) += weight as u64;
+        }
+    }
+
+    let policy = match &arguments.abstention {
+        // with a scale, the worst grade is the first one even if nobody gave it
+        Some(AbstentionPolicy::WorstGrade) if arguments.scale.is_some() => AbstentionPolicy::Custom(0),
+        Some(policy) => policy.clone(),
+        None => {
+            if counts.values().flat_map(BTreeMap::keys).any(Option::is_none) {
+                return Err(CliError::Data(format!("{}: missing grades, see --abstention", arguments.file)));
+            }
+            AbstentionPolicy::Exclude
+        }
+    };
+    let replacement = match policy {
+        AbstentionPolicy::WorstGrade => counts.values().flat_map(BTreeMap::keys).flatten().min().copied(),
+        AbstentionPolicy::Exclude => None,
+        AbstentionPolicy::Custom(grade) => Some(grade),
+    };
+    let tallies = counts
+        .into_iter()
+        .map(|(candidate, counts)| {
+            let mut tally = BTreeMap::new();
+            for (grade, count) in counts {
+                if let Some(grade) = grade.or(replacement) {
+                    *tally.entry(grade).or_insert(0) += count;
+                }
+            }
+            (candidate, tally)
+        })
+        .collect();
+    Ok(tallies)
+}
+
+/// Function that parses a grade, a label or a number of the scale if there is one, any number otherwise
+fn parse_grade(scale: Option<&GradeScale>, field: &str) -> Result<u8, String> {
+    match scale {
+        Some(scale) => scale.parse_grade(field),
+        None => field.parse().map_err(|_| format!("{:?} is not a valid grade", field)),
+    }
+}
+
+/// Tied candidates are ordered by their mean grade, the highest mean first, as `MeanGrade` orders them
+/// from their ballots
+struct TallyMean(BTreeMap<String, f64>);
+
+impl TallyMean {
+    fn new(tallies: &Tallies) -> Self {
+        let mean = |tally: &BTreeMap<u8, u64>| {
+            let total: f64 = tally.values().map(|&count| count as f64).sum();
+            let sum: f64 = tally.iter().map(|(&grade, &count)| grade as f64 * count as f64).sum();
+            if total == 0.0 { 0.0 } else { sum / total }
+        };
+        TallyMean(tallies.iter().map(|(candidate, tally)| (candidate.clone(), mean(tally))).collect())
+    }
+}
+
+impl TieBreaker<u8> for TallyMean {
+    fn compare(&self, a: &String, _: &[u8], b: &String, _: &[u8]) -> Ordering {
+        self.0[a].partial_cmp(&self.0[b]).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Function that aligns rows of cells into a table, the first row being the header
+fn table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| rows.iter().filter_map(|row| row.get(column)).map(|cell| cell.chars().count()).max().unwrap_or(0))
+        .collect();
+
+    let mut lines = Vec::new();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, &width)| format!("{:width$}", cell, width = width))
+            .collect();
+        lines.push(cells.join("  ").trim_end().to_string());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The arguments of a command line, separated by spaces
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(str::to_string).collect()
+    }
+
+    /// Function that parses a command line naming FILE a poll written to a temporary file, then calls `f`
+    fn with_file<T>(name: &str, content: &str, line: &str, f: impl Fn(&Arguments) -> Result<T, CliError>) -> Result<T, CliError> {
+        let path = std::env::temp_dir().join(format!("mj-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        let result = parse_arguments(&args(&line.replace("FILE", &path.to_string_lossy()))).and_then(|arguments| f(&arguments));
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    /// Function that runs a command line on a poll written to a temporary file
+    fn run_on(name: &str, content: &str, line: &str) -> Result<String, CliError> {
+        with_file(name, content, line, run)
+    }
+
+    /// The tally of a candidate
+    fn tally<const N: usize>(counts: [(u8, u64); N]) -> BTreeMap<u8, u64> {
+        BTreeMap::from(counts)
+    }
+
+    #[test]
+    fn calling_parse_arguments() {
+        let arguments = parse_arguments(&args("rank poll.csv")).unwrap();
+        assert_eq!((arguments.command, arguments.file.as_str(), arguments.input, arguments.delimiter), (Command::Rank, "poll.csv", None, ','));
+        assert_eq!((arguments.method, arguments.participation, arguments.format), (BuiltinMethod::MajorityValues, Participation::Equal, OutputFormat::Table));
+        let arguments = parse_arguments(&args("chart - --input json --delimiter ; --method usual --format json --width 20")).unwrap();
+        assert_eq!((arguments.command, arguments.file.as_str(), arguments.input, arguments.delimiter), (Command::Chart, "-", Some(InputFormat::Json), ';'));
+        assert_eq!((arguments.method, arguments.format, arguments.width), (BuiltinMethod::UsualJudgment, OutputFormat::Json, 20));
+        let arguments = parse_arguments(&args("rank poll.csv --candidates Pizza,Chips --tie-breaker registration")).unwrap();
+        assert_eq!(arguments.candidates, Some(vec!["Pizza".to_string(), "Chips".to_string()]));
+        assert_eq!(arguments.tie_breaker, Some(TieBreakRule::Registration));
+        assert_eq!(parse_arguments(&args("rank poll.csv --tie-breaker random:7")).unwrap().tie_breaker, Some(TieBreakRule::Random(7)));
+        assert_eq!(parse_arguments(&args("interactive")).unwrap().command, Command::Interactive);
+
+        let usage = |line: &str| match parse_arguments(&args(line)) {
+            Err(CliError::Usage(message)) => message,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(usage("vote poll.csv"), "unknown command \"vote\"");
+        assert_eq!(usage("rank poll.csv --colour red"), "unknown option --colour");
+        assert_eq!(usage("rank poll.csv --width"), "--width expects a value");
+        assert_eq!(usage("rank poll.csv --width 0"), "invalid width \"0\"");
+        assert_eq!(usage("rank poll.csv --delimiter ;;"), "the delimiter must be a single character");
+        assert_eq!(usage("rank poll.csv --method median"), "unknown method \"median\"");
+        assert_eq!(usage("rank poll.csv --tie-breaker random:seed"), "unknown tie breaker \"random:seed\"");
+        assert_eq!(usage("rank poll.csv --tie-breaker registration"), "--tie-breaker registration needs --candidates");
+        assert_eq!(usage("rank"), "missing input file");
+        assert_eq!(usage("rank poll.csv other.csv"), "unexpected argument \"other.csv\"");
+        assert_eq!(usage("interactive poll.csv"), "unexpected argument \"poll.csv\", interactive reads the ballots from the terminal");
+    }
+
+    #[test]
+    fn calling_config_arguments() {
+        let config = Config::from_toml("file = \"ballots.csv\"\nscale = [\"Bad\", \"Good\"]\ntie-breaker = \"random:7\"\n").unwrap();
+        let (options, file) = config_arguments("elections/budget.toml", &config).unwrap();
+        assert_eq!(options, ["--scale", "Bad,Good", "--tie-breaker", "random:7"]);
+        assert_eq!(file, Some(Path::new("elections").join("ballots.csv").to_string_lossy().into_owned()));
+
+        // the standard input and absolute paths are kept as they are
+        let file = |content: &str| config_arguments("elections/budget.yaml", &Config::from_yaml(content).unwrap()).unwrap().1;
+        assert_eq!(file("file: \"-\"\n"), Some("-".to_string()));
+        let absolute = std::env::temp_dir().join("ballots.csv").to_string_lossy().into_owned();
+        assert_eq!(file(&format!("file: {}\n", absolute)), Some(absolute));
+        assert_eq!(file("method: usual\n"), None);
+
+        let usage = |content: &str| match config_arguments("budget.yaml", &Config::from_yaml(content).unwrap()) {
+            Err(CliError::Usage(message)) => message,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(usage("config: other.yaml\n"), "budget.yaml: a configuration cannot include another one");
+        assert_eq!(usage("colour: red\n"), "budget.yaml: unknown key \"colour\"");
+
+        // the command line overrides the file, and names the poll instead of it
+        let path = std::env::temp_dir().join(format!("mj-{}-config.toml", std::process::id()));
+        std::fs::write(&path, "file = \"ballots.csv\"\nmethod = \"usual\"\nformat = \"json\"\n").unwrap();
+        let config = path.to_string_lossy().into_owned();
+        let arguments = parse_arguments(&args(&format!("rank --config {} --method typical", config))).unwrap();
+        assert_eq!((arguments.method, arguments.format), (BuiltinMethod::TypicalJudgment, OutputFormat::Json));
+        assert_eq!(arguments.file, std::env::temp_dir().join("ballots.csv").to_string_lossy());
+        assert_eq!(parse_arguments(&args(&format!("rank other.csv --config {}", config))).unwrap().file, "other.csv");
+        std::fs::remove_file(&path).unwrap();
+
+        let error = parse_arguments(&args("rank --config budget.ini")).unwrap_err();
+        assert!(matches!(error, CliError::Input(_)), "{:?}", error);
+        assert_eq!(parse_arguments(&args("rank --config")).unwrap_err().message(), "--config expects a value");
+    }
+
+    #[test]
+    fn calling_interactive() {
+        let poll = |line: &str, answers: &str| {
+            let mut output = Vec::new();
+            let result = interactive(&parse_arguments(&args(line)).unwrap(), &mut std::io::Cursor::new(answers), &mut output);
+            (result, String::from_utf8(output).unwrap())
+        };
+
+        // a wrong label is asked again, an empty grade for the first candidate ends the poll
+        let (result, output) = poll("interactive --candidates Pizza,Chips --scale Bad,Good", "Good\nGreat\nbad\n\nGood\n");
+        assert_eq!(result.unwrap(), "\nRanking after 1 ballot\nRank  Candidate  Majority grade  Tie\n1     Pizza      Good\n2     Chips      Bad");
+        assert_eq!(output, "Grades: Bad < Good\n\nVoter 1\n  Pizza:   Chips:   \"Great\" is not a grade of the scale Bad < Good\n  Chips: \
+                            Rank  Candidate  Majority grade  Tie\n1     Pizza      Good\n2     Chips      Bad\n\nVoter 2\n  Pizza: ");
+
+        // the candidates and the grades are asked for, the end of the input drops the ballot being given
+        let (result, output) = poll("interactive", "Pizza, Chips\n\ngood\nreject\nexcellent\npoor\ngood");
+        assert!(result.unwrap().starts_with("\nRanking after 2 ballots\n"));
+        assert!(output.starts_with("Candidates, separated by commas: Grades from the worst to the best"));
+        assert!(output.contains("Grades: Reject < Poor < Acceptable < Fairly good < Good < Very good < Excellent\n"));
+        assert!(output.ends_with("Voter 3\n  Pizza:   Chips: "));
+
+        let (result, _) = poll("interactive --candidates Pizza --scale Bad,Good", "");
+        assert_eq!(result.unwrap(), "\nRanking after 0 ballots\nRank  Candidate  Majority grade  Tie\n1     Pizza");
+        let (result, _) = poll("interactive", " , \n");
+        assert_eq!(result.unwrap_err().message(), "a poll needs at least one candidate");
+    }
+
+    #[test]
+    fn calling_load() {
+        // a weighted ballot counts as many votes as its weight, a ballot of weight 0 none
+        let poll = r#"{"candidates": ["Pizza", "Chips"], "ballots": [{"grades": {"Pizza": 3, "Chips": 1}, "weight": 3},
+                      {"grades": {"Pizza": 0, "Chips": 2}}, {"grades": {"Pizza": 1, "Chips": 1}, "weight": 0}]}"#;
+        let tallies = with_file("weights.json", poll, "rank FILE", load).unwrap();
+        assert_eq!(tallies, Tallies::from([("Chips".to_string(), tally([(1, 3), (2, 1)])), ("Pizza".to_string(), tally([(0, 1), (3, 3)]))]));
+
+        // a tally file with the labels of the scale, the grades without votes dropped
+        let counts = "candidate,Bad,Good\nPizza,0,5\nChips,2,3\n";
+        let tallies = with_file("counts.csv", counts, "rank FILE --input tally-csv --scale Bad,Good", load).unwrap();
+        assert_eq!(tallies, Tallies::from([("Chips".to_string(), tally([(0, 2), (1, 3)])), ("Pizza".to_string(), tally([(1, 5)]))]));
+
+        // the missing grades are the worst grade given, the worst grade of the scale, a given grade or nothing
+        let poll = "Pizza,Chips\n3,\n1,2\n2,1\n";
+        let chips = |line: &str| with_file("abstentions.csv", poll, line, load).map(|tallies| tallies["Chips"].clone());
+        assert_eq!(chips("rank FILE --abstention worst").unwrap(), tally([(1, 2), (2, 1)]));
+        assert_eq!(chips("rank FILE --abstention worst --scale a,b,c,d").unwrap(), tally([(0, 1), (1, 1), (2, 1)]));
+        assert_eq!(chips("rank FILE --abstention 3").unwrap(), tally([(1, 1), (2, 1), (3, 1)]));
+        assert_eq!(chips("rank FILE --abstention exclude").unwrap(), tally([(1, 1), (2, 1)]));
+        let error = chips("rank FILE").unwrap_err();
+        assert!(matches!(error, CliError::Data(_)) && error.message().ends_with("line 2, column 2: \"\" is not a valid grade"), "{:?}", error);
+
+        let error = with_file("scale.csv", poll, "rank FILE --scale Bad,Good --abstention exclude", load).unwrap_err();
+        assert!(error.message().ends_with("line 2, column 1: \"3\" is not a grade of the scale Bad < Good"), "{:?}", error);
+    }
+
+    #[test]
+    fn calling_run() {
+        let poll = "Pizza,Chips\n3,\n1,2\n2,1\n";
+        let error = run_on("lengths.csv", poll, "rank FILE --abstention exclude").unwrap_err();
+        assert!(matches!(error, CliError::Data(_)));
+        assert!(error.message().ends_with(": candidate Chips has 2 grades instead of 3, rank with --participation proportional or another --method"));
+        assert!(run_on("lengths.csv", poll, "chart FILE --abstention exclude").unwrap_err().message().ends_with("instead of 3"));
+        assert_eq!(run_on("lengths.csv", poll, "rank FILE --abstention exclude --participation proportional").unwrap(),
+                   "Rank  Candidate  Majority grade  Tie\n1     Pizza      2\n2     Chips      1");
+
+        let error = run_on("candidates.csv", poll, "rank FILE --abstention 0 --candidates Pizza,Pasta").unwrap_err();
+        assert!(error.message().ends_with(": candidate Chips is not part of --candidates"));
+        assert_eq!(run_on("tie.csv", "Pizza,Chips\n1,1\n", "rank FILE --format json --tie-breaker lexicographic").unwrap(),
+                   "[{\"candidate\":\"Chips\",\"rank\":0,\"tie\":\"broken\",\"majority_grade\":1,\"proponents\":0.0,\"opponents\":0.0},\
+                    {\"candidate\":\"Pizza\",\"rank\":1,\"tie\":\"broken\",\"majority_grade\":1,\"proponents\":0.0,\"opponents\":0.0}]");
+
+        // each error has its exit code
+        let missing = std::env::temp_dir().join("mj-missing.csv");
+        let error = parse_arguments(&args(&format!("rank {}", missing.display()))).and_then(|arguments| run(&arguments)).unwrap_err();
+        assert!(matches!(error, CliError::Input(_)));
+        assert_eq!(error.exit_code(), ExitCode::from(66));
+        assert_eq!(CliError::Data(String::new()).exit_code(), ExitCode::from(65));
+        assert_eq!(CliError::Usage(String::new()).exit_code(), ExitCode::from(64));
+    }
+
+    #[test]
+    fn calling_table() {
+        let rows = [vec!["Candidate".to_string(), "0".to_string(), "1".to_string()], vec!["Pizza".to_string(), "10".to_string()], vec![]];
+        assert_eq!(table(&rows), "Candidate  0   1\nPizza      10\n");
+        assert_eq!(table(&[vec!["Crème brûlée".to_string(), "1".to_string()], vec!["Pâtes".to_string()]]), "Crème brûlée  1\nPâtes");
+        assert_eq!(table(&[]), "");
+    }
+
+    #[test]
+    fn calling_parse_arguments_with_abstention() {
+        let abstention = |line: &str| parse_arguments(&args(line)).map(|arguments| arguments.abstention);
+        assert_eq!(abstention("rank poll.csv --abstention worst").unwrap(), Some(AbstentionPolicy::WorstGrade));
+        assert_eq!(abstention("rank poll.csv --abstention exclude").unwrap(), Some(AbstentionPolicy::Exclude));
+        assert_eq!(abstention("rank poll.csv --abstention 9").unwrap(), Some(AbstentionPolicy::Custom(9)));
+        // a label of the scale, given before or after it, or a number of the scale
+        assert_eq!(abstention("rank poll.csv --abstention good --scale Bad,Good").unwrap(), Some(AbstentionPolicy::Custom(1)));
+        assert_eq!(abstention("rank poll.csv --scale Bad,Good --abstention 0").unwrap(), Some(AbstentionPolicy::Custom(0)));
+
+        let error = abstention("rank poll.csv --scale Bad,Good --abstention 9").unwrap_err();
+        assert!(matches!(error, CliError::Usage(_)));
+        assert_eq!(error.message(), "--abstention: \"9\" is not a grade of the scale Bad < Good");
+        assert_eq!(abstention("rank poll.csv --abstention Good").unwrap_err().message(), "--abstention: \"Good\" is not a valid grade");
+
+        let poll = "Pizza,Chips\nGood,\nGood,\nBad,Good\n";
+        let ranking = run_on("abstention.csv", poll, "rank FILE --scale Bad,Good --abstention good --format json").unwrap();
+        assert!(ranking.starts_with("[{\"candidate\":\"Chips\",\"rank\":0,\"tie\":\"unique\",\"majority_grade\":1,"), "{}", ranking);
+        assert_eq!(run_on("abstention.csv", poll, "tally FILE --scale Bad,Good --abstention 1").unwrap(),
+                   "Candidate  Bad  Good\nChips      0    3\nPizza      1    2");
+    }
 }
@@ -0,0 +1,569 @@
+//! # Majority value
+//! Compressed representation of the majority values of a candidate: the successive median grades
+//! obtained when withdrawing the median grade one vote at a time.
+//!
+//! Withdrawing the (lower) median of sorted grades one by one visits the grades from the middle
+//! outwards, alternating between the grade just below and the grade just above what was already withdrawn
+//! (just above, then just below, withdrawing the upper median).
+//! As long as both of these grades stay the same, the majority values alternate between them,
+//! so the whole sequence is a handful of alternating segments, at most one per distinct grade.
+//! It is built from the tally in O(k) and compared in O(k), k being the number of distinct grades,
+//! whatever the number of votes.
+//!
+//! Candidates with different numbers of votes are compared by `Proportional`: the `i`-th of the `n`
+//! majority values of a candidate stands for the shares of withdrawn votes from `i / n` to `(i + 1) / n`.
+//!
+//! `MajorityValue` is public: computed once, it is kept, sorted and compared with the candidates of other polls
+//! without counting their grades again, and written as JSON with the `json` feature.
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use crate::judgment::TOO_MANY_VOTES;
+use crate::mj::compute_majority_values;
+use crate::options::MedianConvention;
+
+/// `length` successive majority values alternating between `first` and `second`
+/// (a constant run when both are equal)
+#[derive(Debug, Clone)]
+struct Segment<G> {
+    first: G,
+    second: G,
+    length: u64,
+}
+
+impl<G> Segment<G> {
+    /// The majority value at `offset` in the segment
+    fn at(&self, offset: u64) -> &G {
+        if offset.is_multiple_of(2) { &self.first } else { &self.second }
+    }
+}
+
+/// The majority values of a candidate, compared lexicographically
+///
+/// The order is the one of the majority judgment: the greater majority value ranks first. Candidates with
+/// different numbers of votes, e.g. from polls of different sizes, are compared with `cmp_proportional`.
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::MajorityValue;
+/// let pizza = MajorityValue::from_grades(&[0, 2, 3, 3]);
+/// let chips = MajorityValue::from_tally(&BTreeMap::from([(0u8, 1u64), (2, 3)]));
+/// assert!(pizza > chips);
+/// assert_eq!(pizza.to_string(), "2, 3, 0, 3");
+///
+/// let mut candidates = vec![("Chips", chips), ("Pizza", pizza)];
+/// candidates.sort_by(|a, b| b.1.cmp(&a.1));
+/// assert_eq!(candidates[0].0, "Pizza");
+/// ```
+#[derive(Debug, Clone)]
+pub struct MajorityValue<G> {
+    segments: Vec<Segment<G>>,
+}
+
+impl<G: Clone + PartialEq> MajorityValue<G> {
+    /// Function that computes the majority values from the tally of the grades
+    ///
+    /// # Arguments
+    /// * `tally`: the number of votes (or total weight) of each grade
+    ///
+    /// # Returns
+    /// * `MajorityValue<G>`: the consecutive median grades when withdrawing the previous one
+    ///
+    /// # Panics
+    /// * if the tally has more than `u64::MAX` votes
+    pub fn from_tally<C: Copy + Into<u64>>(tally: &BTreeMap<G, C>) -> Self {
+        MajorityValue::from_tally_with(tally, MedianConvention::LowerMedian)
+    }
+
+    /// Function that computes the majority values withdrawing the lower or the upper median
+    ///
+    /// # Arguments
+    /// * `tally`: the number of votes (or total weight) of each grade
+    /// * `convention`: which middle grade is withdrawn from an even number of votes
+    ///
+    /// # Returns
+    /// * `MajorityValue<G>`: the consecutive median grades when withdrawing the previous one
+    ///
+    /// # Panics
+    /// * if the tally has more than `u64::MAX` votes
+    pub fn from_tally_with<C: Copy + Into<u64>>(tally: &BTreeMap<G, C>, convention: MedianConvention) -> Self {
+        trace_span!("median");
+        // the only buffer: each grade with the cumulative count of the votes up to it,
+        // the count of a grade being the difference with the previous one
+        let mut cumulative: Vec<(&G, u64)> = Vec::with_capacity(tally.len());
+        let mut sum = 0;
+        for (grade, &count) in tally.iter() {
+            sum = u64::checked_add(sum, count.into()).expect(TOO_MANY_VOTES);
+            cumulative.push((grade, sum));
+        }
+        let total = sum;
+        let end_of = |index: usize| cumulative[index].1;
+        let start_of = |index: usize| if index == 0 { 0 } else { cumulative[index - 1].1 };
+
+        if total == 0 {
+            return MajorityValue { segments: Vec::new() };
+        }
+        // at most one segment per distinct grade, plus the first median of an odd number of votes
+        let mut majority_value = MajorityValue { segments: Vec::with_capacity(tally.len() + 1) };
+
+        // the position, in the sorted votes, of the first median withdrawn, and its grade
+        let center = (total - 1) / 2;
+        let grade_at = |position: u64| cumulative.partition_point(|&(_, cum)| cum <= position);
+
+        // with an odd number of votes the first median is alone,
+        // then the grades below and above it are withdrawn in pairs
+        let (left, right) = if !total.is_multiple_of(2) {
+            let index = grade_at(center);
+            majority_value.push(cumulative[index].0, cumulative[index].0, 1);
+            (center.checked_sub(1), center + 1)
+        } else {
+            (Some(center), center + 1)
+        };
+
+        let mut pairs = total / 2;
+        if let Some(left) = left.filter(|_| pairs > 0) {
+            let mut left_index = grade_at(left);
+            let mut left_remaining = left - start_of(left_index) + 1;
+            let mut right_index = grade_at(right);
+            let mut right_remaining = end_of(right_index) - right;
+
+            while pairs > 0 {
+                let run = left_remaining.min(right_remaining);
+                let (below, above) = (cumulative[left_index].0, cumulative[right_index].0);
+                match convention {
+                    MedianConvention::LowerMedian => majority_value.push(below, above, 2 * run),
+                    MedianConvention::UpperMedian => majority_value.push(above, below, 2 * run),
+                }
+                pairs -= run;
+                left_remaining -= run;
+                right_remaining -= run;
+
+                if pairs == 0 {
+                    break;
+                }
+                while left_remaining == 0 {
+                    left_index -= 1;
+                    left_remaining = end_of(left_index) - start_of(left_index);
+                }
+                while right_remaining == 0 {
+                    right_index += 1;
+                    right_remaining = end_of(right_index) - start_of(right_index);
+                }
+            }
+        }
+        trace_event!("median", "{} votes, {} distinct grades, {} segments", total, tally.len(), majority_value.segments.len());
+        majority_value
+    }
+
+    /// Function that rebuilds the majority values from their segments, see `segments`
+    ///
+    /// # Arguments
+    /// * `segments`: the successive segments, each with its two alternating values and its length
+    ///
+    /// # Returns
+    /// * `Option<MajorityValue<G>>`: the majority values, None if a segment is empty
+    #[cfg(feature = "json")]
+    pub(crate) fn from_segments(segments: impl IntoIterator<Item = (G, G, u64)>) -> Option<Self> {
+        let mut majority_value = MajorityValue { segments: Vec::new() };
+        for (first, second, length) in segments {
+            if length == 0 {
+                return None;
+            }
+            majority_value.push(&first, &second, length);
+        }
+        Some(majority_value)
+    }
+
+    /// The majority values one at a time, as `majority_values`
+    pub fn iter(&self) -> MajorityValueIter<G> {
+        self.clone().into_iter()
+    }
+
+    /// Append `length` values alternating between `first` and `second`, merged with the last segment if possible
+    fn push(&mut self, first: &G, second: &G, length: u64) {
+        if let Some(last) = self.segments.last_mut() {
+            let constant = last.first == last.second && first == second && *first == last.first;
+            let same_pair = last.length.is_multiple_of(2) && *first == last.first && *second == last.second;
+            if constant || same_pair {
+                last.length += length;
+                return;
+            }
+        }
+        self.segments.push(Segment { first: first.clone(), second: second.clone(), length });
+    }
+
+    /// The majority values as a vector, one value per vote
+    #[cfg(test)]
+    pub(crate) fn to_vec(&self) -> Vec<G> {
+        let mut values = Vec::with_capacity(self.len() as usize);
+        for segment in self.segments.iter() {
+            for offset in 0..segment.length {
+                values.push(segment.at(offset).clone());
+            }
+        }
+        values
+    }
+}
+
+impl<G: Ord + Clone> MajorityValue<G> {
+    /// The majority values of the grades of a candidate, unsorted
+    pub fn from_grades(grades: &[G]) -> Self {
+        compute_majority_values(grades)
+    }
+}
+
+impl<G> MajorityValue<G> {
+    /// The successive segments of values, each with its two alternating values and its length
+    #[cfg(feature = "json")]
+    pub(crate) fn segments(&self) -> impl Iterator<Item = (&G, &G, u64)> {
+        self.segments.iter().map(|segment| (&segment.first, &segment.second, segment.length))
+    }
+}
+
+impl<G: PartialEq> MajorityValue<G> {
+    /// The number of majority values, i.e. the number of votes
+    pub fn len(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.length).sum()
+    }
+
+    /// Whether there is no majority value, i.e. no vote
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Function that finds where two sequences of majority values diverge
+    ///
+    /// # Returns
+    /// * `Option<(u64, &G, &G)>`: the number of values before the first different one, then the
+    ///   values of `self` and `other` there. None if one sequence is a prefix of the other.
+    pub(crate) fn first_difference<'s>(&'s self, other: &'s Self) -> Option<(u64, &'s G, &'s G)> {
+        let (mut i, mut j) = (0, 0);
+        let (mut offset_i, mut offset_j) = (0, 0);
+        let mut position = 0;
+        while i < self.segments.len() && j < other.segments.len() {
+            let (a, b) = (&self.segments[i], &other.segments[j]);
+            let overlap = (a.length - offset_i).min(b.length - offset_j);
+
+            // both segments have a period of 2: if the first two values of the overlap match, all do
+            for step in 0..overlap.min(2) {
+                let (x, y) = (a.at(offset_i + step), b.at(offset_j + step));
+                if x != y {
+                    return Some((position + step, x, y));
+                }
+            }
+
+            position += overlap;
+            offset_i += overlap;
+            offset_j += overlap;
+            if offset_i == a.length {
+                i += 1;
+                offset_i = 0;
+            }
+            if offset_j == b.length {
+                j += 1;
+                offset_j = 0;
+            }
+        }
+        None
+    }
+}
+
+impl<G: PartialEq> MajorityValue<G> {
+    /// The majority values as runs of equal values, each with the number of values up to its end
+    fn runs(&self) -> impl Iterator<Item = (&G, u64)> + '_ {
+        let mut end = 0;
+        self.segments.iter().flat_map(move |segment| {
+            let runs = if segment.first == segment.second { 1 } else { segment.length };
+            let offset = end;
+            end += segment.length;
+            (0..runs).map(move |run| match runs {
+                1 => (&segment.first, offset + segment.length),
+                _ => (segment.at(run), offset + run + 1),
+            })
+        })
+    }
+}
+
+impl<G: Clone> IntoIterator for MajorityValue<G> {
+    type Item = G;
+    type IntoIter = MajorityValueIter<G>;
+
+    fn into_iter(self) -> MajorityValueIter<G> {
+        let remaining = self.segments.iter().map(|segment| segment.length).sum();
+        MajorityValueIter { segments: self.segments, index: 0, offset: 0, remaining }
+    }
+}
+
+/// The successive median grades of a candidate, withdrawing the median grade one vote at a time,
+/// see `majority_values`
+///
+/// The values are produced from the compressed segments one at a time: comparing two candidates
+/// can stop at the first different value, and `nth` skips whole segments at once.
+#[derive(Debug, Clone)]
+pub struct MajorityValueIter<G> {
+    segments: Vec<Segment<G>>,
+    index: usize,
+    offset: u64,
+    remaining: u64,
+}
+
+impl<G: Clone> Iterator for MajorityValueIter<G> {
+    type Item = G;
+
+    fn next(&mut self) -> Option<G> {
+        while self.offset == self.segments.get(self.index)?.length {
+            self.index += 1;
+            self.offset = 0;
+        }
+        let value = self.segments[self.index].at(self.offset).clone();
+        self.offset += 1;
+        self.remaining -= 1;
+        Some(value)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<G> {
+        let mut skipped = (n as u64).min(self.remaining);
+        self.remaining -= skipped;
+        while skipped > 0 {
+            let left = self.segments[self.index].length - self.offset;
+            if skipped < left {
+                self.offset += skipped;
+                break;
+            }
+            skipped -= left;
+            self.index += 1;
+            self.offset = 0;
+        }
+        self.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match usize::try_from(self.remaining) {
+            Ok(remaining) => (remaining, Some(remaining)),
+            Err(_) => (usize::MAX, None),
+        }
+    }
+}
+
+/// Majority values compared by shares of withdrawn votes, for candidates with different numbers of votes.
+/// With the same number of votes, the order is the one of `MajorityValue`. A candidate without votes comes last.
+#[derive(Debug, Clone)]
+pub(crate) struct Proportional<G>(pub(crate) MajorityValue<G>);
+
+impl<G: Ord> MajorityValue<G> {
+    /// Function that compares majority values by shares of withdrawn votes, for candidates with different
+    /// numbers of votes, e.g. from two polls of different sizes
+    ///
+    /// # Returns
+    /// * `Ordering`: the order of `cmp` with the same number of votes, the `i`-th of the `n` values standing
+    ///   for the shares of withdrawn votes from `i / n` to `(i + 1) / n` otherwise. Without votes, last.
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::MajorityValue;
+    /// let small_poll = MajorityValue::from_grades(&[2]);
+    /// let large_poll = MajorityValue::from_grades(&[0, 2, 2]);
+    /// // "2" is a prefix of "2, 0, 2", but the whole small poll is graded 2
+    /// assert!(small_poll < large_poll);
+    /// assert!(small_poll.cmp_proportional(&large_poll).is_gt());
+    /// ```
+    pub fn cmp_proportional(&self, other: &Self) -> Ordering {
+        let (n, m) = (self.len(), other.len());
+        if n == m || n == 0 || m == 0 {
+            return n.min(1).cmp(&m.min(1)).then_with(|| self.cmp(other));
+        }
+        let (mut runs, mut other_runs) = (self.runs(), other.runs());
+        let (mut run, mut other_run) = (runs.next(), other_runs.next());
+        // the runs end at the shares `end / n` and `other_end / m`, compared without rounding
+        while let (Some((value, end)), Some((other_value, other_end))) = (run, other_run) {
+            if value != other_value {
+                return value.cmp(other_value);
+            }
+            let (share, other_share) = (end as u128 * m as u128, other_end as u128 * n as u128);
+            if share <= other_share {
+                run = runs.next();
+            }
+            if other_share <= share {
+                other_run = other_runs.next();
+            }
+        }
+        Ordering::Equal
+    }
+}
+
+impl<G: Ord> Ord for Proportional<G> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp_proportional(&other.0)
+    }
+}
+
+impl<G: Ord> PartialOrd for Proportional<G> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<G: Ord> PartialEq for Proportional<G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<G: Ord> Eq for Proportional<G> {}
+
+impl<G: Ord> Ord for MajorityValue<G> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.first_difference(other) {
+            Some((_, a, b)) => a.cmp(b),
+            // a sequence which is a prefix of the other comes first
+            None => self.len().cmp(&other.len()),
+        }
+    }
+}
+
+impl<G: Ord> PartialOrd for MajorityValue<G> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<G: Ord> PartialEq for MajorityValue<G> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<G: Ord> Eq for MajorityValue<G> {}
+
+/// The values written in full, the repeated pairs and runs shortened, e.g. "2, (1, 3) × 40, 0 × 7"
+impl<G: fmt::Display + PartialEq> fmt::Display for MajorityValue<G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, segment) in self.segments.iter().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            let (first, second, length) = (&segment.first, &segment.second, segment.length);
+            match length {
+                1 => write!(f, "{}", first)?,
+                _ if first == second => write!(f, "{} × {}", first, length)?,
+                2 | 3 => write!(f, "{}, {}", first, second)?,
+                _ => write!(f, "({}, {}) × {}", first, second, length / 2)?,
+            }
+            if length > 1 && first != second && length % 2 == 1 {
+                write!(f, ", {}", first)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tally(grades: &[u8]) -> BTreeMap<u8, u32> {
+        let mut tally = BTreeMap::new();
+        for &grade in grades {
+            *tally.entry(grade).or_insert(0) += 1;
+        }
+        tally
+    }
+
+    /// withdraw the lower median one vote at a time, the reference algorithm
+    fn withdraw_medians(grades: &[u8]) -> Vec<u8> {
+        let mut sorted = grades.to_vec();
+        sorted.sort();
+        let mut values = Vec::new();
+        while !sorted.is_empty() {
+            values.push(sorted.remove((sorted.len() - 1) / 2));
+        }
+        values
+    }
+
+    #[test]
+    fn calling_from_tally() {
+        let grades = [0, 0, 3, 0, 2, 0, 3, 1, 2, 3, 3, 3, 3, 3, 2, 1, 7, 8];
+        let result = MajorityValue::from_tally(&tally(&grades));
+        assert_eq!(result.to_vec(), vec![2, 3, 2, 3, 2, 3, 1, 3, 1, 3, 0, 3, 0, 3, 0, 7, 0, 8]);
+        assert_eq!(result.len(), 18);
+        assert_eq!(alloc::format!("{}", result), "(2, 3) × 3, (1, 3) × 2, (0, 3) × 2, 0, 7, 0, 8");
+        assert_eq!(alloc::format!("{}", MajorityValue::from_tally(&tally(&[1, 1, 1, 2, 2]))), "1, (1, 2) × 2");
+        assert_eq!(alloc::format!("{}", MajorityValue::from_tally(&tally(&[4; 9]))), "4 × 9");
+
+        for grades in [&[][..], &[4], &[1, 1, 1], &[0, 5], &[2, 2, 0, 5, 5, 5, 1], &[0, 0, 0, 0, 9, 9]] {
+            assert_eq!(MajorityValue::from_tally(&tally(grades)).to_vec(), withdraw_medians(grades));
+        }
+        assert_eq!(MajorityValue::from_tally(&tally(&[1, 1, 1, 1, 1])).segments.len(), 1);
+
+        // withdrawing the upper median: the vote n / 2 of the n sorted votes left
+        for grades in [&[][..], &[4], &[0, 5], &[2, 2, 0, 5, 5, 5, 1], &[0, 0, 0, 0, 9, 9], &grades] {
+            let mut sorted = grades.to_vec();
+            sorted.sort();
+            let mut expected = Vec::new();
+            while !sorted.is_empty() {
+                expected.push(sorted.remove(sorted.len() / 2));
+            }
+            assert_eq!(MajorityValue::from_tally_with(&tally(grades), MedianConvention::UpperMedian).to_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn calling_cmp() {
+        let polls: [&[u8]; 8] = [&[0, 1, 2, 3], &[3, 2, 1, 0], &[1, 1, 2, 2], &[0, 2, 2, 3],
+                                 &[2, 2, 2, 2], &[0, 0, 3, 3], &[1, 2, 3, 3], &[0, 1, 1, 3]];
+        for a in polls.iter() {
+            for b in polls.iter() {
+                let expected = withdraw_medians(a).cmp(&withdraw_medians(b));
+                let result = MajorityValue::from_tally(&tally(a)).cmp(&MajorityValue::from_tally(&tally(b)));
+                assert_eq!(result, expected, "{:?} vs {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn calling_cmp_proportional() {
+        // the reference: each of the n majority values repeated to fill as many shares as the other candidate has votes
+        let stretched = |grades: &[u8], times: usize| -> Vec<u8> {
+            withdraw_medians(grades).into_iter().flat_map(|grade| core::iter::repeat_n(grade, times)).collect()
+        };
+        let polls: [&[u8]; 8] = [&[0, 1, 2], &[3, 2, 1, 0], &[1, 2], &[0, 2, 2, 3, 3],
+                                 &[2, 2, 2, 2, 2, 2], &[0, 3], &[1, 2, 3, 3, 2, 2, 1], &[2]];
+        for a in polls.iter() {
+            for b in polls.iter() {
+                let expected = stretched(a, b.len()).cmp(&stretched(b, a.len()));
+                let a_values = Proportional(MajorityValue::from_tally(&tally(a)));
+                let b_values = Proportional(MajorityValue::from_tally(&tally(b)));
+                assert_eq!(a_values.cmp(&b_values), expected, "{:?} vs {:?}", a, b);
+            }
+        }
+        let empty = Proportional(MajorityValue::from_tally(&tally(&[])));
+        assert!(empty < Proportional(MajorityValue::from_tally(&tally(&[0]))));
+    }
+
+    #[test]
+    fn calling_into_iter() {
+        let grades = [0, 0, 3, 0, 2, 0, 3, 1, 2, 3, 3, 3, 3, 3, 2, 1, 7, 8];
+        let values = MajorityValue::from_tally(&tally(&grades));
+        let expected = withdraw_medians(&grades);
+        assert_eq!(values.clone().into_iter().collect::<Vec<u8>>(), expected);
+        assert_eq!(values.clone().into_iter().size_hint(), (18, Some(18)));
+
+        for n in 0..20 {
+            assert_eq!(values.clone().into_iter().nth(n), expected.get(n).copied(), "nth({})", n);
+        }
+        let mut iter = values.into_iter();
+        assert_eq!((iter.nth(9), iter.next(), iter.size_hint()), (Some(3), Some(0), (7, Some(7))));
+        assert_eq!(MajorityValue::from_tally(&BTreeMap::from([(5u8, 1u64 << 40)])).into_iter().nth(1 << 39), Some(5));
+    }
+
+    #[test]
+    fn calling_first_difference() {
+        let a = MajorityValue::from_tally(&tally(&[0, 2, 2, 3]));
+        let b = MajorityValue::from_tally(&tally(&[0, 2, 2, 2]));
+        assert_eq!(a.first_difference(&b), Some((3, &3, &2)));
+        assert_eq!(b.first_difference(&a), Some((3, &2, &3)));
+        assert_eq!(a.first_difference(&a), None);
+    }
+}
@@ -0,0 +1,30 @@
+//! # Methods
+//! The ways to rank the candidates of a poll:
+//! * `majority_judgment_ranking`: the majority judgment, with the ranks and the ties
+//! * `majority_judgment_with_options`: the same with another `BuiltinMethod`, a `TieBreaker`,
+//!   a `TieOrder`, a `Participation` or a `MedianConvention`
+//! * `majority_judgment_with_method`: the same with a `RankingMethod` of another crate
+//! * `majority_judgment_weighted` and `majority_judgment_from_tally`: weighted ballots and counted votes
+//! * `majority_values` and `MajorityValue`: the successive median grades of a single candidate, compared
+//!   across polls
+//! * `majority_judgment_batch`: many independent polls at once
+//! * `try_majority_judgment_with_options`, `try_majority_judgment_from_tally` and
+//!   `try_majority_judgment_from_tally_with_options`: a `RankingError` instead of a panic
+//!
+//! `majority_judgment` is kept as a wrapper of `majority_judgment_ranking` returning `(candidate, rank)` tuples.
+#[cfg(feature = "std")]
+pub use crate::batch::{majority_judgment_batch, majority_judgment_batch_with_options};
+#[cfg(feature = "parallel")]
+pub use crate::batch::majority_judgment_batch_parallel;
+#[cfg(feature = "parallel")]
+pub use crate::mj::majority_judgment_parallel;
+pub use crate::checked::{try_majority_judgment_from_tally, try_majority_judgment_from_tally_with_options, try_majority_judgment_with_options, RankingError};
+pub use crate::majority_value::{MajorityValue, MajorityValueIter};
+pub use crate::mj::{
+    majority_judgment, majority_judgment_from_tally, majority_judgment_ranking, majority_judgment_weighted,
+    majority_judgment_winner, majority_judgment_with_method, majority_judgment_with_options, majority_values, rank_subset,
+    top_n,
+};
+pub use crate::options::{BuiltinMethod, MedianConvention, Options, Participation, TieOrder};
+pub use crate::ranking_method::{Average, MajorityGauge, MajorityValues, Merit, RankingMethod, TypicalJudgment, UsualJudgment};
+pub use crate::tie_breaker::{DrawKey, Lexicographic, MeanGrade, RandomTieBreaker, RegistrationOrder, TieBreaker};
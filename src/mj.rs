@@ -2,9 +2,22 @@
 //! These are the functions to calculate the majority judgment of a poll.
 //! All sub-functions are private and are not exposed to the user.
 //! The user only needs to call the majority_judgment function.
-use std::collections::BTreeMap;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use alloc::vec;
+use core::borrow::Borrow;
+use crate::majority_value::{MajorityValue, MajorityValueIter};
+use crate::checked::RankingError;
+use crate::judgment::{median_shares, median_shares_with, total_votes, TOO_MANY_VOTES};
+use crate::options::{GradeValue, MedianConvention, Options, Participation, BuiltinMethod, TieOrder};
+use crate::ranking::{Ranking, TieStatus};
+use crate::ranking_method::{Average, MajorityGauge, MajorityValues, Merit, ProportionalValues, RankingMethod, TypicalJudgment, UsualJudgment};
+use crate::tie_breaker::TieBreaker;
 
 /// Function that checks that all the lengths of the polls are the same otherwise it throws an error
+///
+/// A poll without candidate, or with a single candidate, is always valid.
+///
 /// # Arguments
 /// * `poll_data`: a BTreeMap with the poll data
 ///
@@ -25,8 +38,10 @@ use std::collections::BTreeMap;
 /// poll_data.insert("Chips", vec![0, 3, 2, 3, 4]);
 /// check_poll_length(&poll_data);
 ///
-fn check_poll_length(poll_data: &BTreeMap<String, Vec<u8>>) -> Result<(), &str> {
-    let first_poll_length = poll_data.values().next().unwrap().len();
+pub(crate) fn check_poll_length<K, G>(poll_data: &BTreeMap<K, Vec<G>>) -> Result<(), &str> {
+    let Some(first_poll_length) = poll_data.values().next().map(Vec::len) else {
+        return Ok(());
+    };
     for poll in poll_data.values() {
         if poll.len() != first_poll_length {
             panic!("The polls have different lengths!")
@@ -37,164 +52,758 @@ fn check_poll_length(poll_data: &BTreeMap<String, Vec<u8>>) -> Result<(), &str>
 }
 
 /// Function that calculates the majority judgment of a poll
+///
+/// The grades can be of any type `G` implementing `Ord + Clone`:
+/// `u8`, `i8`, `u16`, or a custom enum deriving `Ord` (the greatest value is the best grade).
+/// The candidates can be of any type `K` implementing `Ord`: their names as `String`,
+/// or identifiers such as `u64` or an enum, without allocating strings.
+///
+/// A poll without candidate gives an empty ranking, a single candidate is ranked first
+/// and candidates without any grade are tied.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+///
+/// # Returns
+/// * `Vec<(&K, usize)>`: a vector of tuple with the candidate and its rank,
+///   tied candidates share the same rank and are listed by increasing key, see `TieOrder`
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::majority_judgment;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert(17u64, vec![0, 2, 3]);
+/// poll_data.insert(42u64, vec![1, 3, 3]);
+/// assert_eq!(majority_judgment(&poll_data), vec![(&42, 0), (&17, 1)]);
+/// ```
+pub fn majority_judgment<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>) -> Vec<(&K, usize)> {
+    majority_judgment_ranking(poll_data).to_vec()
+}
+
+/// Function that finds the winner of a poll without ranking the other candidates
+///
+/// Only the candidates with the best majority grade can win: the others are dominated and pruned
+/// as soon as their median is known, and the majority values are computed for the remaining ones
+/// only. The result is the first candidate of `majority_judgment`, when it is not tied.
+///
 /// # Arguments
-/// * `poll_data`: a BTreeMap<String, Vec<u8>> with the poll data
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
 ///
 /// # Returns
-/// * `Vec<(&String, usize)>`: a vector of tuple with the candidate and its rank
-pub fn majority_judgment(poll_data: &BTreeMap<String, Vec<u8>>) -> Vec<(&String, usize)> {
+/// * `Option<&K>`: the winner, `None` for a poll without candidate or when the best candidates are tied
+///
+/// # Panics
+/// * if the candidates do not have the same number of grades
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::majority_judgment_winner;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![0, 2, 3]);
+/// poll_data.insert("Chips", vec![1, 3, 3]);
+/// poll_data.insert("Soup", vec![0, 0, 1]);
+/// assert_eq!(majority_judgment_winner(&poll_data), Some(&"Chips"));
+///
+/// poll_data.insert("Pasta", vec![3, 1, 3]);
+/// assert_eq!(majority_judgment_winner(&poll_data), None);
+/// ```
+pub fn majority_judgment_winner<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>) -> Option<&K> {
+    let _ = check_poll_length(poll_data);
 
-    let _ = check_poll_length(&poll_data);
+    let mut best_median: Option<G> = None;
+    let mut contenders: Vec<(&K, BTreeMap<G, u64>)> = Vec::new();
+    for (candidate, grades) in poll_data {
+        let tally = compute_frequency_of_grades(grades);
+        let median = median_shares(&tally).map(|shares| shares.median);
+        if !contenders.is_empty() && median < best_median {
+            continue;
+        }
+        if contenders.is_empty() || median > best_median {
+            best_median = median;
+            contenders.clear();
+        }
+        contenders.push((candidate, tally));
+    }
 
-    let mut majority_values = BTreeMap::new();
-    for (item, grades) in poll_data {
-        majority_values.insert(item, compute_majority_values(grades.to_vec()));
+    let mut contenders = contenders.into_iter().map(|(candidate, tally)| (candidate, MajorityValue::from_tally(&tally)));
+    let (mut winner, mut best) = contenders.next()?;
+    let mut tied = false;
+    for (candidate, majority_value) in contenders {
+        match majority_value.cmp(&best) {
+            core::cmp::Ordering::Greater => {
+                (winner, best, tied) = (candidate, majority_value, false);
+            }
+            core::cmp::Ordering::Equal => tied = true,
+            core::cmp::Ordering::Less => {}
+        }
     }
+    (!tied).then_some(winner)
+}
 
-    let mut majority_values_vec: Vec<(&&String, &Vec<u32>)> = majority_values.iter().collect();
-    majority_values_vec.sort_by(|a, b| b.1.partial_cmp(a.1).unwrap());
+/// Function that ranks the best candidates of a poll only, e.g. its podium
+///
+/// The `n` best candidates are selected in linear time with `select_nth_unstable_by`,
+/// only they are sorted, instead of the thousands of candidates of a large field.
+/// The candidates tied with the last one selected share its rank: they are kept too.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `n`: the number of candidates wanted
+///
+/// # Returns
+/// * `Ranking`: the first candidates of `majority_judgment_ranking`, at least `n` of them
+///   unless the poll has fewer candidates, more when the `n`-th one is tied
+///
+/// # Panics
+/// * if the candidates do not have the same number of grades
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::top_n;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![0, 2, 3]);
+/// poll_data.insert("Chips", vec![1, 3, 3]);
+/// poll_data.insert("Soup", vec![0, 0, 1]);
+/// poll_data.insert("Pasta", vec![0, 1, 2]);
+/// assert_eq!(top_n(&poll_data, 2).to_vec(), vec![(&"Chips", 0), (&"Pizza", 1)]);
+/// ```
+pub fn top_n<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>, n: usize) -> Ranking<'_, K, G> {
+    let _ = check_poll_length(poll_data);
 
-    let mut final_ranking:Vec<(&String, usize)> = Vec::new();
-    for (rank, (item, _)) in majority_values_vec.iter().enumerate() {
-        final_ranking.push((item, rank));
+    let mut merits: Vec<(&K, MajorityValue<G>)> = Vec::with_capacity(poll_data.len());
+    let mut shares = BTreeMap::new();
+    for (candidate, grades) in poll_data {
+        let tally = compute_frequency_of_grades(grades);
+        shares.insert(candidate, median_shares(&tally));
+        merits.push((candidate, compute_majority_values_from_tally(tally)));
+    }
+    if n == 0 {
+        merits.clear();
+    } else if n < merits.len() {
+        merits.select_nth_unstable_by(n - 1, |a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        let rest = merits.split_off(n);
+        let last = &merits[n - 1].1;
+        let tied: Vec<_> = rest.into_iter().filter(|(_, merit)| merit == last).collect();
+        merits.extend(tied);
     }
 
-    return final_ranking
+    rank_candidates(merits.into_iter().collect(), poll_data, None, TieOrder::default())
+        .with_majority_grades(|candidate| shares[candidate].clone())
 }
 
-/// This function computes the median grades, when each time withdrawing the median grade.
-/// It provides a simple efficient way to rank candidates even if the initial median grade is the same.
+/// Function that ranks the candidates left when others withdraw, from a ranking already computed
+///
+/// The majority judgment ranks each candidate on its own grades: a withdrawal never changes the order of
+/// the other candidates, so their merits are not computed again. The ranks of the withdrawn candidates are
+/// closed, and a tie left with a single candidate is no longer a tie.
+///
 /// # Arguments
-/// * grades: Vec<u8> all the collected grades unsorted
+/// * `ranking`: the ranking of all the candidates, e.g. from `majority_judgment_ranking`
+/// * `candidates`: the candidates still running
 ///
 /// # Returns
-/// * Vec<u32> The consecutive median grades when withdrawing the previous one
-fn compute_majority_values(grades: Vec<u8>) -> Vec<u32> {
+/// * `Ranking`: the candidates still running, in the order of `ranking`, with their majority grades and metadata
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{majority_judgment_ranking, rank_subset};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![0, 2, 3]);
+/// poll_data.insert("Chips".to_string(), vec![1, 3, 3]);
+/// poll_data.insert("Soup".to_string(), vec![0, 0, 1]);
+///
+/// let ranking = majority_judgment_ranking(&poll_data);
+/// // what if Chips drops out?
+/// let without_chips = rank_subset(&ranking, &["Pizza", "Soup"]);
+/// assert_eq!(without_chips.to_vec(), vec![(&"Pizza".to_string(), 0), (&"Soup".to_string(), 1)]);
+/// ```
+pub fn rank_subset<'a, K, Q, G>(ranking: &Ranking<'a, K, G>, candidates: &[&Q]) -> Ranking<'a, K, G>
+where
+    K: Borrow<Q>,
+    Q: PartialEq + ?Sized,
+    G: Clone,
+{
+    ranking.retain(|candidate| candidates.contains(&candidate.borrow()))
+}
 
-    let tally = compute_frequency_of_grades(grades.clone());
+/// Function that calculates the majority judgment of a poll and reports the ties
+///
+/// Candidates with exactly the same majority values cannot be separated by the majority judgment:
+/// they share the same rank and are marked as `TieStatus::Tied`.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+///
+/// # Returns
+/// * `Ranking`: the candidates from the best to the worst, with their rank, tie status and majority grade
+pub fn majority_judgment_ranking<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>) -> Ranking<'_, K, G> {
+    majority_judgment_with_options(poll_data, &Options::default())
+}
 
-    let keys = tally.keys().collect::<Vec<&u8>>();
-    let mut values = tally.values().collect::<Vec<&u32>>().iter().map(|&x| *x).collect::<Vec<u32>>();
-    let total_votes = grades.len() as u32;
+/// Function that calculates the majority judgment of a poll with the given options
+///
+/// The majority values need the same number of grades for every candidate, unless they are compared by shares
+/// of withdrawn grades with `Participation::Proportional`. The other methods only compare shares of grades:
+/// they also rank candidates with different numbers of grades, e.g. when abstentions are excluded
+/// with `AbstentionPolicy::Exclude`.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `options`: the options of the majority judgment, e.g. the tie breaker
+///
+/// # Returns
+/// * `Ranking`: the candidates from the best to the worst, with their rank and tie status,
+///   and the "none of the above" candidate of the options, see `Ranking::verdict`
+///
+/// # Panics
+/// * if the "none of the above" candidate of the options is not part of the poll data
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{majority_judgment_with_options, Lexicographic, Options};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![0, 2, 3]);
+/// poll_data.insert("Chips".to_string(), vec![3, 2, 0]);
+///
+/// let ranking = majority_judgment_with_options(&poll_data, &Options::new().tie_breaker(Lexicographic));
+/// assert_eq!(ranking.to_vec(), vec![(&"Chips".to_string(), 0), (&"Pizza".to_string(), 1)]);
+/// ```
+pub fn majority_judgment_with_options<'a, K: Ord, G: Ord + Clone>(
+    poll_data: &'a BTreeMap<K, Vec<G>>,
+    options: &Options<G, K>,
+) -> Ranking<'a, K, G> {
 
-    let mut majority_values : Vec<u32> = Vec::new();
+    if options.method == BuiltinMethod::MajorityValues && options.participation == Participation::Equal {
+        let _ = check_poll_length(poll_data);
+    }
 
-    for _ in 0..total_votes {
-        let total: u32 = values.clone().into_iter().sum();
-        let total_f32 = total as f32;
+    majority_judgment_with_method(poll_data, &OptionsMethod::new(options), options)
+}
 
-        let values_f32: Vec<f32> = values.clone().into_iter().map(|x| x as f32).collect();
-        let cumsum: Vec<f32> = values_f32.clone().into_iter().scan(0.0, |sum, val| {
-            *sum += val / total_f32;
-            Some(*sum)
-        }).collect();
+/// Function that ranks a poll with a ranking method of another crate, see `RankingMethod`
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `method`: the ranking method, used instead of `Options::method`
+/// * `options`: the tie breaker, the tie order and the "none of the above" candidate of the ranking,
+///   the options of the built-in methods (`participation` and the grade values) being ignored
+///
+/// # Returns
+/// * `Ranking`: the candidates by decreasing merit, with their rank, tie status and majority grade
+///
+/// # Panics
+/// * if the "none of the above" candidate of the options is not part of the poll data
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{majority_judgment_with_method, Options, UsualJudgment};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![1u8, 2, 2]);
+/// poll_data.insert("Chips", vec![2u8, 2, 3]);
+/// assert_eq!(majority_judgment_with_method(&poll_data, &UsualJudgment, &Options::new()).winners(), vec![&"Chips"]);
+/// ```
+pub fn majority_judgment_with_method<'a, K: Ord, G: Ord + Clone, M: RankingMethod<G>>(
+    poll_data: &'a BTreeMap<K, Vec<G>>,
+    method: &M,
+    options: &Options<G, K>,
+) -> Ranking<'a, K, G> {
+    let mut merits = BTreeMap::new();
+    let mut shares = BTreeMap::new();
+    for (item, grades) in poll_data {
+        let tally = compute_frequency_of_grades(grades);
+        merits.insert(item, method.merit(&tally));
+        shares.insert(item, median_shares_with(&tally, options.median));
+    }
 
+    let ranking = rank_candidates(merits, poll_data, options.tie_breaker.as_deref(), options.tie_order)
+        .with_majority_grades(|candidate| shares[candidate].clone());
+    with_none_of_the_above(ranking, poll_data, options)
+}
 
-        let idx: u32 = median_grade(cumsum);
+/// Function that marks the "none of the above" candidate of the options in a ranking
+///
+/// # Panics
+/// * if the candidate is not part of the poll data
+fn with_none_of_the_above<'a, K: Ord, G>(ranking: Ranking<'a, K, G>, poll_data: &'a BTreeMap<K, Vec<G>>, options: &Options<G, K>) -> Ranking<'a, K, G> {
+    match &options.none_of_the_above {
+        None => ranking,
+        Some(candidate) => {
+            let (candidate, _) = poll_data.get_key_value(candidate).expect("\"none of the above\" must be a candidate of the poll");
+            ranking.with_none_of_the_above(candidate)
+        }
+    }
+}
+
+/// Function that calculates the majority judgment of a poll, evaluating the candidates in parallel
+///
+/// The candidates are split evenly between as many scoped threads as the available parallelism,
+/// each thread computing the merits (e.g. the majority values) of its candidates.
+/// The result is the same as `majority_judgment_with_options`.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `options`: the options of the majority judgment, e.g. the tie breaker
+///
+/// # Returns
+/// * `Ranking`: the candidates from the best to the worst, with their rank and tie status
+#[cfg(feature = "parallel")]
+pub fn majority_judgment_parallel<'a, K: Ord + Sync, G: Ord + Clone + Send + Sync>(
+    poll_data: &'a BTreeMap<K, Vec<G>>,
+    options: &Options<G, K>,
+) -> Ranking<'a, K, G> {
+
+    if options.method == BuiltinMethod::MajorityValues && options.participation == Participation::Equal {
+        let _ = check_poll_length(poll_data);
+    }
+
+    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let candidates: Vec<(&K, &Vec<G>)> = poll_data.iter().collect();
+    let chunk_size = candidates.len().div_ceil(threads).max(1);
+    let method = OptionsMethod::new(options);
+    let method = &method;
+
+    let median = options.median;
+    let evaluated: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = candidates
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || {
+                chunk.iter()
+                    .map(|&(item, grades)| {
+                        let tally = compute_frequency_of_grades(grades);
+                        (item, method.merit(&tally), median_shares_with(&tally, median))
+                    })
+                    .collect::<Vec<_>>()
+            }))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("a candidate evaluation thread panicked"))
+            .collect()
+    });
+    let mut merits = BTreeMap::new();
+    let mut shares = BTreeMap::new();
+    for (item, merit, median) in evaluated {
+        merits.insert(item, merit);
+        shares.insert(item, median);
+    }
+
+    let ranking = rank_candidates(merits, poll_data, options.tie_breaker.as_deref(), options.tie_order)
+        .with_majority_grades(|candidate| shares[candidate].clone());
+    with_none_of_the_above(ranking, poll_data, options)
+}
+
+/// The ranking method chosen by the options, see `Options::method`.
+/// Its merits panic for the mean grade without `grade_value`.
+pub(crate) struct OptionsMethod<G> {
+    method: BuiltinMethod,
+    participation: Participation,
+    grade_value: GradeValue<G>,
+    median: MedianConvention,
+}
+
+impl<G> OptionsMethod<G> {
+    /// The method of the options, with their participation, grade values and median convention
+    pub(crate) fn new<K>(options: &Options<G, K>) -> Self {
+        OptionsMethod { method: options.method, participation: options.participation, grade_value: options.value(), median: options.median }
+    }
 
-        // extra safeguard to prevent panic because no key found at the given index.
-        if let Some(key) = keys.get(idx as usize) {
-            let key_clone = (**key).clone();
-            majority_values.push(key_clone.try_into().unwrap());
-        } else {
-            println!("No key found at index {}", idx);
+    /// A method counted from tallies, e.g. by a `TallyAccumulator`, ignoring the participation
+    #[cfg(feature = "std")]
+    pub(crate) fn from_tally(method: BuiltinMethod, grade_value: GradeValue<G>) -> Self {
+        OptionsMethod { method, participation: Participation::Equal, grade_value, median: MedianConvention::LowerMedian }
+    }
+}
+
+impl<G: Ord + Clone> RankingMethod<G> for OptionsMethod<G> {
+    type Merit = Merit<G>;
+
+    fn merit(&self, tally: &BTreeMap<G, u64>) -> Merit<G> {
+        match (self.method, self.participation) {
+            (BuiltinMethod::MajorityValues, Participation::Proportional) => ProportionalValues.merit_with(tally, self.median),
+            (BuiltinMethod::MajorityValues, Participation::Equal) => MajorityValues.merit_with(tally, self.median),
+            (BuiltinMethod::MajorityGauge, _) => MajorityGauge.merit_with(tally, self.median),
+            (BuiltinMethod::UsualJudgment, _) => UsualJudgment.merit_with(tally, self.median),
+            (BuiltinMethod::TypicalJudgment, _) => TypicalJudgment.merit_with(tally, self.median),
+            (BuiltinMethod::MeanGrade, _) => Average::from_value(self.grade_value)
+                .expect("BuiltinMethod::MeanGrade needs Options::grade_value to average the grades")
+                .merit(tally),
         }
+    }
+}
 
-        // remove median grade of the total count of votes
-        // by changing removing a counted vote in the value vector at index idx
-        values = values.into_iter().enumerate().map(|(i, x)| {
-            if i == idx as usize {
-                x - 1
-            } else {
-                x
-            }
-        }).collect::<Vec<_>>();
+/// Function that calculates the majority judgment of a poll where each ballot carries a weight
+///
+/// The grade given by a ballot counts as many times as the weight of the ballot,
+/// e.g. the number of shares of a shareholder or the number of delegated votes.
+///
+/// The weights are integers on purpose: the votes are counted and compared exactly, so the ranking is the same on
+/// every platform, which `f64` weights would not guarantee. Fractional weights are scaled to integers first,
+/// e.g. shares held to the hundredth counted in hundredths of a share.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, each vector ordered by ballot
+/// * `weights`: the weight of each ballot, in the same order as the grades
+///
+/// # Returns
+/// * `Result<Vec<(&K, usize)>, RankingError>`: a vector of tuple with the candidate and its rank,
+///   tied candidates share the same rank, or `RankingError::DifferentNumbersOfWeights` if the number of weights
+///   differs from the number of grades of a candidate
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{majority_judgment_weighted, RankingError};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![3, 0]);
+/// poll_data.insert("Chips", vec![1, 2]);
+/// assert_eq!(majority_judgment_weighted(&poll_data, &[3, 2]).unwrap()[0], (&"Pizza", 0));
+/// assert!(matches!(majority_judgment_weighted(&poll_data, &[3]), Err(RankingError::DifferentNumbersOfWeights { weights: 1, grades: 2 })));
+/// ```
+pub fn majority_judgment_weighted<'a, K: Ord, G: Ord + Clone>(
+    poll_data: &'a BTreeMap<K, Vec<G>>,
+    weights: &[u32],
+) -> Result<Vec<(&'a K, usize)>, RankingError> {
+
+    if let Some(grades) = poll_data.values().map(Vec::len).find(|&grades| grades != weights.len()) {
+        return Err(RankingError::DifferentNumbersOfWeights { weights: weights.len() as u64, grades: grades as u64 });
+    }
+
+    let mut majority_values = BTreeMap::new();
+    for (item, grades) in poll_data {
+        let tally = compute_weighted_frequency_of_grades(grades, weights);
+        majority_values.insert(item, compute_majority_values_from_tally(tally));
     }
-    return majority_values
+
+    Ok(rank_candidates(majority_values, poll_data, None, TieOrder::default()).to_vec())
 }
 
-/// Function that compute the frequency of each grade in BTreeMap structure
+/// Function that calculates the majority judgment of a poll given as the number of votes of each grade
+///
+/// Many systems store the counts of each grade rather than the ballots: the majority values are
+/// computed from the counts directly, in O(k) for k distinct grades, without expanding them into vectors.
 ///
 /// # Arguments
-/// * `grades`:  Vec<u8> unsorted numbers representing the grades
+/// * `tallies`: for each candidate, the number of votes of each grade, the absent grades counting 0
 ///
 /// # Returns
-/// * BTreeMap<u8, u32>, first is the grade, the second is the number of time, it has been given
+/// * `Vec<(&K, usize)>`: a vector of tuple with the candidate and its rank,
+///   tied candidates share the same rank
+///
+/// # Panics
+/// * if the candidates have different numbers of votes
+/// * if a candidate has more than `u64::MAX` votes, see `try_majority_judgment_from_tally`
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::majority_judgment_from_tally;
+/// let mut tallies = BTreeMap::new();
+/// tallies.insert("Pizza".to_string(), BTreeMap::from([(0, 3_000_000_000u64), (2, 2_000_000_000)]));
+/// tallies.insert("Chips".to_string(), BTreeMap::from([(1, 5_000_000_000u64)]));
+/// assert_eq!(majority_judgment_from_tally(&tallies)[0].0, "Chips");
+/// ```
+pub fn majority_judgment_from_tally<K: Ord, G: Ord + Clone>(tallies: &BTreeMap<K, BTreeMap<G, u64>>) -> Vec<(&K, usize)> {
+    let mut totals = tallies.values().map(|tally| total_votes(tally.values()).expect(TOO_MANY_VOTES));
+    if let Some(first) = totals.next() {
+        if totals.any(|total| total != first) {
+            panic!("The polls have different lengths!")
+        }
+    }
+
+    let majority_values = tallies
+        .iter()
+        .map(|(item, tally)| (item, MajorityValue::from_tally(tally)))
+        .collect();
+    rank_candidates::<K, G, _>(majority_values, &BTreeMap::new(), None, TieOrder::default()).to_vec()
+}
+
+/// Function that sorts the candidates by decreasing merit, e.g. their majority values
+///
+/// # Arguments
+/// * `merits`: a BTreeMap with what is compared for each candidate
+/// * `poll_data`: the grades of each candidate, handed to the tie breaker
+/// * `tie_breaker`: the rule separating candidates with equal merits, if any
+/// * `tie_order`: the order of the candidates which remain tied
 ///
-fn compute_frequency_of_grades(mut grades: Vec<u8>) -> BTreeMap<u8, u32> {
-    let mut tally: BTreeMap<u8, u32> = BTreeMap::new();
+/// # Returns
+/// * `Ranking`: the candidates from the best to the worst, equal merits being tied
+pub(crate) fn rank_candidates<'a, K: Ord, G, M: Ord>(
+    merits: BTreeMap<&'a K, M>,
+    poll_data: &BTreeMap<K, Vec<G>>,
+    tie_breaker: Option<&dyn TieBreaker<G, K>>,
+    tie_order: TieOrder,
+) -> Ranking<'a, K, G> {
 
-    grades.sort();
-    let grades_group = group_by(grades);
+    trace_span!("sort");
+    let mut merits_vec: Vec<(&K, M)> = merits.into_iter().collect();
+    merits_vec.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| tie_order.arrange(a.0, b.0)));
 
-    for grades in grades_group.iter() {
-        tally.insert( grades[0]
-                      , grades.len().try_into().unwrap());
+    let mut groups: Vec<Vec<&K>> = Vec::new();
+    for (index, (item, merit)) in merits_vec.iter().enumerate() {
+        match groups.last_mut() {
+            Some(group) if index > 0 && merits_vec[index - 1].1 == *merit => group.push(item),
+            _ => groups.push(vec![item]),
+        }
     }
-    return tally
+
+    let mut tiers = Vec::new();
+    for group in groups {
+        match tie_breaker {
+            _ if group.len() == 1 => tiers.push((group, TieStatus::Unique)),
+            None => tiers.push((group, TieStatus::Tied)),
+            Some(tie_breaker) => tiers.extend(break_tie(group, poll_data, tie_breaker, tie_order)),
+        }
+    }
+    trace_event!("sort", "{} candidates in {} ranks", merits_vec.len(), tiers.len());
+
+    Ranking::from_groups(tiers)
+}
+
+/// Function that separates candidates with equal majority values using a tie breaker
+///
+/// # Arguments
+/// * `group`: the tied candidates
+/// * `poll_data`: the grades of each candidate, no grade for a candidate missing from it
+/// * `tie_breaker`: the rule separating the candidates
+/// * `tie_order`: the order of the candidates which remain tied
+///
+/// # Returns
+/// * `Vec<(Vec<&K>, TieStatus)>`: the candidates grouped by rank, from the best to the worst.
+///   Candidates the tie breaker cannot separate either remain tied.
+fn break_tie<'a, K: Ord, G>(
+    mut group: Vec<&'a K>,
+    poll_data: &BTreeMap<K, Vec<G>>,
+    tie_breaker: &dyn TieBreaker<G, K>,
+    tie_order: TieOrder,
+) -> Vec<(Vec<&'a K>, TieStatus)> {
+    let grades = |candidate: &K| poll_data.get(candidate).map_or(&[][..], Vec::as_slice);
+    let compare = |a: &K, b: &K| tie_breaker.compare(a, grades(a), b, grades(b));
+    group.sort_by(|a, b| compare(b, a).then_with(|| tie_order.arrange(*a, *b)));
+
+    let mut tiers: Vec<Vec<&K>> = Vec::new();
+    for item in group {
+        match tiers.last_mut() {
+            Some(tier) if compare(tier[0], item).is_eq() => tier.push(item),
+            _ => tiers.push(vec![item]),
+        }
+    }
+
+    tiers
+        .into_iter()
+        .map(|tier| {
+            let tie = if tier.len() > 1 { TieStatus::Tied } else { TieStatus::Broken };
+            (tier, tie)
+        })
+        .collect()
+}
+
+/// Function that computes the successive median grades of a candidate, withdrawing the median grade one vote at a time
+///
+/// The grades are counted at once, then the median grades are produced one by one: comparing two candidates
+/// can stop as soon as they differ, instead of building the whole sequence for each candidate.
+///
+/// # Arguments
+/// * `grades`: the grades of the candidate, unsorted
+///
+/// # Returns
+/// * `MajorityValueIter<G>`: the majority values, as many as the grades
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::majority_values;
+/// let pizza = majority_values(&[0, 2, 2, 3]);
+/// let chips = majority_values(&[0, 2, 2, 2]);
+///
+/// // the first round where the median grades differ
+/// let round = pizza.zip(chips).position(|(pizza, chips)| pizza != chips);
+/// assert_eq!(round, Some(3));
+/// ```
+pub fn majority_values<G: Ord + Clone>(grades: &[G]) -> MajorityValueIter<G> {
+    compute_majority_values(grades).into_iter()
+}
+
+/// This function computes the median grades, when each time withdrawing the median grade.
+/// It provides a simple efficient way to rank candidates even if the initial median grade is the same.
+/// # Arguments
+/// * grades: &[G] all the collected grades unsorted
+///
+/// # Returns
+/// * MajorityValue<G> The consecutive median grades when withdrawing the previous one
+pub(crate) fn compute_majority_values<G: Ord + Clone>(grades: &[G]) -> MajorityValue<G> {
+    compute_majority_values_from_tally(compute_frequency_of_grades(grades))
+}
+
+/// This function computes the median grades from the frequency of each grade,
+/// when each time withdrawing one vote of the median grade.
+/// The votes are not withdrawn one at a time: the sequence is built from the runs of each grade,
+/// so it takes O(k) for k distinct grades whatever the number of votes.
+/// # Arguments
+/// * tally: BTreeMap<G, u64> the number of votes (or total weight) of each grade
+///
+/// # Returns
+/// * MajorityValue<G> The consecutive median grades when withdrawing the previous one
+fn compute_majority_values_from_tally<G: Ord + Clone>(tally: BTreeMap<G, u64>) -> MajorityValue<G> {
+    MajorityValue::from_tally(&tally)
 }
-/// Function that group the sorted vector in to a vector of sub vectors
-/// I couldn't replicate the group_by function of python, so I reimplemented an equivalent
+
+/// Function that compute the frequency of each grade in BTreeMap structure
+///
+/// The tally is sparse: only the grades given appear, so a scale of 0..=100 or a `u32` scale
+/// costs nothing more than a scale of six grades. Counting takes O(n log k) for n grades and
+/// k distinct grades, without sorting the grades: only the k distinct grades are cloned.
 ///
 /// # Arguments
-/// * `vector`:  Vec<T> of sorted number
+/// * `grades`:  &[G] unsorted grades
 ///
 /// # Returns
-/// * Vec<Vec<T>> vector of vectors, each vector contains the number
+/// * BTreeMap<G, u64>, first is the grade, the second is the number of time, it has been given
 ///
-fn group_by<T: PartialEq + Clone>(vector: Vec<T>) -> Vec<Vec<T>> {
-    let mut result: Vec<Vec<T>> = Vec::new();
+pub(crate) fn compute_frequency_of_grades<G: Ord + Clone>(grades: &[G]) -> BTreeMap<G, u64> {
+    trace_span!("tally");
+    let mut tally: BTreeMap<G, u64> = BTreeMap::new();
 
-    for item in vector.iter() {
-        if let Some(group) = result.last_mut() {
-            if group[0] == *item {
-                group.push(item.clone());
-                continue;
+    for grade in grades {
+        match tally.get_mut(grade) {
+            Some(count) => *count += 1,
+            None => {
+                tally.insert(grade.clone(), 1);
             }
         }
-
-        result.push(vec![item.clone()]);
     }
+    trace_event!("tally", "{} grades, {} distinct grades", grades.len(), tally.len());
+    tally
+}
+/// Function that compute the total weight given to each grade in BTreeMap structure
+///
+/// # Arguments
+/// * `grades`:  &[G] unsorted grades, one per ballot
+/// * `weights`: &[u32] the weight of each ballot
+///
+/// # Returns
+/// * BTreeMap<G, u64>, first is the grade, the second is the total weight of the ballots giving it
+///
+pub(crate) fn compute_weighted_frequency_of_grades<G: Ord + Clone>(grades: &[G], weights: &[u32]) -> BTreeMap<G, u64> {
+    trace_span!("tally");
+    let mut tally: BTreeMap<G, u64> = BTreeMap::new();
 
-    result
+    for (grade, &weight) in grades.iter().zip(weights.iter()) {
+        if weight > 0 {
+            *tally.entry(grade.clone()).or_insert(0) += weight as u64;
+        }
+    }
+    tally
 }
 
-/// Evaluate the median grade from a cumulative sum of grades
+/// Evaluate the median grade from the cumulative counts of votes
 /// # Arguments
-/// * `cumsum_vec`:  Vec<f32> of cumulative sum of grades
+/// * `cumsum_vec`:  &[u64] cumulative counts of votes, grade by grade from the worst to the best
+/// * `convention`: which middle grade is the median of an even number of votes
 ///
 /// # Returns
 /// * u32, the index of the median grade
 ///
 /// # Note
 /// - This is not exactly the median grade, but the index of the median grade
-///     if the number of element is even, it will return the index  (n/2 - 1)  and not the value of the median grade
-/// - Plus, it is found based on a cumulative sum of grades,
-///     so we always try to find the 0.5 value to return the median grade index
-fn median_grade(cumsum_vec: Vec<f32>) -> u32 {
-    // too strict when sometimes I get a 1.000001
-    // verify the last element is a 1
-    // if cumsum_vec.last() != Some(&1.0) {
-    //     panic!("The last element of the cumulative sum vector is not 1.0. \
-    //     Please normalize the vector before calling the function fn median_grade.")
-    // }
-    // verify all element are positive
-    if cumsum_vec.iter().any(|&x| x < 0.0) {
-        panic!("The cumulative sum vector contains negative values. \
-        Please make sure all values are positive before calling the function fn median_grade.")
-    }
+///   if the number of element is even, it will return the index of the vote (n/2 - 1) for the lower median
+///   and of the vote n/2 for the upper median, not the value of the median grade
+/// - The last cumulative count is the total number of votes. The lower median is the first grade whose
+///   cumulative count reaches half of it, the upper median the first one exceeding half of it: both are the
+///   same for an odd total. Everything is computed on integers, so the result is exact.
+pub(crate) fn median_grade(cumsum_vec: &[u64], convention: MedianConvention) -> u32 {
+    let total = match cumsum_vec.last() {
+        Some(&total) => total,
+        None => return 0,
+    };
 
     for (idx, &val) in cumsum_vec.iter().enumerate() {
-        if val >= 0.5 {
-            return idx.try_into().unwrap()
+        let reached = match convention {
+            // `val` is at most `total`: compared to the votes left without doubling it
+            MedianConvention::LowerMedian => val >= total - val,
+            MedianConvention::UpperMedian => val > total - val,
+        };
+        if reached {
+            return idx as u32
         }
     }
-    return cumsum_vec.len() as u32 - 1u32
+    cumsum_vec.len() as u32 - 1u32
 }
 
 
+#[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tie_breaker::{splitmix64, Lexicographic, MeanGrade, RegistrationOrder};
+
+    #[test]
+    fn calling_majority_judgment_winner() {
+        let mut poll_data: BTreeMap<u64, Vec<u8>> = BTreeMap::new();
+        assert_eq!(majority_judgment_winner(&poll_data), None);
+        poll_data.insert(0, vec![]);
+        assert_eq!(majority_judgment_winner(&poll_data), Some(&0));
+        poll_data.insert(1, vec![]);
+        assert_eq!(majority_judgment_winner(&poll_data), None);
+
+        for seed in 0..200u64 {
+            let poll_data: BTreeMap<u64, Vec<u8>> = (0..6)
+                .map(|candidate| {
+                    let grades = (0..9).map(|ballot| (splitmix64(seed * 100 + candidate * 10 + ballot) % 4) as u8);
+                    (candidate, grades.collect())
+                })
+                .collect();
+            let ranking = majority_judgment_ranking(&poll_data);
+            let expected = match ranking.iter().next() {
+                Some(first) if first.tie == TieStatus::Unique => Some(first.candidate),
+                _ => None,
+            };
+            assert_eq!(majority_judgment_winner(&poll_data), expected, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn calling_top_n() {
+        for seed in 0..100u64 {
+            let poll_data: BTreeMap<u64, Vec<u8>> = (0..12)
+                .map(|candidate| {
+                    let grades = (0..5).map(|ballot| (splitmix64(seed * 100 + candidate * 10 + ballot) % 3) as u8);
+                    (candidate, grades.collect())
+                })
+                .collect();
+            let ranking = majority_judgment_ranking(&poll_data);
+            for n in [0, 1, 3, 12, 20] {
+                let expected: Vec<_> = match n {
+                    0 => Vec::new(),
+                    _ => {
+                        let last_rank = ranking.candidates()[n.min(12) - 1].rank;
+                        ranking.iter().filter(|candidate| candidate.rank <= last_rank).cloned().collect()
+                    }
+                };
+                assert_eq!(top_n(&poll_data, n).candidates(), expected.as_slice(), "seed {} n {}", seed, n);
+            }
+        }
+    }
+
+    #[test]
+    fn calling_rank_subset() {
+        for seed in 0..50u64 {
+            let poll_data: BTreeMap<u64, Vec<u8>> = (0..6)
+                .map(|candidate| (candidate, (0..4).map(|ballot| (splitmix64(seed * 100 + candidate * 10 + ballot) % 3) as u8).collect()))
+                .collect();
+            let ranking = majority_judgment_ranking(&poll_data);
+            let running: Vec<&u64> = poll_data.keys().filter(|&&candidate| splitmix64(seed + candidate).is_multiple_of(2)).collect();
+            let subset: BTreeMap<u64, Vec<u8>> = running.iter().map(|&&candidate| (candidate, poll_data[&candidate].clone())).collect();
+
+            let summary = |ranking: &Ranking<'_, u64, u8>| -> Vec<_> {
+                ranking.iter().map(|ranked| (*ranked.candidate, ranked.rank, ranked.tie, ranked.majority_grade)).collect()
+            };
+            assert_eq!(summary(&rank_subset(&ranking, &running)), summary(&majority_judgment_ranking(&subset)), "seed {}", seed);
+        }
+    }
 
     #[test]
     fn calling_check_poll_length() {
@@ -222,17 +831,337 @@ mod tests {
                  (&"Pizza".to_string(), 3)]);
     }
 
+    #[test]
+    fn calling_majority_judgment_with_ties() {
+        let mut poll_data: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![0, 2, 1, 3]);
+        poll_data.insert("Chips".to_string(), vec![3, 1, 2, 0]);
+        poll_data.insert("Pasta".to_string(), vec![0, 1, 1, 1]);
+        poll_data.insert("Bread".to_string(), vec![3, 3, 3, 3]);
+
+        assert_eq!(
+            majority_judgment(&poll_data),
+            vec![(&"Bread".to_string(), 0),
+                 (&"Chips".to_string(), 1),
+                 (&"Pizza".to_string(), 1),
+                 (&"Pasta".to_string(), 3)]);
+
+        let ranking = majority_judgment_ranking(&poll_data);
+        assert!(ranking.has_ties());
+        assert_eq!(ranking.ties(), vec![vec![&"Chips".to_string(), &"Pizza".to_string()]]);
+        assert_eq!(ranking.candidates()[1].tie, TieStatus::Tied);
+        assert_eq!(ranking.candidates()[3].tie, TieStatus::Unique);
+
+        let ranking = majority_judgment_with_options(&poll_data, &Options::new().tie_order(TieOrder::Descending));
+        assert_eq!(ranking.ties(), vec![vec![&"Pizza".to_string(), &"Chips".to_string()]]);
+        assert_eq!(ranking.candidates()[1].rank, ranking.candidates()[2].rank);
+
+        // the mean grade cannot separate Pizza and Chips either, they stay in the tie order
+        let options = Options::new().tie_breaker(MeanGrade).tie_order(TieOrder::Descending);
+        assert_eq!(majority_judgment_with_options(&poll_data, &options), ranking);
+    }
+
+    #[test]
+    fn calling_majority_judgment_with_options() {
+        let mut poll_data: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![0, 2, 1, 3]);
+        poll_data.insert("Chips".to_string(), vec![3, 1, 2, 0]);
+        poll_data.insert("Pasta".to_string(), vec![0, 1, 1, 1]);
+        poll_data.insert("Bread".to_string(), vec![1, 0, 0, 1]);
+
+        let ranking = majority_judgment_with_options(
+            &poll_data,
+            &Options::new().tie_breaker(RegistrationOrder::new(["Pizza", "Pasta", "Chips"])));
+        assert_eq!(
+            ranking.to_vec(),
+            vec![(&"Pizza".to_string(), 0),
+                 (&"Chips".to_string(), 1),
+                 (&"Pasta".to_string(), 2),
+                 (&"Bread".to_string(), 3)]);
+        assert_eq!(ranking.candidates()[0].tie, TieStatus::Broken);
+        assert!(!ranking.has_ties());
+
+        // the mean grade cannot separate Pizza and Chips
+        let ranking = majority_judgment_with_options(&poll_data, &Options::new().tie_breaker(MeanGrade));
+        assert_eq!(ranking.ties(), vec![vec![&"Chips".to_string(), &"Pizza".to_string()]]);
+        assert_eq!(ranking.candidates()[2].tie, TieStatus::Unique);
+    }
+
+    #[test]
+    fn calling_majority_judgment_on_edge_cases() {
+        let methods = [BuiltinMethod::MajorityValues, BuiltinMethod::MajorityGauge, BuiltinMethod::UsualJudgment,
+                       BuiltinMethod::TypicalJudgment, BuiltinMethod::MeanGrade];
+        // the ranking of every method, as owned tuples
+        let rank = |poll_data: &BTreeMap<String, Vec<u8>>| -> Vec<Vec<(String, usize)>> {
+            methods
+                .iter()
+                .map(|&method| {
+                    let options = Options::new().method(method).grade_value(|&grade| grade as f64);
+                    let ranking = majority_judgment_with_options(poll_data, &options);
+                    ranking.iter().map(|ranked| (ranked.candidate.clone(), ranked.rank)).collect()
+                })
+                .collect()
+        };
+
+        // no candidate: an empty ranking
+        let mut poll_data: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        assert!(rank(&poll_data).iter().all(Vec::is_empty));
+        assert!(majority_judgment_weighted(&poll_data, &[]).unwrap().is_empty());
+        assert!(majority_judgment_from_tally(&BTreeMap::<String, BTreeMap<u8, u64>>::new()).is_empty());
+        assert!(majority_judgment_ranking(&poll_data).winners().is_empty());
+
+        // a single candidate wins, with or without grades
+        poll_data.insert("Pizza".to_string(), vec![]);
+        assert!(rank(&poll_data).iter().all(|ranking| ranking == &vec![("Pizza".to_string(), 0)]));
+        poll_data.insert("Pizza".to_string(), vec![2, 0]);
+        assert_eq!(majority_judgment(&poll_data), vec![(&"Pizza".to_string(), 0)]);
+
+        // candidates without grades are all tied
+        poll_data.insert("Pizza".to_string(), vec![]);
+        poll_data.insert("Chips".to_string(), vec![]);
+        let expected = vec![("Chips".to_string(), 0), ("Pizza".to_string(), 0)];
+        assert!(rank(&poll_data).iter().all(|ranking| ranking == &expected));
+        assert_eq!(majority_judgment_weighted(&poll_data, &[]).unwrap(), majority_judgment(&poll_data));
+        assert!(majority_judgment_ranking(&poll_data).has_ties());
+    }
+
+    #[test]
+    fn calling_majority_judgment_with_proportional_participation() {
+        let mut poll_data: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![0, 2, 1, 3]);
+        poll_data.insert("Chips".to_string(), vec![3, 1, 2, 0]);
+        poll_data.insert("Pasta".to_string(), vec![0, 1, 1, 1]);
+        let options = Options::new().participation(Participation::Proportional);
+        let ranking = majority_judgment_with_options(&poll_data, &options);
+        assert_eq!(ranking, majority_judgment_ranking(&poll_data));
+
+        // each grade given twice: the same shares of grades, the same majority values by shares
+        let twice: Vec<u8> = poll_data["Pasta"].iter().flat_map(|&grade| [grade, grade]).collect();
+        poll_data.insert("Pasta".to_string(), twice);
+        poll_data.insert("Bread".to_string(), vec![]);
+        let ranking = majority_judgment_with_options(&poll_data, &options).to_vec();
+        assert_eq!(
+            ranking,
+            vec![(&"Chips".to_string(), 0),
+                 (&"Pizza".to_string(), 0),
+                 (&"Pasta".to_string(), 2),
+                 (&"Bread".to_string(), 3)]);
+    }
+
+    #[test]
+    fn calling_majority_judgment_with_methods() {
+        let mut poll_data: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![0, 2, 2, 2, 2, 2, 3, 3, 3, 3]);
+        poll_data.insert("Chips".to_string(), vec![1, 1, 1, 1, 2, 3, 3, 3, 3, 3]);
+        poll_data.insert("Pasta".to_string(), vec![0, 0, 0, 1, 1, 1, 1, 3, 3, 3]);
+
+        let ranking = |method| majority_judgment_with_options(&poll_data, &Options::new().method(method)).to_vec();
+        assert_eq!(
+            ranking(BuiltinMethod::MajorityValues),
+            vec![(&"Chips".to_string(), 0),
+                 (&"Pizza".to_string(), 1),
+                 (&"Pasta".to_string(), 2)]);
+        assert_eq!(
+            ranking(BuiltinMethod::MajorityGauge),
+            vec![(&"Chips".to_string(), 0),
+                 (&"Pizza".to_string(), 1),
+                 (&"Pasta".to_string(), 2)]);
+        assert_eq!(
+            ranking(BuiltinMethod::UsualJudgment),
+            vec![(&"Chips".to_string(), 0),
+                 (&"Pizza".to_string(), 1),
+                 (&"Pasta".to_string(), 2)]);
+        assert_eq!(
+            ranking(BuiltinMethod::TypicalJudgment),
+            vec![(&"Pizza".to_string(), 0),
+                 (&"Chips".to_string(), 1),
+                 (&"Pasta".to_string(), 2)]);
+
+        // 2.2 for Pizza, 2.1 for Chips, 1.3 for Pasta
+        assert_eq!(
+            majority_judgment_with_options(&poll_data, &Options::new().mean_grade()).to_vec(),
+            vec![(&"Pizza".to_string(), 0),
+                 (&"Chips".to_string(), 1),
+                 (&"Pasta".to_string(), 2)]);
+
+        assert_eq!(
+            majority_judgment_with_options(&poll_data, &Options::new().exact_mean_grade()).to_vec(),
+            majority_judgment_with_options(&poll_data, &Options::new().mean_grade()).to_vec());
+
+        // 0.1 + 0.5 and 0.3 + 0.3 differ in floating point, not as fractions
+        let mut tenths: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        tenths.insert("Pizza".to_string(), vec![1, 5]);
+        tenths.insert("Chips".to_string(), vec![3, 3]);
+        let float = Options::new().method(BuiltinMethod::MeanGrade).grade_value(|&grade| grade as f64 * 0.1);
+        assert_eq!(majority_judgment_with_options(&tenths, &float).to_vec()[1], (&"Pizza".to_string(), 1));
+        let exact = Options::new().method(BuiltinMethod::MeanGrade).exact_grade_value(|&grade| grade as i64);
+        assert_eq!(majority_judgment_with_options(&tenths, &exact).to_vec()[1], (&"Pizza".to_string(), 0));
+        let both = exact.grade_value(|&grade| grade as f64 * 0.1);
+        assert!(majority_judgment_with_options(&tenths, &both).has_ties());
+
+        // the shares do not need the same number of grades, e.g. with excluded abstentions
+        poll_data.insert("Pasta".to_string(), vec![3, 3, 3, 3]);
+        let options = Options::new().method(BuiltinMethod::MajorityGauge);
+        assert_eq!(majority_judgment_with_options(&poll_data, &options).to_vec()[0], (&"Pasta".to_string(), 0));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn calling_majority_judgment_parallel() {
+        let mut poll_data: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        for candidate in 0..50u8 {
+            let grades = (0..20u32).map(|voter| ((voter * 7 + candidate as u32 * 3) % 6) as u8).collect();
+            poll_data.insert(format!("Candidate {}", candidate), grades);
+        }
+
+        for method in [BuiltinMethod::MajorityValues, BuiltinMethod::MajorityGauge, BuiltinMethod::MeanGrade] {
+            let options = Options::new().method(method).grade_value(|grade| *grade as f64);
+            assert_eq!(
+                majority_judgment_parallel(&poll_data, &options),
+                majority_judgment_with_options(&poll_data, &options));
+        }
+    }
+
+    #[test]
+    fn calling_majority_judgment_with_custom_grades() {
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+        enum Mention {
+            Reject,
+            Poor,
+            Good,
+            Excellent,
+        }
+        use Mention::*;
+
+        let mut poll_data: BTreeMap<String, Vec<Mention>> = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![Reject, Reject, Excellent, Reject, Good]);
+        poll_data.insert("Chips".to_string(), vec![Reject, Poor, Good, Good, Excellent]);
+        poll_data.insert("Pasta".to_string(), vec![Poor, Poor, Reject, Good, Poor]);
+
+        let result = majority_judgment(&poll_data);
+        assert_eq!(
+            result,
+            vec![(&"Chips".to_string(), 0),
+                 (&"Pasta".to_string(), 1),
+                 (&"Pizza".to_string(), 2)]);
+    }
+
+    #[test]
+    fn calling_majority_judgment_with_candidate_ids() {
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        enum Dish { Pizza, Chips, Pasta }
+
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert(Dish::Pizza, vec![0, 2, 3]);
+        poll_data.insert(Dish::Chips, vec![1, 3, 3]);
+        poll_data.insert(Dish::Pasta, vec![3, 2, 0]);
+        assert_eq!(majority_judgment(&poll_data), vec![(&Dish::Chips, 0), (&Dish::Pizza, 1), (&Dish::Pasta, 1)]);
+
+        let options = Options::new().tie_breaker(RegistrationOrder::new([Dish::Pasta, Dish::Pizza]));
+        assert_eq!(majority_judgment_with_options(&poll_data, &options).to_vec(),
+                   vec![(&Dish::Chips, 0), (&Dish::Pasta, 1), (&Dish::Pizza, 2)]);
+        let options = Options::new().tie_breaker(Lexicographic);
+        assert_eq!(majority_judgment_with_options(&poll_data, &options).winners(), vec![&Dish::Chips]);
+    }
+
+    #[test]
+    fn calling_majority_judgment_with_wide_grades() {
+        // a score out of 100
+        let mut poll_data: BTreeMap<String, Vec<u16>> = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![12, 57, 99, 63, 40]);
+        poll_data.insert("Chips".to_string(), vec![58, 58, 2, 100, 61]);
+        assert_eq!(majority_judgment(&poll_data), vec![(&"Chips".to_string(), 0), (&"Pizza".to_string(), 1)]);
+
+        // a handful of grades spread over the whole u32 range
+        let mut poll_data: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), [0, u32::MAX, 1_000_000].repeat(1000));
+        poll_data.insert("Chips".to_string(), [999_999, 1_000_000, 1_000_001].repeat(1000));
+        assert_eq!(compute_frequency_of_grades(&poll_data["Pizza"]).len(), 3);
+        assert_eq!(majority_judgment(&poll_data), vec![(&"Chips".to_string(), 0), (&"Pizza".to_string(), 1)]);
+    }
+
+    #[test]
+    fn calling_majority_judgment_with_signed_grades() {
+        let mut poll_data: BTreeMap<String, Vec<i8>> = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![-2, -2, 1, -1, 2]);
+        poll_data.insert("Chips".to_string(), vec![-1, 0, 0, 1, 2]);
+
+        let result = majority_judgment(&poll_data);
+        assert_eq!(
+            result,
+            vec![(&"Chips".to_string(), 0),
+                 (&"Pizza".to_string(), 1)]);
+    }
+
+    #[test]
+    fn calling_majority_judgment_weighted() {
+        let mut poll_data: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![3, 0, 0]);
+        poll_data.insert("Chips".to_string(), vec![1, 2, 2]);
+
+        assert_eq!(
+            majority_judgment_weighted(&poll_data, &[1, 1, 1]).unwrap(),
+            majority_judgment(&poll_data));
+        assert_eq!(
+            majority_judgment_weighted(&poll_data, &[5, 1, 1]).unwrap(),
+            vec![(&"Pizza".to_string(), 0),
+                 (&"Chips".to_string(), 1)]);
+        let error = majority_judgment_weighted(&poll_data, &[5, 1]).unwrap_err();
+        assert_eq!(error, RankingError::DifferentNumbersOfWeights { weights: 2, grades: 3 });
+        assert_eq!(error.to_string(), "2 weights are given for 3 grades");
+    }
+
+    #[test]
+    fn calling_majority_judgment_from_tally() {
+        let mut poll_data: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![0, 0, 3, 0, 2, 0, 3, 1, 2, 3]);
+        poll_data.insert("Chips".to_string(), vec![0, 1, 0, 2, 1, 2, 2, 3, 2, 3]);
+        poll_data.insert("Pasta".to_string(), vec![0, 1, 0, 1, 2, 1, 3, 2, 3, 3]);
+        poll_data.insert("Bread".to_string(), vec![0, 1, 2, 1, 1, 2, 1, 2, 2, 3]);
+
+        let tallies: BTreeMap<String, BTreeMap<u8, u64>> = poll_data
+            .iter()
+            .map(|(candidate, grades)| (candidate.clone(), compute_frequency_of_grades(grades)))
+            .collect();
+        assert_eq!(majority_judgment_from_tally(&tallies), majority_judgment(&poll_data));
+
+        // far more votes than a vector could hold
+        let mut tallies = BTreeMap::new();
+        tallies.insert("Pizza", BTreeMap::from([(0, 1u64 << 40), (3, (1 << 40) + 1)]));
+        tallies.insert("Chips", BTreeMap::from([(2, (1u64 << 41) + 1)]));
+        assert_eq!(majority_judgment_from_tally(&tallies), vec![(&"Pizza", 0), (&"Chips", 1)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn calling_majority_judgment_from_tally_with_different_totals() {
+        let mut tallies = BTreeMap::new();
+        tallies.insert("Pizza", BTreeMap::from([(0, 2u64)]));
+        tallies.insert("Chips", BTreeMap::from([(1, 3u64)]));
+        majority_judgment_from_tally(&tallies);
+    }
+
+    #[test]
+    fn calling_compute_weighted_frequency_of_grades() {
+        let grades = vec![0, 3, 0, 2, 3];
+        let result = compute_weighted_frequency_of_grades(&grades, &[2, 1, 4, 0, 3]);
+        let mut expected = BTreeMap::new();
+        expected.insert(0, 6);
+        expected.insert(3, 4);
+        assert_eq!(result, expected);
+    }
+
     #[test]
     fn calling_compute_majority_values() {
         let grades = vec![0, 0, 3, 0, 2, 0, 3, 1, 2, 3, 3, 3, 3, 3, 2, 1, 7 ,8];
-        let result = compute_majority_values(grades);
+        let result = compute_majority_values(&grades).to_vec();
         assert_eq!(result, vec![2, 3, 2, 3, 2, 3, 1, 3, 1, 3, 0, 3, 0, 3, 0, 7, 0, 8]);
     }
 
     #[test]
     fn calling_compute_frequency_of_grades() {
         let grades = vec![0, 0, 3, 0, 2, 0, 3, 1, 2, 3, 3, 3, 3, 3, 2, 1, 7 ,8];
-        let result = compute_frequency_of_grades(grades);
+        let result = compute_frequency_of_grades(&grades);
         let mut expected = BTreeMap::new();
         expected.insert(0, 4);
         expected.insert(1, 2);
@@ -243,26 +1172,30 @@ mod tests {
         assert_eq!(result, expected);
     }
 
-    #[test]
-    fn calling_group_by() {
-        let grades = vec![0, 0, 0, 0, 1, 1, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 7, 8];
-        let result = group_by(grades);
-        let expected = vec![vec![0, 0, 0, 0], vec![1, 1], vec![2, 2, 2], vec![3, 3, 3, 3, 3, 3, 3], vec![7], vec![8]];
-        assert_eq!(result, expected);
-    }
-
     #[test]
     fn calling_median_grade() {
-        let cumsum_vec = vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.51, 0.52, 0.6, 0.7, 0.8, 0.9, 1.0];
-        let result = median_grade(cumsum_vec);
+        let cumsum_vec = vec![0, 10, 20, 30, 40, 50, 51, 52, 60, 70, 80, 90, 100];
+        let result = median_grade(&cumsum_vec, MedianConvention::LowerMedian);
         assert_eq!(result, 5);
 
-        let cumsum_vec = vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.51, 0.52, 0.6, 0.7, 0.8, 0.9, 0.99, 1.0];
-        let result = median_grade(cumsum_vec);
+        let cumsum_vec = vec![0, 10, 20, 30, 40, 50, 51, 52, 60, 70, 80, 90, 99, 100];
+        let result = median_grade(&cumsum_vec, MedianConvention::LowerMedian);
         assert_eq!(result, 5);
 
-        let cumsum_vec = vec![0.0, 0.1, 0.2, 0.3, 0.4, 0.41, 0.43, 0.45, 0.5, 1.0];
-        let result = median_grade(cumsum_vec);
+        let cumsum_vec = vec![0, 10, 20, 30, 40, 41, 43, 45, 50, 100];
+        let result = median_grade(&cumsum_vec, MedianConvention::LowerMedian);
         assert_eq!(result, 8);
+
+        // odd totals: the median vote is the 2nd of 3, the 4th of 7
+        assert_eq!(median_grade(&[1, 2, 3], MedianConvention::LowerMedian), 1);
+        assert_eq!(median_grade(&[3, 3, 7], MedianConvention::UpperMedian), 2);
+        assert_eq!(median_grade(&[0, 4, 7], MedianConvention::LowerMedian), 1);
+
+        // even totals: the 2nd or the 3rd vote of 4, exactly at the boundary of a grade
+        let lower_and_upper = |cumsum: &[u64]| (median_grade(cumsum, MedianConvention::LowerMedian), median_grade(cumsum, MedianConvention::UpperMedian));
+        assert_eq!(lower_and_upper(&[2, 4]), (0, 1));
+        assert_eq!(lower_and_upper(&[1, 3, 4]), (1, 1));
+        assert_eq!(lower_and_upper(&[0, 2, 2, 4]), (1, 3));
+        assert_eq!(lower_and_upper(&[3, 3, 7]), (2, 2));
     }
 }
\ No newline at end of file
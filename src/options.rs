@@ -0,0 +1,290 @@
+//! # Options
+//! The options of the majority judgment, see `majority_judgment_with_options`.
+use alloc::boxed::Box;
+use alloc::string::String;
+use core::cmp::Ordering;
+use crate::tie_breaker::TieBreaker;
+
+/// The ranking methods built into the crate, see `RankingMethod` to rank with another one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BuiltinMethod {
+    /// Balinski–Laraki majority values: the successive median grades obtained
+    /// when withdrawing the median grade one vote at a time
+    #[default]
+    MajorityValues,
+    /// Majority gauge: the majority grade, then `p` if `p > q` and `-q` otherwise,
+    /// with `p` the share of grades above the majority grade and `q` the share below.
+    /// Computed once per candidate instead of withdrawing the votes one by one.
+    MajorityGauge,
+    /// Usual judgment: the majority grade, then `(p - q) / (1 - p - q)`
+    UsualJudgment,
+    /// Typical judgment: the majority grade, then `p - q`
+    TypicalJudgment,
+    /// Score voting: the average grade, the grades being converted to numbers by `Options::grade_value`,
+    /// or exactly by `Options::exact_grade_value`.
+    /// Unlike the median, the average moves with every grade, so a few extreme grades can change the winner.
+    MeanGrade,
+}
+
+/// The number a grade counts for in the mean grade
+pub(crate) enum GradeValue<G> {
+    /// No conversion: the mean grade cannot be computed
+    Missing,
+    /// A floating point number, the average being rounded
+    Float(fn(&G) -> f64),
+    /// An integer, the average being an exact fraction
+    Exact(fn(&G) -> i64),
+}
+
+impl<G> Clone for GradeValue<G> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<G> Copy for GradeValue<G> {}
+
+impl<G> GradeValue<G> {
+    /// The exact conversion if there is one, the floating point one otherwise
+    pub(crate) fn new(float: Option<fn(&G) -> f64>, exact: Option<fn(&G) -> i64>) -> Self {
+        match (exact, float) {
+            (Some(exact), _) => GradeValue::Exact(exact),
+            (None, Some(float)) => GradeValue::Float(float),
+            (None, None) => GradeValue::Missing,
+        }
+    }
+}
+
+/// How the majority values handle candidates graded by different numbers of voters
+///
+/// The other ranking methods only compare shares of grades: they accept different numbers of grades either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Participation {
+    /// Every candidate must have the same number of grades, the majority judgment panics otherwise
+    #[default]
+    Equal,
+    /// The majority values are compared by shares of withdrawn grades: withdrawing one grade of a candidate
+    /// graded 10 times weighs as much as withdrawing two grades of a candidate graded 20 times.
+    /// A candidate without any grade ranks last.
+    Proportional,
+}
+
+/// Which of the two middle grades is the majority grade of an even number of votes
+///
+/// Balinski and Laraki take the lower median: a candidate graded "Good" by half of the voters and "Poor"
+/// by the other half gets "Poor", the grade a majority agrees the candidate deserves at least.
+/// An odd number of votes has a single median grade either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MedianConvention {
+    /// The worse of the two middle grades, the majority judgment itself
+    #[default]
+    LowerMedian,
+    /// The better of the two middle grades, e.g. to reproduce the results of another tool
+    UpperMedian,
+}
+
+/// The order in which the candidates sharing the same rank are listed
+///
+/// Tied candidates are equivalent, but the results list them one after the other: the order
+/// is fixed by their keys only, so that it does not change with the ranking method, the tie breaker,
+/// the platform or the version of the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TieOrder {
+    /// By increasing key, e.g. the alphabetical order of the names
+    #[default]
+    Ascending,
+    /// By decreasing key
+    Descending,
+}
+
+impl TieOrder {
+    /// Function that compares two tied candidates, `Less` meaning `a` is listed first
+    pub(crate) fn arrange<K: Ord + ?Sized>(self, a: &K, b: &K) -> Ordering {
+        match self {
+            TieOrder::Ascending => a.cmp(b),
+            TieOrder::Descending => b.cmp(a),
+        }
+    }
+}
+
+/// Options of the majority judgment
+///
+/// The majority values, the majority gauge, the usual and the typical judgments only count votes
+/// and compare exact fractions of them, without any floating point number: they give the same ranking
+/// on every platform. Only the mean grade converts the grades to `f64`, unless `exact_grade_value` is set.
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{Lexicographic, Options, BuiltinMethod};
+/// let options: Options<u8> = Options::new()
+///     .method(BuiltinMethod::UsualJudgment)
+///     .tie_breaker(Lexicographic);
+/// ```
+pub struct Options<G, K = String> {
+    /// The rule ranking the candidates, majority values by default
+    pub method: BuiltinMethod,
+    /// The rule consulted when candidates are equal according to the ranking method.
+    /// Without tie breaker, such candidates share the same rank.
+    pub tie_breaker: Option<Box<dyn TieBreaker<G, K>>>,
+    /// The number a grade counts for, needed by `BuiltinMethod::MeanGrade`
+    pub grade_value: Option<fn(&G) -> f64>,
+    /// The integer a grade counts for, making `BuiltinMethod::MeanGrade` exact. Used instead of `grade_value` if both are set.
+    pub exact_grade_value: Option<fn(&G) -> i64>,
+    /// The order of the candidates sharing the same rank, by increasing key by default
+    pub tie_order: TieOrder,
+    /// Whether the majority values require the same number of grades for every candidate, the default
+    pub participation: Participation,
+    /// The pseudo-candidate "none of the above", see `Ranking::verdict`
+    pub none_of_the_above: Option<K>,
+    /// The majority grade of an even number of votes, the lower median by default
+    pub median: MedianConvention,
+}
+
+impl<G, K> Options<G, K> {
+    /// The default options: majority values without tie breaker
+    pub fn new() -> Self {
+        Options {
+            method: BuiltinMethod::default(),
+            tie_breaker: None,
+            grade_value: None,
+            exact_grade_value: None,
+            tie_order: TieOrder::default(),
+            participation: Participation::default(),
+            none_of_the_above: None,
+            median: MedianConvention::default(),
+        }
+    }
+
+    /// Set the ranking method, builder style
+    pub fn method(mut self, method: BuiltinMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Set the tie breaker, builder style
+    pub fn tie_breaker(mut self, tie_breaker: impl TieBreaker<G, K> + 'static) -> Self {
+        self.tie_breaker = Some(Box::new(tie_breaker));
+        self
+    }
+
+    /// Set the order of the candidates sharing the same rank, builder style
+    pub fn tie_order(mut self, tie_order: TieOrder) -> Self {
+        self.tie_order = tie_order;
+        self
+    }
+
+    /// Set how the majority values handle candidates with different numbers of grades, builder style
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::{majority_judgment_with_options, Options, Participation};
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza".to_string(), vec![1, 2, 3]);
+    /// poll_data.insert("Chips".to_string(), vec![2, 2, 3, 3, 3, 3]);
+    ///
+    /// let options = Options::new().participation(Participation::Proportional);
+    /// assert_eq!(*majority_judgment_with_options(&poll_data, &options).winners()[0], "Chips");
+    /// ```
+    pub fn participation(mut self, participation: Participation) -> Self {
+        self.participation = participation;
+        self
+    }
+
+    /// Set which middle grade is the majority grade of an even number of votes, builder style.
+    /// The majority values then withdraw that median one vote at a time, and the majority gauge, the usual and
+    /// the typical judgments count the proponents and the opponents around it.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::{majority_judgment_with_options, MedianConvention, Options};
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza", vec![0u8, 0, 3, 3]);
+    /// poll_data.insert("Chips", vec![1u8, 1, 2, 2]);
+    ///
+    /// assert_eq!(majority_judgment_with_options(&poll_data, &Options::new()).winners(), vec![&"Chips"]);
+    /// let options = Options::new().median(MedianConvention::UpperMedian);
+    /// let ranking = majority_judgment_with_options(&poll_data, &options);
+    /// assert_eq!(ranking.winners(), vec![&"Pizza"]);
+    /// assert_eq!(ranking.candidates()[0].majority_grade, Some(3));
+    /// ```
+    pub fn median(mut self, median: MedianConvention) -> Self {
+        self.median = median;
+        self
+    }
+
+    /// Set the candidate standing for "none of the above", builder style: it is ranked like the others, and
+    /// nobody is elected if its majority grade is greater than the majority grade of every real candidate,
+    /// see `Ranking::verdict`
+    ///
+    /// # Panics
+    /// * when ranking, if the candidate is not part of the poll data
+    pub fn none_of_the_above(mut self, candidate: K) -> Self {
+        self.none_of_the_above = Some(candidate);
+        self
+    }
+
+    /// Set the number a grade counts for in `BuiltinMethod::MeanGrade`, builder style
+    pub fn grade_value(mut self, grade_value: fn(&G) -> f64) -> Self {
+        self.grade_value = Some(grade_value);
+        self
+    }
+
+    /// Set the integer a grade counts for in `BuiltinMethod::MeanGrade`, builder style:
+    /// the averages are then compared as exact fractions, two equal averages always being tied
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::{majority_judgment_with_options, Options, BuiltinMethod, TieStatus};
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza".to_string(), vec![1u8, 2]);
+    /// poll_data.insert("Chips".to_string(), vec![0u8, 1, 2, 3]);
+    ///
+    /// let options = Options::new().method(BuiltinMethod::MeanGrade).exact_grade_value(|&grade| grade as i64 * 10);
+    /// assert_eq!(majority_judgment_with_options(&poll_data, &options).candidates()[0].tie, TieStatus::Tied);
+    /// ```
+    pub fn exact_grade_value(mut self, exact_grade_value: fn(&G) -> i64) -> Self {
+        self.exact_grade_value = Some(exact_grade_value);
+        self
+    }
+
+    /// The conversion used by the mean grade
+    pub(crate) fn value(&self) -> GradeValue<G> {
+        GradeValue::new(self.grade_value, self.exact_grade_value)
+    }
+}
+
+impl<G: Copy + Into<f64>, K> Options<G, K> {
+    /// Rank by mean grade, the grades counting for their own value, builder style
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::{majority_judgment_with_options, Options};
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza".to_string(), vec![0u8, 0, 10]);
+    /// poll_data.insert("Chips".to_string(), vec![2u8, 2, 2]);
+    ///
+    /// // 3.33 on average against 2, while the majority grade of Pizza is 0
+    /// let ranking = majority_judgment_with_options(&poll_data, &Options::new().mean_grade());
+    /// assert_eq!(*ranking.winners()[0], "Pizza");
+    /// ```
+    pub fn mean_grade(self) -> Self {
+        self.method(BuiltinMethod::MeanGrade).grade_value(|grade| (*grade).into())
+    }
+}
+
+impl<G: Copy + Into<i64>, K> Options<G, K> {
+    /// Rank by exact mean grade, the grades counting for their own value, builder style
+    pub fn exact_mean_grade(self) -> Self {
+        self.method(BuiltinMethod::MeanGrade).exact_grade_value(|grade| (*grade).into())
+    }
+}
+
+impl<G, K> Default for Options<G, K> {
+    fn default() -> Self {
+        Options::new()
+    }
+}
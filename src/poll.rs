@@ -0,0 +1,753 @@
+//! # Poll
+//! Ballot-oriented input for the majority judgment.
+//! Real polls arrive as per-voter ballots, each one grading every candidate.
+//! A `Poll` collects these ballots, validates them and transposes them into the
+//! per-candidate grade vectors expected by `majority_judgment`.
+//!
+//! The module also gathers the other tools of the ballots: the `AbstentionPolicy` of incomplete ballots,
+//! the `TallyAccumulator` of live polls, the diagnostics of `validate_poll` and the `DuplicatePolicy`
+//! of voters voting twice.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::time::SystemTime;
+pub use crate::abstention::{resolve_abstentions, AbstentionPolicy};
+pub use crate::accumulator::{SnapshotError, TallyAccumulator};
+pub use crate::dedup::{deduplicate, DedupReport, DuplicateBallotError, DuplicatePolicy, DuplicateVoter, Submission};
+pub use crate::grade_range::{GradeRange, OutOfRange};
+pub use crate::validation::{validate_poll, PollDiagnostics};
+use crate::mj::majority_judgment_weighted;
+use crate::ranking::Metadata;
+
+/// The grades given by a single voter, one per candidate
+///
+/// A ballot weighs 1 by default; see `Ballot::with_weight` for weighted polls.
+/// It may record the identifier of its voter, see `Ballot::with_voter` and `dataset::strip_voters`.
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::Ballot;
+/// let ballot = Ballot::new().grade("Pizza", 3).grade("Chips", 1);
+/// assert_eq!(ballot.grades().len(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ballot<G> {
+    grades: Vec<(String, G)>,
+    weight: u32,
+    voter: Option<String>,
+    timestamp: Option<SystemTime>,
+}
+
+impl<G> Ballot<G> {
+    /// Create an empty ballot
+    pub fn new() -> Self {
+        Ballot { grades: Vec::new(), weight: 1, voter: None, timestamp: None }
+    }
+
+    /// Add the grade given to a candidate, builder style
+    ///
+    /// # Arguments
+    /// * `candidate`: the name of the graded candidate
+    /// * `grade`: the grade given by the voter
+    pub fn grade(mut self, candidate: impl Into<String>, grade: G) -> Self {
+        self.grades.push((candidate.into(), grade));
+        self
+    }
+
+    /// Set the weight of the ballot, builder style
+    ///
+    /// # Arguments
+    /// * `weight`: how many times the grades of this ballot count, e.g. a number of shares
+    pub fn with_weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Set the identifier of the voter, builder style
+    ///
+    /// # Arguments
+    /// * `voter`: the identifier of the voter in the export, e.g. a registration number
+    pub fn with_voter(mut self, voter: impl Into<String>) -> Self {
+        self.voter = Some(voter.into());
+        self
+    }
+
+    /// Set when the ballot was cast, builder style
+    ///
+    /// # Arguments
+    /// * `timestamp`: the time the ballot was received, see `Poll::ranking_at`
+    pub fn with_timestamp(mut self, timestamp: SystemTime) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Remove the identifier of the voter, builder style
+    pub fn without_voter(mut self) -> Self {
+        self.voter = None;
+        self
+    }
+
+    /// Function that converts the grades of the ballot, keeping its weight, its voter and its timestamp
+    ///
+    /// # Arguments
+    /// * `convert`: the function converting a grade
+    pub fn map_grades<H>(self, mut convert: impl FnMut(G) -> H) -> Ballot<H> {
+        Ballot {
+            grades: self.grades.into_iter().map(|(candidate, grade)| (candidate, convert(grade))).collect(),
+            weight: self.weight,
+            voter: self.voter,
+            timestamp: self.timestamp,
+        }
+    }
+
+    /// The grades of the ballot, in the order they were given
+    pub fn grades(&self) -> &[(String, G)] {
+        &self.grades
+    }
+
+    /// The weight of the ballot
+    pub fn weight(&self) -> u32 {
+        self.weight
+    }
+
+    /// The identifier of the voter, if recorded
+    pub fn voter(&self) -> Option<&str> {
+        self.voter.as_deref()
+    }
+
+    /// When the ballot was cast, if recorded
+    pub fn timestamp(&self) -> Option<SystemTime> {
+        self.timestamp
+    }
+}
+
+impl<G> Default for Ballot<G> {
+    fn default() -> Self {
+        Ballot::new()
+    }
+}
+
+impl<S: Into<String>, G> FromIterator<(S, G)> for Ballot<G> {
+    fn from_iter<I: IntoIterator<Item = (S, G)>>(iter: I) -> Self {
+        Ballot {
+            grades: iter.into_iter().map(|(candidate, grade)| (candidate.into(), grade)).collect(),
+            weight: 1,
+            voter: None,
+            timestamp: None,
+        }
+    }
+}
+
+/// What is wrong with a ballot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BallotIssue {
+    /// The ballot does not grade this candidate
+    MissingGrade(String),
+    /// The ballot grades this candidate more than once
+    DuplicateGrade(String),
+    /// The ballot grades a candidate that is not part of the poll
+    UnknownCandidate(String),
+    /// The ballot withdraws a grade of this candidate that was never counted, see `TallyAccumulator`
+    UncountedGrade(String),
+    /// The ballot gives this candidate a grade out of the `GradeRange` of the poll
+    OutOfRange(String),
+    /// Counting the ballot would give this candidate more than `u64::MAX` votes, see `TallyAccumulator`
+    TooManyVotes(String),
+}
+
+/// An issue found on a given ballot of a poll
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BallotError {
+    /// Index of the ballot in the order it was added to the poll
+    pub ballot: usize,
+    /// The issue found on this ballot
+    pub issue: BallotIssue,
+}
+
+impl fmt::Display for BallotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.issue {
+            BallotIssue::MissingGrade(candidate) =>
+                write!(f, "ballot {}: no grade for candidate {:?}", self.ballot, candidate),
+            BallotIssue::DuplicateGrade(candidate) =>
+                write!(f, "ballot {}: candidate {:?} is graded more than once", self.ballot, candidate),
+            BallotIssue::UnknownCandidate(candidate) =>
+                write!(f, "ballot {}: unknown candidate {:?}", self.ballot, candidate),
+            BallotIssue::UncountedGrade(candidate) =>
+                write!(f, "ballot {}: the grade of candidate {:?} was never counted", self.ballot, candidate),
+            BallotIssue::OutOfRange(candidate) =>
+                write!(f, "ballot {}: the grade of candidate {:?} is out of the grade range", self.ballot, candidate),
+            BallotIssue::TooManyVotes(candidate) =>
+                write!(f, "ballot {}: candidate {:?} would have more than {} votes", self.ballot, candidate, u64::MAX),
+        }
+    }
+}
+
+impl std::error::Error for BallotError {}
+
+/// Two polls refused by `Poll::merge`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    /// A candidate of the poll is not part of the merged poll
+    MissingCandidate(String),
+    /// A candidate of the merged poll is not part of the poll
+    UnknownCandidate(String),
+    /// Both polls declare a grade range, and not the same
+    GradeRangeMismatch,
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::MissingCandidate(candidate) => write!(f, "candidate {:?} is missing from the merged poll", candidate),
+            MergeError::UnknownCandidate(candidate) => write!(f, "candidate {:?} of the merged poll is not part of the poll", candidate),
+            MergeError::GradeRangeMismatch => write!(f, "the polls accept different grade ranges"),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+/// A poll: the candidates and the ballots collected so far
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{majority_judgment, Ballot, Poll};
+/// let mut poll = Poll::new(["Pizza", "Chips"]);
+/// poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 1).grade("Chips", 3));
+///
+/// let poll_data = poll.poll_data().unwrap();
+/// assert_eq!(majority_judgment(&poll_data)[0], (&"Chips".to_string(), 0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Poll<G> {
+    candidates: Vec<String>,
+    ballots: Vec<Ballot<G>>,
+    metadata: BTreeMap<String, Metadata>,
+    grade_range: Option<GradeRange<G>>,
+}
+
+impl<G> Poll<G> {
+    /// Create a poll without any ballot
+    ///
+    /// # Arguments
+    /// * `candidates`: the candidates, in their registration order. Repeated names are ignored.
+    pub fn new<I, S>(candidates: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut seen = BTreeSet::new();
+        let candidates = candidates
+            .into_iter()
+            .map(Into::into)
+            .filter(|candidate: &String| seen.insert(candidate.clone()))
+            .collect();
+        Poll { candidates, ballots: Vec::new(), metadata: BTreeMap::new(), grade_range: None }
+    }
+
+    /// Function that creates a poll with the same candidates and metadata, without any ballot nor grade range
+    pub(crate) fn without_ballots<H>(&self) -> Poll<H> {
+        Poll { candidates: self.candidates.clone(), ballots: Vec::new(), metadata: self.metadata.clone(), grade_range: None }
+    }
+
+    /// Function that creates a poll with the same candidates, metadata and grade range, without any ballot
+    pub(crate) fn empty_copy(&self) -> Poll<G>
+    where
+        G: Clone,
+    {
+        Poll { grade_range: self.grade_range.clone(), ..self.without_ballots() }
+    }
+
+    /// Declare the grades the poll accepts, builder style: the ballots giving other grades are invalid,
+    /// or their grades are clamped, see `GradeRange`
+    pub fn with_grade_range(mut self, grade_range: GradeRange<G>) -> Self {
+        self.grade_range = Some(grade_range);
+        self
+    }
+
+    /// The grades the poll accepts, None if it accepts any grade
+    pub fn grade_range(&self) -> Option<&GradeRange<G>> {
+        self.grade_range.as_ref()
+    }
+
+    /// Attach a piece of information to a candidate, e.g. its party, replacing the previous value of the key.
+    /// A candidate which is not part of the poll is ignored.
+    ///
+    /// # Arguments
+    /// * `candidate`: the name of the candidate
+    /// * `key`: the name of the information, e.g. `"party"`, `"photo"` or `"description"`
+    /// * `value`: the information
+    pub fn set_metadata(&mut self, candidate: &str, key: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        if self.candidates.iter().any(|registered| registered == candidate) {
+            self.metadata.entry(candidate.to_string()).or_default().insert(key.into(), value.into());
+        }
+        self
+    }
+
+    /// The metadata of the candidates, see `Ranking::with_metadata` to carry it to the results
+    pub fn metadata(&self) -> &BTreeMap<String, Metadata> {
+        &self.metadata
+    }
+
+    /// Add a ballot to the poll. It is only validated when the poll is transposed.
+    pub fn add_ballot(&mut self, ballot: Ballot<G>) -> &mut Self {
+        self.ballots.push(ballot);
+        self
+    }
+
+    /// Function that adds a ballot unless its voter already voted, see `DuplicatePolicy`
+    ///
+    /// The earlier ballots are searched for the voter: load large polls with `add_ballot` and `deduplicate` instead.
+    ///
+    /// # Arguments
+    /// * `ballot`: the ballot, with its voter
+    /// * `policy`: what to do if the voter has a ballot in the poll already
+    ///
+    /// # Returns
+    /// * `Result<Submission<G>, DuplicateBallotError>`: what was done with the ballot, an error if it is
+    ///   refused with `DuplicatePolicy::Reject`. A replaced ballot is withdrawn and the new one is added last.
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::{Ballot, DuplicatePolicy, Poll, Submission};
+    /// let mut poll = Poll::new(["Pizza"]);
+    /// poll.submit(Ballot::new().grade("Pizza", 1).with_voter("token-1"), DuplicatePolicy::Reject).unwrap();
+    /// assert!(poll.submit(Ballot::new().grade("Pizza", 3).with_voter("token-1"), DuplicatePolicy::Reject).is_err());
+    /// assert_eq!(poll.ballots().len(), 1);
+    /// ```
+    pub fn submit(&mut self, ballot: Ballot<G>, policy: DuplicatePolicy) -> Result<Submission<G>, DuplicateBallotError> {
+        let previous = ballot
+            .voter()
+            .and_then(|voter| self.ballots.iter().position(|counted| counted.voter() == Some(voter)));
+        let Some(index) = previous else {
+            self.ballots.push(ballot);
+            return Ok(Submission::Added);
+        };
+        match policy {
+            DuplicatePolicy::Reject => {
+                Err(DuplicateBallotError { voter: ballot.voter().unwrap_or_default().to_string(), ballot: index })
+            }
+            DuplicatePolicy::FirstWins => Ok(Submission::Ignored),
+            DuplicatePolicy::LastWins => {
+                let replaced = self.ballots.remove(index);
+                self.ballots.push(ballot);
+                Ok(Submission::Replaced(replaced))
+            }
+        }
+    }
+
+    /// Function that adds the ballots of another poll over the same candidates, e.g. the paper ballots
+    /// of an online poll
+    ///
+    /// # Arguments
+    /// * `other`: the other poll, whose ballots are added after the ones of this poll
+    ///
+    /// # Returns
+    /// * `Result<&mut Self, MergeError>`: the merged poll, or why the polls cannot be merged, in which case nothing
+    ///   is added. The candidates may be registered in another order. The metadata of `other` fills in the keys
+    ///   this poll lacks, and its grade range applies if this poll declares none.
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::{Ballot, MergeError, Poll};
+    /// let mut online = Poll::new(["Pizza", "Chips"]);
+    /// online.add_ballot(Ballot::new().grade("Pizza", 2).grade("Chips", 1));
+    /// let mut paper = Poll::new(["Chips", "Pizza"]);
+    /// paper.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 3));
+    ///
+    /// online.merge(paper).unwrap();
+    /// assert_eq!(online.poll_data().unwrap()["Chips"], vec![1, 3]);
+    /// assert_eq!(online.merge(Poll::new(["Pizza"])).unwrap_err(), MergeError::MissingCandidate("Chips".to_string()));
+    /// ```
+    pub fn merge(&mut self, other: Poll<G>) -> Result<&mut Self, MergeError>
+    where
+        G: PartialEq,
+    {
+        if let Some(missing) = self.candidates.iter().find(|candidate| !other.candidates.contains(candidate)) {
+            return Err(MergeError::MissingCandidate(missing.clone()));
+        }
+        if let Some(unknown) = other.candidates.iter().find(|candidate| !self.candidates.contains(candidate)) {
+            return Err(MergeError::UnknownCandidate(unknown.clone()));
+        }
+        match (&self.grade_range, other.grade_range) {
+            (Some(range), Some(other_range)) if *range != other_range => return Err(MergeError::GradeRangeMismatch),
+            (None, other_range) => self.grade_range = other_range,
+            _ => {}
+        }
+        for (candidate, metadata) in other.metadata {
+            let merged = self.metadata.entry(candidate).or_default();
+            for (key, value) in metadata {
+                merged.entry(key).or_insert(value);
+            }
+        }
+        self.ballots.extend(other.ballots);
+        Ok(self)
+    }
+
+    /// The candidates in their registration order
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    /// The ballots in the order they were added
+    pub fn ballots(&self) -> &[Ballot<G>] {
+        &self.ballots
+    }
+
+    /// Function that checks every ballot grades each candidate exactly once, within the grade range if any
+    ///
+    /// # Returns
+    /// * `Vec<BallotError>`: all the issues found, ordered by ballot. Empty if the poll is valid.
+    pub fn validate(&self) -> Vec<BallotError>
+    where
+        G: PartialOrd + Clone,
+    {
+        let mut errors = Vec::new();
+        for (index, ballot) in self.ballots.iter().enumerate() {
+            errors.extend(
+                ballot_issues(&self.candidates, ballot, self.grade_range.as_ref())
+                    .into_iter()
+                    .map(|issue| BallotError { ballot: index, issue }),
+            );
+        }
+        errors
+    }
+
+    /// Function that transposes the ballots into the grades of each candidate
+    ///
+    /// # Returns
+    /// * `Result<BTreeMap<String, Vec<G>>, Vec<BallotError>>`: the poll data ready for `majority_judgment`,
+    ///   the grades clamped to the grade range if any, or all the issues found on the ballots
+    pub fn poll_data(&self) -> Result<BTreeMap<String, Vec<G>>, Vec<BallotError>>
+    where
+        G: PartialOrd + Clone,
+    {
+        let errors = self.validate();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut poll_data: BTreeMap<String, Vec<G>> = self
+            .candidates
+            .iter()
+            .map(|candidate| (candidate.clone(), Vec::with_capacity(self.ballots.len())))
+            .collect();
+        for ballot in self.ballots.iter() {
+            for (candidate, grade) in ballot.grades.iter() {
+                if let Some(grades) = poll_data.get_mut(candidate) {
+                    grades.push(self.counted(grade));
+                }
+            }
+        }
+        Ok(poll_data)
+    }
+
+    /// Function that transposes the ballots into the grades of each candidate, counting the candidates
+    /// left without grade on a ballot according to an abstention policy
+    ///
+    /// # Arguments
+    /// * `policy`: how to count a missing grade
+    ///
+    /// # Returns
+    /// * `Result<BTreeMap<String, Vec<G>>, Vec<BallotError>>`: the poll data ready for `majority_judgment`,
+    ///   or the duplicated and unknown candidates found on the ballots
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::{AbstentionPolicy, Ballot, Poll};
+    /// let mut poll = Poll::new(["Pizza", "Chips"]);
+    /// poll.add_ballot(Ballot::new().grade("Pizza", 3).grade("Chips", 1));
+    /// poll.add_ballot(Ballot::new().grade("Pizza", 2));
+    ///
+    /// let poll_data = poll.poll_data_with_abstentions(&AbstentionPolicy::Custom(0)).unwrap();
+    /// assert_eq!(poll_data["Chips"], vec![1, 0]);
+    /// ```
+    pub fn poll_data_with_abstentions(&self, policy: &AbstentionPolicy<G>) -> Result<BTreeMap<String, Vec<G>>, Vec<BallotError>>
+    where
+        G: Ord + Clone,
+    {
+        let errors: Vec<BallotError> = self
+            .validate()
+            .into_iter()
+            .filter(|error| !matches!(error.issue, BallotIssue::MissingGrade(_)))
+            .collect();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut poll_data: BTreeMap<String, Vec<Option<G>>> = self
+            .candidates
+            .iter()
+            .map(|candidate| (candidate.clone(), vec![None; self.ballots.len()]))
+            .collect();
+        for (index, ballot) in self.ballots.iter().enumerate() {
+            for (candidate, grade) in ballot.grades.iter() {
+                if let Some(grades) = poll_data.get_mut(candidate) {
+                    grades[index] = Some(self.counted(grade));
+                }
+            }
+        }
+        Ok(resolve_abstentions(&poll_data, policy))
+    }
+
+    /// Function that gives the grade counted for a grade of a valid ballot, clamped to the grade range
+    pub(crate) fn counted(&self, grade: &G) -> G
+    where
+        G: PartialOrd + Clone,
+    {
+        self.grade_range.as_ref().and_then(|range| range.counted(grade)).unwrap_or_else(|| grade.clone())
+    }
+
+    /// The weight of each ballot, in the same order as the grades returned by `poll_data`
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::{majority_judgment_weighted, Ballot, Poll};
+    /// let mut poll = Poll::new(["Pizza", "Chips"]);
+    /// poll.add_ballot(Ballot::new().grade("Pizza", 3).grade("Chips", 0).with_weight(3));
+    /// poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2).with_weight(2));
+    ///
+    /// let poll_data = poll.poll_data().unwrap();
+    /// let ranking = majority_judgment_weighted(&poll_data, &poll.weights()).unwrap();
+    /// assert_eq!(ranking[0], (&"Pizza".to_string(), 0));
+    /// ```
+    pub fn weights(&self) -> Vec<u32> {
+        self.ballots.iter().map(Ballot::weight).collect()
+    }
+
+    /// Function that keeps the ballots cast during a period, e.g. a rolling window of a dashboard
+    ///
+    /// # Arguments
+    /// * `start`: the first instant of the period, included, None from the beginning of the poll
+    /// * `end`: the last instant of the period, excluded, None until now
+    ///
+    /// # Returns
+    /// * `Poll<G>`: the poll with the same candidates and the ballots cast during the period, in their order.
+    ///   The ballots without timestamp are left out.
+    pub fn window(&self, start: Option<SystemTime>, end: Option<SystemTime>) -> Poll<G>
+    where
+        G: Clone,
+    {
+        let during = |timestamp: SystemTime| {
+            start.is_none_or(|start| timestamp >= start) && end.is_none_or(|end| timestamp < end)
+        };
+        let mut window = self.empty_copy();
+        window.ballots = self.ballots.iter().filter(|ballot| ballot.timestamp.is_some_and(during)).cloned().collect();
+        window
+    }
+
+    /// Function that ranks the candidates as they were at an instant of the poll
+    ///
+    /// # Arguments
+    /// * `instant`: the instant of the ranking, the ballots cast before it being counted with their weights
+    ///
+    /// # Returns
+    /// * `Result<Vec<(String, usize)>, Vec<BallotError>>`: the candidates with their rank at that instant,
+    ///   or the issues found on the ballots cast before it. The ballots without timestamp are left out.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// use majority_judgement_rust::{Ballot, Poll};
+    /// let at = |seconds| UNIX_EPOCH + Duration::from_secs(seconds);
+    /// let mut poll = Poll::new(["Pizza", "Chips"]);
+    /// poll.add_ballot(Ballot::new().grade("Pizza", 2).grade("Chips", 1).with_timestamp(at(10)));
+    /// poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 3).with_timestamp(at(20)));
+    /// poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2).with_timestamp(at(30)));
+    ///
+    /// assert_eq!(poll.ranking_at(at(15)).unwrap()[0].0, "Pizza");
+    /// assert_eq!(poll.ranking_at(at(40)).unwrap()[0].0, "Chips");
+    /// ```
+    pub fn ranking_at(&self, instant: SystemTime) -> Result<Vec<(String, usize)>, Vec<BallotError>>
+    where
+        G: Ord + Clone,
+    {
+        self.window(None, Some(instant)).weighted_ranking()
+    }
+
+    /// Function that ranks the candidates from the ballots cast during a period, e.g. the last hour
+    ///
+    /// # Arguments
+    /// * `start`: the first instant of the period, included
+    /// * `end`: the last instant of the period, excluded
+    ///
+    /// # Returns
+    /// * `Result<Vec<(String, usize)>, Vec<BallotError>>`: the candidates with their rank from the ballots of the period,
+    ///   or the issues found on them
+    pub fn ranking_between(&self, start: SystemTime, end: SystemTime) -> Result<Vec<(String, usize)>, Vec<BallotError>>
+    where
+        G: Ord + Clone,
+    {
+        self.window(Some(start), Some(end)).weighted_ranking()
+    }
+
+    /// Function that ranks the ballots of the poll with their weights, owning the names of the candidates
+    fn weighted_ranking(&self) -> Result<Vec<(String, usize)>, Vec<BallotError>>
+    where
+        G: Ord + Clone,
+    {
+        let poll_data = self.poll_data()?;
+        Ok(majority_judgment_weighted(&poll_data, &self.weights())
+            .expect("the poll data has a grade for each ballot")
+            .into_iter()
+            .map(|(candidate, rank)| (candidate.clone(), rank))
+            .collect())
+    }
+}
+
+/// Function that checks a ballot grades each candidate exactly once
+///
+/// # Arguments
+/// * `candidates`: the candidates of the poll
+/// * `ballot`: the ballot to check
+/// * `grade_range`: the grades accepted, if any
+///
+/// # Returns
+/// * `Vec<BallotIssue>`: the issues of the ballot, the duplicated, unknown and out of range candidates first
+pub(crate) fn ballot_issues<G: PartialOrd + Clone>(
+    candidates: &[String],
+    ballot: &Ballot<G>,
+    grade_range: Option<&GradeRange<G>>,
+) -> Vec<BallotIssue> {
+    let known: BTreeSet<&String> = candidates.iter().collect();
+    let mut graded = BTreeSet::new();
+    let mut issues = Vec::new();
+    for (candidate, grade) in ballot.grades.iter() {
+        if !known.contains(candidate) {
+            issues.push(BallotIssue::UnknownCandidate(candidate.clone()));
+        } else if !graded.insert(candidate) {
+            issues.push(BallotIssue::DuplicateGrade(candidate.clone()));
+        } else if grade_range.is_some_and(|range| range.counted(grade).is_none()) {
+            issues.push(BallotIssue::OutOfRange(candidate.clone()));
+        }
+    }
+    for candidate in candidates.iter().filter(|c| !graded.contains(c)) {
+        issues.push(BallotIssue::MissingGrade(candidate.clone()));
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_poll_data() {
+        let mut poll = Poll::new(["Pizza", "Chips"]);
+        poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2))
+            .add_ballot(Ballot::new().grade("Chips", 3).grade("Pizza", 1));
+
+        let mut expected = BTreeMap::new();
+        expected.insert("Pizza".to_string(), vec![0, 1]);
+        expected.insert("Chips".to_string(), vec![2, 3]);
+        assert_eq!(poll.poll_data(), Ok(expected));
+        assert_eq!(poll.weights(), vec![1, 1]);
+    }
+
+    #[test]
+    fn calling_poll_data_with_abstentions() {
+        let mut poll = Poll::new(["Pizza", "Chips"]);
+        poll.add_ballot(Ballot::new().grade("Pizza", 2).grade("Chips", 1))
+            .add_ballot(Ballot::new().grade("Chips", 3));
+
+        let poll_data = poll.poll_data_with_abstentions(&AbstentionPolicy::WorstGrade).unwrap();
+        assert_eq!(poll_data["Pizza"], vec![2, 1]);
+        let poll_data = poll.poll_data_with_abstentions(&AbstentionPolicy::Exclude).unwrap();
+        assert_eq!(poll_data["Pizza"], vec![2]);
+        assert_eq!(poll_data["Chips"], vec![1, 3]);
+
+        // a poll of optional grades works the same way
+        let mut poll = Poll::new(["Pizza"]);
+        poll.add_ballot(Ballot::new().grade("Pizza", Some(2)))
+            .add_ballot(Ballot::new().grade("Pizza", None));
+        let poll_data = poll.poll_data().unwrap();
+        assert_eq!(resolve_abstentions(&poll_data, &AbstentionPolicy::Custom(0))["Pizza"], vec![2, 0]);
+
+        poll.add_ballot(Ballot::new().grade("Pasta", Some(1)));
+        assert!(poll.poll_data_with_abstentions(&AbstentionPolicy::Exclude).is_err());
+    }
+
+    #[test]
+    fn calling_validate() {
+        let mut poll = Poll::new(["Pizza", "Chips", "Pizza"]);
+        assert_eq!(poll.candidates(), ["Pizza".to_string(), "Chips".to_string()]);
+
+        poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2))
+            .add_ballot(Ballot::new().grade("Pizza", 1).grade("Pizza", 3))
+            .add_ballot([("Pizza", 1), ("Chips", 0), ("Pasta", 2)].into_iter().collect());
+
+        assert_eq!(
+            poll.validate(),
+            vec![BallotError { ballot: 1, issue: BallotIssue::DuplicateGrade("Pizza".to_string()) },
+                 BallotError { ballot: 1, issue: BallotIssue::MissingGrade("Chips".to_string()) },
+                 BallotError { ballot: 2, issue: BallotIssue::UnknownCandidate("Pasta".to_string()) }]);
+        assert_eq!(poll.poll_data().unwrap_err().len(), 3);
+    }
+
+    #[test]
+    fn calling_ranking_at() {
+        use std::time::{Duration, UNIX_EPOCH};
+        let at = |seconds| UNIX_EPOCH + Duration::from_secs(seconds);
+        let mut poll = Poll::new(["Pizza", "Chips"]);
+        poll.add_ballot(Ballot::new().grade("Pizza", 3).grade("Chips", 0).with_timestamp(at(100)).with_weight(2))
+            .add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2).with_timestamp(at(200)))
+            .add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2).with_timestamp(at(300)).with_weight(2))
+            .add_ballot(Ballot::new().grade("Pizza", 3).grade("Chips", 3));
+
+        assert_eq!(poll.window(Some(at(200)), Some(at(300))).ballots().len(), 1);
+        assert_eq!(poll.window(None, None).ballots().len(), 3);
+        assert_eq!(poll.ranking_at(at(100)).unwrap(), vec![("Chips".to_string(), 0), ("Pizza".to_string(), 0)]);
+        assert_eq!(poll.ranking_at(at(250)).unwrap()[0], ("Pizza".to_string(), 0));
+        assert_eq!(poll.ranking_at(at(301)).unwrap()[0], ("Chips".to_string(), 0));
+        assert_eq!(poll.ranking_between(at(150), at(400)).unwrap()[0], ("Chips".to_string(), 0));
+
+        poll.add_ballot(Ballot::new().grade("Pizza", 1).with_timestamp(at(400)));
+        assert!(poll.ranking_at(at(300)).is_ok());
+        assert_eq!(poll.ranking_at(at(500)).unwrap_err().len(), 1);
+    }
+
+    #[test]
+    fn calling_merge() {
+        let mut online = Poll::new(["Pizza", "Chips"]).with_grade_range(GradeRange::between(0, 3));
+        online.set_metadata("Pizza", "party", "Red").add_ballot(Ballot::new().grade("Pizza", 2).grade("Chips", 1));
+        let mut paper = Poll::new(["Chips", "Pizza"]);
+        paper.set_metadata("Pizza", "party", "Blue").set_metadata("Chips", "party", "Green");
+        paper.add_ballot(Ballot::new().grade("Chips", 3).grade("Pizza", 0).with_weight(2));
+
+        online.merge(paper).unwrap();
+        assert_eq!(online.ballots().len(), 2);
+        assert_eq!(online.weights(), vec![1, 2]);
+        assert_eq!((online.metadata()["Pizza"]["party"].as_str(), online.metadata()["Chips"]["party"].as_str()), ("Red", "Green"));
+
+        let unknown = Poll::new(["Pizza", "Chips", "Soup"]);
+        assert_eq!(online.merge(unknown).unwrap_err(), MergeError::UnknownCandidate("Soup".to_string()));
+        let other_scale = Poll::new(["Pizza", "Chips"]).with_grade_range(GradeRange::between(0, 6));
+        let error = online.merge(other_scale).unwrap_err();
+        assert_eq!(error.to_string(), "the polls accept different grade ranges");
+        assert_eq!(online.ballots().len(), 2);
+
+        let mut unbounded = Poll::new(["Pizza", "Chips"]);
+        unbounded.merge(online).unwrap();
+        assert_eq!(unbounded.grade_range(), Some(&GradeRange::between(0, 3)));
+    }
+
+    #[test]
+    fn calling_poll_data_with_grade_range() {
+        let mut poll = Poll::new(["Pizza", "Chips"]).with_grade_range(GradeRange::one_of([0, 2, 4]));
+        poll.add_ballot(Ballot::new().grade("Pizza", 2).grade("Chips", 4))
+            .add_ballot(Ballot::new().grade("Pizza", 3).grade("Chips", -1));
+        assert_eq!(
+            poll.validate(),
+            vec![BallotError { ballot: 1, issue: BallotIssue::OutOfRange("Pizza".to_string()) },
+                 BallotError { ballot: 1, issue: BallotIssue::OutOfRange("Chips".to_string()) }]);
+        assert_eq!(poll.validate()[0].to_string(), "ballot 1: the grade of candidate \"Pizza\" is out of the grade range");
+
+        // clamping counts -1 as the worst grade, but 3 falls in a gap of the scale
+        let poll = poll.with_grade_range(GradeRange::one_of([0, 2, 4]).policy(OutOfRange::Clamp));
+        assert_eq!(poll.validate().len(), 1);
+        let poll = poll.with_grade_range(GradeRange::between(0, 4).policy(OutOfRange::Clamp));
+        assert_eq!(poll.poll_data().unwrap()["Chips"], vec![4, 0]);
+        assert_eq!(poll.window(None, None).grade_range(), poll.grade_range());
+    }
+}
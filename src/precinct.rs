@@ -0,0 +1,206 @@
+//! # Precincts
+//! Elections are counted precinct by precinct, and the partial results are published as the precincts
+//! report: `PrecinctResults` keeps the tally of each precinct, ranks each precinct on its own and rolls
+//! them up into the national tally. A precinct reporting again replaces its previous tally, e.g. a recount.
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::mj::majority_judgment_from_tally;
+
+/// The number of votes of each grade for each candidate
+pub(crate) type Tallies<G> = BTreeMap<String, BTreeMap<G, u64>>;
+
+/// The tallies reported by the precincts of an election
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::PrecinctResults;
+/// let mut results = PrecinctResults::new(["Pizza", "Chips"]);
+/// results.report("North", BTreeMap::from([
+///     ("Pizza".to_string(), BTreeMap::from([(0u8, 10), (2, 30)])),
+///     ("Chips".to_string(), BTreeMap::from([(1u8, 40)])),
+/// ])).unwrap();
+/// results.report("South", BTreeMap::from([
+///     ("Pizza".to_string(), BTreeMap::from([(0u8, 50)])),
+///     ("Chips".to_string(), BTreeMap::from([(1u8, 30), (3, 20)])),
+/// ])).unwrap();
+///
+/// assert_eq!(results.precinct_ranking("North").unwrap()[0].0, "Pizza");
+/// assert_eq!(results.national_ranking()[0].0, "Chips");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrecinctResults<G> {
+    candidates: Vec<String>,
+    precincts: BTreeMap<String, Tallies<G>>,
+}
+
+impl<G: Ord + Clone> PrecinctResults<G> {
+    /// Create the results of an election without any precinct reported yet
+    ///
+    /// # Arguments
+    /// * `candidates`: the candidates running in every precinct
+    pub fn new<I, S>(candidates: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut candidates: Vec<String> = candidates.into_iter().map(Into::into).collect();
+        candidates.sort();
+        candidates.dedup();
+        PrecinctResults { candidates, precincts: BTreeMap::new() }
+    }
+
+    /// Function that records the tally of a precinct
+    ///
+    /// # Arguments
+    /// * `precinct`: the name of the precinct
+    /// * `tallies`: for each candidate, the number of votes of each grade in the precinct
+    ///
+    /// # Returns
+    /// * `Result<Option<BTreeMap<String, BTreeMap<G, u64>>>, PrecinctError>`: the tally the precinct reported
+    ///   before, if any, or why the tally is invalid: every candidate must be graded by the same number of votes
+    pub fn report<P: Into<String>>(&mut self, precinct: P, tallies: Tallies<G>) -> Result<Option<Tallies<G>>, PrecinctError> {
+        let precinct = precinct.into();
+        let error = |issue| PrecinctError { precinct: precinct.clone(), issue };
+        if let Some(unknown) = tallies.keys().find(|candidate| self.candidates.binary_search(candidate).is_err()) {
+            return Err(error(PrecinctIssue::UnknownCandidate(unknown.clone())));
+        }
+        if let Some(missing) = self.candidates.iter().find(|candidate| !tallies.contains_key(*candidate)) {
+            return Err(error(PrecinctIssue::MissingCandidate(missing.clone())));
+        }
+        let mut totals = tallies.iter().map(|(candidate, tally)| (candidate, tally.values().sum::<u64>()));
+        if let Some((_, expected)) = totals.next() {
+            if let Some((candidate, votes)) = totals.find(|&(_, votes)| votes != expected) {
+                return Err(error(PrecinctIssue::UnequalVotes { candidate: candidate.clone(), votes, expected }));
+            }
+        }
+        Ok(self.precincts.insert(precinct, tallies))
+    }
+
+    /// The candidates running in every precinct, by name
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    /// The precincts reported so far, by name
+    pub fn precincts(&self) -> impl Iterator<Item = &str> {
+        self.precincts.keys().map(String::as_str)
+    }
+
+    /// The tally of a precinct, None if it has not reported yet
+    pub fn precinct(&self, precinct: &str) -> Option<&Tallies<G>> {
+        self.precincts.get(precinct)
+    }
+
+    /// The ranking of a precinct on its own, None if it has not reported yet
+    pub fn precinct_ranking(&self, precinct: &str) -> Option<Vec<(&String, usize)>> {
+        self.precincts.get(precinct).map(majority_judgment_from_tally)
+    }
+
+    /// Function that adds up the tallies of the precincts reported so far
+    ///
+    /// # Returns
+    /// * `BTreeMap<String, BTreeMap<G, u64>>`: for each candidate, the number of votes of each grade in all the precincts
+    pub fn national_tally(&self) -> Tallies<G> {
+        let mut national: Tallies<G> = self.candidates.iter().map(|candidate| (candidate.clone(), BTreeMap::new())).collect();
+        for tallies in self.precincts.values() {
+            for (candidate, tally) in tallies {
+                let total = national.entry(candidate.clone()).or_default();
+                for (grade, &count) in tally {
+                    *total.entry(grade.clone()).or_insert(0) += count;
+                }
+            }
+        }
+        national
+    }
+
+    /// The ranking of the national tally, from the precincts reported so far
+    pub fn national_ranking(&self) -> Vec<(String, usize)> {
+        majority_judgment_from_tally(&self.national_tally())
+            .into_iter()
+            .map(|(candidate, rank)| (candidate.clone(), rank))
+            .collect()
+    }
+}
+
+/// What is wrong with the tally of a precinct
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrecinctIssue {
+    /// The tally does not count this candidate
+    MissingCandidate(String),
+    /// The tally counts a candidate who is not running
+    UnknownCandidate(String),
+    /// The candidate is graded by a different number of votes than the others
+    UnequalVotes { candidate: String, votes: u64, expected: u64 },
+}
+
+/// A tally refused by `PrecinctResults::report`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrecinctError {
+    /// The precinct reporting the tally
+    pub precinct: String,
+    /// What is wrong with it
+    pub issue: PrecinctIssue,
+}
+
+impl fmt::Display for PrecinctError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.issue {
+            PrecinctIssue::MissingCandidate(candidate) =>
+                write!(f, "precinct {:?}: no tally for candidate {:?}", self.precinct, candidate),
+            PrecinctIssue::UnknownCandidate(candidate) =>
+                write!(f, "precinct {:?}: unknown candidate {:?}", self.precinct, candidate),
+            PrecinctIssue::UnequalVotes { candidate, votes, expected } =>
+                write!(f, "precinct {:?}: candidate {:?} has {} votes instead of {}", self.precinct, candidate, votes, expected),
+        }
+    }
+}
+
+impl std::error::Error for PrecinctError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tallies(pizza: &[(u8, u64)], chips: &[(u8, u64)]) -> Tallies<u8> {
+        BTreeMap::from([
+            ("Pizza".to_string(), pizza.iter().copied().collect()),
+            ("Chips".to_string(), chips.iter().copied().collect()),
+        ])
+    }
+
+    #[test]
+    fn calling_report() {
+        let mut results = PrecinctResults::new(["Pizza", "Chips"]);
+        assert_eq!(results.national_ranking(), vec![("Chips".to_string(), 0), ("Pizza".to_string(), 0)]);
+
+        assert_eq!(results.report("North", tallies(&[(0, 1), (3, 2)], &[(1, 3)])), Ok(None));
+        assert_eq!(results.report("South", tallies(&[(2, 4)], &[(1, 1), (3, 3)])), Ok(None));
+        assert_eq!(results.national_tally()["Pizza"], BTreeMap::from([(0, 1), (2, 4), (3, 2)]));
+        assert_eq!(results.precinct_ranking("North").unwrap(), vec![(&"Pizza".to_string(), 0), (&"Chips".to_string(), 1)]);
+        assert_eq!(results.precinct_ranking("South").unwrap()[0].0, "Chips");
+        assert_eq!(results.precinct_ranking("East"), None);
+
+        // a recount replaces the previous tally
+        let recount = results.report("South", tallies(&[(2, 4)], &[(1, 4)])).unwrap();
+        assert_eq!(recount, Some(tallies(&[(2, 4)], &[(1, 1), (3, 3)])));
+        assert_eq!(results.national_tally()["Chips"], BTreeMap::from([(1, 7)]));
+        assert_eq!(results.precincts().collect::<Vec<_>>(), ["North", "South"]);
+    }
+
+    #[test]
+    fn calling_report_with_invalid_tally() {
+        let mut results = PrecinctResults::new(["Pizza", "Chips"]);
+        let error = results.report("West", tallies(&[(0, 2)], &[(1, 3)])).unwrap_err();
+        assert_eq!(error.to_string(), "precinct \"West\": candidate \"Pizza\" has 2 votes instead of 3");
+
+        let mut missing = tallies(&[(0, 2)], &[(1, 2)]);
+        missing.remove("Chips");
+        assert_eq!(results.report("West", missing).unwrap_err().issue, PrecinctIssue::MissingCandidate("Chips".to_string()));
+
+        let mut unknown = tallies(&[(0, 2)], &[(1, 2)]);
+        unknown.insert("Soup".to_string(), BTreeMap::from([(0, 2)]));
+        assert_eq!(results.report("West", unknown).unwrap_err().issue, PrecinctIssue::UnknownCandidate("Soup".to_string()));
+        assert_eq!(results.precincts().count(), 0);
+    }
+}
@@ -0,0 +1,19 @@
+//! # Prelude
+//! The types and functions needed by most polls, imported at once:
+//!
+//! ```
+//! use majority_judgement_rust::prelude::*;
+//!
+//! let mut poll = Poll::new(["Pizza", "Chips"]);
+//! poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2));
+//! poll.add_ballot(Ballot::new().grade("Pizza", 1).grade("Chips", 3));
+//!
+//! let poll_data = poll.poll_data().unwrap();
+//! let ranking = majority_judgment_with_options(&poll_data, &Options::new().method(BuiltinMethod::UsualJudgment));
+//! assert_eq!(ranking.candidates()[0].tie, TieStatus::Unique);
+//! assert_eq!(*ranking.winners()[0], "Chips");
+//! ```
+pub use crate::methods::{majority_judgment, majority_judgment_ranking, majority_judgment_with_options, BuiltinMethod, Options, TieBreaker};
+pub use crate::poll::{AbstentionPolicy, Ballot, Poll};
+pub use crate::ranking::{RankedCandidate, Ranking, TieStatus};
+pub use crate::scale::GradeScale;
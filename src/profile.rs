@@ -0,0 +1,109 @@
+//! # Merit profile
+//! The share of each grade received by each candidate, the data behind the classic
+//! stacked-bar chart of the majority judgment: one bar per candidate, split by grade
+//! from the worst to the best, the majority grade lying under the 50% mark.
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+use crate::mj::compute_frequency_of_grades;
+
+/// Function that computes the merit profile of every candidate
+///
+/// Every profile lists the same grades, all the grades given in the poll from the worst to the best,
+/// so that the bars of the candidates line up; a grade a candidate never received has a count of 0.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+///
+/// # Returns
+/// * `BTreeMap<&K, Vec<(G, u64, f64)>>`: for each candidate, each grade with the number of times
+///   it was given and its percentage of the grades of the candidate, between 0 and 100
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::merit_profile;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![0, 2, 2, 2]);
+/// poll_data.insert("Chips".to_string(), vec![1, 1, 2, 2]);
+///
+/// let profile = merit_profile(&poll_data);
+/// assert_eq!(profile[&"Pizza".to_string()], vec![(0, 1, 25.0), (1, 0, 0.0), (2, 3, 75.0)]);
+/// ```
+pub fn merit_profile<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>) -> BTreeMap<&K, Vec<(G, u64, f64)>> {
+    let grades: BTreeSet<&G> = poll_data.values().flatten().collect();
+
+    poll_data
+        .iter()
+        .map(|(candidate, candidate_grades)| (candidate, profile(&compute_frequency_of_grades(candidate_grades), &grades)))
+        .collect()
+}
+
+/// Function that computes the merit profile of every candidate of a poll given as the number of votes of each grade
+///
+/// # Arguments
+/// * `tallies`: for each candidate, the number of votes of each grade, the absent grades counting 0
+///
+/// # Returns
+/// * `BTreeMap<&K, Vec<(G, u64, f64)>>`: the profiles of `merit_profile`, listing the grades with at least one vote
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::merit_profile_from_tally;
+/// let mut tallies = BTreeMap::new();
+/// tallies.insert("Pizza".to_string(), BTreeMap::from([(0, 1u64), (2, 3_000_000_000)]));
+///
+/// let profile = merit_profile_from_tally(&tallies);
+/// assert_eq!(profile[&"Pizza".to_string()][1].0, 2);
+/// ```
+pub fn merit_profile_from_tally<K: Ord, G: Ord + Clone>(tallies: &BTreeMap<K, BTreeMap<G, u64>>) -> BTreeMap<&K, Vec<(G, u64, f64)>> {
+    let grades: BTreeSet<&G> = tallies
+        .values()
+        .flatten()
+        .filter(|&(_, &count)| count > 0)
+        .map(|(grade, _)| grade)
+        .collect();
+
+    tallies.iter().map(|(candidate, tally)| (candidate, profile(tally, &grades))).collect()
+}
+
+/// Function that lists the count and the percentage of each grade of a candidate
+fn profile<G: Ord + Clone>(tally: &BTreeMap<G, u64>, grades: &BTreeSet<&G>) -> Vec<(G, u64, f64)> {
+    let total = tally.values().sum::<u64>().max(1) as f64;
+    grades
+        .iter()
+        .map(|&grade| {
+            let count = tally.get(grade).copied().unwrap_or(0);
+            (grade.clone(), count, 100.0 * count as f64 / total)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_merit_profile() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![3, 0, 3]);
+        poll_data.insert("Chips".to_string(), vec![]);
+
+        let profile = merit_profile(&poll_data);
+        assert_eq!(profile[&"Pizza".to_string()].iter().map(|&(grade, count, _)| (grade, count)).collect::<Vec<_>>(),
+                   vec![(0, 1), (3, 2)]);
+        assert_eq!(profile[&"Chips".to_string()], vec![(0, 0, 0.0), (3, 0, 0.0)]);
+
+        let shares: f64 = profile[&"Pizza".to_string()].iter().map(|&(_, _, share)| share).sum();
+        assert!((shares - 100.0).abs() < 1e-9);
+
+        let tallies: BTreeMap<String, BTreeMap<u8, u64>> = poll_data
+            .iter()
+            .map(|(candidate, grades)| (candidate.clone(), compute_frequency_of_grades(grades)))
+            .collect();
+        assert_eq!(merit_profile_from_tally(&tallies), profile);
+        let mut tallies = tallies;
+        tallies.get_mut("Chips").unwrap().insert(5, 0);
+        assert_eq!(merit_profile_from_tally(&tallies), profile);
+    }
+}
@@ -0,0 +1,261 @@
+//! # Progress of long tallies
+//! Counting millions of ballots takes a while: a command line tool shows a progress bar, a service reports an
+//! estimated completion. `ProgressTally` counts ballots into a `TallyAccumulator` and calls the hook set with
+//! `ProgressTally::on_progress` as it goes, first while the ballots are read (`ProgressStage::Ingestion`, every
+//! `ProgressTally::every` ballots and at the end of each call), then once per candidate evaluated by the
+//! ranking (`ProgressStage::Ranking`). Without a hook the ballots are only counted, the time is not measured.
+use std::fmt;
+use std::num::NonZeroU64;
+use std::time::{Duration, Instant};
+use crate::accumulator::TallyAccumulator;
+use crate::poll::{Ballot, BallotIssue};
+use crate::ranking::Ranking;
+
+/// The step of a tally reported by a `Progress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ProgressStage {
+    /// The ballots are read and counted, `Progress::done` counting the ballots read, valid or not
+    Ingestion,
+    /// The candidates are evaluated and sorted, `Progress::done` counting the candidates evaluated
+    Ranking,
+}
+
+/// How far a stage of a tally is, given to the hook of `ProgressTally::on_progress`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub stage: ProgressStage,
+    /// The ballots read or the candidates evaluated so far
+    pub done: u64,
+    /// The number expected at the end of the stage, if known, see `ProgressTally::total`
+    pub total: Option<u64>,
+    /// The time since the start of the stage
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// The share of the stage done, between 0 and 1, None if the total is not known
+    pub fn fraction(&self) -> Option<f64> {
+        self.total.map(|total| if total == 0 { 1.0 } else { (self.done as f64 / total as f64).min(1.0) })
+    }
+
+    /// Function that estimates the time left before the end of the stage, at the pace so far
+    ///
+    /// # Returns
+    /// * `Option<Duration>`: the time left, None if the total is not known or nothing is done yet
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use majority_judgement_rust::{Progress, ProgressStage};
+    /// let progress = Progress { stage: ProgressStage::Ingestion, done: 250, total: Some(1000), elapsed: Duration::from_secs(10) };
+    /// assert_eq!(progress.fraction(), Some(0.25));
+    /// assert_eq!(progress.estimated_remaining(), Some(Duration::from_secs(30)));
+    /// ```
+    pub fn estimated_remaining(&self) -> Option<Duration> {
+        let total = self.total?;
+        if self.done == 0 {
+            return None;
+        }
+        Some(self.elapsed.mul_f64(total.saturating_sub(self.done) as f64 / self.done as f64))
+    }
+}
+
+impl fmt::Display for Progress {
+    /// Write e.g. "ingestion: 250/1000 (25%), 30s left"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self.stage {
+            ProgressStage::Ingestion => "ingestion",
+            ProgressStage::Ranking => "ranking",
+        };
+        write!(f, "{}: {}", stage, self.done)?;
+        if let (Some(total), Some(fraction)) = (self.total, self.fraction()) {
+            write!(f, "/{} ({:.0}%)", total, fraction * 100.0)?;
+        }
+        if let Some(remaining) = self.estimated_remaining() {
+            write!(f, ", {}s left", remaining.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+/// The ballots read between two reports of the ingestion, by default
+const DEFAULT_EVERY: NonZeroU64 = NonZeroU64::new(10_000).unwrap();
+
+/// A `TallyAccumulator` reporting its progress to a hook
+///
+/// # Example
+/// ```
+/// use std::num::NonZeroU64;
+/// use majority_judgement_rust::{Ballot, ProgressTally, ProgressStage, TallyAccumulator};
+/// let ballots: Vec<Ballot<u8>> = (0..25).map(|voter| Ballot::new().grade("Pizza", voter % 4).grade("Chips", 0)).collect();
+///
+/// let mut reports = Vec::new();
+/// let mut tally = ProgressTally::new(TallyAccumulator::new(["Pizza", "Chips"]))
+///     .total(25)
+///     .every(NonZeroU64::new(10).unwrap())
+///     .on_progress(|progress| reports.push((progress.stage, progress.done)));
+/// assert!(tally.add_ballots(&ballots).is_empty());
+/// assert_eq!(tally.current_ranking().winners(), vec![&"Pizza"]);
+/// drop(tally);
+///
+/// assert_eq!(reports, [(ProgressStage::Ingestion, 10), (ProgressStage::Ingestion, 20), (ProgressStage::Ingestion, 25), (ProgressStage::Ranking, 1), (ProgressStage::Ranking, 2)]);
+/// ```
+pub struct ProgressTally<'f, G> {
+    tally: TallyAccumulator<G>,
+    on_progress: Option<Box<dyn FnMut(Progress) + 'f>>,
+    total: Option<u64>,
+    every: NonZeroU64,
+    /// The ballots read so far, valid or not
+    read: u64,
+    /// The start of the ingestion, when the first ballots are read with a hook
+    started: Option<Instant>,
+}
+
+impl<G: fmt::Debug> fmt::Debug for ProgressTally<'_, G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressTally")
+            .field("tally", &self.tally)
+            .field("total", &self.total)
+            .field("every", &self.every)
+            .field("read", &self.read)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'f, G: Ord + Clone> ProgressTally<'f, G> {
+    /// Create a tally counting into an accumulator, without any hook
+    ///
+    /// # Arguments
+    /// * `tally`: the accumulator, with its candidates, method and grade range
+    pub fn new(tally: TallyAccumulator<G>) -> Self {
+        ProgressTally { tally, on_progress: None, total: None, every: DEFAULT_EVERY, read: 0, started: None }
+    }
+
+    /// Set the hook called with the progress of the tally, builder style
+    pub fn on_progress(mut self, f: impl FnMut(Progress) + 'f) -> Self {
+        self.on_progress = Some(Box::new(f));
+        self
+    }
+
+    /// Set the number of ballots expected, for the fraction done and the time left of the ingestion, builder style
+    pub fn total(mut self, total: u64) -> Self {
+        self.total = Some(total);
+        self
+    }
+
+    /// Set the number of ballots read between two reports of the ingestion, 10 000 by default, builder style
+    pub fn every(mut self, every: NonZeroU64) -> Self {
+        self.every = every;
+        self
+    }
+
+    /// The accumulator with the ballots counted so far
+    pub fn accumulator(&self) -> &TallyAccumulator<G> {
+        &self.tally
+    }
+
+    /// The accumulator, the hook being dropped
+    pub fn into_inner(self) -> TallyAccumulator<G> {
+        self.tally
+    }
+
+    /// Function that counts ballots, reporting the progress of the ingestion
+    ///
+    /// # Arguments
+    /// * `ballots`: the ballots, e.g. a slice or an iterator reading a file. The invalid ballots are not counted
+    ///   and do not stop the ingestion.
+    ///
+    /// # Returns
+    /// * `Vec<(u64, Vec<BallotIssue>)>`: the position of each invalid ballot among all the ballots read by the
+    ///   tally, with its issues
+    pub fn add_ballots<'b, I>(&mut self, ballots: I) -> Vec<(u64, Vec<BallotIssue>)>
+    where
+        I: IntoIterator<Item = &'b Ballot<G>>,
+        G: 'b,
+    {
+        let started = match self.on_progress {
+            Some(_) => Some(*self.started.get_or_insert_with(Instant::now)),
+            None => None,
+        };
+        let mut invalid = Vec::new();
+        let mut reported = None;
+        for ballot in ballots {
+            if let Err(issues) = self.tally.add_ballot(ballot) {
+                invalid.push((self.read, issues));
+            }
+            self.read += 1;
+            if let Some(started) = started.filter(|_| self.read.is_multiple_of(self.every.get())) {
+                self.report(ProgressStage::Ingestion, self.read, self.total, started.elapsed());
+                reported = Some(self.read);
+            }
+        }
+        if let Some(started) = started.filter(|_| reported != Some(self.read)) {
+            self.report(ProgressStage::Ingestion, self.read, self.total, started.elapsed());
+        }
+        invalid
+    }
+
+    /// Function that ranks the candidates from the ballots counted so far, reporting each candidate evaluated
+    ///
+    /// # Returns
+    /// * `Ranking`: the ranking of `TallyAccumulator::current_ranking`
+    pub fn current_ranking(&mut self) -> Ranking<'_, String, G> {
+        let started = self.on_progress.as_ref().map(|_| Instant::now());
+        let total = Some(self.tally.candidates().len() as u64);
+        let method = self.tally.options_method();
+        let on_progress = &mut self.on_progress;
+        self.tally.ranking_reporting(&method, |evaluated| {
+            if let (Some(on_progress), Some(started)) = (on_progress.as_mut(), started) {
+                on_progress(Progress { stage: ProgressStage::Ranking, done: evaluated as u64, total, elapsed: started.elapsed() });
+            }
+        })
+    }
+
+    fn report(&mut self, stage: ProgressStage, done: u64, total: Option<u64>, elapsed: Duration) {
+        if let Some(on_progress) = self.on_progress.as_mut() {
+            on_progress(Progress { stage, done, total, elapsed });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_add_ballots() {
+        let ballots: Vec<Ballot<u8>> = (0..7).map(|voter| Ballot::new().grade("Pizza", voter % 3).grade("Chips", 2)).collect();
+        let invalid = Ballot::new().grade("Pizza", 1);
+        let mut reports = Vec::new();
+        let mut tally = ProgressTally::new(TallyAccumulator::new(["Pizza", "Chips"])).every(NonZeroU64::new(3).unwrap()).on_progress(|progress| reports.push(progress));
+        let issues = tally.add_ballots(ballots.iter().take(3).chain([&invalid]));
+        assert_eq!(issues, [(3, vec![BallotIssue::MissingGrade("Chips".to_string())])]);
+        assert!(tally.add_ballots(&ballots[3..]).is_empty());
+        assert!(tally.add_ballots(&[]).is_empty());
+        assert_eq!(tally.accumulator().ballots(), 7);
+        assert_eq!(tally.current_ranking().winners(), vec![&"Chips"]);
+        drop(tally);
+
+        let done: Vec<(ProgressStage, u64, Option<u64>)> = reports.iter().map(|progress| (progress.stage, progress.done, progress.total)).collect();
+        assert_eq!(done, [
+            (ProgressStage::Ingestion, 3, None),
+            (ProgressStage::Ingestion, 4, None),
+            (ProgressStage::Ingestion, 6, None),
+            (ProgressStage::Ingestion, 8, None),
+            (ProgressStage::Ingestion, 8, None),
+            (ProgressStage::Ranking, 1, Some(2)),
+            (ProgressStage::Ranking, 2, Some(2)),
+        ]);
+        assert_eq!((reports[0].fraction(), reports[0].estimated_remaining()), (None, None));
+        assert_eq!(reports[6].to_string(), "ranking: 2/2 (100%), 0s left");
+
+        // without a hook the ballots are counted all the same
+        let mut silent = ProgressTally::new(TallyAccumulator::new(["Pizza", "Chips"]));
+        assert!(silent.add_ballots(&ballots).is_empty());
+        assert_eq!((silent.accumulator().ballots(), silent.started), (7, None));
+        assert_eq!(silent.current_ranking().winners(), vec![&"Chips"]);
+
+        let start = Progress { stage: ProgressStage::Ingestion, done: 0, total: Some(0), elapsed: Duration::ZERO };
+        assert_eq!((start.fraction(), start.estimated_remaining(), start.to_string()), (Some(1.0), None, "ingestion: 0/0 (100%)".to_string()));
+    }
+}
@@ -0,0 +1,260 @@
+//! # Exit-poll projections
+//! On election night the media report the results before every precinct has counted. The partial national
+//! tally of `PrecinctResults` is not the final one: a precinct counted at 10% weighs as little as its counted
+//! votes, and the precincts still to report do not weigh at all. `project_majority_grades` scales each reported
+//! precinct up to its expected turnout, fills the precincts still to report with the grades of the reported
+//! ones and ranks the projected tally. The confidence band of each majority grade comes from resampling the
+//! reported precincts (bootstrap): it narrows as the precincts report, and closes once they all have.
+use std::collections::BTreeMap;
+use crate::judgment::median_shares;
+use crate::mj::majority_judgment_from_tally;
+use crate::precinct::{PrecinctResults, Tallies};
+use crate::tie_breaker::splitmix64;
+
+/// The projected majority grade of a candidate, see `project_majority_grades`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectedGrade<G> {
+    /// The majority grade of the projected tally, None without any vote
+    pub majority_grade: Option<G>,
+    /// The lowest majority grade of the confidence band
+    pub low: Option<G>,
+    /// The highest majority grade of the confidence band
+    pub high: Option<G>,
+    /// The share of the resampled projections ranking the candidate first, ties included
+    pub win_share: f64,
+}
+
+/// The projected results of an election in progress, see `project_majority_grades`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Projection<G> {
+    /// The share of the expected votes counted so far, from 0 to 1
+    pub counted_share: f64,
+    /// For each candidate, the projected number of votes of each grade
+    pub tallies: BTreeMap<String, BTreeMap<G, u64>>,
+    /// The ranking of the projected tally
+    pub ranking: Vec<(String, usize)>,
+    /// For each candidate, its projected majority grade and confidence band
+    pub grades: BTreeMap<String, ProjectedGrade<G>>,
+}
+
+/// The number of votes counted in a precinct, the same for every candidate
+fn votes<G>(tallies: &Tallies<G>) -> u64 {
+    tallies.values().next().map_or(0, |tally| tally.values().sum())
+}
+
+/// Function that scales a tally to a number of votes, the votes left by the rounding going to the largest
+/// remainders, so that every candidate of a precinct gets exactly as many votes
+fn scale_tally<G: Ord + Clone>(tally: &BTreeMap<G, u64>, votes: u64) -> BTreeMap<G, u64> {
+    let total: u128 = tally.values().map(|&count| count as u128).sum();
+    if total == 0 {
+        return BTreeMap::new();
+    }
+    let mut scaled = BTreeMap::new();
+    let mut remainders = Vec::with_capacity(tally.len());
+    let mut left = votes;
+    for (grade, &count) in tally {
+        let share = count as u128 * votes as u128;
+        let quota = (share / total) as u64;
+        left -= quota;
+        scaled.insert(grade.clone(), quota);
+        remainders.push((share % total, grade));
+    }
+    // the largest remainders first, the lowest grade first among equal remainders
+    remainders.sort_by_key(|&(remainder, _)| core::cmp::Reverse(remainder));
+    for (_, grade) in remainders.into_iter().take(left as usize) {
+        *scaled.get_mut(grade).expect("a grade of the tally") += 1;
+    }
+    scaled.retain(|_, count| *count > 0);
+    scaled
+}
+
+/// Function that adds up the tallies of some precincts, for every candidate
+fn add_up<'t, G: Ord + Clone + 't>(candidates: &[String], precincts: impl Iterator<Item = &'t Tallies<G>>) -> Tallies<G> {
+    let mut sum: Tallies<G> = candidates.iter().map(|candidate| (candidate.clone(), BTreeMap::new())).collect();
+    for tallies in precincts {
+        for (candidate, tally) in tallies {
+            let total = sum.get_mut(candidate).expect("a candidate of the results");
+            for (grade, &count) in tally {
+                *total.entry(grade.clone()).or_insert(0) += count;
+            }
+        }
+    }
+    sum
+}
+
+/// Function that projects the final majority grades of an election from the precincts reported so far
+///
+/// Each reported precinct is scaled up to its expected turnout. Each precinct still to report is filled with
+/// its expected turnout, graded like all the reported precincts together. Each resampled projection draws as
+/// many reported precincts as have reported, with replacement, to grade the precincts still to report;
+/// the draws are reproducible: the same seed gives the same bands.
+///
+/// # Arguments
+/// * `results`: the precincts reported so far
+/// * `turnout`: the expected number of votes of each precinct, a reported precinct left out counting for the
+///   votes it reported
+/// * `level`: the confidence level of the bands, e.g. 0.9 to leave out the 5% lowest and the 5% highest
+///   resampled majority grades
+/// * `iterations`: the number of resampled projections, e.g. 1000
+/// * `seed`: the seed of the draws
+///
+/// # Returns
+/// * `Projection<G>`: the projected tally, ranking and majority grades, with a confidence band for each grade
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{project_majority_grades, PrecinctResults};
+/// let mut results = PrecinctResults::new(["Pizza", "Chips"]);
+/// // North counted 40 of its 400 votes, South counted all of its 50 votes
+/// results.report("North", BTreeMap::from([
+///     ("Pizza".to_string(), BTreeMap::from([(0u8, 10), (2, 30)])),
+///     ("Chips".to_string(), BTreeMap::from([(1u8, 40)])),
+/// ])).unwrap();
+/// results.report("South", BTreeMap::from([
+///     ("Pizza".to_string(), BTreeMap::from([(0u8, 50)])),
+///     ("Chips".to_string(), BTreeMap::from([(1u8, 30), (3, 20)])),
+/// ])).unwrap();
+/// assert_eq!(results.national_ranking()[0].0, "Chips");
+///
+/// let turnout = BTreeMap::from([("North".to_string(), 400), ("South".to_string(), 50)]);
+/// let projection = project_majority_grades(&results, &turnout, 0.9, 200, 42);
+/// assert_eq!(projection.ranking[0].0, "Pizza");
+/// assert_eq!(projection.tallies["Pizza"], BTreeMap::from([(0, 150), (2, 300)]));
+/// assert_eq!(projection.grades["Pizza"].majority_grade, Some(2));
+/// assert!((projection.counted_share - 0.2).abs() < 1e-9);
+/// ```
+pub fn project_majority_grades<G: Ord + Clone>(
+    results: &PrecinctResults<G>,
+    turnout: &BTreeMap<String, u64>,
+    level: f64,
+    iterations: usize,
+    seed: u64,
+) -> Projection<G> {
+    let candidates = results.candidates();
+    let mut counted = 0;
+    let mut expected = 0;
+    let mut scaled = Vec::new();
+    for precinct in results.precincts() {
+        let tallies = results.precinct(precinct).expect("a reported precinct");
+        let votes = votes(tallies);
+        // a precinct without any vote counted yet is still to report
+        if votes > 0 {
+            let turnout = turnout.get(precinct).copied().unwrap_or(votes);
+            counted += votes;
+            expected += turnout;
+            scaled.push(tallies.iter().map(|(candidate, tally)| (candidate.clone(), scale_tally(tally, turnout))).collect::<Tallies<G>>());
+        }
+    }
+    let remaining: Vec<u64> = turnout
+        .iter()
+        .filter(|(precinct, _)| results.precinct(precinct).is_none_or(|tallies| votes(tallies) == 0))
+        .map(|(_, &votes)| votes)
+        .collect();
+    expected += remaining.iter().sum::<u64>();
+
+    let known = add_up(candidates, scaled.iter());
+    // the reported precincts, plus the precincts still to report graded like the pooled precincts
+    let project = |pool: &Tallies<G>| -> Tallies<G> {
+        let filled: Vec<Tallies<G>> = remaining
+            .iter()
+            .map(|&votes| pool.iter().map(|(candidate, tally)| (candidate.clone(), scale_tally(tally, votes))).collect())
+            .collect();
+        add_up(candidates, std::iter::once(&known).chain(filled.iter()))
+    };
+    let tallies = project(&known);
+
+    let mut resampled: BTreeMap<&String, Vec<Option<G>>> = candidates.iter().map(|candidate| (candidate, Vec::new())).collect();
+    let mut wins: BTreeMap<&String, usize> = candidates.iter().map(|candidate| (candidate, 0)).collect();
+    let mut draws = 0;
+    // nothing to resample once every precinct has reported
+    if !scaled.is_empty() && !remaining.is_empty() {
+        let mut state = seed;
+        for _ in 0..iterations {
+            let drawn = (0..scaled.len()).map(|_| {
+                let index = (splitmix64(state) % scaled.len() as u64) as usize;
+                state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                &scaled[index]
+            });
+            let projected = project(&add_up(candidates, drawn));
+            for (candidate, rank) in majority_judgment_from_tally(&projected) {
+                *wins.get_mut(candidate).expect("a candidate of the results") += (rank == 0) as usize;
+            }
+            for (candidate, tally) in &projected {
+                resampled.get_mut(candidate).expect("a candidate of the results").push(median_shares(tally).map(|shares| shares.median));
+            }
+            draws += 1;
+        }
+    }
+
+    let ranking: Vec<(String, usize)> = majority_judgment_from_tally(&tallies)
+        .into_iter()
+        .map(|(candidate, rank)| (candidate.clone(), rank))
+        .collect();
+    let grades = ranking
+        .iter()
+        .map(|(candidate, rank)| {
+            let majority_grade = median_shares(&tallies[candidate]).map(|shares| shares.median);
+            let mut grades = resampled.remove(candidate).unwrap_or_default();
+            grades.sort();
+            // as many resampled grades left out below the band as above it
+            let outside = ((grades.len() as f64 * (1.0 - level.clamp(0.0, 1.0)) / 2.0) as usize).min(grades.len().saturating_sub(1) / 2);
+            let grade = ProjectedGrade {
+                low: grades.get(outside).cloned().unwrap_or_else(|| majority_grade.clone()),
+                high: grades.iter().rev().nth(outside).cloned().unwrap_or_else(|| majority_grade.clone()),
+                win_share: match draws {
+                    0 => (*rank == 0) as u8 as f64,
+                    _ => wins[candidate] as f64 / draws as f64,
+                },
+                majority_grade,
+            };
+            (candidate.clone(), grade)
+        })
+        .collect();
+    let counted_share = if expected > 0 { counted as f64 / expected as f64 } else { 0.0 };
+    Projection { counted_share, tallies, ranking, grades }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tallies(pizza: &[(u8, u64)], chips: &[(u8, u64)]) -> Tallies<u8> {
+        BTreeMap::from([
+            ("Pizza".to_string(), pizza.iter().copied().collect()),
+            ("Chips".to_string(), chips.iter().copied().collect()),
+        ])
+    }
+
+    #[test]
+    fn calling_project_majority_grades() {
+        assert_eq!(scale_tally(&BTreeMap::from([(0u8, 1), (1, 1), (2, 1)]), 10), BTreeMap::from([(0, 4), (1, 3), (2, 3)]));
+        assert_eq!(scale_tally(&BTreeMap::from([(0u8, u64::MAX), (1, 1)]), u64::MAX), BTreeMap::from([(0, u64::MAX - 1), (1, 1)]));
+
+        let mut results = PrecinctResults::new(["Pizza", "Chips"]);
+        results.report("North", tallies(&[(3, 10)], &[(0, 7), (1, 3)])).unwrap();
+        results.report("South", tallies(&[(0, 10)], &[(2, 5), (3, 5)])).unwrap();
+        results.report("West", tallies(&[], &[])).unwrap();
+        let turnout = BTreeMap::from([("North".to_string(), 10), ("South".to_string(), 10), ("East".to_string(), 1000), ("West".to_string(), 5)]);
+
+        // East and West, still to report, weigh much more than North and South
+        let projection = project_majority_grades(&results, &turnout, 0.9, 200, 7);
+        assert!((projection.counted_share - 20.0 / 1025.0).abs() < 1e-12);
+        assert!(projection.tallies.values().all(|tally| tally.values().sum::<u64>() == 1025));
+        assert_eq!(projection.grades["Pizza"].majority_grade, Some(0));
+        assert_eq!((projection.grades["Pizza"].low, projection.grades["Pizza"].high), (Some(0), Some(3)));
+        let share = projection.grades["Pizza"].win_share + projection.grades["Chips"].win_share;
+        assert!(projection.grades["Pizza"].win_share > 0.0 && share >= 1.0);
+        assert_eq!(project_majority_grades(&results, &turnout, 0.9, 200, 7), projection);
+
+        // the bands close once every precinct has reported
+        results.report("East", tallies(&[(2, 1000)], &[(1, 1000)])).unwrap();
+        results.report("West", tallies(&[(2, 5)], &[(1, 5)])).unwrap();
+        let projection = project_majority_grades(&results, &turnout, 0.9, 200, 7);
+        assert_eq!(projection.counted_share, 1.0);
+        assert_eq!(projection.tallies, results.national_tally());
+        assert_eq!(projection.ranking, results.national_ranking());
+        let pizza = &projection.grades["Pizza"];
+        assert_eq!((pizza.majority_grade, pizza.low, pizza.high, pizza.win_share), (Some(2), Some(2), Some(2), 1.0));
+    }
+}
@@ -0,0 +1,256 @@
+//! # Quorum and validity thresholds
+//! Statutes often void a vote with too few voters, and refuse to elect a candidate most voters did not grade
+//! or whose majority grade is too low, e.g. "Insufficient". `ValidityRules` states these thresholds and
+//! `rank_validated` applies them with the ranking: the poll and the candidates failing them are marked
+//! as not valid in the result instead of being silently ranked.
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::abstention::AbstentionPolicy;
+use crate::mj::majority_judgment_with_options;
+use crate::options::Options;
+use crate::poll::{BallotError, Poll};
+use crate::ranking::OwnedRanking;
+
+/// The thresholds a poll and its candidates must reach, none by default
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{rank_validated, Ballot, CandidateInvalidity, Options, Poll, ValidityRules};
+/// let mut poll = Poll::new(["Pizza", "Chips"]);
+/// poll.add_ballot(Ballot::new().grade("Pizza", 1).grade("Chips", 0));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 2));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 1));
+///
+/// let rules = ValidityRules::new().min_ballots(3).min_expressed_share(0.5).min_majority_grade(1);
+/// let validated = rank_validated(&poll, &rules, &Options::new()).unwrap();
+/// assert!(validated.is_valid());
+/// assert_eq!(validated.ranking.ranking().winners(), vec!["Pizza"]);
+/// assert_eq!(validated.candidate_issues["Chips"][0], CandidateInvalidity::TooManyAbstentions { expressed: 1.0 / 3.0, required: 0.5 });
+/// assert_eq!(validated.elected(), vec!["Pizza"]);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidityRules<G> {
+    min_ballots: usize,
+    turnout: Option<(usize, f64)>,
+    min_expressed_share: Option<f64>,
+    min_majority_grade: Option<G>,
+    abstention: AbstentionPolicy<G>,
+}
+
+impl<G> Default for ValidityRules<G> {
+    fn default() -> Self {
+        ValidityRules {
+            min_ballots: 0,
+            turnout: None,
+            min_expressed_share: None,
+            min_majority_grade: None,
+            abstention: AbstentionPolicy::default(),
+        }
+    }
+}
+
+impl<G> ValidityRules<G> {
+    /// The rules without any threshold, the missing grades counting as the worst grade
+    pub fn new() -> Self {
+        ValidityRules::default()
+    }
+
+    /// Require at least this number of ballots, whatever their weights, builder style
+    pub fn min_ballots(mut self, ballots: usize) -> Self {
+        self.min_ballots = ballots;
+        self
+    }
+
+    /// Require the ballots to be at least a share of the electorate, builder style
+    ///
+    /// # Arguments
+    /// * `electorate`: the number of registered voters
+    /// * `share`: the minimum turnout, between 0 and 1, e.g. 0.25 for a quarter of the electorate
+    pub fn min_turnout(mut self, electorate: usize, share: f64) -> Self {
+        self.turnout = Some((electorate, share));
+        self
+    }
+
+    /// Require each candidate to be graded by at least a share of the ballots, the others being abstentions,
+    /// builder style. A candidate below it is not electable.
+    pub fn min_expressed_share(mut self, share: f64) -> Self {
+        self.min_expressed_share = Some(share);
+        self
+    }
+
+    /// Require an electable candidate to have at least this majority grade, builder style
+    pub fn min_majority_grade(mut self, grade: G) -> Self {
+        self.min_majority_grade = Some(grade);
+        self
+    }
+
+    /// Set how the missing grades are counted by the ranking, builder style
+    pub fn abstention(mut self, policy: AbstentionPolicy<G>) -> Self {
+        self.abstention = policy;
+        self
+    }
+}
+
+/// Why a whole poll is not valid
+#[derive(Debug, Clone, PartialEq)]
+pub enum PollInvalidity {
+    /// Fewer ballots than `ValidityRules::min_ballots`
+    TooFewBallots { ballots: usize, required: usize },
+    /// A turnout below `ValidityRules::min_turnout`, as shares of the electorate
+    LowTurnout { turnout: f64, required: f64 },
+}
+
+impl fmt::Display for PollInvalidity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PollInvalidity::TooFewBallots { ballots, required } => write!(f, "{} ballots, at least {} required", ballots, required),
+            PollInvalidity::LowTurnout { turnout, required } =>
+                write!(f, "turnout of {:.1}%, at least {:.1}% required", 100.0 * turnout, 100.0 * required),
+        }
+    }
+}
+
+/// Why a candidate is not electable
+#[derive(Debug, Clone, PartialEq)]
+pub enum CandidateInvalidity {
+    /// A share of ballots grading the candidate below `ValidityRules::min_expressed_share`
+    TooManyAbstentions { expressed: f64, required: f64 },
+    /// A majority grade below `ValidityRules::min_majority_grade`
+    MajorityGradeTooLow,
+}
+
+impl fmt::Display for CandidateInvalidity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CandidateInvalidity::TooManyAbstentions { expressed, required } =>
+                write!(f, "graded by {:.1}% of the ballots, at least {:.1}% required", 100.0 * expressed, 100.0 * required),
+            CandidateInvalidity::MajorityGradeTooLow => write!(f, "majority grade below the minimum"),
+        }
+    }
+}
+
+/// A ranking with the thresholds it fails, see `rank_validated`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidatedRanking<G> {
+    /// The ranking of all the candidates, electable or not
+    pub ranking: OwnedRanking<String, G>,
+    /// Why the poll is not valid, empty if it is
+    pub poll_issues: Vec<PollInvalidity>,
+    /// Why each candidate who is not electable is not, the electable candidates being left out
+    pub candidate_issues: BTreeMap<String, Vec<CandidateInvalidity>>,
+}
+
+impl<G: Clone> ValidatedRanking<G> {
+    /// Whether the poll reaches its quorum and turnout
+    pub fn is_valid(&self) -> bool {
+        self.poll_issues.is_empty()
+    }
+
+    /// Whether a candidate reaches the thresholds of the candidates
+    pub fn is_electable(&self, candidate: &str) -> bool {
+        !self.candidate_issues.contains_key(candidate)
+    }
+
+    /// The best ranked electable candidates, several of them for a tie, none if the poll is not valid
+    pub fn elected(&self) -> Vec<&str> {
+        if !self.is_valid() {
+            return Vec::new();
+        }
+        let ranking = self.ranking.ranking();
+        let electable: Vec<(&str, usize)> = ranking
+            .iter()
+            .filter(|ranked| self.is_electable(ranked.candidate))
+            .map(|ranked| (ranked.candidate.as_str(), ranked.rank))
+            .collect();
+        let best = electable.first().map(|&(_, rank)| rank);
+        electable.into_iter().filter(|&(_, rank)| Some(rank) == best).map(|(candidate, _)| candidate).collect()
+    }
+}
+
+/// Function that ranks a poll and checks it against validity thresholds
+///
+/// # Arguments
+/// * `poll`: the poll, the candidates missing from a ballot being abstentions
+/// * `rules`: the thresholds, and how the ranking counts the abstentions
+/// * `options`: the options of the ranking, see `majority_judgment_with_options`
+///
+/// # Returns
+/// * `Result<ValidatedRanking<G>, Vec<BallotError>>`: the ranking of every candidate with the thresholds
+///   the poll and the candidates fail, or the duplicated and unknown candidates found on the ballots
+pub fn rank_validated<G: Ord + Clone>(poll: &Poll<G>, rules: &ValidityRules<G>, options: &Options<G>) -> Result<ValidatedRanking<G>, Vec<BallotError>> {
+    let poll_data = poll.poll_data_with_abstentions(&rules.abstention)?;
+    let ranking = majority_judgment_with_options(&poll_data, options).to_owned_ranking();
+
+    let ballots = poll.ballots().len();
+    let mut poll_issues = Vec::new();
+    if ballots < rules.min_ballots {
+        poll_issues.push(PollInvalidity::TooFewBallots { ballots, required: rules.min_ballots });
+    }
+    if let Some((electorate, required)) = rules.turnout {
+        let turnout = if electorate == 0 { 0.0 } else { ballots as f64 / electorate as f64 };
+        if turnout < required {
+            poll_issues.push(PollInvalidity::LowTurnout { turnout, required });
+        }
+    }
+
+    let mut candidate_issues: BTreeMap<String, Vec<CandidateInvalidity>> = BTreeMap::new();
+    for ranked in ranking.ranking().iter() {
+        let mut issues = Vec::new();
+        if let Some(required) = rules.min_expressed_share {
+            let graded = poll
+                .ballots()
+                .iter()
+                .filter(|ballot| ballot.grades().iter().any(|(candidate, _)| candidate == ranked.candidate))
+                .count();
+            let expressed = if ballots == 0 { 0.0 } else { graded as f64 / ballots as f64 };
+            if expressed < required {
+                issues.push(CandidateInvalidity::TooManyAbstentions { expressed, required });
+            }
+        }
+        if let Some(minimum) = &rules.min_majority_grade {
+            if ranked.majority_grade.as_ref().is_none_or(|grade| grade < minimum) {
+                issues.push(CandidateInvalidity::MajorityGradeTooLow);
+            }
+        }
+        if !issues.is_empty() {
+            candidate_issues.insert(ranked.candidate.clone(), issues);
+        }
+    }
+    Ok(ValidatedRanking { ranking, poll_issues, candidate_issues })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poll::Ballot;
+
+    #[test]
+    fn calling_rank_validated() {
+        let mut poll = Poll::new(["Pizza", "Chips", "Soup"]);
+        poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2).grade("Soup", 1));
+        poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2).grade("Soup", 1));
+        poll.add_ballot(Ballot::new().grade("Pizza", 1).grade("Soup", 1));
+
+        let validated = rank_validated(&poll, &ValidityRules::new(), &Options::new()).unwrap();
+        assert!(validated.is_valid() && validated.candidate_issues.is_empty());
+        // the abstention counts as the worst grade, 0
+        assert_eq!(validated.elected(), vec!["Chips"]);
+
+        // the winner fails the thresholds, the next electable candidate is elected
+        let rules = ValidityRules::new().min_expressed_share(0.8).min_majority_grade(1);
+        let validated = rank_validated(&poll, &rules, &Options::new()).unwrap();
+        assert_eq!(validated.candidate_issues["Chips"], [CandidateInvalidity::TooManyAbstentions { expressed: 2.0 / 3.0, required: 0.8 }]);
+        assert_eq!(validated.candidate_issues["Pizza"], [CandidateInvalidity::MajorityGradeTooLow]);
+        assert!(!validated.is_electable("Pizza") && validated.is_electable("Soup"));
+        assert_eq!(validated.elected(), vec!["Soup"]);
+        assert_eq!(validated.ranking.ranking().winners(), vec!["Chips"]);
+
+        // a poll below its quorum elects nobody
+        let rules = ValidityRules::new().min_ballots(4).min_turnout(10, 0.5);
+        let validated = rank_validated(&poll, &rules, &Options::new()).unwrap();
+        assert_eq!(validated.poll_issues, [PollInvalidity::TooFewBallots { ballots: 3, required: 4 },
+                                           PollInvalidity::LowTurnout { turnout: 0.3, required: 0.5 }]);
+        assert_eq!(validated.poll_issues[1].to_string(), "turnout of 30.0%, at least 50.0% required");
+        assert!(validated.elected().is_empty());
+    }
+}
@@ -0,0 +1,604 @@
+//! # Ranking
+//! The result of a majority judgment: the candidates ordered from the best to the worst,
+//! with their rank and whether they are tied with other candidates.
+//! Each candidate comes with its majority grade, the "mention majoritaire" announced with the results,
+//! and the `GradeShares` of the voters grading it above and below, the `p` and `q` of the majority gauge.
+//! The candidates may carry the `Metadata` attached to them in the poll, e.g. their party,
+//! so that the results are displayed without another lookup table.
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::vec;
+use crate::judgment::MedianShares;
+use crate::scale::GradeScale;
+
+/// Arbitrary information about a candidate, e.g. `"party"`, `"photo"` or `"description"`
+pub type Metadata = BTreeMap<String, String>;
+
+/// Whether a candidate could be separated from the others
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieStatus {
+    /// No other candidate has the same majority values
+    Unique,
+    /// At least one other candidate has exactly the same majority values,
+    /// the majority judgment alone cannot separate them
+    Tied,
+    /// The candidate had the same majority values as others,
+    /// it was separated from them by the tie breaker
+    Broken,
+}
+
+/// The votes (or total weight) above and below the majority grade of a candidate
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::majority_judgment_ranking;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![0, 2, 2, 3]);
+///
+/// let shares = majority_judgment_ranking(&poll_data).candidates()[0].shares.unwrap();
+/// assert_eq!((shares.above, shares.below, shares.total), (1, 1, 4));
+/// assert_eq!((shares.proponents(), shares.opponents()), (0.25, 0.25));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GradeShares {
+    /// The votes strictly above the majority grade
+    pub above: u64,
+    /// The votes strictly below the majority grade
+    pub below: u64,
+    /// All the votes of the candidate
+    pub total: u64,
+}
+
+impl GradeShares {
+    /// The share of the votes above the majority grade, the `p` of the majority gauge, between 0 and 1
+    pub fn proponents(&self) -> f64 {
+        self.above as f64 / self.total as f64
+    }
+
+    /// The share of the votes below the majority grade, the `q` of the majority gauge, between 0 and 1
+    pub fn opponents(&self) -> f64 {
+        self.below as f64 / self.total as f64
+    }
+}
+
+/// A candidate with its place in the ranking
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankedCandidate<'a, K = String, G = u8> {
+    /// The candidate
+    pub candidate: &'a K,
+    /// The rank of the candidate, 0 being the best. Tied candidates share the same rank
+    /// and the next candidate skips the ranks of the tie, e.g. 0, 1, 1, 3.
+    pub rank: usize,
+    /// Whether the candidate is tied with others
+    pub tie: TieStatus,
+    /// The majority grade of the candidate, its lower median grade. None without any grade,
+    /// or when the ranking is computed from the votes of another poll, e.g. `majority_judgment_weighted`.
+    pub majority_grade: Option<G>,
+    /// The votes above and below the majority grade, None without majority grade
+    pub shares: Option<GradeShares>,
+    /// The metadata of the candidate, see `Ranking::with_metadata`
+    pub metadata: Option<&'a Metadata>,
+}
+
+impl<K, G: Copy + Into<usize>> RankedCandidate<'_, K, G> {
+    /// The label of the majority grade in a scale, None without majority grade or out of the scale
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::majority_judgment_ranking;
+    /// use majority_judgement_rust::scale::{GradeScale, Language};
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza", vec![1u8, 3, 4]);
+    ///
+    /// let ranking = majority_judgment_ranking(&poll_data);
+    /// assert_eq!(ranking.candidates()[0].majority_grade, Some(3));
+    /// let scale = GradeScale::balinski_laraki(Language::English);
+    /// assert_eq!(ranking.candidates()[0].majority_grade_label(&scale), Some("Fairly good"));
+    /// ```
+    pub fn majority_grade_label<'s>(&self, scale: &'s GradeScale) -> Option<&'s str> {
+        self.majority_grade.and_then(|grade| scale.label(grade.into()))
+    }
+}
+
+/// The candidates ordered from the best to the worst
+///
+/// The candidates sharing the same rank are listed in the `TieOrder` of the options,
+/// by increasing key by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ranking<'a, K = String, G = u8> {
+    candidates: Vec<RankedCandidate<'a, K, G>>,
+    none_of_the_above: Option<&'a K>,
+}
+
+/// Who wins a poll with a "none of the above" candidate, see `Ranking::verdict`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict<'a, K> {
+    /// The best ranked real candidates, several of them for a tie
+    Elected(Vec<&'a K>),
+    /// "None of the above" has a better majority grade than every real candidate: nobody is elected
+    NoWinner,
+}
+
+/// A candidate whose rank changed between two rankings, see `Ranking::diff`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankMove<'a, K> {
+    /// The candidate
+    pub candidate: &'a K,
+    /// The rank in the first ranking, 0 for the winner
+    pub before: usize,
+    /// The rank in the second ranking
+    pub after: usize,
+}
+
+/// A candidate whose majority grade changed between two rankings, see `Ranking::diff`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GradeChange<'a, K, G> {
+    /// The candidate
+    pub candidate: &'a K,
+    /// The majority grade in the first ranking
+    pub before: Option<G>,
+    /// The majority grade in the second ranking
+    pub after: Option<G>,
+}
+
+/// The changes from a ranking to another, e.g. between two successive partial counts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankingDiff<'a, K, G> {
+    /// The candidates of both rankings whose rank changed, in the order of the second ranking
+    pub moves: Vec<RankMove<'a, K>>,
+    /// The candidates of both rankings whose majority grade changed, in the order of the second ranking
+    pub grade_changes: Vec<GradeChange<'a, K, G>>,
+    /// The candidates of the second ranking only, in its order
+    pub added: Vec<&'a K>,
+    /// The candidates of the first ranking only, in its order
+    pub removed: Vec<&'a K>,
+}
+
+impl<K, G> RankingDiff<'_, K, G> {
+    /// Whether both rankings rank the same candidates the same way with the same majority grades
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty() && self.grade_changes.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl<'a, K, G> Ranking<'a, K, G> {
+    /// Function that builds a ranking from groups of equivalent candidates
+    ///
+    /// # Arguments
+    /// * `groups`: the candidates sharing the same rank and their tie status, from the best group to the worst
+    pub(crate) fn from_groups(groups: Vec<(Vec<&'a K>, TieStatus)>) -> Self {
+        let mut candidates = Vec::new();
+        for (group, tie) in groups {
+            let rank = candidates.len();
+            for candidate in group {
+                candidates.push(RankedCandidate { candidate, rank, tie, majority_grade: None, shares: None, metadata: None });
+            }
+        }
+        Ranking { candidates, none_of_the_above: None }
+    }
+
+    /// Function that sets the majority grade of each candidate and the votes around it
+    ///
+    /// # Arguments
+    /// * `median_shares`: the function giving the majority grade of a candidate and the votes around it
+    pub(crate) fn with_majority_grades<F: Fn(&K) -> Option<MedianShares<G>>>(mut self, median_shares: F) -> Self {
+        for ranked in self.candidates.iter_mut() {
+            let median = median_shares(ranked.candidate);
+            ranked.shares = median.as_ref().map(|median| GradeShares { above: median.above, below: median.below, total: median.total });
+            ranked.majority_grade = median.map(|median| median.median);
+        }
+        self
+    }
+
+    /// Function that keeps some of the candidates, closing the ranks of the others
+    ///
+    /// # Arguments
+    /// * `keep`: whether a candidate is kept
+    ///
+    /// # Returns
+    /// * `Ranking`: the kept candidates in the same order, a tie left with a single candidate being no longer a tie
+    pub(crate) fn retain<F: Fn(&K) -> bool>(&self, keep: F) -> Self
+    where
+        G: Clone,
+    {
+        let kept: Vec<&RankedCandidate<'a, K, G>> = self.candidates.iter().filter(|ranked| keep(ranked.candidate)).collect();
+        let mut candidates: Vec<RankedCandidate<'a, K, G>> = Vec::with_capacity(kept.len());
+        for (index, ranked) in kept.iter().enumerate() {
+            let rank = match (index.checked_sub(1).map(|previous| kept[previous]), candidates.last()) {
+                (Some(previous), Some(last)) if previous.rank == ranked.rank => last.rank,
+                _ => index,
+            };
+            let alone = kept.iter().filter(|other| other.rank == ranked.rank).count() == 1;
+            let tie = if ranked.tie == TieStatus::Tied && alone { TieStatus::Unique } else { ranked.tie };
+            candidates.push(RankedCandidate {
+                candidate: ranked.candidate,
+                rank,
+                tie,
+                majority_grade: ranked.majority_grade.clone(),
+                shares: ranked.shares,
+                metadata: ranked.metadata,
+            });
+        }
+        let none_of_the_above = self.none_of_the_above.filter(|&candidate| keep(candidate));
+        Ranking { candidates, none_of_the_above }
+    }
+
+    /// Function that marks a candidate as "none of the above", see `Options::none_of_the_above`
+    pub(crate) fn with_none_of_the_above(mut self, candidate: &'a K) -> Self {
+        self.none_of_the_above = Some(candidate);
+        self
+    }
+
+    /// The "none of the above" pseudo-candidate of the ranking, if any
+    pub fn none_of_the_above(&self) -> Option<&'a K> {
+        self.none_of_the_above
+    }
+
+    /// Function that attaches its metadata to each candidate, e.g. `Poll::metadata`
+    ///
+    /// # Arguments
+    /// * `metadata`: the metadata of the candidates, the candidates without any being left without
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::{majority_judgment_ranking, Ballot, Poll};
+    /// let mut poll = Poll::new(["Pizza", "Chips"]);
+    /// poll.set_metadata("Chips", "photo", "https://example.org/chips.png");
+    /// poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2));
+    ///
+    /// let poll_data = poll.poll_data().unwrap();
+    /// let ranking = majority_judgment_ranking(&poll_data).with_metadata(poll.metadata());
+    /// let winner = &ranking.candidates()[0];
+    /// assert_eq!(winner.metadata.unwrap()["photo"], "https://example.org/chips.png");
+    /// assert_eq!(ranking.candidates()[1].metadata, None);
+    /// ```
+    pub fn with_metadata(mut self, metadata: &'a BTreeMap<K, Metadata>) -> Self
+    where
+        K: Ord,
+    {
+        for ranked in self.candidates.iter_mut() {
+            ranked.metadata = metadata.get(ranked.candidate);
+        }
+        self
+    }
+
+    /// The ranked candidates, from the best to the worst
+    pub fn candidates(&self) -> &[RankedCandidate<'a, K, G>] {
+        &self.candidates
+    }
+
+    /// An iterator over the ranked candidates, from the best to the worst
+    pub fn iter(&self) -> core::slice::Iter<'_, RankedCandidate<'a, K, G>> {
+        self.candidates.iter()
+    }
+
+    /// The number of ranked candidates
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    /// Whether there is no candidate in the ranking
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// The candidates sharing the first rank. More than one means a tie for the win.
+    /// The "none of the above" candidate is ranked like the others: see `verdict` for who is elected.
+    pub fn winners(&self) -> Vec<&'a K> {
+        self.candidates
+            .iter()
+            .filter(|c| c.rank == 0)
+            .map(|c| c.candidate)
+            .collect()
+    }
+
+    /// Function that tells who is elected, "none of the above" being a pseudo-candidate
+    ///
+    /// # Returns
+    /// * `Verdict`: `Verdict::NoWinner` if the majority grade of "none of the above" is greater than the
+    ///   majority grade of every real candidate, otherwise the best ranked real candidates,
+    ///   the winners of the ranking without "none of the above"
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::{majority_judgment_with_options, Options, Verdict};
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza".to_string(), vec![0, 1, 1]);
+    /// poll_data.insert("Chips".to_string(), vec![0, 0, 1]);
+    /// poll_data.insert("None of the above".to_string(), vec![2, 2, 1]);
+    ///
+    /// let options = Options::new().none_of_the_above("None of the above".to_string());
+    /// assert_eq!(majority_judgment_with_options(&poll_data, &options).verdict(), Verdict::NoWinner);
+    ///
+    /// poll_data.insert("Soup".to_string(), vec![3, 2, 2]);
+    /// assert_eq!(majority_judgment_with_options(&poll_data, &options).verdict(), Verdict::Elected(vec![&"Soup".to_string()]));
+    /// ```
+    pub fn verdict(&self) -> Verdict<'a, K>
+    where
+        K: PartialEq,
+        G: Ord,
+    {
+        let Some(none_of_the_above) = self.none_of_the_above else { return Verdict::Elected(self.winners()) };
+        let real: Vec<&RankedCandidate<'a, K, G>> = self.candidates.iter().filter(|ranked| ranked.candidate != none_of_the_above).collect();
+        let threshold = self
+            .candidates
+            .iter()
+            .find(|ranked| ranked.candidate == none_of_the_above)
+            .and_then(|ranked| ranked.majority_grade.as_ref());
+        if real.is_empty() || real.iter().all(|ranked| ranked.majority_grade.as_ref() < threshold) {
+            return Verdict::NoWinner;
+        }
+        let best = real[0].rank;
+        Verdict::Elected(real.iter().filter(|ranked| ranked.rank == best).map(|ranked| ranked.candidate).collect())
+    }
+
+    /// Function that compares the ranking to a later one, e.g. to display the deltas between two partial counts
+    ///
+    /// # Arguments
+    /// * `other`: the later ranking
+    ///
+    /// # Returns
+    /// * `RankingDiff`: the rank moves and the majority grade changes from this ranking to `other`,
+    ///   with the candidates found in one of them only
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::{majority_judgment_ranking, RankMove};
+    /// let mut poll_data = BTreeMap::new();
+    /// poll_data.insert("Pizza", vec![2u8, 2, 1]);
+    /// poll_data.insert("Chips", vec![1u8, 1, 3]);
+    /// let early = majority_judgment_ranking(&poll_data).to_owned_ranking();
+    ///
+    /// poll_data.get_mut("Pizza").unwrap().extend([0, 0]);
+    /// poll_data.get_mut("Chips").unwrap().extend([3, 3]);
+    /// let late = majority_judgment_ranking(&poll_data);
+    /// let diff = early.ranking().diff(&late);
+    /// assert_eq!(diff.moves[0], RankMove { candidate: &"Chips", before: 1, after: 0 });
+    /// assert_eq!((diff.grade_changes[0].before, diff.grade_changes[0].after), (Some(1), Some(3)));
+    /// assert_eq!((diff.grade_changes[1].before, diff.grade_changes[1].after), (Some(2), Some(1)));
+    /// ```
+    pub fn diff(&self, other: &Ranking<'a, K, G>) -> RankingDiff<'a, K, G>
+    where
+        K: Ord,
+        G: Clone + PartialEq,
+    {
+        let before: BTreeMap<&K, &RankedCandidate<'a, K, G>> = self.candidates.iter().map(|ranked| (ranked.candidate, ranked)).collect();
+        let after: BTreeMap<&K, &RankedCandidate<'a, K, G>> = other.candidates.iter().map(|ranked| (ranked.candidate, ranked)).collect();
+        let mut diff = RankingDiff { moves: Vec::new(), grade_changes: Vec::new(), added: Vec::new(), removed: Vec::new() };
+        for ranked in &other.candidates {
+            let Some(previous) = before.get(ranked.candidate) else {
+                diff.added.push(ranked.candidate);
+                continue;
+            };
+            if previous.rank != ranked.rank {
+                diff.moves.push(RankMove { candidate: ranked.candidate, before: previous.rank, after: ranked.rank });
+            }
+            if previous.majority_grade != ranked.majority_grade {
+                diff.grade_changes.push(GradeChange {
+                    candidate: ranked.candidate,
+                    before: previous.majority_grade.clone(),
+                    after: ranked.majority_grade.clone(),
+                });
+            }
+        }
+        diff.removed = self.candidates.iter().filter(|ranked| !after.contains_key(ranked.candidate)).map(|ranked| ranked.candidate).collect();
+        diff
+    }
+
+    /// Whether any candidates are tied
+    pub fn has_ties(&self) -> bool {
+        self.candidates.iter().any(|c| c.tie == TieStatus::Tied)
+    }
+
+    /// The groups of tied candidates, from the best group to the worst
+    pub fn ties(&self) -> Vec<Vec<&'a K>> {
+        let mut ties: Vec<Vec<&'a K>> = Vec::new();
+        let mut previous_rank = None;
+        for c in self.candidates.iter().filter(|c| c.tie == TieStatus::Tied) {
+            match ties.last_mut() {
+                Some(group) if previous_rank == Some(c.rank) => group.push(c.candidate),
+                _ => ties.push(vec![c.candidate]),
+            }
+            previous_rank = Some(c.rank);
+        }
+        ties
+    }
+
+    /// The ranking as a vector of tuple (candidate, rank)
+    pub fn to_vec(&self) -> Vec<(&'a K, usize)> {
+        self.candidates.iter().map(|c| (c.candidate, c.rank)).collect()
+    }
+
+    /// Function that copies the candidates into a ranking independent of the poll data,
+    /// e.g. to cache it or to send it to another thread
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use majority_judgement_rust::{majority_judgment_ranking, OwnedRanking};
+    /// let owned: OwnedRanking = {
+    ///     let mut poll_data = BTreeMap::new();
+    ///     poll_data.insert("Pizza".to_string(), vec![0, 1]);
+    ///     poll_data.insert("Chips".to_string(), vec![2, 1]);
+    ///     majority_judgment_ranking(&poll_data).to_owned_ranking()
+    /// };
+    /// assert_eq!(owned.ranking().winners(), vec!["Chips"]);
+    /// ```
+    pub fn to_owned_ranking(&self) -> OwnedRanking<K, G>
+    where
+        K: Clone,
+        G: Clone,
+    {
+        OwnedRanking {
+            candidates: self
+                .candidates
+                .iter()
+                .map(|c| OwnedCandidate {
+                    candidate: c.candidate.clone(),
+                    rank: c.rank,
+                    tie: c.tie,
+                    majority_grade: c.majority_grade.clone(),
+                    shares: c.shares,
+                    metadata: c.metadata.cloned(),
+                })
+                .collect(),
+            none_of_the_above: self.none_of_the_above.cloned(),
+        }
+    }
+}
+
+/// A ranking owning its candidates, see `Ranking::to_owned_ranking`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedRanking<K = String, G = u8> {
+    candidates: Vec<OwnedCandidate<K, G>>,
+    none_of_the_above: Option<K>,
+}
+
+/// A ranked candidate owning its key, majority grade, shares and metadata
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OwnedCandidate<K, G> {
+    candidate: K,
+    rank: usize,
+    tie: TieStatus,
+    majority_grade: Option<G>,
+    shares: Option<GradeShares>,
+    metadata: Option<Metadata>,
+}
+
+impl<K, G> OwnedRanking<K, G> {
+    /// A view of the ranking, with the same methods as the rankings borrowing the poll data
+    pub fn ranking(&self) -> Ranking<'_, K, G>
+    where
+        G: Clone,
+    {
+        Ranking {
+            candidates: self
+                .candidates
+                .iter()
+                .map(|owned| RankedCandidate {
+                    candidate: &owned.candidate,
+                    rank: owned.rank,
+                    tie: owned.tie,
+                    majority_grade: owned.majority_grade.clone(),
+                    shares: owned.shares,
+                    metadata: owned.metadata.as_ref(),
+                })
+                .collect(),
+            none_of_the_above: self.none_of_the_above.as_ref(),
+        }
+    }
+
+    /// The number of ranked candidates
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    /// Whether there is no candidate in the ranking
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// The ranking as a vector of tuple (candidate, rank), consuming it
+    pub fn into_vec(self) -> Vec<(K, usize)> {
+        self.candidates.into_iter().map(|owned| (owned.candidate, owned.rank)).collect()
+    }
+}
+
+impl<K: Clone, G: Clone> From<Ranking<'_, K, G>> for OwnedRanking<K, G> {
+    fn from(ranking: Ranking<'_, K, G>) -> Self {
+        ranking.to_owned_ranking()
+    }
+}
+
+impl<'a, 'r, K, G> IntoIterator for &'r Ranking<'a, K, G> {
+    type Item = &'r RankedCandidate<'a, K, G>;
+    type IntoIter = core::slice::Iter<'r, RankedCandidate<'a, K, G>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.candidates.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_from_groups() {
+        let (a, b, c, d) = ("A".to_string(), "B".to_string(), "C".to_string(), "D".to_string());
+        let ranking: Ranking<'_> = Ranking::from_groups(vec![(vec![&a], TieStatus::Unique),
+                                                (vec![&b, &c], TieStatus::Tied),
+                                                (vec![&d], TieStatus::Broken)]);
+
+        assert_eq!(ranking.to_vec(), vec![(&a, 0), (&b, 1), (&c, 1), (&d, 3)]);
+        assert_eq!(ranking.winners(), vec![&a]);
+        assert!(ranking.has_ties());
+        assert_eq!(ranking.ties(), vec![vec![&b, &c]]);
+        assert_eq!(ranking.candidates()[0].tie, TieStatus::Unique);
+        assert_eq!(ranking.candidates()[2].tie, TieStatus::Tied);
+    }
+
+    #[test]
+    fn calling_verdict() {
+        let (a, b, c, nota) = ("A".to_string(), "B".to_string(), "C".to_string(), "NOTA".to_string());
+        fn grades<'a>(ranking: Ranking<'a>, grades: [(&str, u8); 4]) -> Ranking<'a> {
+            ranking.with_majority_grades(|candidate| {
+                grades.iter().find(|(name, _)| name == candidate).map(|&(_, median)| MedianShares { median, above: 0, below: 0, total: 1 })
+            })
+        }
+        let ranking = Ranking::from_groups(vec![(vec![&nota], TieStatus::Broken), (vec![&a, &b], TieStatus::Tied), (vec![&c], TieStatus::Unique)]);
+        assert_eq!(ranking.verdict(), Verdict::Elected(vec![&nota]));
+
+        // the real candidates with the same majority grade as "none of the above" can still be elected
+        let ranking = grades(ranking.with_none_of_the_above(&nota), [("NOTA", 3), ("A", 3), ("B", 3), ("C", 1)]);
+        assert_eq!(ranking.verdict(), Verdict::Elected(vec![&a, &b]));
+        assert_eq!(ranking.to_owned_ranking().ranking().verdict(), Verdict::Elected(vec![&a, &b]));
+        let ranking = grades(ranking, [("NOTA", 3), ("A", 2), ("B", 2), ("C", 1)]);
+        assert_eq!(ranking.verdict(), Verdict::NoWinner);
+        assert_eq!(ranking.retain(|candidate| candidate != "NOTA").verdict(), Verdict::Elected(vec![&a, &b]));
+    }
+
+    #[test]
+    fn calling_diff() {
+        let (a, b, c, d) = ("A".to_string(), "B".to_string(), "C".to_string(), "D".to_string());
+        fn grades(ranking: Ranking<'_>, median: u8) -> Ranking<'_> {
+            ranking.with_majority_grades(|candidate| (candidate == "B").then_some(MedianShares { median, above: 0, below: 0, total: 1 }))
+        }
+        let before = Ranking::from_groups(vec![(vec![&a], TieStatus::Unique), (vec![&b, &c], TieStatus::Tied)]);
+        assert!(before.diff(&before).is_empty());
+
+        let after = Ranking::from_groups(vec![(vec![&b], TieStatus::Unique), (vec![&d], TieStatus::Unique), (vec![&a], TieStatus::Unique)]);
+        let diff = grades(before, 1).diff(&grades(after, 2));
+        assert_eq!(diff.moves, [RankMove { candidate: &b, before: 1, after: 0 }, RankMove { candidate: &a, before: 0, after: 2 }]);
+        assert_eq!(diff.grade_changes, [GradeChange { candidate: &b, before: Some(1), after: Some(2) }]);
+        assert_eq!((diff.added, diff.removed), (vec![&d], vec![&c]));
+    }
+
+    #[test]
+    fn calling_to_owned_ranking() {
+        let owned = {
+            let (a, b, c) = ("A".to_string(), "B".to_string(), "C".to_string());
+            let metadata = BTreeMap::from([(a.clone(), Metadata::from([("party".to_string(), "Green".to_string())]))]);
+            let ranking = Ranking::from_groups(vec![(vec![&a, &b], TieStatus::Tied), (vec![&c], TieStatus::Unique)]);
+            let ranking = ranking.with_metadata(&metadata).with_majority_grades(|candidate| {
+                (candidate == "A").then_some(MedianShares { median: 4u8, above: 1, below: 0, total: 2 })
+            });
+            OwnedRanking::from(ranking)
+        };
+
+        // the owned ranking outlives the candidates and moves to another thread
+        let owned = std::thread::spawn(move || owned).join().unwrap();
+        assert_eq!(owned.ranking().ties(), vec![vec!["A", "B"]]);
+        assert_eq!(owned.ranking().candidates()[0].metadata.unwrap()["party"], "Green");
+        assert_eq!((owned.ranking().candidates()[0].majority_grade, owned.ranking().candidates()[1].majority_grade), (Some(4), None));
+        assert_eq!(owned.ranking().candidates()[0].shares, Some(GradeShares { above: 1, below: 0, total: 2 }));
+        assert_eq!(owned.ranking().candidates()[0].shares.unwrap().proponents(), 0.5);
+        assert_eq!(owned.len(), 3);
+        assert_eq!(owned.into_vec(), vec![("A".to_string(), 0), ("B".to_string(), 0), ("C".to_string(), 2)]);
+    }
+}
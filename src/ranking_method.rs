@@ -0,0 +1,284 @@
+//! # Ranking methods
+//! A ranking method computes a merit for each candidate from the number of votes of each grade, the candidates
+//! being ranked by decreasing merit. The rules built into the crate implement `RankingMethod`:
+//! * `MajorityValues`: the successive median grades, the majority judgment itself
+//! * `MajorityGauge`, `UsualJudgment` and `TypicalJudgment`: the majority grade, then a score
+//! * `Average`: the average grade
+//!
+//! `BuiltinMethod` selects one of them in the `Options`. Another crate implements `RankingMethod` for its own
+//! rule and ranks with it through `majority_judgment_with_method` or `TallyAccumulator::ranking_with_method`,
+//! keeping the polls, the tallies, the tie breakers and the rankings of this crate.
+//!
+//! The merits of the built-in rules are exact up to `u64::MAX` votes per candidate and panic beyond,
+//! `try_majority_judgment_from_tally_with_options` refusing such tallies with a `RankingError` instead.
+use alloc::collections::BTreeMap;
+use crate::fraction::Fraction;
+use crate::judgment::{majority_gauge, total_votes, typical_judgment, usual_judgment, TOO_MANY_VOTES};
+use crate::majority_value::{MajorityValue, Proportional};
+use crate::options::{GradeValue, MedianConvention};
+
+/// A rule ranking the candidates by a merit computed from their grades
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::{majority_judgment_with_method, Options, RankingMethod};
+/// /// Approval: the number of grades from `Good` (2) up
+/// struct Approval;
+///
+/// impl RankingMethod<u8> for Approval {
+///     type Merit = u64;
+///
+///     fn merit(&self, tally: &BTreeMap<u8, u64>) -> u64 {
+///         tally.range(2..).map(|(_, count)| count).sum()
+///     }
+/// }
+///
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![2u8, 2, 0, 0]);
+/// poll_data.insert("Chips", vec![1u8, 1, 1, 3]);
+/// assert_eq!(majority_judgment_with_method(&poll_data, &Approval, &Options::new()).winners(), vec![&"Pizza"]);
+/// ```
+pub trait RankingMethod<G> {
+    /// What is compared to rank a candidate, the greatest merit ranking first
+    type Merit: Ord;
+
+    /// Function that computes the merit of a candidate
+    ///
+    /// # Arguments
+    /// * `tally`: the number of votes (or total weight) of each grade of the candidate, the absent grades counting 0
+    ///
+    /// # Returns
+    /// * `Self::Merit`: the merit of the candidate, equal merits tying the candidates
+    fn merit(&self, tally: &BTreeMap<G, u64>) -> Self::Merit;
+}
+
+/// The merit computed by the rules of the crate, only compared to another merit of the same rule
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Merit<G: Ord>(pub(crate) MeritKind<G>);
+
+/// What is compared to rank a candidate, depending on the ranking method
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum MeritKind<G: Ord> {
+    /// The successive median grades
+    MajorityValues(MajorityValue<G>),
+    /// The successive median grades, compared by shares of withdrawn grades
+    ProportionalValues(Proportional<G>),
+    /// The majority grade then a score separating candidates with the same majority grade
+    Score(Option<G>, Fraction),
+    /// The average grade
+    Mean(Mean),
+    /// The exact average grade, None without any grade
+    ExactMean(Option<Fraction>),
+}
+
+/// An average grade, totally ordered (a candidate without any grade has the average `-inf`)
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Mean(f64);
+
+impl Ord for Mean {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl PartialOrd for Mean {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Mean {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == core::cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Mean {}
+
+/// Balinski–Laraki majority values: the successive median grades obtained when withdrawing
+/// the median grade one vote at a time, every candidate having the same number of votes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MajorityValues;
+
+impl<G: Ord + Clone> RankingMethod<G> for MajorityValues {
+    type Merit = Merit<G>;
+
+    fn merit(&self, tally: &BTreeMap<G, u64>) -> Merit<G> {
+        self.merit_with(tally, MedianConvention::LowerMedian)
+    }
+}
+
+impl MajorityValues {
+    /// The merit withdrawing the lower or the upper median, see `Options::median`
+    pub(crate) fn merit_with<G: Ord + Clone>(self, tally: &BTreeMap<G, u64>, convention: MedianConvention) -> Merit<G> {
+        Merit(MeritKind::MajorityValues(MajorityValue::from_tally_with(tally, convention)))
+    }
+}
+
+/// The majority values compared by shares of withdrawn grades, see `Participation::Proportional`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct ProportionalValues;
+
+impl<G: Ord + Clone> RankingMethod<G> for ProportionalValues {
+    type Merit = Merit<G>;
+
+    fn merit(&self, tally: &BTreeMap<G, u64>) -> Merit<G> {
+        self.merit_with(tally, MedianConvention::LowerMedian)
+    }
+}
+
+impl ProportionalValues {
+    /// The merit withdrawing the lower or the upper median, see `Options::median`
+    pub(crate) fn merit_with<G: Ord + Clone>(self, tally: &BTreeMap<G, u64>, convention: MedianConvention) -> Merit<G> {
+        Merit(MeritKind::ProportionalValues(Proportional(MajorityValue::from_tally_with(tally, convention))))
+    }
+}
+
+/// Majority gauge: the majority grade, then `p` if `p > q` and `-q` otherwise,
+/// with `p` the share of grades above the majority grade and `q` the share below
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MajorityGauge;
+
+impl<G: Ord + Clone> RankingMethod<G> for MajorityGauge {
+    type Merit = Merit<G>;
+
+    fn merit(&self, tally: &BTreeMap<G, u64>) -> Merit<G> {
+        self.merit_with(tally, MedianConvention::LowerMedian)
+    }
+}
+
+impl MajorityGauge {
+    /// The merit around the lower or the upper median, see `Options::median`
+    pub(crate) fn merit_with<G: Ord + Clone>(self, tally: &BTreeMap<G, u64>, convention: MedianConvention) -> Merit<G> {
+        let (median, score) = majority_gauge(tally, convention);
+        Merit(MeritKind::Score(median, score))
+    }
+}
+
+/// Usual judgment: the majority grade, then `(p - q) / (1 - p - q)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UsualJudgment;
+
+impl<G: Ord + Clone> RankingMethod<G> for UsualJudgment {
+    type Merit = Merit<G>;
+
+    fn merit(&self, tally: &BTreeMap<G, u64>) -> Merit<G> {
+        self.merit_with(tally, MedianConvention::LowerMedian)
+    }
+}
+
+impl UsualJudgment {
+    /// The merit around the lower or the upper median, see `Options::median`
+    pub(crate) fn merit_with<G: Ord + Clone>(self, tally: &BTreeMap<G, u64>, convention: MedianConvention) -> Merit<G> {
+        let (median, score) = usual_judgment(tally, convention);
+        Merit(MeritKind::Score(median, score))
+    }
+}
+
+/// Typical judgment: the majority grade, then `p - q`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TypicalJudgment;
+
+impl<G: Ord + Clone> RankingMethod<G> for TypicalJudgment {
+    type Merit = Merit<G>;
+
+    fn merit(&self, tally: &BTreeMap<G, u64>) -> Merit<G> {
+        self.merit_with(tally, MedianConvention::LowerMedian)
+    }
+}
+
+impl TypicalJudgment {
+    /// The merit around the lower or the upper median, see `Options::median`
+    pub(crate) fn merit_with<G: Ord + Clone>(self, tally: &BTreeMap<G, u64>, convention: MedianConvention) -> Merit<G> {
+        let (median, score) = typical_judgment(tally, convention);
+        Merit(MeritKind::Score(median, score))
+    }
+}
+
+/// Score voting: the average grade, the grades being converted to numbers
+#[derive(Debug)]
+pub struct Average<G> {
+    value: AverageValue<G>,
+}
+
+impl<G> Clone for Average<G> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<G> Copy for Average<G> {}
+
+#[derive(Debug)]
+enum AverageValue<G> {
+    Float(fn(&G) -> f64),
+    Exact(fn(&G) -> i64),
+}
+
+impl<G> Clone for AverageValue<G> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<G> Copy for AverageValue<G> {}
+
+impl<G> Average<G> {
+    /// The average of the numbers the grades count for, rounded as floating point numbers
+    pub fn new(value: fn(&G) -> f64) -> Self {
+        Average { value: AverageValue::Float(value) }
+    }
+
+    /// The average of the integers the grades count for, compared as exact fractions
+    pub fn exact(value: fn(&G) -> i64) -> Self {
+        Average { value: AverageValue::Exact(value) }
+    }
+
+    /// The average of the options, None without any conversion
+    pub(crate) fn from_value(value: GradeValue<G>) -> Option<Self> {
+        match value {
+            GradeValue::Missing => None,
+            GradeValue::Float(value) => Some(Average::new(value)),
+            GradeValue::Exact(value) => Some(Average::exact(value)),
+        }
+    }
+}
+
+impl<G: Ord> RankingMethod<G> for Average<G> {
+    type Merit = Merit<G>;
+
+    fn merit(&self, tally: &BTreeMap<G, u64>) -> Merit<G> {
+        let total = total_votes(tally.values()).expect(TOO_MANY_VOTES);
+        Merit(match self.value {
+            AverageValue::Float(_) if total == 0 => MeritKind::Mean(Mean(f64::NEG_INFINITY)),
+            AverageValue::Float(value) => {
+                let sum: f64 = tally.iter().map(|(grade, &count)| value(grade) * count as f64).sum();
+                MeritKind::Mean(Mean(sum / total as f64))
+            }
+            // below any average, as -inf
+            AverageValue::Exact(_) if total == 0 => MeritKind::ExactMean(None),
+            AverageValue::Exact(value) => {
+                // |value| * total < 2^127 as long as the total of the votes fits in u64
+                let sum: i128 = tally.iter().map(|(grade, &count)| value(grade) as i128 * count as i128).sum();
+                MeritKind::ExactMean(Some(Fraction::new(sum, total as u128)))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_merit() {
+        let (low, high) = (BTreeMap::from([(0u8, 2u64), (3, 1)]), BTreeMap::from([(1u8, 2u64), (2, 1)]));
+        assert!(MajorityValues.merit(&low) < MajorityValues.merit(&high));
+        assert!(MajorityGauge.merit(&low) < MajorityGauge.merit(&high));
+        assert!(UsualJudgment.merit(&low) < UsualJudgment.merit(&high));
+        assert!(TypicalJudgment.merit(&low) < TypicalJudgment.merit(&high));
+        assert!(Average::new(|&grade| grade as f64).merit(&low) < Average::new(|&grade| grade as f64).merit(&high));
+        assert!(Average::exact(|&grade| grade as i64 * 2).merit(&low) > Average::exact(|&grade| grade as i64).merit(&BTreeMap::new()));
+    }
+}
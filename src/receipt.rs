@@ -0,0 +1,274 @@
+//! # Receipts
+//! A receipt lets anyone check a published result against the raw ballot file: it holds a canonical
+//! text of the tally and of the configuration of the count, and a SHA-256 hash covering the ballots too.
+//! Recomputing the receipt from the published ballots with the same configuration must give the same hash.
+//!
+//! The canonical text is one line per fact, the names being escaped so that any name fits on a line:
+//!
+//! ```text
+//! majority-judgment-receipt 1
+//! config method majority-values
+//! candidate Pizza 0:1 2:3
+//! candidate Chips 1:4
+//! ballots 4 3b1f...
+//! ```
+//!
+//! * `config` lines: the configuration, sorted by key, e.g. the ranking method and the scale
+//! * `candidate` lines: each candidate in registration order, with the number of votes (total weight) of each grade
+//! * `ballots` line: the number of ballots and the SHA-256 hash of the ballots, one line per ballot in
+//!   the order of the poll with its weight and its grades in registration order. The voters are left out,
+//!   so an anonymized dataset gives the same receipt as long as the ballots stay in the same order.
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Write};
+use crate::poll::{BallotError, Poll};
+
+/// The version of the canonical text, written on its first line
+const VERSION: u32 = 1;
+
+/// The canonical text of a count and its hash
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    /// The canonical text of the tally and of the configuration, see the module documentation
+    pub canonical: String,
+    /// The SHA-256 hash of the canonical text, in lowercase hexadecimal
+    pub sha256: String,
+}
+
+impl Receipt {
+    /// Function that checks the receipt against a poll and the configuration of the count
+    ///
+    /// # Returns
+    /// * `bool`: whether the poll and the configuration give exactly this receipt
+    pub fn verify<G: Ord + Clone + Display>(&self, poll: &Poll<G>, configuration: &[(&str, &str)]) -> bool {
+        receipt(poll, configuration).is_ok_and(|receipt| receipt == *self)
+    }
+}
+
+impl Display for Receipt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}sha256 {}", self.canonical, self.sha256)
+    }
+}
+
+/// Function that writes the receipt of a poll
+///
+/// # Arguments
+/// * `poll`: the poll, every ballot grading each candidate once
+/// * `configuration`: the settings of the count as key and value, e.g. `("method", "majority-values")`,
+///   in any order
+///
+/// # Returns
+/// * `Result<Receipt, Vec<BallotError>>`: the receipt, or the issues of the ballots if the poll is invalid
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{Ballot, Poll};
+/// use majority_judgement_rust::receipt::receipt;
+/// let mut poll = Poll::new(["Pizza", "Chips"]);
+/// poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 1).grade("Chips", 3).with_weight(2));
+///
+/// let published = receipt(&poll, &[("method", "majority-values")]).unwrap();
+/// assert!(published.canonical.contains("candidate Pizza 0:1 1:2\n"));
+/// assert!(published.verify(&poll, &[("method", "majority-values")]));
+/// assert!(!published.verify(&poll, &[("method", "majority-gauge")]));
+/// ```
+pub fn receipt<G: Ord + Clone + Display>(poll: &Poll<G>, configuration: &[(&str, &str)]) -> Result<Receipt, Vec<BallotError>> {
+    let errors = poll.validate();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let mut tallies: BTreeMap<&str, BTreeMap<&G, u64>> = BTreeMap::new();
+    let mut ballots = String::new();
+    for ballot in poll.ballots() {
+        let _ = write!(ballots, "{}", ballot.weight());
+        for candidate in poll.candidates() {
+            // the poll is valid: each candidate has exactly one grade
+            if let Some((_, grade)) = ballot.grades().iter().find(|(graded, _)| graded == candidate) {
+                let _ = write!(ballots, " {}", escape(&grade.to_string()));
+                *tallies.entry(candidate).or_default().entry(grade).or_insert(0) += u64::from(ballot.weight());
+            }
+        }
+        ballots.push('\n');
+    }
+
+    let mut canonical = format!("majority-judgment-receipt {}\n", VERSION);
+    let mut configuration = configuration.to_vec();
+    configuration.sort();
+    for (key, value) in configuration {
+        let _ = writeln!(canonical, "config {} {}", escape(key), escape(value));
+    }
+    for candidate in poll.candidates() {
+        let _ = write!(canonical, "candidate {}", escape(candidate));
+        for (grade, count) in tallies.get(candidate.as_str()).into_iter().flatten() {
+            let _ = write!(canonical, " {}:{}", escape(&grade.to_string()), count);
+        }
+        canonical.push('\n');
+    }
+    let _ = writeln!(canonical, "ballots {} {}", poll.ballots().len(), hex(&sha256(ballots.as_bytes())));
+
+    let sha256 = hex(&sha256(canonical.as_bytes()));
+    Ok(Receipt { canonical, sha256 })
+}
+
+/// Function that escapes a name for the canonical text: `%`, spaces, `:` and control characters
+/// are written as `%` and their UTF-8 bytes in hexadecimal, e.g. "Bike lanes" as "Bike%20lanes"
+pub(crate) fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c == '%' || c == ':' || c.is_whitespace() || c.is_control() {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                let _ = write!(escaped, "%{:02X}", byte);
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+    escaped
+}
+
+/// Function that reads a name written by `escape`, None if a `%` is not followed by two hexadecimal digits
+/// or the bytes are not UTF-8
+pub(crate) fn unescape(escaped: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let mut rest = escaped.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let digits = tail.get(..2).filter(|digits| digits.iter().all(u8::is_ascii_hexdigit))?;
+            let digits = std::str::from_utf8(digits).ok()?;
+            bytes.push(u8::from_str_radix(digits, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Function that writes bytes in lowercase hexadecimal
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The round constants of SHA-256: the fractional parts of the cube roots of the first 64 primes
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Function that hashes bytes with SHA-256 (FIPS 180-4)
+///
+/// # Arguments
+/// * `bytes`: the message
+///
+/// # Returns
+/// * `[u8; 32]`: the digest
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::receipt::sha256;
+/// assert_eq!(sha256(b"abc")[..4], [0xba, 0x78, 0x16, 0xbf]);
+/// ```
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    let mut state: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+    ];
+    let mut message = bytes.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((bytes.len() as u64).wrapping_mul(8)).to_be_bytes());
+
+    for block in message.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let t1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(K[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(majority);
+            (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 32];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poll::Ballot;
+
+    #[test]
+    fn calling_sha256() {
+        assert_eq!(hex(&sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(
+            hex(&sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(
+            hex(&sha256(&[b'a'; 1000])),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn calling_receipt() {
+        let mut poll = Poll::new(["Bike lanes", "Park"]);
+        poll.add_ballot(Ballot::new().grade("Park", 2).grade("Bike lanes", 0).with_voter("token-1"));
+        poll.add_ballot(Ballot::new().grade("Bike lanes", 3).grade("Park", 2).with_weight(2));
+        let configuration = [("scale", "Reject, Poor, Good, Excellent"), ("method", "majority-values")];
+
+        let published = receipt(&poll, &configuration).unwrap();
+        let lines: Vec<&str> = published.canonical.lines().collect();
+        assert_eq!(lines[..5], [
+            "majority-judgment-receipt 1",
+            "config method majority-values",
+            "config scale Reject,%20Poor,%20Good,%20Excellent",
+            "candidate Bike%20lanes 0:1 3:2",
+            "candidate Park 2:3",
+        ]);
+        assert_eq!(lines[5], format!("ballots 2 {}", hex(&sha256(b"1 0 2\n2 3 2\n"))));
+        assert_eq!(published.sha256, hex(&sha256(published.canonical.as_bytes())));
+
+        // the voters do not matter, the order of the ballots does
+        assert!(published.verify(&crate::dataset::strip_voters(&poll), &configuration));
+        let mut reordered = Poll::new(["Bike lanes", "Park"]);
+        for ballot in poll.ballots().iter().rev() {
+            reordered.add_ballot(ballot.clone());
+        }
+        assert_ne!(receipt(&reordered, &configuration).unwrap(), published);
+
+        poll.add_ballot(Ballot::new().grade("Park", 1));
+        assert!(receipt(&poll, &configuration).is_err());
+    }
+}
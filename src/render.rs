@@ -0,0 +1,214 @@
+//! # Terminal rendering
+//! Horizontal stacked bars of the merit profile, one per candidate from the winner to the last,
+//! the grades going from the worst on the left to the best on the right.
+//! The `|` in the middle of the bars marks 50% of the grades: the grade under it is the majority grade.
+//!
+//! Each grade is drawn with its own symbol, from `.` for the worst to `@` for the best.
+//! With the `color` feature the bars are also colored with ANSI escape codes, from red to green.
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use crate::checked::try_majority_judgment_from_tally_with_options;
+use crate::mj::majority_judgment_ranking;
+use crate::options::Options;
+use crate::profile::{merit_profile, merit_profile_from_tally};
+use crate::ranking::Ranking;
+
+/// The symbols of the grades, from the worst to the best
+const SYMBOLS: [char; 8] = ['.', ':', '-', '=', '+', '*', '#', '@'];
+
+/// The 256-color ANSI palette of the grades, from red to green
+#[cfg(feature = "color")]
+const PALETTE: [u8; 7] = [196, 202, 208, 220, 190, 118, 46];
+
+/// Function that draws the merit profiles of a poll as horizontal stacked bars
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, the same number of grades for every candidate
+/// * `width`: the number of characters of a bar
+/// * `label`: the function writing the name of a grade, used for the legend and the majority grades
+///
+/// # Returns
+/// * `String`: one line per candidate in the order of the majority judgment, followed by the legend
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::render_profile;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![0, 0, 1, 1]);
+/// poll_data.insert("Chips".to_string(), vec![0, 1, 1, 1]);
+///
+/// let chart = render_profile(&poll_data, 8, |grade| ["Bad", "Good"][*grade].to_string());
+/// println!("{}", chart);
+/// ```
+pub fn render_profile<K, G, F>(poll_data: &BTreeMap<K, Vec<G>>, width: usize, label: F) -> String
+where
+    K: Ord + Display,
+    G: Ord + Clone,
+    F: Fn(&G) -> String,
+{
+    draw(&majority_judgment_ranking(poll_data), &merit_profile(poll_data), width, label)
+}
+
+/// Function that draws the merit profiles of a poll given as the number of votes of each grade, see `render_profile`
+///
+/// # Arguments
+/// * `tallies`: for each candidate, the number of votes of each grade, the same number of votes for every candidate
+/// * `width`: the number of characters of a bar
+/// * `label`: the function writing the name of a grade, used for the legend and the majority grades
+///
+/// # Returns
+/// * `String`: the chart of `render_profile`
+///
+/// # Panics
+/// * if the candidates have different numbers of votes, or a candidate more than `u64::MAX` votes
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::render_profile_from_tally;
+/// let mut tallies = BTreeMap::new();
+/// tallies.insert("Pizza".to_string(), BTreeMap::from([(0, 2_000_000_000u64), (1, 2_000_000_000)]));
+/// tallies.insert("Chips".to_string(), BTreeMap::from([(0, 1_000_000_000u64), (1, 3_000_000_000)]));
+///
+/// let chart = render_profile_from_tally(&tallies, 8, |grade| ["Bad", "Good"][*grade].to_string());
+/// assert!(chart.starts_with("Chips"));
+/// ```
+pub fn render_profile_from_tally<K, G, F>(tallies: &BTreeMap<K, BTreeMap<G, u64>>, width: usize, label: F) -> String
+where
+    K: Ord + Display,
+    G: Ord + Clone,
+    F: Fn(&G) -> String,
+{
+    let ranking = try_majority_judgment_from_tally_with_options(tallies, &Options::default())
+        .unwrap_or_else(|error| panic!("{}", error));
+    draw(&ranking, &merit_profile_from_tally(tallies), width, label)
+}
+
+/// Function that draws a bar per candidate of a ranking, followed by the legend
+fn draw<K, G, F>(ranking: &Ranking<'_, K, G>, profiles: &BTreeMap<&K, Vec<(G, u64, f64)>>, width: usize, label: F) -> String
+where
+    K: Ord + Display,
+    G: Ord + Clone,
+    F: Fn(&G) -> String,
+{
+    let grades: Vec<G> = profiles.values().next().map_or(Vec::new(), |profile| {
+        profile.iter().map(|(grade, _, _)| grade.clone()).collect()
+    });
+    let name_width = profiles.keys().map(|candidate| candidate.to_string().chars().count()).max().unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for ranked in ranking.iter() {
+        let profile = &profiles[ranked.candidate];
+        let counts: Vec<u64> = profile.iter().map(|&(_, count, _)| count).collect();
+        let majority_grade = ranked.majority_grade.as_ref().map_or(String::new(), &label);
+        lines.push(format!(
+            "{:name_width$}  {}  {}",
+            ranked.candidate.to_string(),
+            bar(&counts, width),
+            majority_grade,
+            name_width = name_width
+        ));
+    }
+
+    let legend: Vec<String> = grades
+        .iter()
+        .enumerate()
+        .map(|(index, grade)| format!("{} {}", paint(symbol(index, grades.len()), 1, index, grades.len()), label(grade)))
+        .collect();
+    lines.push(legend.join("  "));
+    lines.join("\n")
+}
+
+/// Function that draws one bar, each grade taking a share of the width proportional to its count
+fn bar(counts: &[u64], width: usize) -> String {
+    let total: u128 = counts.iter().map(|&count| count as u128).sum();
+    let mut bar = String::new();
+    let mut drawn = 0;
+    let mut cumulative = 0;
+    for (index, &count) in counts.iter().enumerate() {
+        cumulative += count as u128;
+        // rounding the cumulative counts keeps the bar exactly `width` characters long
+        let end = (cumulative * width as u128 + total / 2).checked_div(total).unwrap_or(0) as usize;
+        let middle = width / 2;
+        if (drawn..end).contains(&middle) {
+            bar.push_str(&paint(symbol(index, counts.len()), middle - drawn, index, counts.len()));
+            bar.push('|');
+            bar.push_str(&paint(symbol(index, counts.len()), end - middle, index, counts.len()));
+        } else if end > drawn {
+            bar.push_str(&paint(symbol(index, counts.len()), end - drawn, index, counts.len()));
+        }
+        drawn = end.max(drawn);
+    }
+    if drawn <= width / 2 {
+        bar.push_str(&" ".repeat(width / 2 - drawn));
+        bar.push('|');
+        bar.push_str(&" ".repeat(width - width / 2));
+    }
+    bar
+}
+
+/// Function that picks the symbol of a grade, spreading the symbols over the grades of the poll
+fn symbol(index: usize, grades: usize) -> char {
+    if grades <= 1 {
+        return SYMBOLS[SYMBOLS.len() - 1];
+    }
+    SYMBOLS[index * (SYMBOLS.len() - 1) / (grades - 1)]
+}
+
+/// Function that repeats the symbol of a grade, colored with the `color` feature
+#[cfg(feature = "color")]
+fn paint(symbol: char, repeat: usize, index: usize, grades: usize) -> String {
+    if repeat == 0 {
+        return String::new();
+    }
+    let color = if grades <= 1 { PALETTE[PALETTE.len() - 1] } else { PALETTE[index * (PALETTE.len() - 1) / (grades - 1)] };
+    format!("\x1b[38;5;{}m{}\x1b[0m", color, symbol.to_string().repeat(repeat))
+}
+
+/// Function that repeats the symbol of a grade, colored with the `color` feature
+#[cfg(not(feature = "color"))]
+fn paint(symbol: char, repeat: usize, _index: usize, _grades: usize) -> String {
+    symbol.to_string().repeat(repeat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Function that removes the ANSI escape codes added by the `color` feature
+    fn strip_colors(text: &str) -> String {
+        let mut plain = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                chars.by_ref().find(|&c| c == 'm');
+            } else {
+                plain.push(c);
+            }
+        }
+        plain
+    }
+
+    #[test]
+    fn calling_render_profile() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![0, 0, 0, 2]);
+        poll_data.insert("Chips".to_string(), vec![0, 1, 2, 2]);
+
+        let labels = ["Bad", "Fair", "Good"];
+        let chart = strip_colors(&render_profile(&poll_data, 8, |grade: &usize| labels[*grade].to_string()));
+        assert_eq!(chart, "Chips  ..==|@@@@  Fair\n\
+                           Pizza  ....|..@@  Bad\n\
+                           . Bad  = Fair  @ Good");
+
+        let tallies = BTreeMap::from([("Pizza", BTreeMap::from([(0, 3u64), (2, 1)])), ("Chips", BTreeMap::from([(0, 1u64), (1, 1), (2, 2)]))]);
+        assert_eq!(strip_colors(&render_profile_from_tally(&tallies, 8, |grade: &usize| labels[*grade].to_string())), chart);
+    }
+
+    #[test]
+    fn calling_bar() {
+        assert_eq!(strip_colors(&bar(&[1, 1, 1], 6)), "..=|=@@");
+        assert_eq!(strip_colors(&bar(&[0, 0], 4)), "  |  ");
+    }
+}
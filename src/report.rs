@@ -0,0 +1,436 @@
+//! # Reports
+//! Self-contained HTML and SVG reports of a poll, to embed the results in a webpage:
+//! * `svg_profile`: the merit profiles as an SVG image of stacked bars, from the winner to the last
+//! * `html_report`: an HTML page with the ranking table followed by the SVG merit profiles
+//! * `html_report_from_tally_with_options`: the same page for a poll given as the number of votes of each grade
+//!
+//! The reports need no stylesheet, script or font: the styles are inline. The percentages they print follow
+//! the `ReportOptions` given to `svg_profile_with_options` and `html_report_with_options`, e.g. two decimals
+//! rounded half up where the law prescribes how the published results are rounded.
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+use crate::checked::try_majority_judgment_from_tally_with_options;
+use crate::mj::majority_judgment_ranking;
+use crate::options::Options;
+use crate::profile::{merit_profile, merit_profile_from_tally};
+use crate::ranking::{Ranking, TieStatus};
+
+/// Width of the bars of the SVG image, in pixels
+const BAR_WIDTH: usize = 480;
+/// Height of a row of the SVG image, in pixels
+const ROW_HEIGHT: usize = 28;
+/// Width of the column of the candidate names, in pixels
+const NAME_WIDTH: usize = 160;
+
+/// How a percentage is rounded to the precision of a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// To the nearest, a half away from zero: 12.5% gives 13%
+    HalfUp,
+    /// To the nearest, a half to the even digit: 12.5% gives 12%, 13.5% gives 14%, as `format!` rounds
+    #[default]
+    HalfEven,
+    /// Toward zero, never overstating a share: 12.9% gives 12%
+    Down,
+    /// Away from zero, unless the share is exact: 12.1% gives 13%
+    Up,
+}
+
+/// How the reports write the percentages
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::report::{ReportOptions, Rounding};
+/// let options = ReportOptions::default();
+/// assert_eq!(options.percent(1, 16), "6.2%");
+///
+/// let options = ReportOptions::default().precision(2).rounding(Rounding::HalfUp);
+/// assert_eq!(options.percent(1, 3), "33.33%");
+/// assert_eq!(options.percent(1, 16), "6.25%");
+/// assert_eq!(ReportOptions::default().precision(0).rounding(Rounding::HalfUp).percent(1, 8), "13%");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportOptions {
+    precision: usize,
+    rounding: Rounding,
+}
+
+impl Default for ReportOptions {
+    /// One decimal, rounded half to even
+    fn default() -> Self {
+        ReportOptions { precision: 1, rounding: Rounding::default() }
+    }
+}
+
+impl ReportOptions {
+    /// The largest number of decimals of a percentage
+    pub const MAX_PRECISION: usize = 16;
+
+    /// Set the number of decimals of the percentages, builder style
+    ///
+    /// # Panics
+    /// * if the precision is greater than `ReportOptions::MAX_PRECISION`
+    pub fn precision(mut self, precision: usize) -> Self {
+        assert!(precision <= Self::MAX_PRECISION, "a percentage has at most {} decimals", Self::MAX_PRECISION);
+        self.precision = precision;
+        self
+    }
+
+    /// Set how the percentages are rounded, builder style
+    pub fn rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// Function that writes the share of `count` votes among `total` votes as a percentage
+    ///
+    /// # Arguments
+    /// * `count`: the votes of the share
+    /// * `total`: all the votes, the share being 0% if there is none
+    ///
+    /// # Returns
+    /// * `String`: the percentage with its decimals and the percent sign, e.g. "33.3%",
+    ///   rounded from the exact share rather than from a float
+    pub fn percent(&self, count: u64, total: u64) -> String {
+        let scale = 10u128.pow(self.precision as u32);
+        let numerator = count as u128 * 100 * scale;
+        let total = total.max(1) as u128;
+        let (quotient, remainder) = (numerator / total, numerator % total);
+        let round_up = match self.rounding {
+            Rounding::HalfUp => 2 * remainder >= total,
+            Rounding::HalfEven => 2 * remainder > total || (2 * remainder == total && quotient % 2 == 1),
+            Rounding::Down => false,
+            Rounding::Up => remainder > 0,
+        };
+        let rounded = quotient + round_up as u128;
+        match self.precision {
+            0 => format!("{}%", rounded),
+            precision => format!("{}.{:0precision$}%", rounded / scale, rounded % scale, precision = precision),
+        }
+    }
+}
+
+/// Function that draws the merit profiles of a poll as an SVG image
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, the same number of grades for every candidate
+/// * `label`: the function writing the name of a grade, used for the legend
+///
+/// # Returns
+/// * `String`: an `<svg>` element, one bar per candidate in the order of the majority judgment,
+///   the grades colored from red (the worst) to green (the best), a line marking 50% of the grades
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::report::svg_profile;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![0, 0, 1, 1]);
+/// poll_data.insert("Chips".to_string(), vec![0, 1, 1, 1]);
+///
+/// let svg = svg_profile(&poll_data, |grade| ["Bad", "Good"][*grade].to_string());
+/// assert!(svg.starts_with("<svg"));
+/// ```
+pub fn svg_profile<K, G, F>(poll_data: &BTreeMap<K, Vec<G>>, label: F) -> String
+where
+    K: Ord + Display,
+    G: Ord + Clone,
+    F: Fn(&G) -> String,
+{
+    svg_profile_with_options(poll_data, label, &ReportOptions::default())
+}
+
+/// Function that draws the merit profiles of a poll as an SVG image, writing the percentages with the given options
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, the same number of grades for every candidate
+/// * `label`: the function writing the name of a grade, used for the legend
+/// * `options`: the precision and the rounding of the percentages
+///
+/// # Returns
+/// * `String`: the `<svg>` element of `svg_profile`
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::report::{svg_profile_with_options, ReportOptions};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![0, 1, 1]);
+///
+/// let svg = svg_profile_with_options(&poll_data, |grade| ["Bad", "Good"][*grade].to_string(), &ReportOptions::default().precision(2));
+/// assert!(svg.contains("<title>Good: 2 (66.67%)</title>"));
+/// ```
+pub fn svg_profile_with_options<K, G, F>(poll_data: &BTreeMap<K, Vec<G>>, label: F, options: &ReportOptions) -> String
+where
+    K: Ord + Display,
+    G: Ord + Clone,
+    F: Fn(&G) -> String,
+{
+    svg(&majority_judgment_ranking(poll_data), &merit_profile(poll_data), label, options)
+}
+
+/// Function that draws an SVG image with a bar per candidate of a ranking, followed by the legend
+fn svg<K, G, F>(ranking: &Ranking<'_, K, G>, profiles: &BTreeMap<&K, Vec<(G, u64, f64)>>, label: F, options: &ReportOptions) -> String
+where
+    K: Ord + Display,
+    G: Ord + Clone,
+    F: Fn(&G) -> String,
+{
+    let grades: Vec<G> = profiles.values().next().map_or(Vec::new(), |profile| {
+        profile.iter().map(|(grade, _, _)| grade.clone()).collect()
+    });
+    let width = NAME_WIDTH + BAR_WIDTH + 10;
+    let height = ROW_HEIGHT * (ranking.len() + 1) + 10;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="14">"#,
+        w = width,
+        h = height
+    );
+    for (row, ranked) in ranking.iter().enumerate() {
+        let y = row * ROW_HEIGHT + 4;
+        let _ = writeln!(svg, r#"<text x="0" y="{}">{}</text>"#, y + 15, escape(&ranked.candidate.to_string()));
+        let mut x = 0.0;
+        let total = profiles[ranked.candidate].iter().map(|(_, count, _)| count).sum();
+        for (index, (grade, count, share)) in profiles[ranked.candidate].iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let bar = share / 100.0 * BAR_WIDTH as f64;
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{:.2}" y="{}" width="{:.2}" height="{}" fill="{}"><title>{}: {} ({})</title></rect>"#,
+                NAME_WIDTH as f64 + x, y, bar, ROW_HEIGHT - 8, color(index, grades.len()), escape(&label(grade)), count,
+                options.percent(*count, total)
+            );
+            x += bar;
+        }
+    }
+
+    // the grade under this line is the majority grade
+    let middle = NAME_WIDTH + BAR_WIDTH / 2;
+    let _ = writeln!(
+        svg,
+        r#"<line x1="{x}" y1="0" x2="{x}" y2="{}" stroke="black" stroke-dasharray="4 2"/>"#,
+        ROW_HEIGHT * ranking.len(),
+        x = middle
+    );
+
+    let y = ROW_HEIGHT * ranking.len() + 4;
+    let step = BAR_WIDTH / grades.len().max(1);
+    for (index, grade) in grades.iter().enumerate() {
+        let x = NAME_WIDTH + index * step;
+        let _ = writeln!(svg, r#"<rect x="{}" y="{}" width="14" height="14" fill="{}"/>"#, x, y + 2, color(index, grades.len()));
+        let _ = writeln!(svg, r#"<text x="{}" y="{}">{}</text>"#, x + 18, y + 14, escape(&label(grade)));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Function that writes the results of a poll as a self-contained HTML page
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, the same number of grades for every candidate
+/// * `title`: the title of the page, e.g. the question of the poll
+/// * `label`: the function writing the name of a grade
+///
+/// # Returns
+/// * `String`: the HTML page with the ranking table (rank, candidate, majority grade) and the merit profiles
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::report::html_report;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![0, 0, 1, 1]);
+/// poll_data.insert("Chips".to_string(), vec![0, 1, 1, 1]);
+///
+/// let html = html_report(&poll_data, "Lunch", |grade| ["Bad", "Good"][*grade].to_string());
+/// assert!(html.contains("<td>Chips</td>"));
+/// ```
+pub fn html_report<K, G, F>(poll_data: &BTreeMap<K, Vec<G>>, title: &str, label: F) -> String
+where
+    K: Ord + Display,
+    G: Ord + Clone,
+    F: Fn(&G) -> String,
+{
+    html_report_with_options(poll_data, title, label, &ReportOptions::default())
+}
+
+/// Function that writes the results of a poll as a self-contained HTML page, writing the percentages
+/// of its merit profiles with the given options
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, the same number of grades for every candidate
+/// * `title`: the title of the page, e.g. the question of the poll
+/// * `label`: the function writing the name of a grade
+/// * `options`: the precision and the rounding of the percentages
+///
+/// # Returns
+/// * `String`: the HTML page of `html_report`
+pub fn html_report_with_options<K, G, F>(poll_data: &BTreeMap<K, Vec<G>>, title: &str, label: F, options: &ReportOptions) -> String
+where
+    K: Ord + Display,
+    G: Ord + Clone,
+    F: Fn(&G) -> String,
+{
+    html(&majority_judgment_ranking(poll_data), &merit_profile(poll_data), title, label, options)
+}
+
+/// Function that writes the results of a poll given as the number of votes of each grade as a self-contained
+/// HTML page, see `html_report_with_options`
+///
+/// # Arguments
+/// * `tallies`: for each candidate, the number of votes of each grade, the same number of votes for every candidate
+/// * `title`: the title of the page, e.g. the question of the poll
+/// * `label`: the function writing the name of a grade
+/// * `options`: the precision and the rounding of the percentages
+///
+/// # Returns
+/// * `String`: the HTML page of `html_report`
+///
+/// # Panics
+/// * if the candidates have different numbers of votes, or a candidate more than `u64::MAX` votes
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::report::{html_report_from_tally_with_options, ReportOptions};
+/// let mut tallies = BTreeMap::new();
+/// tallies.insert("Pizza".to_string(), BTreeMap::from([(0, 1u64), (1, 2_000_000_000)]));
+///
+/// let html = html_report_from_tally_with_options(&tallies, "Lunch", |grade| ["Bad", "Good"][*grade].to_string(), &ReportOptions::default());
+/// assert!(html.contains("<title>Good: 2000000000 (100.0%)</title>"));
+/// ```
+pub fn html_report_from_tally_with_options<K, G, F>(
+    tallies: &BTreeMap<K, BTreeMap<G, u64>>,
+    title: &str,
+    label: F,
+    options: &ReportOptions,
+) -> String
+where
+    K: Ord + Display,
+    G: Ord + Clone,
+    F: Fn(&G) -> String,
+{
+    let ranking = try_majority_judgment_from_tally_with_options(tallies, &Options::default())
+        .unwrap_or_else(|error| panic!("{}", error));
+    html(&ranking, &merit_profile_from_tally(tallies), title, label, options)
+}
+
+/// Function that writes the HTML page of a ranking and the merit profiles of its candidates
+fn html<K, G, F>(ranking: &Ranking<'_, K, G>, profiles: &BTreeMap<&K, Vec<(G, u64, f64)>>, title: &str, label: F, options: &ReportOptions) -> String
+where
+    K: Ord + Display,
+    G: Ord + Clone,
+    F: Fn(&G) -> String,
+{
+    let mut html = String::new();
+    let _ = writeln!(html, "<!DOCTYPE html>");
+    let _ = writeln!(html, r#"<html><head><meta charset="utf-8"><title>{}</title></head>"#, escape(title));
+    let _ = writeln!(html, r#"<body style="font-family: sans-serif">"#);
+    let _ = writeln!(html, "<h1>{}</h1>", escape(title));
+    let _ = writeln!(html, r#"<table style="border-collapse: collapse; margin-bottom: 1em">"#);
+    let _ = writeln!(html, "<tr><th>Rank</th><th>Candidate</th><th>Majority grade</th><th></th></tr>");
+    for ranked in ranking.iter() {
+        let majority_grade = ranked.majority_grade.as_ref().map_or(String::new(), &label);
+        let tie = match ranked.tie {
+            TieStatus::Unique => "",
+            TieStatus::Tied => "tied",
+            TieStatus::Broken => "tie broken",
+        };
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            ranked.rank + 1,
+            escape(&ranked.candidate.to_string()),
+            escape(&majority_grade),
+            tie
+        );
+    }
+    let _ = writeln!(html, "</table>");
+    let _ = writeln!(html, "{}", svg(ranking, profiles, label, options));
+    let _ = writeln!(html, "</body></html>");
+    html
+}
+
+/// Function that picks the color of a grade, from red for the worst to green for the best
+fn color(index: usize, grades: usize) -> String {
+    let hue = if grades <= 1 { 120 } else { index * 120 / (grades - 1) };
+    format!("hsl({}, 70%, 50%)", hue)
+}
+
+/// Function that escapes the text written in HTML or SVG
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mj::compute_frequency_of_grades;
+
+    #[test]
+    fn calling_html_report() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Fish & Chips".to_string(), vec![0, 2, 2, 2]);
+        poll_data.insert("Pizza".to_string(), vec![0, 0, 1, 2]);
+
+        let labels = ["Bad", "Fair", "Good"];
+        let html = html_report(&poll_data, "<Lunch>", |grade: &usize| labels[*grade].to_string());
+        assert!(html.contains("<h1>&lt;Lunch&gt;</h1>"));
+        assert!(html.contains("<tr><td>1</td><td>Fish &amp; Chips</td><td>Good</td><td></td></tr>"));
+        assert!(html.contains("<tr><td>2</td><td>Pizza</td><td>Bad</td><td></td></tr>"));
+
+        // one rectangle per grade given to a candidate, plus one per grade in the legend
+        let svg = svg_profile(&poll_data, |grade: &usize| labels[*grade].to_string());
+        assert_eq!(svg.matches("<rect").count(), 2 + 3 + 3);
+        assert!(svg.contains("<title>Good: 3 (75.0%)</title>"));
+        assert_eq!(color(0, 3), "hsl(0, 70%, 50%)");
+        assert_eq!(color(2, 3), "hsl(120, 70%, 50%)");
+
+        let options = ReportOptions::default().precision(0).rounding(Rounding::Down);
+        let html = html_report_with_options(&poll_data, "Lunch", |grade: &usize| labels[*grade].to_string(), &options);
+        assert!(html.contains("<title>Good: 3 (75%)</title>"));
+
+        let tallies: BTreeMap<String, BTreeMap<usize, u64>> = poll_data
+            .iter()
+            .map(|(candidate, grades)| (candidate.clone(), compute_frequency_of_grades(grades)))
+            .collect();
+        assert_eq!(html_report_from_tally_with_options(&tallies, "Lunch", |grade: &usize| labels[*grade].to_string(), &options), html);
+    }
+
+    #[test]
+    fn calling_percent() {
+        let percent = |precision, rounding, count, total| {
+            ReportOptions::default().precision(precision).rounding(rounding).percent(count, total)
+        };
+        // 2/3 is 66.666...%, 1/8 is 12.5%, 3/8 is 37.5%
+        assert_eq!(percent(1, Rounding::HalfUp, 2, 3), "66.7%");
+        assert_eq!(percent(1, Rounding::Down, 2, 3), "66.6%");
+        assert_eq!(percent(0, Rounding::HalfUp, 1, 8), "13%");
+        assert_eq!(percent(0, Rounding::HalfEven, 1, 8), "12%");
+        assert_eq!(percent(0, Rounding::HalfEven, 3, 8), "38%");
+        assert_eq!(percent(0, Rounding::Up, 1, 1000), "1%");
+        assert_eq!(percent(3, Rounding::Up, 1, 8), "12.500%");
+        assert_eq!(percent(2, Rounding::HalfUp, 0, 0), "0.00%");
+        assert_eq!(percent(16, Rounding::Down, u64::MAX, u64::MAX), "100.0000000000000000%");
+        // the default matches the formatting of the float share
+        for (count, total) in [(1, 3), (1, 16), (3, 16), (5, 7), (1, 40)] {
+            assert_eq!(ReportOptions::default().percent(count, total), format!("{:.1}%", 100.0 * count as f64 / total as f64));
+        }
+    }
+}
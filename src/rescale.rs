@@ -0,0 +1,392 @@
+//! # Rescaling
+//! Conversion of the grades of a source scale into the grades of a majority judgment scale,
+//! e.g. scores from 0 to 10 into 7 grades, so polls collected on different scales can be merged.
+//!
+//! A `Rescaler` cuts a numeric source scale into buckets, grade `i` being given to the values
+//! between the thresholds `i - 1` and `i`. `remap` converts the grades of a discrete scale with a table.
+//! Both only accept increasing conversions: a better source grade never gets a worse grade.
+//!
+//! An organization changing its standard scale between two elections, e.g. from 5 grades to 7, migrates the
+//! tallies of the old elections with `migrate_tallies` and the same kind of table: the `Migration` reports the
+//! source grades merged into the same grade, the votes they held and the candidates whose rank changed.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Debug;
+use crate::mj::majority_judgment_from_tally;
+use crate::poll::Poll;
+
+/// How a `Rescaler` cuts the range of the source scale into grades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bucketing {
+    /// Buckets of the same width, e.g. from 0 to 10 in 5 grades: [0, 2), [2, 4), ..., [8, 10]
+    #[default]
+    Uniform,
+    /// The nearest grade once the range is stretched over the grades, e.g. from 0 to 10 in 5 grades:
+    /// 0 and 1 get grade 0, 2 to 3 get grade 1, ..., 9 and 10 get grade 4
+    Nearest,
+}
+
+/// Why a conversion between two scales is rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RescaleError {
+    /// The range of the source scale is empty or not finite, or no grade is asked
+    InvalidRange,
+    /// The threshold at this index is not finite or not above the previous threshold
+    UnorderedThreshold(usize),
+    /// The table gives this source grade a worse grade than a lower source grade
+    NotMonotonic(String),
+    /// This source grade has no grade in the table
+    UnknownGrade(String),
+}
+
+impl fmt::Display for RescaleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RescaleError::InvalidRange => write!(f, "the range of the scale is empty"),
+            RescaleError::UnorderedThreshold(index) => write!(f, "threshold {} is not above the previous one", index),
+            RescaleError::NotMonotonic(grade) => write!(f, "grade {} gets a worse grade than a lower grade", grade),
+            RescaleError::UnknownGrade(grade) => write!(f, "grade {} has no grade in the table", grade),
+        }
+    }
+}
+
+impl std::error::Error for RescaleError {}
+
+/// The conversion of a numeric source scale into the grades `0..grades()`
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{Bucketing, Rescaler};
+/// let rescaler = Rescaler::new(0.0, 10.0, 7, Bucketing::Uniform).unwrap();
+/// assert_eq!(rescaler.grade(0.0), 0);
+/// assert_eq!(rescaler.grade(5.0), 3);
+/// assert_eq!(rescaler.grade(10.0), 6);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rescaler {
+    thresholds: Vec<f64>,
+}
+
+impl Rescaler {
+    /// Function that cuts the range of the source scale into grades
+    ///
+    /// # Arguments
+    /// * `min`, `max`: the worst and the best values of the source scale
+    /// * `grades`: the number of grades of the target scale
+    /// * `bucketing`: how the range is cut
+    ///
+    /// # Returns
+    /// * `Result<Rescaler, RescaleError>`: the conversion, or `InvalidRange` if `min` is not below `max` or `grades` is 0
+    pub fn new(min: f64, max: f64, grades: u8, bucketing: Bucketing) -> Result<Self, RescaleError> {
+        if !(min.is_finite() && max.is_finite() && min < max) || grades == 0 {
+            return Err(RescaleError::InvalidRange);
+        }
+        let width = max - min;
+        let thresholds = (1..grades)
+            .map(|grade| match bucketing {
+                Bucketing::Uniform => min + width * grade as f64 / grades as f64,
+                Bucketing::Nearest => min + width * (grade as f64 - 0.5) / (grades - 1) as f64,
+            })
+            .collect();
+        Ok(Rescaler { thresholds })
+    }
+
+    /// Function that builds the conversion from the lowest value of each grade but the worst
+    ///
+    /// # Arguments
+    /// * `thresholds`: the lowest value of grade 1, of grade 2, ..., increasing
+    ///
+    /// # Returns
+    /// * `Result<Rescaler, RescaleError>`: the conversion into `thresholds.len() + 1` grades,
+    ///   or the first threshold which is not above the previous one
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::Rescaler;
+    /// let rescaler = Rescaler::from_thresholds([50.0, 70.0, 90.0]).unwrap();
+    /// assert_eq!(rescaler.grade(75.0), 2);
+    /// assert!(Rescaler::from_thresholds([50.0, 40.0]).is_err());
+    /// ```
+    pub fn from_thresholds(thresholds: impl IntoIterator<Item = f64>) -> Result<Self, RescaleError> {
+        let thresholds: Vec<f64> = thresholds.into_iter().collect();
+        for (index, &threshold) in thresholds.iter().enumerate() {
+            if !threshold.is_finite() || (index > 0 && threshold <= thresholds[index - 1]) {
+                return Err(RescaleError::UnorderedThreshold(index));
+            }
+        }
+        if thresholds.len() >= u8::MAX as usize {
+            return Err(RescaleError::InvalidRange);
+        }
+        Ok(Rescaler { thresholds })
+    }
+
+    /// The number of grades of the target scale
+    pub fn grades(&self) -> usize {
+        self.thresholds.len() + 1
+    }
+
+    /// The lowest value of grade 1, of grade 2, ...
+    pub fn thresholds(&self) -> &[f64] {
+        &self.thresholds
+    }
+
+    /// The grade of a value: values below the range get the worst grade, values above it the best one
+    /// and a NaN the worst one
+    pub fn grade(&self, value: f64) -> u8 {
+        self.thresholds.partition_point(|&threshold| threshold <= value) as u8
+    }
+
+    /// Function that converts the grades of the poll data
+    ///
+    /// # Arguments
+    /// * `poll_data`: a BTreeMap<K, Vec<S>> with the grades of the source scale
+    ///
+    /// # Returns
+    /// * `BTreeMap<K, Vec<u8>>`: the same poll data with the grades of the target scale
+    pub fn rescale<K: Ord + Clone, S: Copy + Into<f64>>(&self, poll_data: &BTreeMap<K, Vec<S>>) -> BTreeMap<K, Vec<u8>> {
+        poll_data
+            .iter()
+            .map(|(candidate, grades)| (candidate.clone(), grades.iter().map(|&grade| self.grade(grade.into())).collect()))
+            .collect()
+    }
+
+    /// Function that converts the grades of the ballots of a poll, keeping their weights and voters
+    ///
+    /// # Arguments
+    /// * `poll`: the poll graded on the source scale
+    ///
+    /// # Returns
+    /// * `Poll<u8>`: the same poll with the grades of the target scale, to be merged with other polls on this scale
+    pub fn rescale_poll<S: Copy + Into<f64>>(&self, poll: &Poll<S>) -> Poll<u8> {
+        let mut rescaled = poll.without_ballots();
+        for ballot in poll.ballots() {
+            rescaled.add_ballot(ballot.clone().map_grades(|grade| self.grade(grade.into())));
+        }
+        rescaled
+    }
+}
+
+/// Function that converts the grades of a discrete scale with a table
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<S>> with the grades of the source scale
+/// * `table`: the grade of the target scale of each grade of the source scale
+///
+/// # Returns
+/// * `Result<BTreeMap<K, Vec<G>>, RescaleError>`: the poll data with the grades of the target scale,
+///   `NotMonotonic` if the table gives a source grade a worse grade than a lower one,
+///   `UnknownGrade` if a grade of the poll is missing from the table
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::remap;
+/// let table = BTreeMap::from([("A", 2), ("B", 1), ("C", 1), ("D", 0)]);
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec!["D", "C", "B"]);
+/// // "A" < "B" in the source scale but A is the best letter: the table is decreasing
+/// assert!(remap(&poll_data, &table).is_err());
+///
+/// let table = BTreeMap::from([("A", 0), ("B", 1), ("C", 1), ("D", 2)]);
+/// assert_eq!(remap(&poll_data, &table).unwrap()["Pizza"], vec![2, 1, 1]);
+/// ```
+pub fn remap<K, S, G>(poll_data: &BTreeMap<K, Vec<S>>, table: &BTreeMap<S, G>) -> Result<BTreeMap<K, Vec<G>>, RescaleError>
+where
+    K: Ord + Clone,
+    S: Ord + Debug,
+    G: Ord + Clone,
+{
+    check_monotonic(table)?;
+    let mut remapped = BTreeMap::new();
+    for (candidate, grades) in poll_data {
+        let grades = grades
+            .iter()
+            .map(|grade| table.get(grade).cloned().ok_or_else(|| RescaleError::UnknownGrade(format!("{:?}", grade))))
+            .collect::<Result<Vec<G>, RescaleError>>()?;
+        remapped.insert(candidate.clone(), grades);
+    }
+    Ok(remapped)
+}
+
+/// Function that checks a table never gives a source grade a worse grade than a lower source grade
+fn check_monotonic<S: Debug, G: Ord>(table: &BTreeMap<S, G>) -> Result<(), RescaleError> {
+    let mut previous: Option<&G> = None;
+    for (source, grade) in table {
+        if previous.is_some_and(|previous| grade < previous) {
+            return Err(RescaleError::NotMonotonic(format!("{:?}", source)));
+        }
+        previous = Some(grade);
+    }
+    Ok(())
+}
+
+/// The tallies of an election migrated onto a new scale, with what the migration lost, see `migrate_tallies`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration<K, S, G> {
+    /// For each candidate, the number of votes of each grade of the new scale
+    pub tallies: BTreeMap<K, BTreeMap<G, u64>>,
+    /// The grades of the new scale given to several source grades, with these source grades
+    pub merged: BTreeMap<G, Vec<S>>,
+    /// The votes on source grades merged with another source grade having votes, which the new tallies
+    /// no longer tell apart
+    pub merged_votes: u64,
+    /// The candidates whose rank changed, with their rank on the old scale and on the new one
+    pub rank_changes: BTreeMap<K, (usize, usize)>,
+}
+
+impl<K, S, G> Migration<K, S, G> {
+    /// Whether the new tallies tell apart every vote the old ones did, the ranking being the same
+    pub fn is_lossless(&self) -> bool {
+        self.merged_votes == 0
+    }
+}
+
+/// Function that migrates the tallies of an election onto a new grade scale with a table
+///
+/// # Arguments
+/// * `tallies`: for each candidate, the number of votes of each grade of the old scale
+/// * `table`: the grade of the new scale of each grade of the old scale
+///
+/// # Returns
+/// * `Result<Migration<K, S, G>, RescaleError>`: the tallies on the new scale and the loss report,
+///   `NotMonotonic` if the table gives a source grade a worse grade than a lower one,
+///   `UnknownGrade` if a grade with votes is missing from the table
+///
+/// # Panics
+/// * if the candidates have different numbers of votes
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::migrate_tallies;
+/// let mut tallies = BTreeMap::new();
+/// tallies.insert("Pizza", BTreeMap::from([(0u8, 2), (3, 1)]));
+/// tallies.insert("Chips", BTreeMap::from([(1u8, 2), (2, 1)]));
+///
+/// // from 5 grades to 7, every grade keeping its own
+/// let seven = BTreeMap::from([(0, 0), (1, 2), (2, 3), (3, 4), (4, 6)]);
+/// let migration = migrate_tallies(&tallies, &seven).unwrap();
+/// assert_eq!(migration.tallies["Chips"], BTreeMap::from([(2, 2), (3, 1)]));
+/// assert!(migration.is_lossless());
+///
+/// // from 5 grades to 3, "Poor" and "Fair" merging, "Good" and "Very good" too but without any vote on "Very good"
+/// let three = BTreeMap::from([(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+/// let migration = migrate_tallies(&tallies, &three).unwrap();
+/// assert_eq!(migration.merged[&1], vec![1, 2]);
+/// assert_eq!(migration.merged_votes, 3);
+/// ```
+pub fn migrate_tallies<K, S, G>(tallies: &BTreeMap<K, BTreeMap<S, u64>>, table: &BTreeMap<S, G>) -> Result<Migration<K, S, G>, RescaleError>
+where
+    K: Ord + Clone,
+    S: Ord + Clone + Debug,
+    G: Ord + Clone,
+{
+    check_monotonic(table)?;
+    let mut sources: BTreeMap<&G, Vec<&S>> = BTreeMap::new();
+    for (source, grade) in table {
+        sources.entry(grade).or_default().push(source);
+    }
+    let merged: BTreeMap<G, Vec<S>> = sources
+        .into_iter()
+        .filter(|(_, sources)| sources.len() > 1)
+        .map(|(grade, sources)| (grade.clone(), sources.into_iter().cloned().collect()))
+        .collect();
+
+    let mut migrated = BTreeMap::new();
+    // the votes of each source grade, by grade of the new scale
+    let mut votes: BTreeMap<&G, BTreeMap<&S, u64>> = BTreeMap::new();
+    for (candidate, tally) in tallies {
+        let mut new_tally = BTreeMap::new();
+        for (source, &count) in tally.iter().filter(|(_, &count)| count > 0) {
+            let grade = table.get(source).ok_or_else(|| RescaleError::UnknownGrade(format!("{:?}", source)))?;
+            *votes.entry(grade).or_default().entry(source).or_insert(0) += count;
+            *new_tally.entry(grade.clone()).or_insert(0) += count;
+        }
+        migrated.insert(candidate.clone(), new_tally);
+    }
+    let merged_votes = votes.values().filter(|sources| sources.len() > 1).flat_map(|sources| sources.values()).sum();
+
+    let old_ranks: BTreeMap<&K, usize> = majority_judgment_from_tally(tallies).into_iter().collect();
+    let rank_changes = majority_judgment_from_tally(&migrated)
+        .into_iter()
+        .filter(|(candidate, rank)| old_ranks[candidate] != *rank)
+        .map(|(candidate, rank)| (candidate.clone(), (old_ranks[candidate], rank)))
+        .collect();
+    Ok(Migration { tallies: migrated, merged, merged_votes, rank_changes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poll::Ballot;
+
+    #[test]
+    fn calling_new() {
+        let uniform = Rescaler::new(0.0, 10.0, 5, Bucketing::Uniform).unwrap();
+        assert_eq!(uniform.thresholds(), &[2.0, 4.0, 6.0, 8.0]);
+        let grades: Vec<u8> = (0..=10).map(|score| uniform.grade(score as f64)).collect();
+        assert_eq!(grades, vec![0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 4]);
+
+        let nearest = Rescaler::new(0.0, 10.0, 5, Bucketing::Nearest).unwrap();
+        let grades: Vec<u8> = (0..=10).map(|score| nearest.grade(score as f64)).collect();
+        assert_eq!(grades, vec![0, 0, 1, 1, 2, 2, 2, 3, 3, 4, 4]);
+
+        assert_eq!(uniform.grade(-3.0), 0);
+        assert_eq!(uniform.grade(f64::NAN), 0);
+        assert_eq!(Rescaler::new(0.0, 10.0, 1, Bucketing::Nearest).unwrap().grades(), 1);
+        assert_eq!(Rescaler::new(1.0, 1.0, 5, Bucketing::Uniform), Err(RescaleError::InvalidRange));
+        assert_eq!(Rescaler::from_thresholds([1.0, f64::NAN]), Err(RescaleError::UnorderedThreshold(1)));
+    }
+
+    #[test]
+    fn calling_rescale_poll() {
+        let mut poll = Poll::new(["Pizza", "Chips"]);
+        poll.add_ballot(Ballot::new().grade("Pizza", 9.5f32).grade("Chips", 1.0).with_weight(3));
+        let rescaler = Rescaler::new(0.0, 10.0, 7, Bucketing::Uniform).unwrap();
+
+        let rescaled = rescaler.rescale_poll(&poll);
+        assert_eq!(rescaled.ballots()[0].grades(), &[("Pizza".to_string(), 6), ("Chips".to_string(), 0)]);
+        assert_eq!(rescaled.ballots()[0].weight(), 3);
+        assert_eq!(rescaler.rescale(&poll.poll_data().unwrap()), rescaled.poll_data().unwrap());
+    }
+
+    #[test]
+    fn calling_remap() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![1, 5, 3]);
+        let table = BTreeMap::from([(1, 0), (3, 1), (5, 1)]);
+        assert_eq!(remap(&poll_data, &table).unwrap()["Pizza"], vec![0, 1, 1]);
+
+        let table = BTreeMap::from([(1, 0), (5, 1)]);
+        assert_eq!(remap(&poll_data, &table), Err(RescaleError::UnknownGrade("3".to_string())));
+        let table = BTreeMap::from([(1, 1), (3, 0), (5, 1)]);
+        assert_eq!(remap(&poll_data, &table), Err(RescaleError::NotMonotonic("3".to_string())));
+    }
+
+    #[test]
+    fn calling_migrate_tallies() {
+        let mut tallies = BTreeMap::new();
+        tallies.insert("Pizza", BTreeMap::from([(1, 3), (2, 0), (4, 2)]));
+        tallies.insert("Chips", BTreeMap::from([(2, 3), (3, 2)]));
+        tallies.insert("Soup", BTreeMap::from([(0, 5)]));
+
+        // Pizza and Chips tie once "Poor" and "Fair" merge
+        let three = BTreeMap::from([(0, "Bad"), (1, "Fair"), (2, "Fair"), (3, "Good"), (4, "Good")]);
+        let migration = migrate_tallies(&tallies, &three).unwrap();
+        assert_eq!(migration.tallies["Pizza"], BTreeMap::from([("Fair", 3), ("Good", 2)]));
+        assert_eq!(migration.merged, BTreeMap::from([("Fair", vec![1, 2]), ("Good", vec![3, 4])]));
+        assert_eq!((migration.merged_votes, migration.is_lossless()), (10, false));
+        assert_eq!(migration.rank_changes, BTreeMap::from([("Pizza", (1, 0))]));
+
+        // merging the grades without votes loses nothing
+        let migration = migrate_tallies(&tallies, &BTreeMap::from([(0, 0), (1, 1), (2, 2), (3, 3), (4, 4), (5, 4)])).unwrap();
+        assert!(migration.is_lossless() && migration.rank_changes.is_empty());
+        assert_eq!(migration.merged, BTreeMap::from([(4, vec![4, 5])]));
+        let lossy = BTreeMap::from([(0, 0), (1, 1), (2, 2), (3, 3), (4, 3), (5, 3)]);
+        assert!(!migrate_tallies(&tallies, &lossy).unwrap().is_lossless());
+
+        assert_eq!(migrate_tallies(&tallies, &BTreeMap::from([(0, 0), (1, 1), (2, 2), (4, 3)])), Err(RescaleError::UnknownGrade("3".to_string())));
+        let decreasing = BTreeMap::from([(0, 1), (1, 0), (2, 2), (3, 3), (4, 4)]);
+        assert_eq!(migrate_tallies(&tallies, &decreasing), Err(RescaleError::NotMonotonic("1".to_string())));
+    }
+}
@@ -0,0 +1,154 @@
+//! # Differential re-tally
+//! A recount finds a few ballots to strike, late ballots arrive after the results were published: instead of
+//! transposing and ranking the whole poll again, `retally_with_changes` counts the poll once, applies the
+//! changes to the counts and ranks the candidates before and after them, telling which candidates moved.
+use std::collections::BTreeMap;
+use std::fmt;
+use crate::accumulator::TallyAccumulator;
+use crate::poll::{Ballot, BallotError, Poll};
+
+/// A candidate whose rank changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankChange {
+    /// The candidate, by name
+    pub candidate: String,
+    /// The rank before the changes, 0 for the winner
+    pub before: usize,
+    /// The rank after the changes
+    pub after: usize,
+}
+
+/// The counts before and after a set of changes, see `retally_with_changes`
+#[derive(Debug, Clone)]
+pub struct Retally<G> {
+    /// The counts before the changes
+    pub before: TallyAccumulator<G>,
+    /// The counts after the changes, e.g. to rank them with `TallyAccumulator::current_ranking`
+    pub after: TallyAccumulator<G>,
+    /// The candidates whose rank changed, in the order of the ranking after the changes
+    pub changes: Vec<RankChange>,
+}
+
+impl<G: Ord + Clone> Retally<G> {
+    /// Whether the winners after the changes are not the winners before them
+    pub fn winner_changed(&self) -> bool {
+        self.before.current_ranking().winners() != self.after.current_ranking().winners()
+    }
+}
+
+/// A change refused by `retally_with_changes`, nothing being counted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetallyError {
+    /// The ballots of the poll itself are invalid
+    InvalidPoll(Vec<BallotError>),
+    /// A ballot to add is invalid, `ballot` being its index among the added ballots
+    Added(BallotError),
+    /// A ballot to remove is invalid or was never counted, `ballot` being its index among the removed ballots
+    Removed(BallotError),
+}
+
+impl fmt::Display for RetallyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetallyError::InvalidPoll(errors) => match errors.first() {
+                Some(error) => write!(f, "invalid poll, {}", error),
+                None => write!(f, "invalid poll"),
+            },
+            RetallyError::Added(error) => write!(f, "added {}", error),
+            RetallyError::Removed(error) => write!(f, "removed {}", error),
+        }
+    }
+}
+
+impl std::error::Error for RetallyError {}
+
+/// Function that ranks a poll before and after adding and removing ballots
+///
+/// # Arguments
+/// * `poll`: the poll as it was ranked, its grade range applying to the changes as well
+/// * `added`: the ballots to count, e.g. late ballots
+/// * `removed`: the ballots to strike, e.g. found invalid by a recount, as they were added with the same weight
+///
+/// # Returns
+/// * `Result<Retally<G>, RetallyError>`: the counts before and after the changes with the candidates who moved,
+///   or the first invalid ballot. The removed ballots are withdrawn before the added ones are counted.
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::{retally_with_changes, Ballot, Poll, RankChange};
+/// let mut poll = Poll::new(["Pizza", "Chips"]);
+/// poll.add_ballot(Ballot::new().grade("Pizza", 3).grade("Chips", 1));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 0).grade("Chips", 2));
+/// poll.add_ballot(Ballot::new().grade("Pizza", 3).grade("Chips", 0));
+///
+/// let late = [Ballot::new().grade("Pizza", 0).grade("Chips", 3), Ballot::new().grade("Pizza", 0).grade("Chips", 2)];
+/// let struck = [Ballot::new().grade("Pizza", 3).grade("Chips", 0)];
+/// let retally = retally_with_changes(&poll, &late, &struck).unwrap();
+/// assert!(retally.winner_changed());
+/// assert_eq!(retally.changes[0], RankChange { candidate: "Chips".to_string(), before: 1, after: 0 });
+/// assert_eq!(retally.after.ballots(), 4);
+/// ```
+pub fn retally_with_changes<G: Ord + Clone>(poll: &Poll<G>, added: &[Ballot<G>], removed: &[Ballot<G>]) -> Result<Retally<G>, RetallyError> {
+    let mut before = TallyAccumulator::new(poll.candidates().iter().cloned());
+    if let Some(range) = poll.grade_range() {
+        before = before.grade_range(range.clone());
+    }
+    let mut errors = Vec::new();
+    for (index, ballot) in poll.ballots().iter().enumerate() {
+        if let Err(issues) = before.add_ballot(ballot) {
+            errors.extend(issues.into_iter().map(|issue| BallotError { ballot: index, issue }));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(RetallyError::InvalidPoll(errors));
+    }
+
+    let mut after = before.clone();
+    for (index, ballot) in removed.iter().enumerate() {
+        after.remove_ballot(ballot).map_err(|issues| RetallyError::Removed(BallotError { ballot: index, issue: issues[0].clone() }))?;
+    }
+    for (index, ballot) in added.iter().enumerate() {
+        after.add_ballot(ballot).map_err(|issues| RetallyError::Added(BallotError { ballot: index, issue: issues[0].clone() }))?;
+    }
+
+    let ranks_before: BTreeMap<&String, usize> =
+        before.current_ranking().candidates().iter().map(|ranked| (ranked.candidate, ranked.rank)).collect();
+    let changes = after
+        .current_ranking()
+        .candidates()
+        .iter()
+        .filter(|ranked| ranks_before[ranked.candidate] != ranked.rank)
+        .map(|ranked| RankChange { candidate: ranked.candidate.clone(), before: ranks_before[ranked.candidate], after: ranked.rank })
+        .collect();
+    Ok(Retally { before, after, changes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poll::BallotIssue;
+
+    #[test]
+    fn calling_retally_with_changes() {
+        let mut poll = Poll::new(["Pizza", "Chips", "Soup"]);
+        poll.add_ballot(Ballot::new().grade("Pizza", 2).grade("Chips", 1).grade("Soup", 0));
+        poll.add_ballot(Ballot::new().grade("Pizza", 2).grade("Chips", 1).grade("Soup", 0));
+
+        let same = retally_with_changes(&poll, &[], &[]).unwrap();
+        assert!(same.changes.is_empty() && !same.winner_changed());
+
+        let soup = Ballot::new().grade("Pizza", 0).grade("Chips", 0).grade("Soup", 3);
+        let retally = retally_with_changes(&poll, &[soup.clone(), soup.clone(), soup], &[]).unwrap();
+        assert_eq!(retally.changes.iter().map(|change| (change.candidate.as_str(), change.before, change.after)).collect::<Vec<_>>(),
+                   [("Soup", 2, 0), ("Pizza", 0, 1), ("Chips", 1, 2)]);
+        assert_eq!(retally.before.ballots(), 2);
+
+        let never_cast = Ballot::new().grade("Pizza", 1).grade("Chips", 1).grade("Soup", 1);
+        let error = retally_with_changes(&poll, &[], &[never_cast]).unwrap_err();
+        assert_eq!(error, RetallyError::Removed(BallotError { ballot: 0, issue: BallotIssue::UncountedGrade("Pizza".to_string()) }));
+        assert_eq!(error.to_string(), "removed ballot 0: the grade of candidate \"Pizza\" was never counted");
+
+        poll.add_ballot(Ballot::new().grade("Pizza", 1));
+        assert!(matches!(retally_with_changes(&poll, &[], &[]), Err(RetallyError::InvalidPoll(errors)) if errors[0].ballot == 2));
+    }
+}
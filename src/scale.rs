@@ -0,0 +1,263 @@
+//! # Grade scale
+//! The ordered labels of the grades of a poll, from the worst to the best, e.g.
+//! "Reject", "Poor", "Fair", "Good", "Very good", "Excellent".
+//! The grade `i` of a poll is the `i`-th label of its scale.
+//! Grades collected on another scale are converted with a `Rescaler` or `remap`.
+//!
+//! Ballots often write the grades as their labels. A tolerant scale also reads the labels whatever
+//! their case, accents and spaces, so "tres  BIEN" is read as "Très bien".
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+pub use crate::rescale::{remap, Bucketing, RescaleError, Rescaler};
+
+/// The language of the labels of a preset scale
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    /// English labels, e.g. "Very good"
+    #[default]
+    English,
+    /// French labels, e.g. "Très bien"
+    French,
+}
+
+/// The labels of the grades, from the worst (grade 0) to the best
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GradeScale {
+    labels: Vec<String>,
+    tolerant: bool,
+}
+
+impl GradeScale {
+    /// Create a scale from its labels, from the worst grade to the best
+    pub fn new<I, S>(labels: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        GradeScale { labels: labels.into_iter().map(Into::into).collect(), tolerant: false }
+    }
+
+    /// The seven grades of Balinski and Laraki, in English
+    pub const BALINSKI_LARAKI_EN: [&'static str; 7] =
+        ["Reject", "Poor", "Acceptable", "Fairly good", "Good", "Very good", "Excellent"];
+    /// The seven grades of Balinski and Laraki, in French
+    pub const BALINSKI_LARAKI_FR: [&'static str; 7] =
+        ["À rejeter", "Insuffisant", "Passable", "Assez bien", "Bien", "Très bien", "Excellent"];
+    /// The five grades of a school report, in English
+    pub const SCHOOL_EN: [&'static str; 5] = ["Insufficient", "Pass", "Fair", "Good", "Very good"];
+    /// The five grades of a school report, in French
+    pub const SCHOOL_FR: [&'static str; 5] = ["Insuffisant", "Passable", "Assez bien", "Bien", "Très bien"];
+    /// The three grades of a thumbs vote, in English
+    pub const THUMBS_EN: [&'static str; 3] = ["Thumbs down", "Neutral", "Thumbs up"];
+    /// The three grades of a thumbs vote, in French
+    pub const THUMBS_FR: [&'static str; 3] = ["Pouce baissé", "Neutre", "Pouce levé"];
+
+    /// The seven grades of Balinski and Laraki, from "Reject" to "Excellent"
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::scale::{GradeScale, Language};
+    /// let scale = GradeScale::balinski_laraki(Language::French).tolerant();
+    /// assert_eq!(scale.len(), 7);
+    /// assert_eq!(scale.parse("tres bien"), Some(5));
+    /// ```
+    pub fn balinski_laraki(language: Language) -> Self {
+        match language {
+            Language::English => GradeScale::new(Self::BALINSKI_LARAKI_EN),
+            Language::French => GradeScale::new(Self::BALINSKI_LARAKI_FR),
+        }
+    }
+
+    /// The five grades of a school report, from "Insufficient" to "Very good"
+    pub fn school(language: Language) -> Self {
+        match language {
+            Language::English => GradeScale::new(Self::SCHOOL_EN),
+            Language::French => GradeScale::new(Self::SCHOOL_FR),
+        }
+    }
+
+    /// The three grades of a thumbs vote, from "Thumbs down" to "Thumbs up"
+    pub fn thumbs(language: Language) -> Self {
+        match language {
+            Language::English => GradeScale::new(Self::THUMBS_EN),
+            Language::French => GradeScale::new(Self::THUMBS_FR),
+        }
+    }
+
+    /// Read the labels whatever their case, accents and spaces, builder style
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::GradeScale;
+    /// let scale = GradeScale::new(["À rejeter", "Passable", "Très bien"]).tolerant();
+    /// assert_eq!(scale.parse("a rejeter"), Some(0));
+    /// assert_eq!(scale.parse("TRES  BIEN"), Some(2));
+    /// ```
+    pub fn tolerant(mut self) -> Self {
+        self.tolerant = true;
+        self
+    }
+
+    /// The labels, from the worst grade to the best
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// The number of grades of the scale
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Whether the scale has no grade
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// The label of a grade, None if the grade is out of the scale
+    pub fn label(&self, grade: usize) -> Option<&str> {
+        self.labels.get(grade).map(String::as_str)
+    }
+
+    /// Function that reads a grade written as its label or as its number
+    ///
+    /// # Arguments
+    /// * `text`: the label or the number of the grade
+    ///
+    /// # Returns
+    /// * `Option<u8>`: the grade, None if it is not part of the scale
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::GradeScale;
+    /// let scale = GradeScale::new(["Bad", "Good"]);
+    /// assert_eq!(scale.parse("Good"), Some(1));
+    /// assert_eq!(scale.parse("0"), Some(0));
+    /// assert_eq!(scale.parse("2"), None);
+    /// ```
+    pub fn parse(&self, text: &str) -> Option<u8> {
+        self.parse_grade(text).ok()
+    }
+
+    /// Function that reads a grade written as its label or as its number, explaining why it cannot
+    ///
+    /// Its errors fit the loaders of the `csv` module, which add the line and the column of the field.
+    ///
+    /// # Arguments
+    /// * `text`: the label or the number of the grade
+    ///
+    /// # Returns
+    /// * `Result<u8, String>`: the grade, or why it is not part of the scale
+    ///
+    /// # Example
+    /// ```
+    /// use majority_judgement_rust::GradeScale;
+    /// let scale = GradeScale::new(["Bad", "Good"]).tolerant();
+    /// assert_eq!(scale.parse_grade("good"), Ok(1));
+    /// assert_eq!(scale.parse_grade("Great").unwrap_err(), "\"Great\" is not a grade of the scale Bad < Good");
+    /// ```
+    pub fn parse_grade(&self, text: &str) -> Result<u8, String> {
+        let trimmed = text.trim();
+        let matches: Vec<usize> = match self.tolerant {
+            true => {
+                let folded = fold(trimmed);
+                (0..self.labels.len()).filter(|&index| fold(&self.labels[index]) == folded).collect()
+            }
+            false => self.labels.iter().position(|label| label == trimmed).into_iter().collect(),
+        };
+        match matches[..] {
+            [index] => return u8::try_from(index).map_err(|_| format!("{:?} is beyond the 256th grade", text)),
+            [first, second, ..] => {
+                return Err(format!("{:?} matches both {:?} and {:?}", text, self.labels[first], self.labels[second]))
+            }
+            [] => {}
+        }
+        trimmed
+            .parse::<u8>()
+            .ok()
+            .filter(|&grade| (grade as usize) < self.labels.len())
+            .ok_or_else(|| format!("{:?} is not a grade of the scale {}", text, self))
+    }
+}
+
+/// Function that simplifies a label for a tolerant comparison: lowercase, without accents,
+/// the spaces trimmed and collapsed
+fn fold(label: &str) -> String {
+    let mut folded = String::with_capacity(label.len());
+    for c in label.trim().chars().flat_map(char::to_lowercase) {
+        match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => folded.push('a'),
+            'ç' => folded.push('c'),
+            'è' | 'é' | 'ê' | 'ë' => folded.push('e'),
+            'ì' | 'í' | 'î' | 'ï' => folded.push('i'),
+            'ñ' => folded.push('n'),
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' => folded.push('o'),
+            'ù' | 'ú' | 'û' | 'ü' => folded.push('u'),
+            'ý' | 'ÿ' => folded.push('y'),
+            'æ' => folded.push_str("ae"),
+            'œ' => folded.push_str("oe"),
+            // the combining accents of decomposed labels, e.g. "e\u{301}" for "é"
+            '\u{300}'..='\u{36f}' => {}
+            c if c.is_whitespace() => {
+                if !folded.ends_with(' ') {
+                    folded.push(' ');
+                }
+            }
+            c => folded.push(c),
+        }
+    }
+    folded
+}
+
+impl fmt::Display for GradeScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.labels.join(" < "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_parse() {
+        let scale = GradeScale::new(["Reject", "Poor", "Good"]);
+        assert_eq!(scale.parse(" Poor "), Some(1));
+        assert_eq!(scale.parse("poor"), None);
+        assert_eq!(scale.parse("2"), Some(2));
+        assert_eq!(scale.parse("3"), None);
+        assert_eq!(scale.label(2), Some("Good"));
+        assert_eq!(scale.to_string(), "Reject < Poor < Good");
+    }
+
+    #[test]
+    fn calling_parse_grade() {
+        let scale = GradeScale::new(["À rejeter", "Insuffisant", "Passable", "Assez bien", "Bien", "Très bien", "Excellent"]);
+        assert_eq!(scale.parse_grade("très bien"), Err("\"très bien\" is not a grade of the scale \
+                                                       À rejeter < Insuffisant < Passable < Assez bien < Bien < Très bien < Excellent".to_string()));
+
+        let scale = scale.tolerant();
+        assert_eq!(scale.parse_grade("très bien"), Ok(5));
+        assert_eq!(scale.parse_grade(" TRES\tBIEN "), Ok(5));
+        assert_eq!(scale.parse_grade("tre\u{300}s bien"), Ok(5));
+        assert_eq!(scale.parse_grade("a rejeter"), Ok(0));
+        assert_eq!(scale.parse_grade("6"), Ok(6));
+        assert!(scale.parse_grade("bien!").is_err());
+
+        let ambiguous = GradeScale::new(["Élevé", "élevé"]).tolerant();
+        assert_eq!(ambiguous.parse_grade("ELEVE"), Err("\"ELEVE\" matches both \"Élevé\" and \"élevé\"".to_string()));
+    }
+
+    #[test]
+    fn calling_presets() {
+        for language in [Language::English, Language::French] {
+            assert_eq!(GradeScale::balinski_laraki(language).len(), 7);
+            assert_eq!(GradeScale::school(language).len(), 5);
+            assert_eq!(GradeScale::thumbs(language).len(), 3);
+        }
+        assert_eq!(GradeScale::school(Language::default()).label(4), Some("Very good"));
+        assert_eq!(GradeScale::thumbs(Language::French).tolerant().parse("pouce leve"), Some(2));
+    }
+}
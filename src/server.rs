@@ -0,0 +1,347 @@
+//! # Live poll server
+//! A small HTTP/1.1 server (enabled by the `server` feature) counting the ballots of a live poll
+//! with a `TallyAccumulator`, built on `std::net` only:
+//! * `POST /poll` with `{"candidates": ["Pizza", "Chips"]}` and an optional `"method"`
+//!   (`majority-values`, `majority-gauge`, `usual`, `typical` or `mean`) opens a new poll,
+//!   dropping the ballots of the previous one
+//! * `POST /ballot` with a ballot of the `json` format, e.g. `{"grades": {"Pizza": 2, "Chips": 0}, "weight": 3}`,
+//!   counts it and answers `{"ballots": n}`
+//! * `GET /ranking` answers the current ranking, as written by `Ranking::to_json`
+//!
+//! Errors are answered as `{"error": "..."}`: 400 for a body which is not valid JSON, 404 or 405 for
+//! an unknown endpoint, 409 before the first poll is opened and 422 for an invalid ballot.
+//!
+//! A slow or hostile client cannot exhaust the server: the request line and headers are limited to 8 KiB
+//! (431 beyond) and the body to 1 MiB (413 beyond), a connection silent for 10 seconds is closed, and
+//! the connections beyond 64 answered at once are answered 503.
+//!
+//! ```no_run
+//! use std::net::TcpListener;
+//! use majority_judgement_rust::server::TallyServer;
+//! let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+//! TallyServer::new().serve(listener).unwrap();
+//! ```
+//!
+//! The server has no authentication: run it behind a proxy checking who may vote.
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use crate::accumulator::TallyAccumulator;
+use crate::json::{ballot_from_json, parse, FromJson, ToJson, Value};
+use crate::options::BuiltinMethod;
+use crate::poll::BallotError;
+
+/// The largest body accepted, larger requests are answered 413
+const MAX_BODY: usize = 1 << 20;
+
+/// The largest request line and headers accepted together, larger requests are answered 431
+const MAX_HEAD: u64 = 8 << 10;
+
+/// The connections answered at once, the next ones are answered 503
+const MAX_CONNECTIONS: usize = 64;
+
+/// How long a connection may wait for the client, reading or writing, before it is closed
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An HTTP request, reduced to what the endpoints need
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    /// The HTTP method, e.g. `GET`
+    pub method: String,
+    /// The path, without the query string
+    pub path: String,
+    /// The body, empty without `Content-Length`
+    pub body: String,
+}
+
+/// An HTTP response with a JSON body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// The status code, e.g. 200
+    pub status: u16,
+    /// The JSON body
+    pub body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: Value) -> Self {
+        Response { status, body: value.to_string() }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Response::json(status, Value::Object(vec![("error".to_string(), Value::String(message.into()))]))
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            413 => "Payload Too Large",
+            422 => "Unprocessable Entity",
+            431 => "Request Header Fields Too Large",
+            503 => "Service Unavailable",
+            _ => "",
+        }
+    }
+
+    /// Function that writes the response on a connection, which is closed afterwards
+    pub fn write_to(&self, stream: &mut impl Write) -> io::Result<()> {
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            self.reason(),
+            self.body.len(),
+            self.body
+        )?;
+        stream.flush()
+    }
+}
+
+/// Function that reads a request from a connection
+///
+/// # Returns
+/// * `io::Result<Result<Request, Response>>`: the request, or the response rejecting it
+///   (400 if it is not HTTP, 431 if its request line and headers are too large, 413 if its body is too large)
+pub fn read_request(stream: &mut impl BufRead) -> io::Result<Result<Request, Response>> {
+    let mut head = (&mut *stream).take(MAX_HEAD);
+    let too_large = || Ok(Err(Response::error(431, format!("the request line and headers exceed {} bytes", MAX_HEAD))));
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    if head.limit() == 0 && !line.ends_with('\n') {
+        return too_large();
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next()) else {
+        return Ok(Err(Response::error(400, "invalid request line")));
+    };
+    let (method, path) = (method.to_string(), target.split('?').next().unwrap_or_default().to_string());
+
+    let mut length = 0;
+    loop {
+        line.clear();
+        let read = head.read_line(&mut line)?;
+        if head.limit() == 0 && !line.ends_with('\n') {
+            return too_large();
+        }
+        if read == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let Ok(value) = value.trim().parse() else {
+                    return Ok(Err(Response::error(400, "invalid Content-Length")));
+                };
+                length = value;
+            }
+        }
+    }
+    if length > MAX_BODY {
+        return Ok(Err(Response::error(413, format!("the body exceeds {} bytes", MAX_BODY))));
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body)?;
+    match String::from_utf8(body) {
+        Ok(body) => Ok(Ok(Request { method, path, body })),
+        Err(_) => Ok(Err(Response::error(400, "the body is not UTF-8"))),
+    }
+}
+
+/// The state of the live poll, shared by the connections
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::server::{Request, TallyServer};
+/// let server = TallyServer::new();
+/// let request = |method: &str, path: &str, body: &str| {
+///     server.handle(&Request { method: method.to_string(), path: path.to_string(), body: body.to_string() })
+/// };
+/// assert_eq!(request("POST", "/poll", r#"{"candidates": ["Pizza", "Chips"]}"#).status, 201);
+/// assert_eq!(request("POST", "/ballot", r#"{"grades": {"Pizza": 0, "Chips": 2}}"#).body, r#"{"ballots":1}"#);
+/// assert_eq!(
+///     request("GET", "/ranking", "").body,
+///     r#"[{"candidate":"Chips","rank":0,"tie":"unique","majority_grade":2,"proponents":0.0,"opponents":0.0},"#.to_string()
+///         + r#"{"candidate":"Pizza","rank":1,"tie":"unique","majority_grade":0,"proponents":0.0,"opponents":0.0}]"#);
+/// ```
+#[derive(Debug, Default)]
+pub struct TallyServer {
+    tally: Mutex<Option<TallyAccumulator<u32>>>,
+}
+
+impl TallyServer {
+    /// Create a server without poll, the first request being usually `POST /poll`
+    pub fn new() -> Self {
+        TallyServer::default()
+    }
+
+    /// Function that answers a request, see the module documentation for the endpoints
+    pub fn handle(&self, request: &Request) -> Response {
+        let json = || parse(&request.body).map_err(|error| Response::error(400, error.to_string()));
+        let result = match (request.method.as_str(), request.path.as_str()) {
+            ("POST", "/poll") => json().and_then(|body| self.open_poll(&body)),
+            ("POST", "/ballot") => json().and_then(|body| self.add_ballot(&body)),
+            ("GET", "/ranking") => self.ranking(),
+            (_, "/poll" | "/ballot" | "/ranking") => Err(Response::error(405, format!("{} is not allowed", request.method))),
+            (_, path) => Err(Response::error(404, format!("no endpoint {}", path))),
+        };
+        result.unwrap_or_else(|response| response)
+    }
+
+    /// Function that serves the requests of a listener, each connection on its own thread, until it fails
+    ///
+    /// At most 64 connections are answered at once, the others being answered 503 right away.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        let connections = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for stream in listener.incoming() {
+                let mut stream = stream?;
+                if connections.fetch_add(1, Ordering::AcqRel) >= MAX_CONNECTIONS {
+                    connections.fetch_sub(1, Ordering::AcqRel);
+                    let _ = stream.set_write_timeout(Some(TIMEOUT));
+                    let _ = Response::error(503, "too many connections, retry later").write_to(&mut stream);
+                    continue;
+                }
+                let slot = Slot(&connections);
+                scope.spawn(move || {
+                    // a client closing the connection early only loses its own answer
+                    let _ = self.answer(stream);
+                    drop(slot);
+                });
+            }
+            Ok(())
+        })
+    }
+
+    fn answer(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let response = match read_request(&mut reader)? {
+            Ok(request) => self.handle(&request),
+            Err(response) => response,
+        };
+        response.write_to(&mut &stream)
+    }
+
+    fn open_poll(&self, body: &Value) -> Result<Response, Response> {
+        let candidates = body
+            .get("candidates")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Response::error(422, "\"candidates\" must be an array"))?
+            .iter()
+            .map(String::from_json)
+            .collect::<Result<Vec<String>, String>>()
+            .map_err(|message| Response::error(422, message))?;
+        let method = match body.get("method").map(|method| method.as_str().unwrap_or_default()) {
+            None | Some("majority-values") => BuiltinMethod::MajorityValues,
+            Some("majority-gauge") => BuiltinMethod::MajorityGauge,
+            Some("usual") => BuiltinMethod::UsualJudgment,
+            Some("typical") => BuiltinMethod::TypicalJudgment,
+            Some("mean") => BuiltinMethod::MeanGrade,
+            Some(method) => return Err(Response::error(422, format!("unknown method {:?}", method))),
+        };
+
+        let tally = TallyAccumulator::new(candidates).method(method).exact_grade_value(|&grade| grade as i64);
+        let candidates = tally.candidates().iter().map(ToJson::to_json).collect();
+        *self.lock() = Some(tally);
+        Ok(Response::json(201, Value::Object(vec![("candidates".to_string(), Value::Array(candidates))])))
+    }
+
+    fn add_ballot(&self, body: &Value) -> Result<Response, Response> {
+        let ballot = ballot_from_json(body).map_err(|message| Response::error(422, message))?;
+        let mut tally = self.lock();
+        let tally = tally.as_mut().ok_or_else(no_poll)?;
+        tally.add_ballot(&ballot).map_err(|issues| {
+            let messages: Vec<String> = issues
+                .into_iter()
+                .map(|issue| BallotError { ballot: tally.ballots() as usize, issue }.to_string())
+                .collect();
+            Response::error(422, messages.join(", "))
+        })?;
+        Ok(Response::json(200, Value::Object(vec![("ballots".to_string(), tally.ballots().to_json())])))
+    }
+
+    fn ranking(&self) -> Result<Response, Response> {
+        let tally = self.lock();
+        let tally = tally.as_ref().ok_or_else(no_poll)?;
+        Ok(Response { status: 200, body: tally.current_ranking().to_json() })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<TallyAccumulator<u32>>> {
+        // the tally is updated only once a ballot is valid, so it is consistent even after a panic
+        self.tally.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A connection being answered, released when its thread ends, even by a panic
+struct Slot<'a>(&'a AtomicUsize);
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+fn no_poll() -> Response {
+    Response::error(409, "no poll is open, POST /poll first")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, path: &str, body: &str) -> Request {
+        Request { method: method.to_string(), path: path.to_string(), body: body.to_string() }
+    }
+
+    #[test]
+    fn calling_handle() {
+        let server = TallyServer::new();
+        assert_eq!(server.handle(&request("GET", "/ranking", "")).status, 409);
+        assert_eq!(server.handle(&request("POST", "/poll", "{")).status, 400);
+        assert_eq!(server.handle(&request("POST", "/poll", r#"{"candidates": ["Pizza", "Chips"], "method": "median"}"#)).status, 422);
+        assert_eq!(server.handle(&request("POST", "/poll", r#"{"candidates": ["Pizza", "Chips"]}"#)).status, 201);
+
+        let ballot = server.handle(&request("POST", "/ballot", r#"{"grades": {"Pizza": 3, "Chips": 1}, "weight": 2}"#));
+        assert_eq!((ballot.status, ballot.body), (200, r#"{"ballots":1}"#.to_string()));
+        let invalid = server.handle(&request("POST", "/ballot", r#"{"grades": {"Pizza": 3}}"#));
+        assert_eq!((invalid.status, invalid.body), (422, r#"{"error":"ballot 1: no grade for candidate \"Chips\""}"#.to_string()));
+        server.handle(&request("POST", "/ballot", r#"{"grades": {"Pizza": 0, "Chips": 2}}"#));
+
+        // 3, 3, 0 against 1, 1, 2
+        let ranking = server.handle(&request("GET", "/ranking", ""));
+        assert!(ranking.body.starts_with(r#"[{"candidate":"Pizza","rank":0"#));
+        assert_eq!(server.handle(&request("GET", "/ballot", "")).status, 405);
+        assert_eq!(server.handle(&request("GET", "/", "")).status, 404);
+    }
+
+    #[test]
+    fn calling_read_request() {
+        let raw = "POST /ballot?poll=1 HTTP/1.1\r\nHost: localhost\r\ncontent-length: 2\r\n\r\n{}";
+        let request = read_request(&mut raw.as_bytes()).unwrap().unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str(), request.body.as_str()), ("POST", "/ballot", "{}"));
+
+        let raw = format!("POST /ballot HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1);
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap().unwrap_err().status, 413);
+        assert_eq!(read_request(&mut "hello\r\n\r\n".as_bytes()).unwrap().unwrap_err().status, 400);
+
+        // the headers are not read beyond the limit, however long they are
+        let raw = format!("GET /ranking HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(MAX_HEAD as usize));
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap().unwrap_err().status, 431);
+        let raw = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_HEAD as usize));
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap().unwrap_err().status, 431);
+        let raw = format!("GET /ranking HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(MAX_HEAD as usize - 100));
+        assert_eq!(read_request(&mut raw.as_bytes()).unwrap().unwrap().path, "/ranking");
+
+        let mut written = Vec::new();
+        Response::error(404, "no endpoint /").write_to(&mut written).unwrap();
+        assert!(String::from_utf8(written).unwrap().starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
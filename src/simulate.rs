@@ -0,0 +1,347 @@
+//! # Simulations
+//! Synthetic electorates for Monte Carlo studies and benchmarks: an `Electorate` draws the grades
+//! of its voters for its candidates from a `Distribution`:
+//! * `Uniform`: every grade equally likely, whatever the candidate
+//! * `Polarized`: the voters love or hate the candidates, grading them at the ends of the scale
+//! * `Normal`: the grades spread around the quality of each candidate, a good candidate getting good grades
+//!
+//! The quality of a candidate goes from 0 (the worst grade) to 1 (the best grade), the candidates being
+//! evenly spread between them by default. The same seed gives the same electorate.
+//!
+//! `strategic_resistance` then measures how often strategic voters change the winner: a share of the
+//! voters exaggerate, grading their favorite candidate with the best grade and the others with the worst.
+//!
+//! # Example
+//! ```
+//! use majority_judgement_rust::majority_judgment;
+//! use majority_judgement_rust::simulate::{Distribution, Electorate};
+//! // how often does the best candidate win when the voters disagree that much?
+//! let wins = (0..20)
+//!     .filter(|&seed| {
+//!         let electorate = Electorate::new(101, 3, 7).distribution(Distribution::Normal { spread: 2.0 }).seed(seed);
+//!         majority_judgment(&electorate.poll_data())[0].0 == "Candidate 2"
+//!     })
+//!     .count();
+//! assert!(wins > 10);
+//! ```
+use std::collections::BTreeMap;
+use crate::mj::{check_poll_length, majority_judgment_ranking};
+use crate::poll::{Ballot, Poll};
+use crate::tie_breaker::splitmix64;
+
+/// How the voters grade the candidates
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Distribution {
+    /// Every grade is equally likely
+    #[default]
+    Uniform,
+    /// A voter grades a candidate at the two ends of the scale: in the best `width` grades with a probability
+    /// equal to the quality of the candidate, in the worst `width` grades otherwise
+    Polarized {
+        /// The number of grades at each end of the scale, at least 1
+        width: u8,
+    },
+    /// The grades follow a normal distribution centered on the quality of the candidate, rounded and
+    /// clamped to the scale
+    Normal {
+        /// The standard deviation, in grades
+        spread: f64,
+    },
+}
+
+/// A synthetic electorate, builder style
+#[derive(Debug, Clone, PartialEq)]
+pub struct Electorate {
+    voters: usize,
+    grades: u8,
+    qualities: Vec<f64>,
+    distribution: Distribution,
+    seed: u64,
+}
+
+impl Electorate {
+    /// Create an electorate grading uniformly, with the seed 0
+    ///
+    /// # Arguments
+    /// * `voters`: the number of voters
+    /// * `candidates`: the number of candidates, named "Candidate 0", "Candidate 1", ... by increasing quality
+    /// * `grades`: the number of grades of the scale, the grades going from 0 to `grades - 1`
+    ///
+    /// # Panics
+    /// * if the scale has no grade
+    pub fn new(voters: usize, candidates: usize, grades: u8) -> Self {
+        assert!(grades > 0, "the scale must have at least one grade");
+        let qualities = (0..candidates).map(|candidate| (candidate + 1) as f64 / (candidates + 1) as f64).collect();
+        Electorate { voters, grades, qualities, distribution: Distribution::default(), seed: 0 }
+    }
+
+    /// Set how the voters grade the candidates, builder style
+    pub fn distribution(mut self, distribution: Distribution) -> Self {
+        self.distribution = distribution;
+        self
+    }
+
+    /// Set the quality of each candidate, from 0 to 1, builder style: the number of qualities is the
+    /// number of candidates
+    pub fn qualities<I: IntoIterator<Item = f64>>(mut self, qualities: I) -> Self {
+        self.qualities = qualities.into_iter().map(|quality| quality.clamp(0.0, 1.0)).collect();
+        self
+    }
+
+    /// Set the seed of the draw, builder style
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// The quality of each candidate, from 0 to 1
+    pub fn candidate_qualities(&self) -> &[f64] {
+        &self.qualities
+    }
+
+    /// Function that draws the grades of the voters
+    ///
+    /// # Returns
+    /// * `BTreeMap<String, Vec<u8>>`: the poll data, the `i`-th grade of every candidate coming from the `i`-th voter
+    pub fn poll_data(&self) -> BTreeMap<String, Vec<u8>> {
+        let mut random = Random { state: self.seed };
+        let mut columns = vec![Vec::with_capacity(self.voters); self.qualities.len()];
+        for _ in 0..self.voters {
+            for (grades, &quality) in columns.iter_mut().zip(&self.qualities) {
+                grades.push(self.grade(quality, &mut random));
+            }
+        }
+        columns
+            .into_iter()
+            .enumerate()
+            .map(|(candidate, grades)| (format!("Candidate {}", candidate), grades))
+            .collect()
+    }
+
+    /// Function that draws the ballots of the voters, the same grades as `poll_data`
+    ///
+    /// # Returns
+    /// * `Poll<u8>`: one ballot per voter grading every candidate
+    pub fn poll(&self) -> Poll<u8> {
+        let poll_data = self.poll_data();
+        let mut poll = Poll::new(poll_data.keys());
+        for voter in 0..self.voters {
+            let ballot = poll_data
+                .iter()
+                .fold(Ballot::new(), |ballot, (candidate, grades)| ballot.grade(candidate.clone(), grades[voter]));
+            poll.add_ballot(ballot);
+        }
+        poll
+    }
+
+    /// Function that draws the grade of a voter for a candidate of the given quality
+    fn grade(&self, quality: f64, random: &mut Random) -> u8 {
+        let best = self.grades - 1;
+        match self.distribution {
+            Distribution::Uniform => random.below(self.grades as u64) as u8,
+            Distribution::Polarized { width } => {
+                let width = width.clamp(1, self.grades);
+                let offset = random.below(width as u64) as u8;
+                if random.unit() < quality { best - offset } else { offset }
+            }
+            Distribution::Normal { spread } => {
+                let grade = quality * best as f64 + spread * random.normal();
+                grade.round().clamp(0.0, best as f64) as u8
+            }
+        }
+    }
+}
+
+/// How often strategic voters changed the winner, see `strategic_resistance`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resistance<'a, K> {
+    /// The winners of the sincere poll, more than one if they are tied
+    pub sincere_winners: Vec<&'a K>,
+    /// The number of polls with strategic voters
+    pub trials: usize,
+    /// The number of trials whose winners differ from the sincere winners
+    pub changed: usize,
+    /// For each candidate, the number of trials it won, ties included
+    pub wins: BTreeMap<&'a K, usize>,
+}
+
+impl<K> Resistance<'_, K> {
+    /// The share of the trials whose winners differ from the sincere winners, 0 without trial
+    pub fn change_rate(&self) -> f64 {
+        match self.trials {
+            0 => 0.0,
+            trials => self.changed as f64 / trials as f64,
+        }
+    }
+}
+
+/// Function that measures how often a share of strategic voters changes the winner of a poll
+///
+/// In each trial, `share` of the voters are drawn at random and exaggerate: each of them grades its favorite
+/// candidate (the one it graded best, the first by key among equals) with `best` and every other candidate
+/// with `worst`. The draws are reproducible: the same seed gives the same resistance.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the sincere grades, each vector ordered by voter
+/// * `worst`, `best`: the worst and the best grades of the scale
+/// * `share`: the share of strategic voters, from 0 to 1
+/// * `trials`: the number of polls with strategic voters, e.g. 1000
+/// * `seed`: the seed of the draws
+///
+/// # Returns
+/// * `Resistance`: the sincere winners and how often the strategic voters changed them
+///
+/// # Panics
+/// * if the candidates have different numbers of grades
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::simulate::{strategic_resistance, Distribution, Electorate};
+/// let electorate = Electorate::new(201, 4, 7).distribution(Distribution::Normal { spread: 1.5 }).seed(3);
+/// let poll_data = electorate.poll_data();
+///
+/// let resistance = strategic_resistance(&poll_data, &0, &6, 0.1, 50, 42);
+/// assert_eq!(resistance.sincere_winners, vec!["Candidate 3"]);
+/// assert!(resistance.change_rate() < 0.5);
+/// ```
+pub fn strategic_resistance<'a, K: Ord, G: Ord + Clone>(
+    poll_data: &'a BTreeMap<K, Vec<G>>,
+    worst: &G,
+    best: &G,
+    share: f64,
+    trials: usize,
+    seed: u64,
+) -> Resistance<'a, K> {
+    let _ = check_poll_length(poll_data);
+    let sincere_winners = majority_judgment_ranking(poll_data).winners();
+    let mut wins: BTreeMap<&K, usize> = poll_data.keys().map(|candidate| (candidate, 0)).collect();
+    let voters = poll_data.values().next().map_or(0, Vec::len);
+    let strategic = ((share.clamp(0.0, 1.0) * voters as f64).round() as usize).min(voters);
+    let candidates: Vec<(&K, &Vec<G>)> = poll_data.iter().collect();
+    let favorites: Vec<usize> = (0..voters)
+        .map(|voter| {
+            // the first candidate among those graded best
+            let mut favorite = 0;
+            for (index, (_, grades)) in candidates.iter().enumerate() {
+                if grades[voter] > candidates[favorite].1[voter] {
+                    favorite = index;
+                }
+            }
+            favorite
+        })
+        .collect();
+
+    let mut random = Random { state: seed };
+    let mut order: Vec<usize> = (0..voters).collect();
+    let mut changed = 0;
+    for _ in 0..trials {
+        // the first `strategic` voters of a partial Fisher-Yates shuffle
+        for index in 0..strategic {
+            let drawn = index + random.below((voters - index) as u64) as usize;
+            order.swap(index, drawn);
+        }
+        let mut perturbed: BTreeMap<&K, Vec<G>> = poll_data.iter().map(|(candidate, grades)| (candidate, grades.clone())).collect();
+        for &voter in &order[..strategic] {
+            for (index, grades) in perturbed.values_mut().enumerate() {
+                grades[voter] = if index == favorites[voter] { best.clone() } else { worst.clone() };
+            }
+        }
+        let winners = majority_judgment_ranking(&perturbed).winners();
+        if !winners.iter().map(|winner| **winner).eq(sincere_winners.iter().copied()) {
+            changed += 1;
+        }
+        for winner in winners {
+            if let Some(count) = wins.get_mut(*winner) {
+                *count += 1;
+            }
+        }
+    }
+    Resistance { sincere_winners, trials, changed, wins }
+}
+
+/// The random numbers of an electorate (SplitMix64)
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    /// The next random number
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        splitmix64(self.state)
+    }
+
+    /// A random number in `0..bound`
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// A random number in `[0, 1)`
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A random number of the standard normal distribution (Box-Muller)
+    fn normal(&mut self) -> f64 {
+        let (u, v) = (1.0 - self.unit(), self.unit());
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_poll_data() {
+        let electorate = Electorate::new(200, 3, 5).seed(7);
+        let poll_data = electorate.poll_data();
+        assert_eq!(poll_data, electorate.poll_data());
+        assert_ne!(poll_data, electorate.clone().seed(8).poll_data());
+        assert_eq!(poll_data.len(), 3);
+        assert!(poll_data.values().all(|grades| grades.len() == 200 && (0..5).all(|grade| grades.contains(&grade))));
+
+        let polarized = electorate.clone().distribution(Distribution::Polarized { width: 1 }).poll_data();
+        assert!(polarized.values().flatten().all(|&grade| grade == 0 || grade == 4));
+        let loved = polarized["Candidate 2"].iter().filter(|&&grade| grade == 4).count();
+        assert!(loved > polarized["Candidate 0"].iter().filter(|&&grade| grade == 4).count());
+
+        let normal = electorate.distribution(Distribution::Normal { spread: 0.0 }).qualities([0.0, 0.5, 1.0]).poll_data();
+        assert_eq!((normal["Candidate 0"][0], normal["Candidate 1"][0], normal["Candidate 2"][0]), (0, 2, 4));
+    }
+
+    #[test]
+    fn calling_strategic_resistance() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza", vec![2, 2, 2, 1, 1]);
+        poll_data.insert("Chips", vec![1, 1, 1, 3, 3]);
+
+        // no strategic voter, or ones agreeing with the sincere winner, change nothing
+        let sincere = strategic_resistance(&poll_data, &0, &3, 0.0, 10, 1);
+        assert_eq!((sincere.sincere_winners, sincere.changed, sincere.wins[&"Pizza"]), (vec![&"Pizza"], 0, 10));
+
+        // all the voters exaggerating: three voters love Pizza, two love Chips
+        let all = strategic_resistance(&poll_data, &0, &3, 1.0, 5, 1);
+        assert_eq!((all.changed, all.change_rate()), (0, 0.0));
+
+        // two of the five voters cannot change the winner either, whoever they are
+        assert_eq!(strategic_resistance(&poll_data, &0, &3, 0.4, 50, 7).changed, 0);
+
+        // a single strategic voter breaks a tie toward its favorite, Chips for the voter grading both 2
+        poll_data.insert("Chips", vec![1, 1, 2, 2, 2]);
+        let resistance = strategic_resistance(&poll_data, &0, &3, 0.2, 100, 7);
+        assert_eq!(resistance.sincere_winners, vec![&"Chips", &"Pizza"]);
+        assert_eq!(resistance.changed, 100);
+        assert_eq!(resistance.wins[&"Chips"] + resistance.wins[&"Pizza"], 100);
+        assert!(resistance.wins[&"Chips"] > resistance.wins[&"Pizza"]);
+        assert!(strategic_resistance(&BTreeMap::<&str, Vec<u8>>::new(), &0, &3, 0.5, 3, 0).sincere_winners.is_empty());
+    }
+
+    #[test]
+    fn calling_poll() {
+        let electorate = Electorate::new(10, 2, 3).distribution(Distribution::Normal { spread: 1.0 }).seed(1);
+        let poll = electorate.poll();
+        assert_eq!(poll.ballots().len(), 10);
+        assert_eq!(poll.poll_data().unwrap(), electorate.poll_data());
+    }
+}
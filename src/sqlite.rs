@@ -0,0 +1,544 @@
+//! # SQLite backend
+//! Loads ballots from an SQLite table and writes the results back to another table (enabled by
+//! the `sqlite` feature, which links the system `libsqlite3`), so that an election night can be
+//! counted by a batch job next to the database of the voting platform.
+//!
+//! The ballots table has one row per grade: the ballot it belongs to, the candidate and the grade,
+//! and optionally the weight of the ballot. The names of the table and of its columns are set with
+//! a `BallotTable`:
+//!
+//! ```text
+//! CREATE TABLE ballots (ballot TEXT, candidate TEXT, grade INTEGER);
+//! INSERT INTO ballots VALUES ('v1', 'Pizza', 2), ('v1', 'Chips', 0), ('v2', 'Pizza', 1), ...
+//! ```
+//!
+//! The results table has one row per candidate: `rank` (0 being the best, as in `Ranking`),
+//! `candidate` and `tie` (`unique`, `tied` or `broken`, as in `Ranking::to_json`).
+use std::collections::BTreeMap;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::fmt;
+use std::path::Path;
+use std::ptr::{self, NonNull};
+use crate::poll::{Ballot, Poll};
+use crate::ranking::{Ranking, TieStatus};
+
+#[repr(C)]
+struct RawDatabase {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct RawStatement {
+    _private: [u8; 0],
+}
+
+const SQLITE_OK: c_int = 0;
+const SQLITE_MISUSE: c_int = 21;
+const SQLITE_ROW: c_int = 100;
+const SQLITE_DONE: c_int = 101;
+const SQLITE_NULL: c_int = 5;
+const SQLITE_OPEN_READWRITE: c_int = 0x2;
+const SQLITE_OPEN_CREATE: c_int = 0x4;
+/// The destructor telling SQLite to copy a bound text before the call returns
+const SQLITE_TRANSIENT: isize = -1;
+
+#[link(name = "sqlite3")]
+extern "C" {
+    fn sqlite3_open_v2(filename: *const c_char, database: *mut *mut RawDatabase, flags: c_int, vfs: *const c_char) -> c_int;
+    fn sqlite3_close_v2(database: *mut RawDatabase) -> c_int;
+    fn sqlite3_errmsg(database: *mut RawDatabase) -> *const c_char;
+    fn sqlite3_prepare_v2(
+        database: *mut RawDatabase,
+        sql: *const c_char,
+        bytes: c_int,
+        statement: *mut *mut RawStatement,
+        tail: *mut *const c_char,
+    ) -> c_int;
+    fn sqlite3_step(statement: *mut RawStatement) -> c_int;
+    fn sqlite3_reset(statement: *mut RawStatement) -> c_int;
+    fn sqlite3_finalize(statement: *mut RawStatement) -> c_int;
+    fn sqlite3_column_type(statement: *mut RawStatement, column: c_int) -> c_int;
+    fn sqlite3_column_text(statement: *mut RawStatement, column: c_int) -> *const u8;
+    fn sqlite3_column_bytes(statement: *mut RawStatement, column: c_int) -> c_int;
+    fn sqlite3_column_int64(statement: *mut RawStatement, column: c_int) -> i64;
+    fn sqlite3_bind_text(statement: *mut RawStatement, index: c_int, text: *const c_char, bytes: c_int, destructor: isize) -> c_int;
+    fn sqlite3_bind_int64(statement: *mut RawStatement, index: c_int, value: i64) -> c_int;
+}
+
+/// An error of the database, or of the ballots it holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteError {
+    /// The SQLite result code, None when the database works but its content is not a valid poll
+    pub code: Option<i32>,
+    /// What is wrong
+    pub message: String,
+}
+
+impl SqliteError {
+    fn content(message: impl Into<String>) -> Self {
+        SqliteError { code: None, message: message.into() }
+    }
+}
+
+impl fmt::Display for SqliteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code {
+            Some(code) => write!(f, "SQLite error {}: {}", code, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SqliteError {}
+
+/// A connection to an SQLite database, closed when dropped
+#[derive(Debug)]
+pub struct Database {
+    handle: NonNull<RawDatabase>,
+}
+
+impl Database {
+    /// Function that opens a database, creating the file if it does not exist
+    ///
+    /// # Arguments
+    /// * `path`: the database file, or `:memory:` for a temporary database
+    ///
+    /// # Returns
+    /// * `Result<Database, SqliteError>`: the connection, or why the file cannot be opened
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SqliteError> {
+        let path = CString::new(path.as_ref().to_string_lossy().into_owned())
+            .map_err(|_| SqliteError { code: Some(SQLITE_MISUSE), message: "the path contains a NUL byte".to_string() })?;
+        let mut handle = ptr::null_mut();
+        // SAFETY: the path is a valid C string and `handle` a valid place for the connection
+        let code = unsafe { sqlite3_open_v2(path.as_ptr(), &mut handle, SQLITE_OPEN_READWRITE | SQLITE_OPEN_CREATE, ptr::null()) };
+        let Some(handle) = NonNull::new(handle) else {
+            return Err(SqliteError { code: Some(code), message: "out of memory".to_string() });
+        };
+        // the connection is closed by `drop` even if it failed to open
+        let database = Database { handle };
+        if code != SQLITE_OK {
+            return Err(database.error(code));
+        }
+        Ok(database)
+    }
+
+    /// Function that runs SQL statements which return no rows, e.g. to create the tables of the ballots
+    ///
+    /// # Arguments
+    /// * `sql`: one or several statements separated by `;`
+    ///
+    /// # Returns
+    /// * `Result<(), SqliteError>`: the first error of the statements
+    pub fn execute(&self, sql: &str) -> Result<(), SqliteError> {
+        let mut rest = sql;
+        while !rest.trim().is_empty() {
+            let (statement, tail) = self.prepare(rest)?;
+            if let Some(mut statement) = statement {
+                while statement.step()? {}
+            }
+            rest = tail;
+        }
+        Ok(())
+    }
+
+    fn error(&self, code: c_int) -> SqliteError {
+        // SAFETY: the handle is an open connection, the message is a C string owned by SQLite
+        let message = unsafe { CStr::from_ptr(sqlite3_errmsg(self.handle.as_ptr())) };
+        SqliteError { code: Some(code), message: message.to_string_lossy().into_owned() }
+    }
+
+    /// Function that compiles the first statement of `sql`, None if it is only a comment,
+    /// with the text after this statement
+    fn prepare<'a>(&self, sql: &'a str) -> Result<(Option<Statement<'_>>, &'a str), SqliteError> {
+        let bytes = c_int::try_from(sql.len()).map_err(|_| SqliteError::content("the SQL text is too long"))?;
+        let mut handle = ptr::null_mut();
+        let mut tail = ptr::null();
+        // SAFETY: `sql` is valid for `bytes` bytes, SQLite does not read further
+        let code = unsafe { sqlite3_prepare_v2(self.handle.as_ptr(), sql.as_ptr().cast(), bytes, &mut handle, &mut tail) };
+        if code != SQLITE_OK {
+            return Err(self.error(code));
+        }
+        // SAFETY: the tail points into `sql`, after a complete statement so on a character boundary
+        let consumed = if tail.is_null() { sql.len() } else { unsafe { tail.cast::<u8>().offset_from(sql.as_ptr()) as usize } };
+        let statement = NonNull::new(handle).map(|handle| Statement { database: self, handle });
+        Ok((statement, &sql[consumed..]))
+    }
+
+    fn statement(&self, sql: &str) -> Result<Statement<'_>, SqliteError> {
+        self.prepare(sql)?.0.ok_or_else(|| SqliteError::content("empty statement"))
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        // SAFETY: the handle is owned by this value, the statements borrowing it are already finalized
+        unsafe {
+            sqlite3_close_v2(self.handle.as_ptr());
+        }
+    }
+}
+
+/// A compiled statement, finalized when dropped
+struct Statement<'a> {
+    database: &'a Database,
+    handle: NonNull<RawStatement>,
+}
+
+impl Statement<'_> {
+    /// Function that runs the statement until its next row, false once it is done
+    fn step(&mut self) -> Result<bool, SqliteError> {
+        // SAFETY: the statement is compiled and not finalized
+        match unsafe { sqlite3_step(self.handle.as_ptr()) } {
+            SQLITE_ROW => Ok(true),
+            SQLITE_DONE => Ok(false),
+            code => Err(self.database.error(code)),
+        }
+    }
+
+    /// The text of a column of the current row, None if it is NULL
+    fn text(&self, column: c_int) -> Option<String> {
+        // SAFETY: the statement is on a row; the text stays valid until the next call on the statement
+        unsafe {
+            if sqlite3_column_type(self.handle.as_ptr(), column) == SQLITE_NULL {
+                return None;
+            }
+            let text = sqlite3_column_text(self.handle.as_ptr(), column);
+            let bytes = sqlite3_column_bytes(self.handle.as_ptr(), column) as usize;
+            Some(match text.is_null() {
+                true => String::new(),
+                false => String::from_utf8_lossy(std::slice::from_raw_parts(text, bytes)).into_owned(),
+            })
+        }
+    }
+
+    /// The integer of a column of the current row, None if it is NULL
+    fn integer(&self, column: c_int) -> Option<i64> {
+        // SAFETY: the statement is on a row
+        unsafe {
+            match sqlite3_column_type(self.handle.as_ptr(), column) {
+                SQLITE_NULL => None,
+                _ => Some(sqlite3_column_int64(self.handle.as_ptr(), column)),
+            }
+        }
+    }
+
+    fn bind_text(&mut self, index: c_int, text: &str) -> Result<(), SqliteError> {
+        let bytes = c_int::try_from(text.len()).map_err(|_| SqliteError::content("the text is too long"))?;
+        // SAFETY: SQLite copies the text before returning (SQLITE_TRANSIENT)
+        let code = unsafe { sqlite3_bind_text(self.handle.as_ptr(), index, text.as_ptr().cast(), bytes, SQLITE_TRANSIENT) };
+        self.check(code)
+    }
+
+    fn bind_integer(&mut self, index: c_int, value: i64) -> Result<(), SqliteError> {
+        // SAFETY: the statement is compiled and not finalized
+        let code = unsafe { sqlite3_bind_int64(self.handle.as_ptr(), index, value) };
+        self.check(code)
+    }
+
+    /// Function that rewinds the statement once it is done, to run it again with new values
+    fn reset(&mut self) -> Result<(), SqliteError> {
+        // SAFETY: the statement is compiled and not finalized
+        let code = unsafe { sqlite3_reset(self.handle.as_ptr()) };
+        self.check(code)
+    }
+
+    fn check(&self, code: c_int) -> Result<(), SqliteError> {
+        match code {
+            SQLITE_OK => Ok(()),
+            code => Err(self.database.error(code)),
+        }
+    }
+}
+
+impl Drop for Statement<'_> {
+    fn drop(&mut self) {
+        // SAFETY: the handle is owned by this value
+        unsafe {
+            sqlite3_finalize(self.handle.as_ptr());
+        }
+    }
+}
+
+/// Function that quotes an SQL identifier, so that any table or column name can be mapped
+fn identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The names of the table holding the ballots and of its columns
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::sqlite::BallotTable;
+/// // SELECT "voter_id", "option", "mention", "weight" FROM "votes"
+/// let table = BallotTable::new("votes")
+///     .ballot_column("voter_id")
+///     .candidate_column("option")
+///     .grade_column("mention")
+///     .weight_column("weight");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BallotTable {
+    table: String,
+    ballot: String,
+    candidate: String,
+    grade: String,
+    weight: Option<String>,
+}
+
+impl BallotTable {
+    /// The mapping of a table with the columns `ballot`, `candidate` and `grade`, without weights
+    pub fn new(table: impl Into<String>) -> Self {
+        BallotTable {
+            table: table.into(),
+            ballot: "ballot".to_string(),
+            candidate: "candidate".to_string(),
+            grade: "grade".to_string(),
+            weight: None,
+        }
+    }
+
+    /// Set the column identifying the ballot of each grade, e.g. a voter id, builder style
+    pub fn ballot_column(mut self, column: impl Into<String>) -> Self {
+        self.ballot = column.into();
+        self
+    }
+
+    /// Set the column with the name of the candidate, builder style
+    pub fn candidate_column(mut self, column: impl Into<String>) -> Self {
+        self.candidate = column.into();
+        self
+    }
+
+    /// Set the column with the grade, builder style
+    pub fn grade_column(mut self, column: impl Into<String>) -> Self {
+        self.grade = column.into();
+        self
+    }
+
+    /// Set the column with the weight of the ballot, the same on every row of the ballot, builder style.
+    /// A NULL weight counts as 1.
+    pub fn weight_column(mut self, column: impl Into<String>) -> Self {
+        self.weight = Some(column.into());
+        self
+    }
+
+    fn query(&self) -> String {
+        let mut columns = vec![identifier(&self.ballot), identifier(&self.candidate), identifier(&self.grade)];
+        columns.extend(self.weight.as_deref().map(identifier));
+        format!("SELECT {} FROM {}", columns.join(", "), identifier(&self.table))
+    }
+}
+
+/// Function that loads a poll from a table of the database
+///
+/// The candidates and the ballots are listed in the order of their first row. Each ballot keeps
+/// the value of the ballot column as its voter, see `dataset::strip_voters` to publish the poll.
+///
+/// # Arguments
+/// * `database`: the database
+/// * `table`: the names of the table and of its columns
+/// * `parse_grade`: the function parsing a grade from the grade column read as text, a NULL grade
+///   being read as an empty text like an empty CSV field, e.g. `csv::parse_number` or `GradeScale::parse_grade`
+///
+/// # Returns
+/// * `Result<Poll<G>, SqliteError>`: the poll with one ballot per value of the ballot column, or the first error found
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::sqlite::{load_poll, BallotTable, Database};
+/// let database = Database::open(":memory:").unwrap();
+/// database.execute("CREATE TABLE ballots (ballot TEXT, candidate TEXT, grade INTEGER);
+///                   INSERT INTO ballots VALUES ('v1', 'Pizza', 2), ('v1', 'Chips', 0), ('v2', 'Pizza', 1), ('v2', 'Chips', 3);").unwrap();
+///
+/// let poll = load_poll(&database, &BallotTable::new("ballots"), |grade| grade.parse::<u8>().map_err(|e| e.to_string())).unwrap();
+/// assert_eq!(poll.candidates(), &["Pizza".to_string(), "Chips".to_string()]);
+/// assert_eq!(poll.poll_data().unwrap()["Chips"], vec![0, 3]);
+/// ```
+pub fn load_poll<G, F>(database: &Database, table: &BallotTable, parse_grade: F) -> Result<Poll<G>, SqliteError>
+where
+    F: Fn(&str) -> Result<G, String>,
+{
+    let mut statement = database.statement(&table.query())?;
+    let mut candidates = Vec::new();
+    // the voter and the weight of each ballot, and its grades
+    let mut ballots: Vec<(String, u32)> = Vec::new();
+    let mut grades: Vec<Vec<(String, G)>> = Vec::new();
+    let mut positions = BTreeMap::new();
+    while statement.step()? {
+        let ballot = statement.text(0).ok_or_else(|| SqliteError::content("a row has no ballot"))?;
+        let candidate = statement.text(1).ok_or_else(|| SqliteError::content(format!("ballot {} has no candidate", ballot)))?;
+        let grade = parse_grade(&statement.text(2).unwrap_or_default())
+            .map_err(|message| SqliteError::content(format!("ballot {}, candidate {}: {}", ballot, candidate, message)))?;
+        let weight = match table.weight.is_some().then(|| statement.integer(3)).flatten() {
+            None => 1,
+            Some(weight) => u32::try_from(weight)
+                .map_err(|_| SqliteError::content(format!("ballot {}: invalid weight {}", ballot, weight)))?,
+        };
+
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate.clone());
+        }
+        let position = *positions.entry(ballot.clone()).or_insert_with(|| {
+            ballots.push((ballot.clone(), weight));
+            grades.push(Vec::new());
+            ballots.len() - 1
+        });
+        if ballots[position].1 != weight {
+            return Err(SqliteError::content(format!("ballot {} has several weights", ballot)));
+        }
+        grades[position].push((candidate, grade));
+    }
+
+    let mut poll = Poll::new(candidates);
+    for ((voter, weight), grades) in ballots.into_iter().zip(grades) {
+        let ballot = grades
+            .into_iter()
+            .fold(Ballot::new(), |ballot, (candidate, grade)| ballot.grade(candidate, grade));
+        poll.add_ballot(ballot.with_weight(weight).with_voter(voter));
+    }
+    Ok(poll)
+}
+
+/// Function that writes a ranking to a results table, created if it does not exist
+///
+/// The previous results of the table are replaced in a single transaction, so a failed write leaves them untouched.
+///
+/// # Arguments
+/// * `database`: the database
+/// * `table`: the name of the results table
+/// * `ranking`: the ranking to write, one row per candidate
+///
+/// # Returns
+/// * `Result<(), SqliteError>`: the first error of the database
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::majority_judgment_ranking;
+/// use majority_judgement_rust::sqlite::{save_ranking, Database};
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![1, 2]);
+/// poll_data.insert("Chips".to_string(), vec![0, 3]);
+///
+/// let database = Database::open(":memory:").unwrap();
+/// save_ranking(&database, "results", &majority_judgment_ranking(&poll_data)).unwrap();
+/// ```
+pub fn save_ranking<K: fmt::Display, G>(database: &Database, table: &str, ranking: &Ranking<'_, K, G>) -> Result<(), SqliteError> {
+    let table = identifier(table);
+    database.execute(&format!(
+        "CREATE TABLE IF NOT EXISTS {} (rank INTEGER NOT NULL, candidate TEXT PRIMARY KEY, tie TEXT NOT NULL)",
+        table
+    ))?;
+    database.execute("BEGIN")?;
+    let written = (|| -> Result<(), SqliteError> {
+        database.execute(&format!("DELETE FROM {}", table))?;
+        let mut insert = database.statement(&format!("INSERT INTO {} (rank, candidate, tie) VALUES (?1, ?2, ?3)", table))?;
+        for ranked in ranking.iter() {
+            let tie = match ranked.tie {
+                TieStatus::Unique => "unique",
+                TieStatus::Tied => "tied",
+                TieStatus::Broken => "broken",
+            };
+            insert.bind_integer(1, ranked.rank as i64)?;
+            insert.bind_text(2, &ranked.candidate.to_string())?;
+            insert.bind_text(3, tie)?;
+            while insert.step()? {}
+            insert.reset()?;
+        }
+        Ok(())
+    })();
+    match written {
+        Ok(()) => database.execute("COMMIT"),
+        Err(error) => {
+            // the error of the insert tells what went wrong, not the one of the rollback, e.g. a transaction
+            // already rolled back by SQLite
+            let _ = database.execute("ROLLBACK");
+            Err(error)
+        }
+    }
+}
+
+/// Function that reads a results table written by `save_ranking`
+///
+/// # Arguments
+/// * `database`: the database
+/// * `table`: the name of the results table
+///
+/// # Returns
+/// * `Result<Vec<(String, usize)>, SqliteError>`: the candidates with their rank, from the best
+pub fn load_ranking(database: &Database, table: &str) -> Result<Vec<(String, usize)>, SqliteError> {
+    let mut statement = database.statement(&format!("SELECT candidate, rank FROM {} ORDER BY rank, rowid", identifier(table)))?;
+    let mut ranking = Vec::new();
+    while statement.step()? {
+        let candidate = statement.text(0).unwrap_or_default();
+        let rank = statement.integer(1).and_then(|rank| usize::try_from(rank).ok());
+        ranking.push((candidate, rank.ok_or_else(|| SqliteError::content("invalid rank"))?));
+    }
+    Ok(ranking)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mj::majority_judgment_ranking;
+
+    fn parse_number(grade: &str) -> Result<u8, String> {
+        grade.parse().map_err(|_| format!("{:?} is not a valid grade", grade))
+    }
+
+    #[test]
+    fn calling_load_poll() {
+        let database = Database::open(":memory:").unwrap();
+        database
+            .execute(
+                "CREATE TABLE \"vote\"\"s\" (voter TEXT, option TEXT, mention INTEGER, weight INTEGER);
+                 INSERT INTO \"vote\"\"s\" VALUES ('v1', 'Pizza', 2, NULL), ('v2', 'Pizza', 0, 3), ('v1', 'Chips', 1, NULL),
+                                              ('v2', 'Chips', 3, 3);",
+            )
+            .unwrap();
+        let table = BallotTable::new("vote\"s").ballot_column("voter").candidate_column("option").grade_column("mention");
+
+        let poll = load_poll(&database, &table, parse_number).unwrap();
+        assert_eq!(poll.ballots().len(), 2);
+        assert_eq!(poll.ballots()[1].grades(), &[("Pizza".to_string(), 0), ("Chips".to_string(), 3)]);
+        assert_eq!((poll.ballots()[1].voter(), poll.ballots()[1].weight()), (Some("v2"), 1));
+
+        let poll = load_poll(&database, &table.clone().weight_column("weight"), parse_number).unwrap();
+        assert_eq!(poll.weights(), vec![1, 3]);
+
+        database.execute("INSERT INTO \"vote\"\"s\" VALUES ('v3', 'Pizza', 'great', 1)").unwrap();
+        let error = load_poll(&database, &table, parse_number).unwrap_err();
+        assert_eq!(error.to_string(), "ballot v3, candidate Pizza: \"great\" is not a valid grade");
+        assert!(load_poll(&database, &BallotTable::new("missing"), parse_number).unwrap_err().code.is_some());
+    }
+
+    #[test]
+    fn calling_save_ranking() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![1, 2]);
+        poll_data.insert("Chips".to_string(), vec![0, 3]);
+        poll_data.insert("Pasta".to_string(), vec![1, 2]);
+        let ranking = majority_judgment_ranking(&poll_data);
+
+        let database = Database::open(":memory:").unwrap();
+        save_ranking(&database, "results", &ranking).unwrap();
+        save_ranking(&database, "results", &ranking).unwrap();
+        assert_eq!(
+            load_ranking(&database, "results").unwrap(),
+            vec![("Pasta".to_string(), 0), ("Pizza".to_string(), 0), ("Chips".to_string(), 2)]
+        );
+
+        // two candidates written with the same name: the insert fails and the previous results are kept
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Name(u8);
+        impl fmt::Display for Name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "Pizza")
+            }
+        }
+        let poll_data = BTreeMap::from([(Name(0), vec![1, 2]), (Name(1), vec![0, 3])]);
+        let error = save_ranking(&database, "results", &majority_judgment_ranking(&poll_data)).unwrap_err();
+        assert!(error.message.contains("UNIQUE"), "{}", error.message);
+        assert_eq!(load_ranking(&database, "results").unwrap().len(), 3);
+    }
+}
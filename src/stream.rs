@@ -0,0 +1,134 @@
+//! # Streaming ingestion
+//! A service reading its ballots from a message queue receives them asynchronously. `ingest` pulls them
+//! one at a time from any async source and counts them into a `TallyAccumulator`, the invalid ballots being
+//! reported rather than stopping the stream.
+//!
+//! The source is written like `futures::stream::unfold`: a state, e.g. the receiver of a channel, and an async
+//! function giving the next ballot with the state back, or None when the stream ends. With tokio:
+//!
+//! ```text
+//! let errors = ingest(&mut tally, receiver, |mut receiver| async move {
+//!     receiver.recv().await.map(|ballot| (ballot, receiver))
+//! }).await;
+//! ```
+//!
+//! The module needs no runtime: it only uses the futures of the standard library.
+use std::future::Future;
+use crate::accumulator::TallyAccumulator;
+use crate::poll::{Ballot, BallotError};
+
+/// Function that counts the ballots of an asynchronous source until it ends
+///
+/// # Arguments
+/// * `tally`: the accumulator counting the ballots, each ballot being counted as soon as it arrives,
+///   so that the ballots received before the future is dropped stay counted
+/// * `state`: the state of the source, e.g. the receiver of a channel
+/// * `next`: the async function taking the state and giving the next ballot with the state, None at the end
+///
+/// # Returns
+/// * `Vec<BallotError>`: the issues of the ballots refused, `ballot` being the index of the ballot in the stream
+///
+/// # Example
+/// ```
+/// use std::collections::VecDeque;
+/// use std::future::Future;
+/// use std::pin::pin;
+/// use std::task::{Context, Poll, Waker};
+/// use majority_judgement_rust::stream::ingest;
+/// use majority_judgement_rust::{Ballot, TallyAccumulator};
+///
+/// let queue = VecDeque::from([
+///     Ballot::new().grade("Pizza", 3).grade("Chips", 1),
+///     Ballot::new().grade("Pizza", 9),
+///     Ballot::new().grade("Pizza", 2).grade("Chips", 0),
+/// ]);
+/// let mut tally = TallyAccumulator::new(["Pizza", "Chips"]);
+/// let ingestion = ingest(&mut tally, queue, |mut queue| async move {
+///     queue.pop_front().map(|ballot| (ballot, queue))
+/// });
+/// // any executor runs it, e.g. tokio; this stream never waits
+/// let errors = match pin!(ingestion).poll(&mut Context::from_waker(Waker::noop())) {
+///     Poll::Ready(errors) => errors,
+///     Poll::Pending => unreachable!(),
+/// };
+/// assert_eq!(errors[0].ballot, 1);
+/// assert_eq!(tally.ballots(), 2);
+/// ```
+pub async fn ingest<G, S, F, Fut>(tally: &mut TallyAccumulator<G>, state: S, mut next: F) -> Vec<BallotError>
+where
+    G: Ord + Clone,
+    F: FnMut(S) -> Fut,
+    Fut: Future<Output = Option<(Ballot<G>, S)>>,
+{
+    let mut errors = Vec::new();
+    let mut state = state;
+    let mut index = 0;
+    while let Some((ballot, next_state)) = next(state).await {
+        if let Err(issues) = tally.add_ballot(&ballot) {
+            errors.extend(issues.into_iter().map(|issue| BallotError { ballot: index, issue }));
+        }
+        state = next_state;
+        index += 1;
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+    use crate::poll::BallotIssue;
+
+    /// A source ready every other poll, like a queue waiting for its messages
+    struct Flaky {
+        ballots: Vec<Ballot<u8>>,
+        ready: bool,
+    }
+
+    impl Future for &mut Flaky {
+        type Output = Option<Ballot<u8>>;
+
+        fn poll(mut self: std::pin::Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+            self.ready = !self.ready;
+            if self.ready {
+                Poll::Ready(self.ballots.pop())
+            } else {
+                context.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn calling_ingest() {
+        let mut flaky = Flaky {
+            ballots: vec![
+                Ballot::new().grade("Pizza", 2).grade("Chips", 0),
+                Ballot::new().grade("Pizza", 1).grade("Soup", 2),
+                Ballot::new().grade("Pizza", 3).grade("Chips", 1),
+            ],
+            ready: true,
+        };
+        let mut tally = TallyAccumulator::new(["Pizza", "Chips"]);
+        let mut polls = 1;
+        let errors = {
+            let mut ingestion = pin!(ingest(&mut tally, &mut flaky, |flaky| async move {
+                let ballot = (&mut *flaky).await;
+                ballot.map(|ballot| (ballot, flaky))
+            }));
+            loop {
+                match ingestion.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+                    Poll::Ready(errors) => break errors,
+                    Poll::Pending => polls += 1,
+                }
+            }
+        };
+        assert_eq!(polls, 5);
+        assert_eq!(errors.iter().map(|error| error.issue.clone()).collect::<Vec<_>>(),
+                   [BallotIssue::UnknownCandidate("Soup".to_string()), BallotIssue::MissingGrade("Chips".to_string())]);
+        assert!(errors.iter().all(|error| error.ballot == 1));
+        assert_eq!(tally.ballots(), 2);
+        assert_eq!(*tally.current_ranking().winners()[0], "Pizza");
+    }
+}
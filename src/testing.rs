@@ -0,0 +1,212 @@
+//! # Testing
+//! Random polls and invariants of the majority judgment, to fuzz the pipelines built on this crate
+//! (e.g. the loading of the ballots, the storage of the results) against properties which must hold
+//! whatever the poll:
+//! * `check_reference`: the ranking is the one of the naive algorithm, withdrawing the median grades one by one
+//! * `check_ballot_order`: the order of the ballots does not matter
+//! * `check_pareto`: a candidate graded at least as well as another by every voter, and better by one, ranks higher
+//! * `check_monotonicity`: raising a grade of a candidate never lowers its rank
+//!
+//! Adding the same grade to all the candidates is not an invariant: with `A = [0, 3]` and `B = [1, 2]`,
+//! `B` wins, but with a third voter grading both `3`, `A = [0, 3, 3]` wins against `B = [1, 2, 3]`.
+//!
+//! The checks return a description of the violation instead of panicking, to be reported with the poll.
+//! This module needs the `testing` feature.
+//!
+//! # Example
+//! ```
+//! use majority_judgement_rust::testing::{check_all, PollGenerator};
+//! let mut generator = PollGenerator::new(42);
+//! for _ in 0..100 {
+//!     let poll_data = generator.poll(4, 25, 6);
+//!     check_all(&poll_data).unwrap();
+//! }
+//! ```
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use crate::mj::majority_judgment;
+use crate::tie_breaker::splitmix64;
+
+/// A reproducible generator of random polls (SplitMix64): the same seed gives the same polls
+#[derive(Debug, Clone)]
+pub struct PollGenerator {
+    state: u64,
+}
+
+impl PollGenerator {
+    /// Create the generator from its seed
+    pub fn new(seed: u64) -> Self {
+        PollGenerator { state: seed }
+    }
+
+    /// The next random number
+    pub fn next_u64(&mut self) -> u64 {
+        let value = splitmix64(self.state);
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        value
+    }
+
+    /// A random number in `0..bound`, 0 if `bound` is 0
+    pub fn below(&mut self, bound: u64) -> u64 {
+        if bound == 0 {
+            return 0;
+        }
+        self.next_u64() % bound
+    }
+
+    /// Function that draws a random poll
+    ///
+    /// # Arguments
+    /// * `candidates`: the number of candidates, named "Candidate 0", "Candidate 1", ...
+    /// * `ballots`: the number of grades of each candidate
+    /// * `grades`: the number of grades of the scale, the grades being drawn uniformly in `0..grades`
+    ///
+    /// # Returns
+    /// * `BTreeMap<String, Vec<u8>>`: the poll data
+    pub fn poll(&mut self, candidates: usize, ballots: usize, grades: u8) -> BTreeMap<String, Vec<u8>> {
+        (0..candidates)
+            .map(|candidate| {
+                let grades = (0..ballots).map(|_| self.below(grades as u64) as u8).collect();
+                (format!("Candidate {}", candidate), grades)
+            })
+            .collect()
+    }
+}
+
+/// Function that ranks a poll with the naive algorithm, the reference of the property tests
+///
+/// The majority values of each candidate are computed by withdrawing the lower median grade one
+/// vote at a time, then the candidates are sorted by decreasing majority values.
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data, the same number of grades for every candidate
+///
+/// # Returns
+/// * `Vec<(&K, usize)>`: a vector of tuple with the candidate and its rank,
+///   tied candidates share the same rank and are listed by increasing key
+pub fn reference_majority_judgment<K: Ord, G: Ord + Clone>(poll_data: &BTreeMap<K, Vec<G>>) -> Vec<(&K, usize)> {
+    let mut values: Vec<(&K, Vec<G>)> = poll_data
+        .iter()
+        .map(|(candidate, grades)| {
+            let mut sorted = grades.clone();
+            sorted.sort();
+            let mut majority_values = Vec::with_capacity(sorted.len());
+            while !sorted.is_empty() {
+                majority_values.push(sorted.remove((sorted.len() - 1) / 2));
+            }
+            (candidate, majority_values)
+        })
+        .collect();
+    values.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut ranking: Vec<(&K, usize)> = Vec::with_capacity(values.len());
+    for (index, (candidate, majority_values)) in values.iter().enumerate() {
+        let rank = if index > 0 && values[index - 1].1 == *majority_values { ranking[index - 1].1 } else { index };
+        ranking.push((candidate, rank));
+    }
+    ranking
+}
+
+/// Function that checks the ranking against `reference_majority_judgment`
+pub fn check_reference<K: Ord + Debug, G: Ord + Clone + Debug>(poll_data: &BTreeMap<K, Vec<G>>) -> Result<(), String> {
+    let ranking = majority_judgment(poll_data);
+    let reference = reference_majority_judgment(poll_data);
+    if ranking != reference {
+        return Err(format!("the ranking {:?} differs from the reference {:?}", ranking, reference));
+    }
+    Ok(())
+}
+
+/// Function that checks that reversing the order of the ballots does not change the ranking
+pub fn check_ballot_order<K: Ord + Clone + Debug, G: Ord + Clone + Debug>(poll_data: &BTreeMap<K, Vec<G>>) -> Result<(), String> {
+    let reversed: BTreeMap<K, Vec<G>> = poll_data
+        .iter()
+        .map(|(candidate, grades)| (candidate.clone(), grades.iter().rev().cloned().collect()))
+        .collect();
+    let ranking = majority_judgment(poll_data);
+    let reversed_ranking = majority_judgment(&reversed);
+    if ranking != reversed_ranking {
+        return Err(format!("the ranking {:?} becomes {:?} with the ballots reversed", ranking, reversed_ranking));
+    }
+    Ok(())
+}
+
+/// Function that checks that a candidate dominating another one, ballot by ballot, ranks higher
+pub fn check_pareto<K: Ord + Debug, G: Ord + Clone + Debug>(poll_data: &BTreeMap<K, Vec<G>>) -> Result<(), String> {
+    let ranks: BTreeMap<&K, usize> = majority_judgment(poll_data).into_iter().collect();
+    for (a, a_grades) in poll_data.iter() {
+        for (b, b_grades) in poll_data.iter() {
+            let dominates = a_grades.len() == b_grades.len()
+                && a_grades.iter().zip(b_grades).all(|(x, y)| x >= y)
+                && a_grades.iter().zip(b_grades).any(|(x, y)| x > y);
+            if dominates && ranks[a] >= ranks[b] {
+                return Err(format!("{:?} is graded better than {:?} by every voter but ranks {} against {}", a, b, ranks[a], ranks[b]));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Function that checks that raising the worst grade of a candidate to the best grade of the poll
+/// never lowers its rank
+pub fn check_monotonicity<K: Ord + Clone + Debug, G: Ord + Clone + Debug>(poll_data: &BTreeMap<K, Vec<G>>) -> Result<(), String> {
+    let Some(best) = poll_data.values().flatten().max() else { return Ok(()) };
+    let ranks: BTreeMap<&K, usize> = majority_judgment(poll_data).into_iter().collect();
+    for (candidate, grades) in poll_data.iter() {
+        let Some(worst) = grades.iter().enumerate().min_by_key(|&(_, grade)| grade).map(|(index, _)| index) else { continue };
+        let mut raised = poll_data.clone();
+        raised.get_mut(candidate).unwrap()[worst] = best.clone();
+        let rank = majority_judgment(&raised).into_iter().find(|(other, _)| *other == candidate).map(|(_, rank)| rank);
+        if rank.is_some_and(|rank| rank > ranks[candidate]) {
+            return Err(format!("{:?} falls from rank {} to {:?} when one of its grades is raised", candidate, ranks[candidate], rank));
+        }
+    }
+    Ok(())
+}
+
+/// Function that runs all the checks of this module on a poll
+pub fn check_all<K: Ord + Clone + Debug, G: Ord + Clone + Debug>(poll_data: &BTreeMap<K, Vec<G>>) -> Result<(), String> {
+    check_reference(poll_data)?;
+    check_ballot_order(poll_data)?;
+    check_pareto(poll_data)?;
+    check_monotonicity(poll_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_check_all() {
+        let mut generator = PollGenerator::new(2024);
+        for round in 0..300 {
+            let candidates = 1 + generator.below(5) as usize;
+            let ballots = 1 + generator.below(30) as usize;
+            let grades = 1 + generator.below(7) as u8;
+            let poll_data = generator.poll(candidates, ballots, grades);
+            if let Err(violation) = check_all(&poll_data) {
+                panic!("round {}: {} in {:?}", round, violation, poll_data);
+            }
+        }
+    }
+
+    #[test]
+    fn calling_poll_generator() {
+        let poll_data = PollGenerator::new(7).poll(3, 10, 4);
+        assert_eq!(poll_data, PollGenerator::new(7).poll(3, 10, 4));
+        assert_eq!(poll_data.len(), 3);
+        assert!(poll_data.values().all(|grades| grades.len() == 10 && grades.iter().all(|&grade| grade < 4)));
+    }
+
+    #[test]
+    fn calling_check_with_identical_grades() {
+        // not an invariant, see the documentation of the module
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("A", vec![0, 3]);
+        poll_data.insert("B", vec![1, 2]);
+        assert_eq!(majority_judgment(&poll_data)[0].0, &"B");
+        poll_data.values_mut().for_each(|grades| grades.push(3));
+        assert_eq!(majority_judgment(&poll_data)[0].0, &"A");
+        assert!(check_all(&poll_data).is_ok());
+    }
+}
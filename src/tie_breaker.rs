@@ -0,0 +1,243 @@
+//! # Tie breakers
+//! Rules consulted when candidates have exactly the same majority values.
+//! The majority judgment alone cannot separate such candidates, a tie breaker decides
+//! in a reproducible way instead of relying on the iteration order of the poll data.
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// A rule ordering two candidates whose majority values are equal
+///
+/// `K` is the type of the candidates, their names as `String` by default.
+pub trait TieBreaker<G, K: ?Sized = String> {
+    /// Function that compares two tied candidates
+    ///
+    /// # Arguments
+    /// * `a`, `a_grades`: the first candidate and its grades
+    /// * `b`, `b_grades`: the second candidate and its grades
+    ///
+    /// # Returns
+    /// * `Ordering::Greater` if `a` ranks before `b`, `Ordering::Less` if `b` ranks before `a`,
+    ///   `Ordering::Equal` if the rule cannot separate them either
+    fn compare(&self, a: &K, a_grades: &[G], b: &K, b_grades: &[G]) -> Ordering;
+}
+
+/// Tied candidates are ordered alphabetically by name, or by increasing identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Lexicographic;
+
+impl<G, K: Ord + ?Sized> TieBreaker<G, K> for Lexicographic {
+    fn compare(&self, a: &K, _: &[G], b: &K, _: &[G]) -> Ordering {
+        b.cmp(a)
+    }
+}
+
+/// Tied candidates are ordered by their registration order, e.g. the order of `Poll::candidates`.
+/// Candidates missing from the registration list come last and stay tied.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RegistrationOrder<K = String> {
+    candidates: Vec<K>,
+}
+
+impl<K: PartialEq> RegistrationOrder<K> {
+    /// Create the rule from the candidates in their registration order
+    pub fn new<I, S>(candidates: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<K>,
+    {
+        RegistrationOrder { candidates: candidates.into_iter().map(Into::into).collect() }
+    }
+
+    fn position(&self, candidate: &K) -> usize {
+        self.candidates.iter().position(|c| c == candidate).unwrap_or(usize::MAX)
+    }
+}
+
+impl<G, K: PartialEq> TieBreaker<G, K> for RegistrationOrder<K> {
+    fn compare(&self, a: &K, _: &[G], b: &K, _: &[G]) -> Ordering {
+        self.position(b).cmp(&self.position(a))
+    }
+}
+
+/// Tied candidates are ordered by a random draw, reproducible from its seed.
+/// The draw only depends on the seed and the candidates, not on the order of the poll data,
+/// so publishing the seed before the count lets anyone replay the draw of a binding election:
+/// 1. the bytes of the candidate given by `DrawKey` are hashed with 64-bit FNV-1a: a name as its UTF-8 bytes
+///    followed by the byte `0xff`, an integer identifier as its little-endian bytes,
+/// 2. the number drawn for the candidate is the first output of SplitMix64 seeded with `seed ^ hash`,
+/// 3. the greatest number ranks first.
+///
+/// The same draw in Python:
+/// ```text
+/// def draw(seed, name):
+///     mask = (1 << 64) - 1
+///     h = 0xcbf29ce484222325
+///     for byte in name.encode() + b"\xff":
+///         h = ((h ^ byte) * 0x100000001b3) & mask
+///     z = ((seed ^ h) + 0x9e3779b97f4a7c15) & mask
+///     z = ((z ^ (z >> 30)) * 0xbf58476d1ce4e5b9) & mask
+///     z = ((z ^ (z >> 27)) * 0x94d049bb133111eb) & mask
+///     return z ^ (z >> 31)
+/// ```
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::RandomTieBreaker;
+/// let draw = RandomTieBreaker::with_seed(20240609);
+/// assert_eq!(draw.draw("Pizza"), 9860523353035968851);
+/// assert_eq!(draw.draw(&7u32), 6573775442837654410);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RandomTieBreaker {
+    seed: u64,
+}
+
+impl RandomTieBreaker {
+    /// Create the rule from the seed of the draw, e.g. published before the count or drawn in public
+    pub fn with_seed(seed: u64) -> Self {
+        RandomTieBreaker { seed }
+    }
+
+    /// The seed of the draw
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The number drawn for a candidate, the greatest number ranks first, to publish along with the seed
+    pub fn draw<K: DrawKey + ?Sized>(&self, candidate: &K) -> u64 {
+        let mut hash = FNV_OFFSET;
+        candidate.draw_bytes(&mut |bytes| hash = fnv1a(hash, bytes));
+        splitmix64(self.seed ^ hash)
+    }
+}
+
+impl<G, K: DrawKey + ?Sized> TieBreaker<G, K> for RandomTieBreaker {
+    fn compare(&self, a: &K, _: &[G], b: &K, _: &[G]) -> Ordering {
+        self.draw(a).cmp(&self.draw(b))
+    }
+}
+
+/// Tied candidates are ordered by their mean grade, the highest mean first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MeanGrade;
+
+impl<G: Clone + Into<f64>, K: ?Sized> TieBreaker<G, K> for MeanGrade {
+    fn compare(&self, _: &K, a_grades: &[G], _: &K, b_grades: &[G]) -> Ordering {
+        mean(a_grades).partial_cmp(&mean(b_grades)).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Function that computes the mean of grades, 0 for no grade
+fn mean<G: Clone + Into<f64>>(grades: &[G]) -> f64 {
+    if grades.is_empty() {
+        return 0.0;
+    }
+    grades.iter().cloned().map(Into::into).sum::<f64>() / grades.len() as f64
+}
+
+/// A candidate drawn by `RandomTieBreaker`, with a byte encoding fixed once and for all:
+/// unlike `core::hash::Hash`, it is the same on every platform and in every version,
+/// so that a draw published with its seed can be replayed anywhere.
+pub trait DrawKey {
+    /// Function that gives the bytes of the candidate to `write`, in one or more calls
+    ///
+    /// # Arguments
+    /// * `write`: called with the successive bytes of the candidate
+    fn draw_bytes(&self, write: &mut dyn FnMut(&[u8]));
+}
+
+/// A name is drawn from its UTF-8 bytes followed by the byte `0xff`, which never appears in UTF-8
+impl DrawKey for str {
+    fn draw_bytes(&self, write: &mut dyn FnMut(&[u8])) {
+        write(self.as_bytes());
+        write(&[0xff]);
+    }
+}
+
+impl DrawKey for String {
+    fn draw_bytes(&self, write: &mut dyn FnMut(&[u8])) {
+        self.as_str().draw_bytes(write);
+    }
+}
+
+impl<K: DrawKey + ?Sized> DrawKey for &K {
+    fn draw_bytes(&self, write: &mut dyn FnMut(&[u8])) {
+        (**self).draw_bytes(write);
+    }
+}
+
+/// An integer identifier is drawn from its little-endian bytes, whatever the endianness of the platform,
+/// `usize` and `isize` as 8 bytes whatever the width of the platform
+macro_rules! draw_key_integer {
+    ($($integer:ty => $as:ty),*) => {
+        $(impl DrawKey for $integer {
+            fn draw_bytes(&self, write: &mut dyn FnMut(&[u8])) {
+                write(&(*self as $as).to_le_bytes());
+            }
+        })*
+    };
+}
+
+draw_key_integer!(u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => u64,
+                  i8 => i8, i16 => i16, i32 => i32, i64 => i64, i128 => i128, isize => i64);
+
+/// The offset basis of 64-bit FNV-1a
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Function that hashes bytes with 64-bit FNV-1a, continuing from `hash`
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3))
+}
+
+/// SplitMix64 finalizer, mixing the bits of `x`
+pub(crate) fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_compare() {
+        let (a, b) = ("A".to_string(), "B".to_string());
+        let no_grades: [u8; 0] = [];
+
+        assert_eq!(Lexicographic.compare(&a, &no_grades, &b, &no_grades), Ordering::Greater);
+        assert_eq!(RegistrationOrder::new(["B", "A"]).compare(&a, &no_grades, &b, &no_grades), Ordering::Less);
+        assert_eq!(RegistrationOrder::new(["B"]).compare(&a, &no_grades, &a, &no_grades), Ordering::Equal);
+        assert_eq!(MeanGrade.compare(&a, &[1u8, 3], &b, &[2u8, 1]), Ordering::Greater);
+
+        let random = RandomTieBreaker::with_seed(42);
+        assert_eq!(random.compare(&a, &no_grades, &b, &no_grades),
+                   random.compare(&b, &no_grades, &a, &no_grades).reverse());
+        assert_eq!(random.compare(&a, &no_grades, &b, &no_grades),
+                   RandomTieBreaker::with_seed(42).compare(&a, &no_grades, &b, &no_grades));
+
+        // integers are drawn from their little-endian bytes on every platform
+        assert_eq!(random.draw(&7u32), splitmix64(42 ^ fnv1a(FNV_OFFSET, &[7, 0, 0, 0])));
+        assert_eq!(random.draw(&7usize), random.draw(&7u64));
+    }
+
+    #[test]
+    fn calling_draw() {
+        // the output of the Python snippet in the documentation of RandomTieBreaker
+        let draw = RandomTieBreaker::with_seed(20240609);
+        assert_eq!(draw.draw("Pizza"), 9860523353035968851);
+        assert_eq!(draw.draw(&"Pizza".to_string()), 9860523353035968851);
+        assert_eq!(draw.draw(&7u32), 6573775442837654410);
+
+        let draw = RandomTieBreaker::with_seed(42);
+        assert_eq!(draw.draw("Crêpes"), 18348025792876874285);
+        assert_eq!(draw.draw(""), 15115884112480647336);
+        assert_eq!(draw.draw(&7u64), 433336591550563701);
+
+        let draw = RandomTieBreaker::with_seed(0);
+        assert_eq!((draw.draw("A"), draw.draw("B")), (7670114647902151741, 3023671168027263730));
+        assert_eq!(draw.compare(&"A", &[0u8], &"B", &[0u8]), Ordering::Greater);
+    }
+}
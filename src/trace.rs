@@ -0,0 +1,160 @@
+//! # Tracing
+//! Spans around the steps of a ranking, to profile large elections and audit their intermediate values:
+//! * `tally`: counting the grades of a candidate
+//! * `median`: computing the majority values of a candidate from its counts
+//! * `sort`: sorting the candidates by their merits
+//!
+//! The spans report their duration to the `Subscriber` installed with `set_subscriber`, e.g. a
+//! `SpanTotals` adding them up or a `StderrSubscriber` writing them to the standard error.
+//! At `Level::Debug` the steps also report their intermediate values, e.g. the number of votes of
+//! each candidate. Without subscriber the spans are not even timed, and without the `tracing` feature
+//! they are not compiled at all.
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// How much a subscriber is told
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// The duration of the spans
+    Info,
+    /// The duration of the spans and the intermediate values of the steps
+    Debug,
+}
+
+/// What receives the spans and the events of the rankings
+pub trait Subscriber: Send + Sync {
+    /// The most detailed level the subscriber wants
+    fn level(&self) -> Level;
+
+    /// A span ended after `elapsed`
+    fn on_span(&self, span: &'static str, elapsed: Duration);
+
+    /// An intermediate value of a span, at `Level::Debug`
+    fn on_event(&self, span: &'static str, message: &str);
+}
+
+static SUBSCRIBER: OnceLock<&'static dyn Subscriber> = OnceLock::new();
+
+/// Function that installs the subscriber of the whole program, as a logger
+///
+/// # Arguments
+/// * `subscriber`: the subscriber, e.g. a `static`
+///
+/// # Returns
+/// * `Result<(), &'static dyn Subscriber>`: the subscriber back if one was already installed
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::majority_judgment;
+/// use majority_judgement_rust::trace::{set_subscriber, SpanTotals};
+/// static TOTALS: SpanTotals = SpanTotals::new();
+/// assert!(set_subscriber(&TOTALS).is_ok());
+///
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza", vec![0, 2, 3]);
+/// poll_data.insert("Chips", vec![1, 3, 3]);
+/// majority_judgment(&poll_data);
+/// assert_eq!(TOTALS.totals()["tally"].0, 2);
+/// ```
+pub fn set_subscriber(subscriber: &'static dyn Subscriber) -> Result<(), &'static dyn Subscriber> {
+    SUBSCRIBER.set(subscriber)
+}
+
+/// A span being timed, reported to the subscriber when dropped
+pub(crate) struct Span {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl Span {
+    /// Start a span, timed only if a subscriber is installed
+    pub(crate) fn enter(name: &'static str) -> Self {
+        Span { name, start: SUBSCRIBER.get().map(|_| Instant::now()) }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let (Some(start), Some(subscriber)) = (self.start, SUBSCRIBER.get()) {
+            subscriber.on_span(self.name, start.elapsed());
+        }
+    }
+}
+
+/// Function that reports an intermediate value, formatted only if a subscriber wants it
+pub(crate) fn event<F: FnOnce() -> String>(span: &'static str, message: F) {
+    if let Some(subscriber) = SUBSCRIBER.get().filter(|subscriber| subscriber.level() >= Level::Debug) {
+        subscriber.on_event(span, &message());
+    }
+}
+
+/// A subscriber adding up the number and the duration of the spans of each step
+#[derive(Debug, Default)]
+pub struct SpanTotals {
+    totals: Mutex<BTreeMap<&'static str, (u64, Duration)>>,
+}
+
+impl SpanTotals {
+    /// Create a subscriber without any span yet, e.g. in a `static`
+    pub const fn new() -> Self {
+        SpanTotals { totals: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// The number of spans of each step and their total duration
+    pub fn totals(&self) -> BTreeMap<&'static str, (u64, Duration)> {
+        self.totals.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+impl Subscriber for SpanTotals {
+    fn level(&self) -> Level {
+        Level::Info
+    }
+
+    fn on_span(&self, span: &'static str, elapsed: Duration) {
+        let mut totals = self.totals.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let total = totals.entry(span).or_default();
+        total.0 += 1;
+        total.1 += elapsed;
+    }
+
+    fn on_event(&self, _span: &'static str, _message: &str) {}
+}
+
+/// A subscriber writing each span and event on a line of the standard error
+#[derive(Debug, Clone, Copy)]
+pub struct StderrSubscriber {
+    /// The most detailed level written
+    pub level: Level,
+}
+
+impl Subscriber for StderrSubscriber {
+    fn level(&self) -> Level {
+        self.level
+    }
+
+    fn on_span(&self, span: &'static str, elapsed: Duration) {
+        eprintln!("[majority-judgment] {} took {:?}", span, elapsed);
+    }
+
+    fn on_event(&self, span: &'static str, message: &str) {
+        eprintln!("[majority-judgment] {}: {}", span, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_span_totals() {
+        let totals = SpanTotals::new();
+        totals.on_span("tally", Duration::from_millis(2));
+        totals.on_span("tally", Duration::from_millis(3));
+        totals.on_span("sort", Duration::from_millis(1));
+        assert_eq!(totals.totals()["tally"], (2, Duration::from_millis(5)));
+        assert_eq!(totals.totals().len(), 2);
+    }
+}
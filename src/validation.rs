@@ -0,0 +1,146 @@
+//! # Validation
+//! Diagnostics of the poll data before tallying, to report the problems to the end users
+//! instead of panicking (`majority_judgment` panics on candidates with different numbers of grades)
+//! or silently ranking dubious data.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::RangeBounds;
+
+/// The problems found in the poll data, see `validate_poll`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollDiagnostics<'a, K, G> {
+    /// The number of grades of most candidates, the number expected for all of them
+    pub expected_grades: usize,
+    /// The candidates with another number of grades, with their number of grades
+    pub mismatched_lengths: Vec<(&'a K, usize)>,
+    /// The candidates without any grade
+    pub empty_candidates: Vec<&'a K>,
+    /// The grades out of the scale: the candidate, the index of the grade and the grade
+    pub out_of_scale: Vec<(&'a K, usize, &'a G)>,
+    /// The groups of candidates with the same name once trimmed and lowercased, e.g. "Pizza" and "pizza "
+    pub duplicate_candidates: Vec<Vec<&'a K>>,
+}
+
+impl<K, G> PollDiagnostics<'_, K, G> {
+    /// Whether no problem was found
+    pub fn is_valid(&self) -> bool {
+        self.mismatched_lengths.is_empty()
+            && self.empty_candidates.is_empty()
+            && self.out_of_scale.is_empty()
+            && self.duplicate_candidates.is_empty()
+    }
+}
+
+impl<K: fmt::Display, G: fmt::Display> fmt::Display for PollDiagnostics<'_, K, G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut problems = Vec::new();
+        for (candidate, length) in self.mismatched_lengths.iter() {
+            problems.push(format!("candidate {} has {} grades instead of {}", candidate, length, self.expected_grades));
+        }
+        for candidate in self.empty_candidates.iter() {
+            problems.push(format!("candidate {} has no grade", candidate));
+        }
+        for (candidate, index, grade) in self.out_of_scale.iter() {
+            problems.push(format!("grade {} of candidate {} is out of the scale: {}", index, candidate, grade));
+        }
+        for group in self.duplicate_candidates.iter() {
+            let names: Vec<String> = group.iter().map(|candidate| format!("{:?}", candidate.to_string())).collect();
+            problems.push(format!("candidates {} are the same", names.join(", ")));
+        }
+        if problems.is_empty() {
+            return write!(f, "the poll is valid");
+        }
+        write!(f, "{}", problems.join("\n"))
+    }
+}
+
+/// Function that checks the poll data and reports all the problems found
+///
+/// # Arguments
+/// * `poll_data`: a BTreeMap<K, Vec<G>> with the poll data
+/// * `scale`: the range of the valid grades, e.g. `0..6` for six grades or `..` to accept any grade
+///
+/// # Returns
+/// * `PollDiagnostics`: the problems found, see `PollDiagnostics::is_valid`
+///
+/// # Example
+/// ```
+/// use std::collections::BTreeMap;
+/// use majority_judgement_rust::validate_poll;
+/// let mut poll_data = BTreeMap::new();
+/// poll_data.insert("Pizza".to_string(), vec![0, 2, 7]);
+/// poll_data.insert("Chips".to_string(), vec![1, 3]);
+///
+/// let diagnostics = validate_poll(&poll_data, 0..6);
+/// assert!(!diagnostics.is_valid());
+/// assert_eq!(diagnostics.out_of_scale, vec![(&"Pizza".to_string(), 2, &7)]);
+/// ```
+pub fn validate_poll<'a, K, G, R>(poll_data: &'a BTreeMap<K, Vec<G>>, scale: R) -> PollDiagnostics<'a, K, G>
+where
+    K: Ord + fmt::Display,
+    G: Ord,
+    R: RangeBounds<G>,
+{
+    let mut lengths: BTreeMap<usize, usize> = BTreeMap::new();
+    for grades in poll_data.values() {
+        *lengths.entry(grades.len()).or_insert(0) += 1;
+    }
+    // the most common number of grades, the greatest one in case of a tie
+    let expected_grades = lengths
+        .iter()
+        .max_by_key(|&(&length, &count)| (count, length))
+        .map_or(0, |(&length, _)| length);
+
+    let mut normalized: BTreeMap<String, Vec<&K>> = BTreeMap::new();
+    for candidate in poll_data.keys() {
+        let name = candidate.to_string().split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        normalized.entry(name).or_default().push(candidate);
+    }
+
+    PollDiagnostics {
+        expected_grades,
+        mismatched_lengths: poll_data
+            .iter()
+            .filter(|(_, grades)| grades.len() != expected_grades)
+            .map(|(candidate, grades)| (candidate, grades.len()))
+            .collect(),
+        empty_candidates: poll_data
+            .iter()
+            .filter(|(_, grades)| grades.is_empty())
+            .map(|(candidate, _)| candidate)
+            .collect(),
+        out_of_scale: poll_data
+            .iter()
+            .flat_map(|(candidate, grades)| grades.iter().enumerate().map(move |(index, grade)| (candidate, index, grade)))
+            .filter(|(_, _, grade)| !scale.contains(grade))
+            .collect(),
+        duplicate_candidates: normalized.into_values().filter(|group| group.len() > 1).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_validate_poll() {
+        let mut poll_data = BTreeMap::new();
+        poll_data.insert("Pizza".to_string(), vec![0, 2, 3]);
+        poll_data.insert("pizza ".to_string(), vec![1, 1, 1]);
+        poll_data.insert("Chips".to_string(), vec![1, 3]);
+        poll_data.insert("Pasta".to_string(), vec![]);
+
+        let diagnostics = validate_poll(&poll_data, 1..=3);
+        assert_eq!(diagnostics.expected_grades, 3);
+        assert_eq!(diagnostics.mismatched_lengths, vec![(&"Chips".to_string(), 2), (&"Pasta".to_string(), 0)]);
+        assert_eq!(diagnostics.empty_candidates, vec![&"Pasta".to_string()]);
+        assert_eq!(diagnostics.out_of_scale, vec![(&"Pizza".to_string(), 0, &0)]);
+        assert_eq!(diagnostics.duplicate_candidates, vec![vec![&"Pizza".to_string(), &"pizza ".to_string()]]);
+        assert_eq!(diagnostics.to_string().lines().count(), 5);
+
+        poll_data.retain(|candidate, _| candidate == "Pizza");
+        let diagnostics = validate_poll(&poll_data, ..);
+        assert!(diagnostics.is_valid());
+        assert_eq!(diagnostics.to_string(), "the poll is valid");
+    }
+}
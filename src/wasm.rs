@@ -0,0 +1,165 @@
+//! # WebAssembly
+//! Entry points for client-side poll widgets (enabled by the `wasm` feature): the poll goes in
+//! as JSON, the ranking comes out as JSON, see the `json` module for both formats.
+//!
+//! The crate has no dependency, so the exports do not rely on `wasm-bindgen`: the strings cross
+//! the boundary as bytes in the memory of the module. Build the module with
+//! ```text
+//! cargo rustc --release --lib --crate-type cdylib --target wasm32-unknown-unknown --features wasm
+//! ```
+//! and call it from JavaScript:
+//! ```js
+//! const { instance } = await WebAssembly.instantiateStreaming(fetch("majority_judgement_rust.wasm"));
+//! const { memory, mj_alloc, mj_free, mj_rank_from_json } = instance.exports;
+//!
+//! function rankFromJson(json) {
+//!   const input = new TextEncoder().encode(json);
+//!   const pointer = mj_alloc(input.length);
+//!   new Uint8Array(memory.buffer, pointer, input.length).set(input);
+//!   const output = mj_rank_from_json(pointer, input.length);
+//!   mj_free(pointer, input.length);
+//!   const length = new DataView(memory.buffer).getUint32(output, true);
+//!   const result = new TextDecoder().decode(new Uint8Array(memory.buffer, output + 4, length));
+//!   mj_free(output, length + 4);
+//!   return JSON.parse(result);
+//! }
+//! ```
+use std::collections::BTreeMap;
+use std::alloc::{alloc, dealloc, Layout};
+use crate::checked::try_majority_judgment_from_tally_with_options;
+use crate::json::{ToJson, Value};
+use crate::mj::compute_weighted_frequency_of_grades;
+use crate::options::Options;
+use crate::poll::Poll;
+
+/// Function that ranks a poll written in JSON
+///
+/// Weighted ballots count as many times as their weight: the candidates are ranked from the total weight
+/// given to each grade, without repeating the grades of a ballot.
+///
+/// # Arguments
+/// * `json`: the poll, see the `json` module for the format
+///
+/// # Returns
+/// * `String`: the ranking as written by `Ranking::to_json`, or `{"error": "..."}` if the poll
+///   cannot be read or is invalid
+///
+/// # Example
+/// ```
+/// use majority_judgement_rust::wasm::rank_from_json;
+/// let json = r#"{"candidates": ["Pizza", "Chips"],
+///                "ballots": [{"grades": {"Pizza": 0, "Chips": 2}},
+///                            {"grades": {"Pizza": 1, "Chips": 3}}]}"#;
+/// assert_eq!(
+///     rank_from_json(json),
+///     r#"[{"candidate":"Chips","rank":0,"tie":"unique","majority_grade":2,"proponents":0.5,"opponents":0.0},"#.to_string()
+///         + r#"{"candidate":"Pizza","rank":1,"tie":"unique","majority_grade":0,"proponents":0.5,"opponents":0.0}]"#);
+/// ```
+pub fn rank_from_json(json: &str) -> String {
+    let error = |message: String| Value::Object(vec![("error".to_string(), message.to_json())]).to_string();
+
+    let poll: Poll<u32> = match Poll::from_json(json) {
+        Ok(poll) => poll,
+        Err(e) => return error(e.to_string()),
+    };
+    let poll_data = match poll.poll_data() {
+        Ok(poll_data) => poll_data,
+        Err(errors) => return error(errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")),
+    };
+    if poll_data.is_empty() {
+        return "[]".to_string();
+    }
+
+    let weights = poll.weights();
+    let tallies: BTreeMap<String, BTreeMap<u32, u64>> = poll_data
+        .into_iter()
+        .map(|(candidate, grades)| {
+            let tally = compute_weighted_frequency_of_grades(&grades, &weights);
+            (candidate, tally)
+        })
+        .collect();
+    match try_majority_judgment_from_tally_with_options(&tallies, &Options::default()) {
+        Ok(ranking) => ranking.to_json(),
+        Err(e) => error(e.to_string()),
+    }
+}
+
+/// Function that allocates `len` bytes in the memory of the module, to write the input of `mj_rank_from_json`
+#[no_mangle]
+pub extern "C" fn mj_alloc(len: usize) -> *mut u8 {
+    match Layout::array::<u8>(len) {
+        Ok(layout) if len > 0 => unsafe { alloc(layout) },
+        _ => std::ptr::NonNull::dangling().as_ptr(),
+    }
+}
+
+/// Function that frees the bytes allocated by `mj_alloc` or returned by `mj_rank_from_json`
+///
+/// # Safety
+/// `ptr` must come from `mj_alloc(len)`, or from `mj_rank_from_json` with `len` the length of the
+/// result plus 4, and must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn mj_free(ptr: *mut u8, len: usize) {
+    if let Ok(layout) = Layout::array::<u8>(len) {
+        if len > 0 {
+            dealloc(ptr, layout);
+        }
+    }
+}
+
+/// Function that ranks the poll written in JSON at `ptr`, see `rank_from_json`
+///
+/// # Returns
+/// * `*mut u8`: the result, the length of the JSON text as a little-endian `u32`
+///   followed by the text, to free with `mj_free(pointer, length + 4)`
+///
+/// # Safety
+/// `ptr` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mj_rank_from_json(ptr: *const u8, len: usize) -> *mut u8 {
+    let input = std::slice::from_raw_parts(ptr, len);
+    let output = match std::str::from_utf8(input) {
+        Ok(json) => rank_from_json(json),
+        Err(_) => Value::Object(vec![("error".to_string(), "the poll is not valid UTF-8".to_string().to_json())]).to_string(),
+    };
+
+    let mut buffer = Vec::with_capacity(output.len() + 4);
+    buffer.extend_from_slice(&(output.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(output.as_bytes());
+    let pointer = mj_alloc(buffer.len());
+    std::ptr::copy_nonoverlapping(buffer.as_ptr(), pointer, buffer.len());
+    pointer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calling_rank_from_json() {
+        let json = r#"{"candidates": ["Pizza", "Chips"],
+                       "ballots": [{"grades": {"Pizza": 0, "Chips": 2}, "weight": 2},
+                                   {"grades": {"Pizza": 3, "Chips": 1}, "weight": 3}]}"#;
+        assert!(rank_from_json(json).starts_with(r#"[{"candidate":"Pizza","rank":0"#));
+        // a heavy ballot is not repeated grade by grade
+        let heavy = r#"{"candidates": ["Pizza", "Chips"],
+                        "ballots": [{"grades": {"Pizza": 0, "Chips": 2}, "weight": 4000000000},
+                                    {"grades": {"Pizza": 3, "Chips": 1}, "weight": 3}]}"#;
+        assert!(rank_from_json(heavy).starts_with(r#"[{"candidate":"Chips","rank":0"#));
+        assert_eq!(rank_from_json(r#"{"candidates": []}"#), "[]");
+        assert!(rank_from_json("{").starts_with(r#"{"error":"#));
+        assert!(rank_from_json(r#"{"candidates": ["Pizza"], "ballots": [{"grades": {}}]}"#).contains("no grade"));
+
+        let output = unsafe {
+            let pointer = mj_alloc(json.len());
+            std::ptr::copy_nonoverlapping(json.as_ptr(), pointer, json.len());
+            let result = mj_rank_from_json(pointer, json.len());
+            mj_free(pointer, json.len());
+            let length = u32::from_le_bytes(std::slice::from_raw_parts(result, 4).try_into().unwrap()) as usize;
+            let output = String::from_utf8(std::slice::from_raw_parts(result.add(4), length).to_vec()).unwrap();
+            mj_free(result, length + 4);
+            output
+        };
+        assert_eq!(output, rank_from_json(json));
+    }
+}